@@ -0,0 +1,24 @@
+CREATE TABLE word_instances (
+    id         INTEGER PRIMARY KEY,
+    word       TEXT    NOT NULL,
+    guild_id   INTEGER,
+    channel_id INTEGER NOT NULL,
+    user_id    INTEGER NOT NULL,
+    -- milliseconds since the unix epoch
+    timestamp  INTEGER NOT NULL
+);
+
+CREATE INDEX word_instances_timestamp ON word_instances (timestamp);
+
+CREATE TABLE settings (
+    guild_id INTEGER NOT NULL,
+    key      TEXT    NOT NULL,
+    value    TEXT    NOT NULL,
+    PRIMARY KEY (guild_id, key)
+);
+
+CREATE TABLE opt_outs (
+    guild_id INTEGER NOT NULL,
+    user_id  INTEGER NOT NULL,
+    PRIMARY KEY (guild_id, user_id)
+);
@@ -0,0 +1,5 @@
+CREATE TABLE blocklist (
+    id      INTEGER PRIMARY KEY,
+    -- a word, or a regex between slashes
+    pattern TEXT    NOT NULL
+);
@@ -0,0 +1,5 @@
+-- opt-outs were never wired up to anything
+DROP TABLE opt_outs;
+
+-- `forget` deletes by word
+CREATE INDEX word_instances_word ON word_instances (word);
@@ -0,0 +1,254 @@
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Serializer};
+use tracing::warn;
+
+use crate::delivery::Failure;
+
+/// Something the audit log hears about, sent to `--audit-webhook-url` as
+/// JSON, e.g. `{"event": "post", "word": "pizza", ...}`.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum AuditEvent {
+    /// Pino posted a word. The channel isn't known through `--webhook-url`.
+    Post {
+        word: String,
+        #[serde(serialize_with = "snowflake")]
+        channel_id: Option<u64>,
+        #[serde(serialize_with = "snowflake")]
+        guild_id: Option<u64>,
+        timestamp: DateTime<Utc>,
+    },
+    /// Someone used a command that isn't for everyone, like `!pino set`.
+    Command {
+        command: String,
+        #[serde(serialize_with = "snowflake")]
+        user_id: Option<u64>,
+        #[serde(serialize_with = "snowflake")]
+        channel_id: Option<u64>,
+        #[serde(serialize_with = "snowflake")]
+        guild_id: Option<u64>,
+        timestamp: DateTime<Utc>,
+    },
+    /// Discord refused a message of pino.
+    Error {
+        failure: Failure,
+        error: String,
+        #[serde(serialize_with = "snowflake")]
+        guild_id: Option<u64>,
+        timestamp: DateTime<Utc>,
+    },
+}
+
+/// Serialize an id as a string, like Discord does: JavaScript numbers can't
+/// hold them.
+fn snowflake<S: Serializer>(id: &Option<u64>, serializer: S) -> Result<S::Ok, S::Error> {
+    id.map(|id| id.to_string()).serialize(serializer)
+}
+
+impl AuditEvent {
+    /// One readable line, for when the webhook is a Discord one.
+    pub fn describe(&self) -> String {
+        let place = |guild_id: &Option<u64>| match guild_id {
+            Some(guild_id) => format!("in server {}", guild_id),
+            None => "outside of servers".to_owned(),
+        };
+
+        match self {
+            AuditEvent::Post { word, guild_id, .. } => {
+                format!("🦜 Posted `{}` {}", word, place(guild_id))
+            }
+            AuditEvent::Command {
+                command,
+                user_id,
+                guild_id,
+                ..
+            } => format!(
+                "🔧 <@{}> used `{}` {}",
+                user_id.unwrap_or_default(),
+                command,
+                place(guild_id)
+            ),
+            AuditEvent::Error {
+                failure,
+                error,
+                guild_id,
+                ..
+            } => format!("⚠️ {:?} error {}: {}", failure, place(guild_id), error),
+        }
+    }
+}
+
+/// Body of the request: the event, with a `content` so that Discord webhooks
+/// accept it too. Other receivers can ignore it.
+#[derive(Serialize)]
+struct Payload<'a> {
+    #[serde(flatten)]
+    event: &'a AuditEvent,
+    content: String,
+}
+
+/// Post `event` to the audit webhook at `url`.
+pub async fn send_audit_event(
+    client: &reqwest::Client,
+    url: &str,
+    event: &AuditEvent,
+) -> anyhow::Result<()> {
+    let payload = Payload {
+        event,
+        content: event.describe(),
+    };
+
+    client
+        .post(url)
+        .json(&payload)
+        .send()
+        .await
+        .context("sending audit event")?
+        .error_for_status()
+        .context("delivering audit event")?;
+
+    Ok(())
+}
+
+/// Sends the events to `--audit-webhook-url`. Cheap to clone: the clones
+/// share the connections of the same client.
+#[derive(Debug, Clone)]
+pub struct Auditor {
+    client: reqwest::Client,
+    url: String,
+}
+
+impl Auditor {
+    pub fn new(url: String) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            url,
+        }
+    }
+
+    /// Send `event` in the background, nothing waits for the audit log.
+    /// Failing is only logged: auditing it would fail the same way.
+    pub fn record(&self, event: AuditEvent) {
+        let auditor = self.clone();
+
+        tokio::spawn(async move {
+            if let Err(e) = send_audit_event(&auditor.client, &auditor.url, &event).await {
+                warn!("Error sending an audit event: {:#}", e);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{http_server, shutdown::Shutdown};
+    use chrono::TimeZone;
+    use hyper::{Body, Request, StatusCode};
+    use tokio::sync::mpsc;
+
+    fn time() -> DateTime<Utc> {
+        Utc.timestamp_opt(1_600_000_000, 0).unwrap()
+    }
+
+    fn post() -> AuditEvent {
+        AuditEvent::Post {
+            word: "pizza".to_owned(),
+            channel_id: Some(42),
+            guild_id: Some(7),
+            timestamp: time(),
+        }
+    }
+
+    #[test]
+    fn payloads() {
+        let value = serde_json::to_value(post()).unwrap();
+        assert_eq!(
+            serde_json::json!({
+                "event": "post",
+                "word": "pizza",
+                "channel_id": "42",
+                "guild_id": "7",
+                "timestamp": "2020-09-13T12:26:40Z",
+            }),
+            value
+        );
+
+        let error = AuditEvent::Error {
+            failure: Failure::LostChannel,
+            error: "Missing Access".to_owned(),
+            guild_id: None,
+            timestamp: time(),
+        };
+        let value = serde_json::to_value(&error).unwrap();
+        assert_eq!("error", value["event"]);
+        assert_eq!("lost_channel", value["failure"]);
+        assert!(value["guild_id"].is_null());
+
+        let command = AuditEvent::Command {
+            command: "set".to_owned(),
+            user_id: Some(99),
+            channel_id: Some(42),
+            guild_id: Some(7),
+            timestamp: time(),
+        };
+        assert_eq!("command", serde_json::to_value(&command).unwrap()["event"]);
+        assert_eq!("🔧 <@99> used `set` in server 7", command.describe());
+    }
+
+    /// A webhook answering `status` to everything, with the bodies it
+    /// received.
+    fn webhook(status: StatusCode) -> (String, mpsc::UnboundedReceiver<String>, Shutdown) {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/audit", listener.local_addr().unwrap());
+        let (sent, received) = mpsc::unbounded_channel();
+        let mut shutdown = Shutdown::default();
+
+        let handler = move |request: Request<Body>| {
+            let sent = sent.clone();
+
+            async move {
+                let body = hyper::body::to_bytes(request.into_body()).await.unwrap();
+                sent.send(String::from_utf8(body.to_vec()).unwrap())
+                    .unwrap();
+
+                http_server::response(status, "text/plain", String::new())
+            }
+        };
+        shutdown.spawn(move |stop| async move {
+            http_server::serve(listener, handler, stop).await.unwrap();
+        });
+
+        (url, received, shutdown)
+    }
+
+    #[tokio::test]
+    async fn sending() {
+        let (url, mut received, _shutdown) = webhook(StatusCode::NO_CONTENT);
+
+        let client = reqwest::Client::new();
+        send_audit_event(&client, &url, &post()).await.unwrap();
+
+        let body: serde_json::Value =
+            serde_json::from_str(&received.recv().await.unwrap()).unwrap();
+        assert_eq!("post", body["event"]);
+        assert_eq!("pizza", body["word"]);
+        assert_eq!("42", body["channel_id"]);
+        assert_eq!("🦜 Posted `pizza` in server 7", body["content"]);
+
+        // in the background, through the same client
+        let auditor = Auditor::new(url);
+        auditor.record(post());
+        assert!(received.recv().await.unwrap().contains(r#""event":"post""#));
+    }
+
+    #[tokio::test]
+    async fn refused() {
+        let (url, mut received, _shutdown) = webhook(StatusCode::BAD_REQUEST);
+
+        let client = reqwest::Client::new();
+        assert!(send_audit_event(&client, &url, &post()).await.is_err());
+        assert!(received.recv().await.is_some());
+    }
+}
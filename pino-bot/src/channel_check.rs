@@ -0,0 +1,146 @@
+use serenity::model::permissions::Permissions;
+use std::fmt;
+
+/// What pino is about to post.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Style {
+    Text,
+    /// Like the weekly summary, which also needs Embed Links.
+    Embed,
+}
+
+/// Why pino shouldn't post in a channel.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Unfit {
+    /// Pino can't see the channel or can't send messages there, e.g. an
+    /// announcement channel.
+    CantSend,
+    /// Pino can send messages, but they would come without their embed.
+    CantEmbed,
+    /// Slowmode of this many seconds, longer than `--max-slowmode`: pino's
+    /// messages would be refused.
+    Slowmode(u16),
+}
+
+impl fmt::Display for Unfit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Unfit::CantSend => write!(f, "missing the Send Messages permission"),
+            Unfit::CantEmbed => write!(f, "missing the Embed Links permission"),
+            Unfit::Slowmode(seconds) => write!(f, "slowmode of {} seconds", seconds),
+        }
+    }
+}
+
+/// Whether pino can post `style` in a channel where it has `permissions`,
+/// with a slowmode of `slowmode` seconds, 0 if off. Slowmode longer than
+/// `max_slowmode` seconds is avoided, unless pino can manage the messages or
+/// the channel: Discord doesn't slow those down.
+pub fn check(
+    permissions: Permissions,
+    slowmode: u16,
+    style: Style,
+    max_slowmode: u64,
+) -> Result<(), Unfit> {
+    if !permissions.contains(Permissions::VIEW_CHANNEL | Permissions::SEND_MESSAGES) {
+        return Err(Unfit::CantSend);
+    }
+
+    if style == Style::Embed && !permissions.embed_links() {
+        return Err(Unfit::CantEmbed);
+    }
+
+    let exempt =
+        permissions.intersects(Permissions::MANAGE_MESSAGES | Permissions::MANAGE_CHANNELS);
+
+    if u64::from(slowmode) > max_slowmode && !exempt {
+        return Err(Unfit::Slowmode(slowmode));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MINUTE: u64 = 60;
+
+    fn member() -> Permissions {
+        Permissions::VIEW_CHANNEL
+            | Permissions::SEND_MESSAGES
+            | Permissions::EMBED_LINKS
+            | Permissions::READ_MESSAGE_HISTORY
+    }
+
+    #[test]
+    fn permissions() {
+        assert_eq!(Ok(()), check(member(), 0, Style::Text, MINUTE));
+        assert_eq!(Ok(()), check(member(), 0, Style::Embed, MINUTE));
+
+        // announcement channels only let the admins talk
+        let read_only = member() - Permissions::SEND_MESSAGES;
+        assert_eq!(
+            Err(Unfit::CantSend),
+            check(read_only, 0, Style::Text, MINUTE)
+        );
+        let hidden = member() - Permissions::VIEW_CHANNEL;
+        assert_eq!(Err(Unfit::CantSend), check(hidden, 0, Style::Text, MINUTE));
+
+        let no_embeds = member() - Permissions::EMBED_LINKS;
+        assert_eq!(Ok(()), check(no_embeds, 0, Style::Text, MINUTE));
+        assert_eq!(
+            Err(Unfit::CantEmbed),
+            check(no_embeds, 0, Style::Embed, MINUTE)
+        );
+
+        assert_eq!(
+            Ok(()),
+            check(
+                Permissions::ADMINISTRATOR | member(),
+                0,
+                Style::Embed,
+                MINUTE
+            )
+        );
+        assert_eq!(
+            Err(Unfit::CantSend),
+            check(Permissions::empty(), 0, Style::Text, MINUTE)
+        );
+    }
+
+    #[test]
+    fn slowmode() {
+        assert_eq!(Ok(()), check(member(), 60, Style::Text, MINUTE));
+        assert_eq!(
+            Err(Unfit::Slowmode(61)),
+            check(member(), 61, Style::Text, MINUTE)
+        );
+        assert_eq!(Err(Unfit::Slowmode(1)), check(member(), 1, Style::Text, 0));
+        assert_eq!(Ok(()), check(member(), 0, Style::Text, 0));
+
+        // the moderators aren't slowed down
+        for exempt in &[Permissions::MANAGE_MESSAGES, Permissions::MANAGE_CHANNELS] {
+            assert_eq!(
+                Ok(()),
+                check(member() | *exempt, 21600, Style::Text, MINUTE)
+            );
+        }
+
+        // a channel pino can't post in at all says so first
+        let read_only = member() - Permissions::SEND_MESSAGES;
+        assert_eq!(
+            Err(Unfit::CantSend),
+            check(read_only, 3600, Style::Text, MINUTE)
+        );
+    }
+
+    #[test]
+    fn reasons() {
+        assert_eq!("slowmode of 120 seconds", Unfit::Slowmode(120).to_string());
+        assert_eq!(
+            "missing the Send Messages permission",
+            Unfit::CantSend.to_string()
+        );
+    }
+}
@@ -0,0 +1,338 @@
+use serde::{Serialize, Serializer};
+use std::{ffi::OsString, net::SocketAddr, num::NonZeroU64, path::PathBuf};
+use structopt::StructOpt;
+
+use crate::{duration, i18n, logging, reply_context, selection, self_echo, sharding, tracking};
+
+/// Shown in place of the secret options by `!pino config`.
+const REDACTED: &str = "***";
+
+/// Pino repeats the most said words of your Discord server. Without a
+/// subcommand, it runs.
+// Parsed once, the size of `run` doesn't matter
+#[allow(clippy::large_enum_variant)]
+#[derive(StructOpt, Debug)]
+#[structopt(name = "pino-bot")]
+pub enum Cli {
+    /// Connect to Discord and start listening, the default
+    Run(Options),
+    /// Check a config file without starting, listing every problem
+    Validate {
+        #[structopt(long)]
+        config: PathBuf,
+    },
+    /// Write the words of a server in a state file to a file that `!pino import` accepts
+    Export {
+        #[structopt(long)]
+        state_file: PathBuf,
+        #[structopt(long)]
+        out: PathBuf,
+        /// The id of the server, else the words said outside of any
+        #[structopt(long)]
+        guild: Option<u64>,
+    },
+    /// Add the words of a file from `!pino export` to a server in a state file, which is created if missing. Pino must not be running with it
+    Import {
+        #[structopt(long)]
+        state_file: PathBuf,
+        #[structopt(long = "in")]
+        input: PathBuf,
+        /// The id of the server, else the words said outside of any
+        #[structopt(long)]
+        guild: Option<u64>,
+    },
+    /// Print the most said words of a server in a state file
+    Stats {
+        #[structopt(long)]
+        state_file: PathBuf,
+        /// How many words
+        #[structopt(long, default_value = "10")]
+        top: usize,
+        /// The id of the server, else the words said outside of any
+        #[structopt(long)]
+        guild: Option<u64>,
+    },
+}
+
+/// The subcommands, and what clap answers to by itself.
+const SUBCOMMANDS: &[&str] = &[
+    "run",
+    "validate",
+    "export",
+    "import",
+    "stats",
+    "help",
+    "-h",
+    "--help",
+    "-V",
+    "--version",
+];
+
+/// The arguments with `run` in front when there's no subcommand, so that
+/// `pino-bot --token-file token` keeps working.
+pub fn default_to_run(mut args: Vec<OsString>) -> Vec<OsString> {
+    let subcommand = args
+        .get(1)
+        .and_then(|arg| arg.to_str())
+        .is_some_and(|arg| SUBCOMMANDS.contains(&arg));
+
+    if !subcommand {
+        args.insert(1.min(args.len()), "run".into());
+    }
+
+    args
+}
+
+#[derive(StructOpt, Serialize, Debug)]
+pub struct Options {
+    /// The discord token to use, if there's neither `--token-file` nor `DISCORD_TOKEN`. Avoid it, arguments are visible to other users
+    #[structopt(long, env = "PINO_TOKEN", hide_env_values = true)]
+    #[serde(serialize_with = "redact")]
+    pub token: Option<String>,
+    /// File containing the discord token. Wins over `DISCORD_TOKEN` and `--token`
+    #[structopt(long)]
+    pub token_file: Option<PathBuf>,
+    /// Min interval between messages, e.g. `10m` or `1h30m`. A bare number is seconds
+    #[structopt(long, default_value = "10m", parse(try_from_str = duration::parse_seconds))]
+    pub interval_low: u64,
+    /// Max interval between messages, e.g. `20m` or `1h30m`. A bare number is seconds
+    #[structopt(long, default_value = "20m", parse(try_from_str = duration::parse_seconds))]
+    pub interval_high: u64,
+    /// How long after connecting pino only listens, e.g. `5m`: messages on demand are still sent. A bare number is seconds, `0` turns it off
+    #[structopt(long, default_value = "5m", parse(try_from_str = duration::parse_seconds_or_zero))]
+    pub warmup: u64,
+    /// Words are separated by a whitespace
+    #[structopt(long, default_value = "^[a-zA-ZàáèéìíòóùúÀÁÈÉÌÍÒÓÙÚ']+$")]
+    pub word_regex: String,
+    /// Unicode normalization of the words before matching them: `none`, `nfc`, `nfd`, `nfkc` or `nfkd`. `nfc` and `nfkc` merge the words typed with combining accents with the precomposed ones, `nfkc` also folds ligatures and full width letters. With `nfd` and `nfkd` the accents are separate characters, the word regex must accept them
+    #[structopt(long, default_value = "none")]
+    pub unicode_normalize: tracking::Normalization,
+    /// Instances of words older than this, e.g. `30m` or `2h`, are deleted to save space and forget dead memes. A bare number is seconds
+    #[structopt(long, default_value = "30m", parse(try_from_str = duration::parse_seconds))]
+    pub max_age: u64,
+    /// Max random boost to word count. If set to 3, a word said 8 times might be texted even if there's a word texted 10 times.
+    #[structopt(long, default_value = "10")]
+    pub max_boost: usize,
+    /// What makes a word the one to say: `count`, how many times it was said, or `trending`, how fast it's growing: its count in the last `--trending-window` over the one in the window before. The boost only applies to `count`
+    #[structopt(long, default_value = "count")]
+    pub scoring: selection::Scoring,
+    /// Length of the two windows compared by `--scoring trending` and `!pino top --trending`, e.g. `5m`. A bare number is seconds
+    #[structopt(long, default_value = "5m", parse(try_from_str = duration::parse_seconds))]
+    pub trending_window: u64,
+    /// If no words have been said, the bot will print this word as default. Leave blank to not print anything by default. Servers can change it with `!pino settings`
+    #[structopt(long)]
+    pub default_word: Option<String>,
+    /// File where the learned words are saved, so that they survive restarts. Leave blank to not persist anything.
+    #[structopt(long)]
+    pub state_file: Option<PathBuf>,
+    /// Interval between automatic saves of the state file, e.g. `5m`. A bare number is seconds
+    #[structopt(long, default_value = "5m", parse(try_from_str = duration::parse_seconds))]
+    pub save_interval: u64,
+    /// Database where every word instance is stored, e.g. `sqlite://pino.db`. Replaces the state file.
+    #[cfg(feature = "sqlite")]
+    #[structopt(long, conflicts_with = "state_file")]
+    pub db: Option<String>,
+    /// File containing words to ignore, one per line. Reloaded on SIGHUP.
+    #[structopt(long)]
+    pub stop_words_file: Option<PathBuf>,
+    /// Amount of word count snapshots (taken once a minute) kept in memory for trend analysis
+    #[structopt(long, default_value = "60")]
+    pub snapshot_history: usize,
+    /// Post words through this Discord webhook, with the word as username, instead of in the most recent channel
+    #[structopt(long)]
+    #[serde(serialize_with = "redact")]
+    pub webhook_url: Option<String>,
+    /// Send a JSON event to this webhook whenever pino posts a word, someone uses a command that isn't for everyone, or Discord refuses a message. Discord webhooks work too
+    #[structopt(long)]
+    #[serde(serialize_with = "redact")]
+    pub audit_webhook_url: Option<String>,
+    /// Template of the posted messages (default `{word}`). `{word}` is replaced with the word and `{count}` with how many times it has been said. Servers can change it with `!pino settings`
+    #[structopt(long)]
+    pub message_template: Option<String>,
+    /// Prefix of the commands, e.g. `?pino`. Servers can change it with `!pino prefix`
+    #[structopt(long, default_value = "!pino")]
+    pub prefix: String,
+    /// Post an emoji matching the word instead of the word itself. Servers can change it with `!pino settings`
+    #[structopt(long)]
+    pub emoji_mode: bool,
+    /// JSON file mapping words to emoji, e.g. `{"pizza": "🍕"}`. Words without an emoji are spelled out in letter emoji
+    #[structopt(long)]
+    pub emoji_map_file: Option<PathBuf>,
+    /// Max amount of words remembered. When there are more, the ones that haven't been said for the longest time are forgotten
+    #[structopt(long)]
+    pub max_words: Option<usize>,
+    /// 👎 reactions needed within 10 minutes to delete a message of pino and block its word for an hour
+    #[structopt(long, default_value = "3")]
+    pub veto_count: usize,
+    /// DM the owner of the application when the same kind of error happens more than this many times in an hour in a server, at most once a day each. `0` turns it off
+    #[structopt(long, default_value = "10")]
+    pub error_alerts: usize,
+    /// Count the messages by day of the week and hour, for `!pino heatmap`
+    #[structopt(long)]
+    pub heatmap: bool,
+    /// Max amount of words in the image of `!pino wordcloud`, the most said ones
+    #[structopt(long, default_value = "50")]
+    pub wordcloud_max_words: usize,
+    /// TrueType or OpenType font of `!pino wordcloud`. Without it, the word cloud is sent as text
+    #[structopt(long)]
+    pub wordcloud_font_file: Option<PathBuf>,
+    /// Max amount of words learned from a single user in 10 seconds, so that flooding the chat doesn't decide what pino says. Unlimited by default
+    #[structopt(long)]
+    pub rate_limit_words: Option<usize>,
+    /// Messages longer than this many characters, like pasted code or the output of other bots, aren't learned from
+    #[structopt(long, default_value = "2000")]
+    pub max_message_length: usize,
+    /// What is learned from messages made only of words pino said within `--max-age`, like another parrot bot or a bridge repeating it: `ignore` nothing, `halve` every other one, `full` everything
+    #[structopt(long, default_value = "ignore")]
+    pub self_echo: self_echo::SelfEcho,
+    /// Channels with a slowmode longer than this, e.g. `5m`, aren't posted in, unless pino can manage their messages. A bare number is seconds, `0` avoids every channel with slowmode
+    #[structopt(long, default_value = "5m", parse(try_from_str = duration::parse_seconds_or_zero))]
+    pub max_slowmode: u64,
+    /// How the words are posted: `plain` messages, or with `reference` as a reply to the last message saying the word in the channel, for context
+    #[structopt(long, default_value = "plain")]
+    pub reply_style: reply_context::ReplyStyle,
+    /// Count which words are said in the same message, for `!pino cooccurrence`
+    #[structopt(long)]
+    pub track_cooccurrence: bool,
+    /// Register `/pino` for every guild at once instead of in each guild at startup. Discord takes up to an hour to show global commands
+    #[structopt(long)]
+    pub global_slash_commands: bool,
+    /// Greet the new members of the server of this channel there, telling them what everyone is talking about. Needs the Server Members intent
+    #[structopt(long)]
+    pub welcome_channel: Option<NonZeroU64>,
+    /// Template of the greetings of `--welcome-channel`, `{user}` is replaced with a mention of the new member and `{word}` with the most said word. In the language of the server by default
+    #[structopt(long)]
+    pub welcome_template: Option<String>,
+    /// Every Sunday at midnight UTC, post the summary of the week of the server of this channel there
+    #[structopt(long)]
+    pub summary_channel: Option<NonZeroU64>,
+    /// Seed of the random choices, i.e. the time between messages and the boosts, to make them repeatable. Random by default
+    #[structopt(long)]
+    pub seed: Option<u64>,
+    /// Language of the replies to the commands, `en` (default) or `it`. Servers can change it with `!pino settings`
+    #[structopt(long)]
+    pub language: Option<i18n::Lang>,
+    /// What is logged, e.g. `debug` or `info,serenity=warn`. Wins over `RUST_LOG`, `info` by default
+    #[structopt(long)]
+    pub log_level: Option<String>,
+    /// How the log is written: `text` or `json`, one object per line
+    #[structopt(long, default_value = "text")]
+    pub log_format: logging::LogFormat,
+    /// Serve Prometheus metrics at `/metrics` on this address, e.g. `127.0.0.1:9091`. Off by default
+    #[structopt(long)]
+    pub metrics_addr: Option<SocketAddr>,
+    /// Serve the `/healthz` (background tasks running) and `/readyz` (connected to Discord) probes on this address, e.g. `127.0.0.1:8081`. Off by default
+    #[structopt(long)]
+    pub health_addr: Option<SocketAddr>,
+    /// `/readyz` fails when Discord sent nothing for this many minutes
+    #[structopt(long, default_value = "10")]
+    pub heartbeat_minutes: u64,
+    /// Split the connection to Discord into this many shards, all run by this process. Needed by bots in more than 2500 servers
+    #[structopt(long)]
+    pub shards: Option<u32>,
+    /// Run only some of the shards, e.g. `0..4/8` runs shards 0 to 3 of 8 while other processes run the rest
+    #[structopt(long, conflicts_with = "shards")]
+    pub shard_range: Option<sharding::ShardRange>,
+    /// Don't connect to Discord: read `[#channel] [@user] message` lines from stdin, and print what would be sent and when. Nothing is loaded nor saved
+    #[structopt(long)]
+    pub dry_run: bool,
+    /// How many times faster time passes in `--dry-run`, e.g. `60` to wait a second instead of a minute
+    #[structopt(long, default_value = "1")]
+    pub time_scale: f64,
+    /// TOML file with the options, named like the flags with underscores (e.g. `max_boost = 3`), and the settings of single servers in `[guilds.<id>]` tables. The command line wins over the file
+    #[structopt(long)]
+    pub config: Option<PathBuf>,
+}
+
+/// Serialize a secret option as [`REDACTED`], if it's there.
+fn redact<S: Serializer>(secret: &Option<String>, serializer: S) -> Result<S::Ok, S::Error> {
+    secret
+        .as_ref()
+        .map(|_secret| REDACTED)
+        .serialize(serializer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn subcommands() {
+        let args = |args: &[&str]| default_to_run(args.iter().map(OsString::from).collect());
+
+        assert_eq!(args(&["pino-bot", "run"]), args(&["pino-bot"]));
+        assert_eq!(
+            args(&["pino-bot", "run", "--max-boost", "3"]),
+            args(&["pino-bot", "--max-boost", "3"])
+        );
+        assert_eq!(vec!["pino-bot", "--help"], args(&["pino-bot", "--help"]));
+
+        match Cli::from_iter(args(&["pino-bot", "--max-boost", "3"])) {
+            Cli::Run(options) => assert_eq!(3, options.max_boost),
+            cli => panic!("not run: {:?}", cli),
+        }
+
+        match Cli::from_iter(args(&["pino-bot", "stats", "--state-file", "pino.json"])) {
+            Cli::Stats {
+                state_file,
+                top,
+                guild,
+            } => {
+                assert_eq!(Path::new("pino.json"), state_file);
+                assert_eq!(10, top);
+                assert_eq!(None, guild);
+            }
+            cli => panic!("not stats: {:?}", cli),
+        }
+
+        let import = &[
+            "pino-bot",
+            "import",
+            "--state-file",
+            "a.json",
+            "--in",
+            "b.json",
+        ];
+        assert!(matches!(Cli::from_iter(args(import)), Cli::Import { .. }));
+        // `export` needs `--out`, and the options of `run` are only for it
+        assert!(
+            Cli::from_iter_safe(args(&["pino-bot", "export", "--state-file", "a.json"])).is_err()
+        );
+        assert!(Cli::from_iter_safe(args(&[
+            "pino-bot",
+            "validate",
+            "--config",
+            "pino.toml",
+            "--max-boost",
+            "3"
+        ]))
+        .is_err());
+    }
+
+    #[test]
+    fn redacted_options() {
+        let options = Options::from_iter(&[
+            "pino-bot",
+            "--token",
+            "secret-token",
+            "--webhook-url",
+            "https://discord.com/api/webhooks/secret",
+            "--audit-webhook-url",
+            "https://discord.com/api/webhooks/secret-audit",
+            "--max-boost",
+            "3",
+        ]);
+        let json = serde_json::to_string_pretty(&options).unwrap();
+
+        assert!(!json.contains("secret"));
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(REDACTED, value["token"]);
+        assert_eq!(REDACTED, value["webhook_url"]);
+        assert_eq!(REDACTED, value["audit_webhook_url"]);
+        assert_eq!(3, value["max_boost"]);
+        assert_eq!("none", value["unicode_normalize"]);
+        // options that weren't given are null
+        assert!(value["token_file"].is_null());
+    }
+}
@@ -0,0 +1,188 @@
+use anyhow::Context;
+use serenity::{
+    async_trait,
+    builder::{CreateActionRow, CreateEmbed, CreateMessage},
+    model::{
+        channel::{Message, ReactionType},
+        id::{ChannelId, GuildId, MessageId, UserId},
+        permissions::Permissions,
+    },
+};
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::{messenger::Messenger, Messaging};
+
+/// Where a command was run and how to answer it, so that the same handler
+/// serves `!pino top` and `/pino top`.
+#[async_trait]
+pub trait CommandIo: Send + Sync {
+    fn guild_id(&self) -> Option<GuildId>;
+
+    fn channel_id(&self) -> ChannelId;
+
+    /// Who ran the command.
+    fn author_id(&self) -> UserId;
+
+    /// Permissions of the author in the channel. Outside of guilds nobody has
+    /// any permission.
+    async fn permissions(&self, context: &serenity::client::Context) -> Permissions;
+
+    async fn reply_text(
+        &self,
+        context: &serenity::client::Context,
+        content: String,
+    ) -> serenity::Result<()>;
+
+    /// Reply with `embed`, under `content` if any.
+    async fn reply_embed(
+        &self,
+        context: &serenity::client::Context,
+        content: Option<String>,
+        embed: CreateEmbed,
+    ) -> serenity::Result<()>;
+
+    /// Reply with `embed` and buttons or menus under it. Returns the id of
+    /// the reply, to take them away later.
+    async fn reply_components(
+        &self,
+        context: &serenity::client::Context,
+        embed: CreateEmbed,
+        components: Vec<CreateActionRow>,
+    ) -> serenity::Result<MessageId>;
+
+    /// Reply so that only the author sees it, where Discord allows it.
+    async fn reply_ephemeral(
+        &self,
+        context: &serenity::client::Context,
+        content: String,
+    ) -> serenity::Result<()>;
+
+    /// Answer without words, e.g. during a cooldown.
+    async fn react(&self, context: &serenity::client::Context, emoji: &str)
+        -> serenity::Result<()>;
+}
+
+/// How the commands talk on Discord.
+async fn messenger(context: &serenity::client::Context) -> Arc<dyn Messenger> {
+    context
+        .data
+        .read()
+        .await
+        .get::<Messaging>()
+        .expect("Messaging to be in context")
+        .clone()
+}
+
+/// A prefix command answers in the channel of the message. Messages can't be
+/// ephemeral.
+#[async_trait]
+impl CommandIo for Message {
+    fn guild_id(&self) -> Option<GuildId> {
+        self.guild_id
+    }
+
+    fn channel_id(&self) -> ChannelId {
+        self.channel_id
+    }
+
+    fn author_id(&self) -> UserId {
+        self.author.id
+    }
+
+    async fn permissions(&self, context: &serenity::client::Context) -> Permissions {
+        let guild_id = match self.guild_id {
+            Some(guild_id) => guild_id,
+            None => return Permissions::empty(),
+        };
+
+        let permissions = async {
+            let guild = guild_id.to_partial_guild(&context.http).await?;
+            let member = guild.member(&context.http, self.author.id).await?;
+            let channel = self
+                .channel_id
+                .to_channel(context)
+                .await?
+                .guild()
+                .context("not a guild channel")?;
+
+            Ok::<_, anyhow::Error>(guild.user_permissions_in(&channel, &member))
+        }
+        .await;
+
+        permissions.unwrap_or_else(|e| {
+            warn!("Error fetching permissions of {}: {:#}", self.author.id, e);
+            Permissions::empty()
+        })
+    }
+
+    async fn reply_text(
+        &self,
+        context: &serenity::client::Context,
+        content: String,
+    ) -> serenity::Result<()> {
+        messenger(context)
+            .await
+            .send_text(self.channel_id, &content)
+            .await
+            .map(drop)
+    }
+
+    async fn reply_embed(
+        &self,
+        context: &serenity::client::Context,
+        content: Option<String>,
+        embed: CreateEmbed,
+    ) -> serenity::Result<()> {
+        match content {
+            None => messenger(context)
+                .await
+                .send_embed(self.channel_id, embed)
+                .await
+                .map(drop),
+            Some(content) => self
+                .channel_id
+                .send_message(
+                    &context.http,
+                    CreateMessage::new().content(content).embed(embed),
+                )
+                .await
+                .map(drop),
+        }
+    }
+
+    async fn reply_components(
+        &self,
+        context: &serenity::client::Context,
+        embed: CreateEmbed,
+        components: Vec<CreateActionRow>,
+    ) -> serenity::Result<MessageId> {
+        messenger(context)
+            .await
+            .send_components(self.channel_id, embed, components)
+            .await
+    }
+
+    async fn reply_ephemeral(
+        &self,
+        context: &serenity::client::Context,
+        content: String,
+    ) -> serenity::Result<()> {
+        self.reply_text(context, content).await
+    }
+
+    async fn react(
+        &self,
+        context: &serenity::client::Context,
+        emoji: &str,
+    ) -> serenity::Result<()> {
+        messenger(context)
+            .await
+            .react(
+                self.channel_id,
+                self.id,
+                ReactionType::Unicode(emoji.to_owned()),
+            )
+            .await
+    }
+}
@@ -0,0 +1,4264 @@
+use anyhow::Context;
+use chrono::{DateTime, Duration, Utc};
+use pino_core::summary::{week_start, TOP_WORDS};
+use serenity::{
+    builder::{
+        CreateAttachment, CreateCommand, CreateEmbed, CreateInteractionResponse,
+        CreateInteractionResponseMessage, CreateMessage, EditMessage,
+    },
+    model::{
+        application::{CommandInteraction, ComponentInteraction, ComponentInteractionDataKind},
+        channel::{Message, ReactionType},
+        id::{ChannelId, GuildId, UserId},
+        permissions::Permissions,
+    },
+    prelude::*,
+    utils::MessageBuilder,
+};
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    future::Future,
+    hash::Hash,
+    pin::Pin,
+    sync::{Arc, RwLock},
+};
+use tracing::{debug, error, info_span, warn, Instrument};
+
+use crate::{
+    audit,
+    blocklist::{Blocklist, Pattern},
+    command_guard::{CommandGuard, Verdict},
+    command_io::CommandIo,
+    components::{self, Action, ComponentId, View},
+    cooccurrence,
+    debug_dump::{self, DebugDump},
+    delivery, duration, guild_key,
+    guild_settings::{self, SettingError, Settings},
+    heatmap, histogram,
+    i18n::{tr, Lang},
+    ids,
+    messenger::Messenger,
+    pause::{format_duration, PauseState},
+    persistence::{self, FrequencySnapshot, ImportError},
+    runtime_config::ConfigError,
+    scheduled_posts::{self, ScheduledPost},
+    selection::{Scoring, Selection},
+    sent_log,
+    slash::{self, OptionKind, SlashCommand, SlashOption},
+    stats, summary, tracking, trending, voting, wordcloud, ActivityCounters, ActivityHeatmap,
+    BlockedWords, ChannelActivity, Config, Cooccurrences, GuildSettings, MessageMap, Messaging,
+    MetricsRegistry, Owner, Paused, ReadySession, RecentChannel, Schedule, ScheduledPosts,
+    SentMessages, Session, SnapshotHistory, StartupOptions, StartupTime, TaskSupervisor,
+    WeeklySummaries, WordMap, WordOfTheWeek, WordStore, WordcloudSettings,
+};
+#[cfg(feature = "sqlite")]
+use crate::{db, DbWriter};
+
+/// Default prefix of the commands, e.g. `!pino top 10`, if neither the command
+/// line nor the guild picks another one.
+pub const PREFIX: &str = "!pino";
+/// Max length of a custom prefix, in characters.
+const PREFIX_MAX_LENGTH: usize = 5;
+
+/// Amount of words listed by `!pino cooccurrence`.
+const COOCCURRENCE_COUNT: usize = 10;
+
+/// Default amount of words listed by `!pino top`.
+const TOP_DEFAULT: usize = 5;
+/// Max amount of words listed by `!pino top`, which is also the max amount
+/// of fields in an embed.
+const TOP_MAX: usize = 25;
+/// `!pino top` can be used once every this many seconds in each channel.
+const TOP_COOLDOWN_SECONDS: i64 = 10;
+/// `!pino speak` can be used once every this many seconds in each channel.
+const SPEAK_COOLDOWN_SECONDS: i64 = 60;
+
+/// Length of the buckets of the `!pino stats` sparkline.
+const STATS_BUCKET_MINUTES: i64 = 10;
+/// Max amount of buckets of the `!pino stats` sparkline.
+const STATS_MAX_BUCKETS: usize = 48;
+
+/// Words in each page of `!pino leaderboard`.
+const LEADERBOARD_PAGE_SIZE: usize = 10;
+/// Seconds the invoker of `!pino reset` has to confirm.
+const RESET_TIMEOUT_SECONDS: i64 = 30;
+const CONFIRM: &str = "✅";
+/// Reaction to commands used during their cooldown, quieter than a reply.
+const COOLING_DOWN: &str = "⏳";
+
+/// Pages of the leaderboard can be flipped for this many seconds.
+const LEADERBOARD_TIMEOUT_SECONDS: i64 = 60;
+const PREVIOUS_PAGE: &str = "◀️";
+const NEXT_PAGE: &str = "▶️";
+
+/// Entries in each message of `!pino blocked`.
+const BLOCKLIST_PAGE_SIZE: usize = 20;
+
+/// Commands listed in each reply of `!pino help`, to stay below the length limit.
+const HELP_PAGE_SIZE: usize = 15;
+
+/// Default amount of messages listed by `!pino history`.
+const HISTORY_DEFAULT: usize = 10;
+/// Messages listed in each reply of `!pino history`, to stay below the length limit.
+const HISTORY_PAGE_SIZE: usize = 10;
+/// Channels in each message of `!pino activity`.
+const ACTIVITY_PAGE_SIZE: usize = 20;
+/// Words in each message of `!pino votes`.
+const VOTES_PAGE_SIZE: usize = 20;
+
+/// A parsed command: its name and the whitespace separated arguments.
+#[derive(Debug, PartialEq)]
+pub struct Invocation<'a> {
+    pub name: &'a str,
+    pub args: Vec<&'a str>,
+}
+
+/// Parse a message into a command invocation. Both `<prefix> <name> [args...]`
+/// and, if the prefix starts with a symbol, the short form `<symbol><name> [args...]`
+/// (e.g. `!top` for `!pino`) are accepted.
+/// Returns `None` if the message isn't a command.
+pub fn parse<'a>(prefix: &str, content: &'a str) -> Option<Invocation<'a>> {
+    let mut tokens = content.split_whitespace();
+    let first = tokens.next()?;
+
+    let name = if first == prefix {
+        tokens.next()?
+    } else {
+        let symbol = prefix.chars().next().filter(char::is_ascii_punctuation)?;
+        first.strip_prefix(symbol).filter(|name| !name.is_empty())?
+    };
+
+    Some(Invocation {
+        name,
+        args: tokens.collect(),
+    })
+}
+
+/// Parse a message starting with a mention of pino, e.g. `@pino top 10`.
+/// This works whatever the prefix is, so that nobody gets locked out by a broken one.
+pub fn parse_mention(bot_id: u64, content: &str) -> Option<Invocation<'_>> {
+    let mut tokens = content.split_whitespace();
+    let mention = tokens.next()?.strip_prefix("<@")?.strip_suffix('>')?;
+    // nickname mentions have an exclamation mark
+    let id = mention.strip_prefix('!').unwrap_or(mention);
+
+    if id.parse::<u64>().ok()? != bot_id {
+        return None;
+    }
+
+    Some(Invocation {
+        name: tokens.next()?,
+        args: tokens.collect(),
+    })
+}
+
+/// Check that a custom prefix is short and can be typed as a single word.
+pub fn validate_prefix(prefix: &str, lang: Lang) -> Result<(), String> {
+    let length = prefix.chars().count();
+
+    if length == 0 || length > PREFIX_MAX_LENGTH {
+        return Err(tr(lang, "prefix.length", &[("max", &PREFIX_MAX_LENGTH)]));
+    }
+
+    if prefix.chars().any(char::is_whitespace) {
+        return Err(tr(lang, "prefix.spaces", &[]));
+    }
+
+    Ok(())
+}
+
+/// Remembers when something was last used, to allow it once every cooldown.
+pub struct Cooldowns<K> {
+    last_use: HashMap<K, DateTime<Utc>>,
+}
+
+impl<K: Eq + Hash> Cooldowns<K> {
+    /// Returns true and records the use if the cooldown of `key` is over.
+    pub fn try_use(&mut self, key: K, now: DateTime<Utc>, cooldown: Duration) -> bool {
+        // forget expired entries, so that the map doesn't grow forever
+        self.last_use
+            .retain(|_key, &mut last| now - last < cooldown);
+
+        match self.last_use.entry(key) {
+            Entry::Occupied(_) => false,
+            Entry::Vacant(entry) => {
+                entry.insert(now);
+                true
+            }
+        }
+    }
+}
+
+impl<K> Default for Cooldowns<K> {
+    fn default() -> Self {
+        Self {
+            last_use: HashMap::new(),
+        }
+    }
+}
+
+/// Per user cooldowns of every command.
+pub struct UserCooldowns;
+
+impl TypeMapKey for UserCooldowns {
+    type Value = Arc<RwLock<CommandGuard>>;
+}
+
+/// Per channel cooldowns of the commands.
+pub struct CommandCooldowns;
+
+impl TypeMapKey for CommandCooldowns {
+    type Value = Arc<RwLock<Cooldowns<(ChannelId, &'static str)>>>;
+}
+
+/// A word with the amount of times it was said and the last time it was heard.
+#[derive(Debug, PartialEq)]
+pub struct TopWord {
+    pub word: String,
+    pub count: usize,
+    pub last_heard: DateTime<Utc>,
+}
+
+/// The `n` most said words that aren't blocked, most said first. Ties are
+/// sorted alphabetically.
+pub fn top_words(words: &WordMap, blocklist: &Blocklist, n: usize) -> Vec<TopWord> {
+    let mut top: Vec<_> = words
+        .iter()
+        .filter(|(word, _instances)| !blocklist.is_blocked(word))
+        .filter_map(|(word, instances)| {
+            let last_heard = *instances.as_ref().last()?;
+
+            Some(TopWord {
+                word: word.clone(),
+                count: instances.len(),
+                last_heard,
+            })
+        })
+        .collect();
+
+    top.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.word.cmp(&b.word)));
+    top.truncate(n);
+
+    top
+}
+
+/// How a command is used, e.g. "Usage: `!pino forget <word>`".
+fn usage(locale: Locale, usage: &str) -> String {
+    tr(
+        locale.lang,
+        "usage",
+        &[("usage", &format!("{} {}", locale.prefix, usage))],
+    )
+}
+
+/// Take `--trending` out of the arguments of `!pino top`, wherever it is.
+fn split_trending_flag<'a>(args: &[&'a str]) -> (bool, Vec<&'a str>) {
+    let trending = args.contains(&"--trending");
+    let rest = args
+        .iter()
+        .copied()
+        .filter(|arg| *arg != "--trending")
+        .collect();
+
+    (trending, rest)
+}
+
+/// Parse the optional amount of words of `!pino top`.
+fn parse_top_amount(args: &[&str], locale: Locale) -> Result<usize, String> {
+    match args.first() {
+        None => Ok(TOP_DEFAULT),
+        Some(arg) => match arg.parse::<usize>() {
+            Ok(0) | Err(_) => Err(tr(
+                locale.lang,
+                "top.invalid",
+                &[
+                    ("input", arg),
+                    ("example", &format!("{} top 10", locale.prefix)),
+                ],
+            )),
+            Ok(n) => Ok(n.min(TOP_MAX)),
+        },
+    }
+}
+
+/// Parse the optional amount of messages of `!pino history`.
+fn parse_history_amount(args: &[&str], locale: Locale) -> Result<usize, String> {
+    match args.first() {
+        None => Ok(HISTORY_DEFAULT),
+        Some(arg) => match arg.parse::<usize>() {
+            Ok(0) | Err(_) => Err(tr(
+                locale.lang,
+                "history.invalid",
+                &[
+                    ("input", arg),
+                    ("example", &format!("{} history 10", locale.prefix)),
+                ],
+            )),
+            Ok(n) => Ok(n.min(sent_log::LOG_CAPACITY)),
+        },
+    }
+}
+
+/// Discord markup showing a time relative to now, like "5 minutes ago".
+pub fn relative_time(time: DateTime<Utc>) -> String {
+    format!("<t:{}:R>", time.timestamp())
+}
+
+/// Tell when the next message is going to be sent.
+fn describe_next(
+    next: Option<DateTime<Utc>>,
+    pause: &PauseState,
+    now: DateTime<Utc>,
+    locale: Locale,
+) -> String {
+    let lang = locale.lang;
+
+    if !pause.is_paused(now) {
+        return match next {
+            Some(next) => tr(lang, "next.active", &[("time", &relative_time(next))]),
+            None => tr(lang, "next.waiting", &[]),
+        };
+    }
+
+    match pause.until() {
+        Some(until) => tr(
+            lang,
+            "next.paused_until",
+            &[("time", &relative_time(until))],
+        ),
+        None => tr(
+            lang,
+            "next.paused",
+            &[("resume", &format!("{} resume", locale.prefix))],
+        ),
+    }
+}
+
+/// Remove a word from the words of `guild`. Returns the amount of dropped instances.
+fn forget_word(words: &mut WordStore, guild: Option<GuildId>, word: &str) -> usize {
+    words.forget(guild_key(guild), &word.to_lowercase())
+}
+
+/// Forget the pairs of a word in `guild`, if pairs are counted.
+fn forget_pairs(data: &TypeMap, guild: Option<GuildId>, word: &str) {
+    if let Some(cooccurrences) = data.get::<Cooccurrences>() {
+        if let Some(pairs) = cooccurrences.write().unwrap().get_mut(&guild_key(guild)) {
+            cooccurrence::forget(pairs, &word.to_lowercase());
+        }
+    }
+}
+
+/// Amount of pages needed to show `len` words. There's always at least one page.
+fn page_count(len: usize) -> usize {
+    len.div_ceil(LEADERBOARD_PAGE_SIZE).max(1)
+}
+
+/// Parse the optional page number (starting from 1) of `!pino leaderboard`.
+/// Returns the 0-based page index.
+fn parse_page(args: &[&str], locale: Locale) -> Result<usize, String> {
+    match args.first() {
+        None => Ok(0),
+        Some(arg) => match arg.parse::<usize>() {
+            Ok(0) | Err(_) => Err(tr(
+                locale.lang,
+                "leaderboard.invalid",
+                &[
+                    ("input", arg),
+                    ("example", &format!("{} leaderboard 2", locale.prefix)),
+                ],
+            )),
+            Ok(page) => Ok(page - 1),
+        },
+    }
+}
+
+/// Text of a page of the leaderboard.
+fn leaderboard_page(top: &[TopWord], page: usize, lang: Lang) -> String {
+    top.iter()
+        .enumerate()
+        .skip(page * LEADERBOARD_PAGE_SIZE)
+        .take(LEADERBOARD_PAGE_SIZE)
+        .map(|(i, top)| {
+            tr(
+                lang,
+                "leaderboard.line",
+                &[
+                    ("rank", &(i + 1)),
+                    ("word", &top.word),
+                    ("count", &top.count),
+                ],
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// One line of `!pino settings` for each key, telling whether the guild changed it.
+fn settings_lines(guild: &Settings, cli: &Settings, lang: Lang) -> Vec<String> {
+    let resolved = guild.resolve(cli);
+
+    guild_settings::KEYS
+        .iter()
+        .map(|&key| {
+            let value = resolved.get(key).expect("every key to be resolved");
+
+            if guild.get(key).is_some() {
+                tr(
+                    lang,
+                    "settings.this_server",
+                    &[("key", &key), ("value", &value)],
+                )
+            } else {
+                format!("{} = {}", key, value)
+            }
+        })
+        .collect()
+}
+
+/// Who can use a command.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Permission {
+    Everyone,
+    /// Administrators and whoever can manage messages.
+    Moderator,
+    Admin,
+    /// The owner of the application pino runs as.
+    Owner,
+}
+
+impl Permission {
+    /// Who has the permission, to be used in sentences.
+    fn who(self, lang: Lang) -> String {
+        let key = match self {
+            Permission::Everyone => "who.everyone",
+            Permission::Moderator => "who.moderator",
+            Permission::Admin => "who.admin",
+            Permission::Owner => "who.owner",
+        };
+
+        tr(lang, key, &[])
+    }
+}
+
+/// Future returned by the command handlers.
+type CommandFuture<'a> = Pin<Box<dyn Future<Output = ()> + Send + 'a>>;
+
+/// The language of the guild a command is run in, and the prefix its hints show.
+#[derive(Debug, Clone, Copy)]
+pub struct Locale<'a> {
+    pub lang: Lang,
+    pub prefix: &'a str,
+}
+
+/// Runs a command that reads or answers the message itself, given the
+/// arguments after its name and the locale of the guild.
+type MessageHandler = for<'a> fn(
+    &'a serenity::client::Context,
+    &'a Message,
+    &'a [&'a str],
+    Locale<'a>,
+) -> CommandFuture<'a>;
+
+/// Runs a command that only talks through [`CommandIo`], like a [`MessageHandler`].
+type IoHandler = for<'a> fn(
+    &'a serenity::client::Context,
+    &'a dyn CommandIo,
+    &'a [&'a str],
+    Locale<'a>,
+) -> CommandFuture<'a>;
+
+/// How a command runs. Only the ones talking through [`CommandIo`] can be
+/// slash commands.
+enum Handler {
+    Message(MessageHandler),
+    /// Also `/pino <name>`, with these options as its arguments.
+    Slash(IoHandler, &'static [SlashOption]),
+}
+
+/// An entry of the command table. Its description is translated under `help.<name>`.
+pub struct Command {
+    pub name: &'static str,
+    /// Arguments of the command, as shown by `!pino help`.
+    pub usage: &'static str,
+    /// Checked before running the handler.
+    pub permission: Permission,
+    /// Seconds each user has to wait before using the command again. Administrators don't.
+    pub cooldown: i64,
+    handler: Handler,
+}
+
+/// Every command, in the order they are listed by `!pino help`.
+static COMMANDS: &[Command] = &[
+    Command {
+        name: "help",
+        usage: "",
+        permission: Permission::Everyone,
+        cooldown: 10,
+        handler: Handler::Message(|context, msg, _args, locale| {
+            Box::pin(send_help(context, msg, locale))
+        }),
+    },
+    Command {
+        name: "version",
+        usage: "",
+        permission: Permission::Everyone,
+        cooldown: 10,
+        handler: Handler::Message(|context, msg, _args, locale| {
+            Box::pin(send_version(context, msg, locale.lang))
+        }),
+    },
+    Command {
+        name: "uptime",
+        usage: "",
+        permission: Permission::Everyone,
+        cooldown: 10,
+        handler: Handler::Message(|context, msg, _args, locale| {
+            Box::pin(send_uptime(context, msg, locale.lang))
+        }),
+    },
+    Command {
+        name: "histogram",
+        usage: "",
+        permission: Permission::Everyone,
+        cooldown: 10,
+        handler: Handler::Message(|context, msg, _args, _locale| {
+            Box::pin(send_histogram(context, msg))
+        }),
+    },
+    Command {
+        name: "heatmap",
+        usage: "",
+        permission: Permission::Everyone,
+        cooldown: 10,
+        handler: Handler::Message(|context, msg, _args, locale| {
+            Box::pin(send_heatmap(context, msg, locale.lang))
+        }),
+    },
+    Command {
+        name: "server-summary",
+        usage: "",
+        permission: Permission::Everyone,
+        cooldown: 30,
+        handler: Handler::Message(|context, msg, _args, locale| {
+            Box::pin(send_summary(context, msg, locale.lang))
+        }),
+    },
+    Command {
+        name: "activity",
+        usage: "",
+        permission: Permission::Admin,
+        cooldown: 30,
+        handler: Handler::Message(|context, msg, _args, locale| {
+            Box::pin(send_activity(context, msg, locale.lang))
+        }),
+    },
+    Command {
+        name: "nominate",
+        usage: "<word>",
+        permission: Permission::Everyone,
+        cooldown: 10,
+        handler: Handler::Message(|context, msg, args, locale| {
+            Box::pin(nominate(context, msg, args, locale))
+        }),
+    },
+    Command {
+        name: "vote",
+        usage: "<word>",
+        permission: Permission::Everyone,
+        cooldown: 5,
+        handler: Handler::Message(|context, msg, args, locale| {
+            Box::pin(vote(context, msg, args, locale))
+        }),
+    },
+    Command {
+        name: "votes",
+        usage: "",
+        permission: Permission::Everyone,
+        cooldown: 10,
+        handler: Handler::Message(|context, msg, _args, locale| {
+            Box::pin(send_votes(context, msg, locale.lang))
+        }),
+    },
+    Command {
+        name: "wordcloud",
+        usage: "",
+        permission: Permission::Everyone,
+        cooldown: 30,
+        handler: Handler::Message(|context, msg, _args, locale| {
+            Box::pin(send_wordcloud(context, msg, locale.lang))
+        }),
+    },
+    Command {
+        name: "top",
+        usage: "[n] [--trending]",
+        permission: Permission::Everyone,
+        cooldown: 10,
+        handler: Handler::Slash(
+            |context, io, args, locale| Box::pin(send_top(context, io, args, locale)),
+            &[
+                SlashOption {
+                    name: "amount",
+                    description: "How many words",
+                    kind: OptionKind::Amount,
+                    required: false,
+                },
+                SlashOption {
+                    name: "trending",
+                    description: "The words growing the fastest instead",
+                    kind: OptionKind::Flag,
+                    required: false,
+                },
+            ],
+        ),
+    },
+    Command {
+        name: "stats",
+        usage: "<word>",
+        permission: Permission::Everyone,
+        cooldown: 5,
+        handler: Handler::Slash(
+            |context, io, args, locale| Box::pin(send_stats(context, io, args, locale)),
+            &[SlashOption {
+                name: "word",
+                description: "The word",
+                kind: OptionKind::Word,
+                required: true,
+            }],
+        ),
+    },
+    Command {
+        name: "cooccurrence",
+        usage: "<word>",
+        permission: Permission::Everyone,
+        cooldown: 5,
+        handler: Handler::Message(|context, msg, args, locale| {
+            Box::pin(send_cooccurrence(context, msg, args, locale))
+        }),
+    },
+    Command {
+        name: "leaderboard",
+        usage: "[page]",
+        permission: Permission::Everyone,
+        cooldown: 10,
+        handler: Handler::Message(|context, msg, args, locale| {
+            Box::pin(send_leaderboard(context, msg, args, locale))
+        }),
+    },
+    Command {
+        name: "speak",
+        usage: "",
+        permission: Permission::Everyone,
+        cooldown: 30,
+        handler: Handler::Slash(
+            |context, io, _args, locale| Box::pin(speak(context, io, locale.lang)),
+            &[],
+        ),
+    },
+    Command {
+        name: "when",
+        usage: "",
+        permission: Permission::Everyone,
+        cooldown: 5,
+        handler: Handler::Message(|context, msg, _args, locale| {
+            Box::pin(when(context, msg, locale))
+        }),
+    },
+    Command {
+        name: "history",
+        usage: "[n]",
+        permission: Permission::Everyone,
+        cooldown: 10,
+        handler: Handler::Message(|context, msg, args, locale| {
+            Box::pin(history(context, msg, args, locale))
+        }),
+    },
+    Command {
+        name: "status",
+        usage: "",
+        permission: Permission::Everyone,
+        cooldown: 5,
+        handler: Handler::Message(|context, msg, _args, locale| {
+            Box::pin(status(context, msg, locale))
+        }),
+    },
+    Command {
+        name: "forget",
+        usage: "<word>",
+        permission: Permission::Moderator,
+        cooldown: 2,
+        handler: Handler::Slash(
+            |context, io, args, locale| Box::pin(forget(context, io, args, locale)),
+            &[SlashOption {
+                name: "word",
+                description: "The word to forget",
+                kind: OptionKind::Word,
+                required: true,
+            }],
+        ),
+    },
+    Command {
+        name: "reset",
+        usage: "",
+        permission: Permission::Moderator,
+        cooldown: 2,
+        handler: Handler::Message(|context, msg, _args, locale| {
+            Box::pin(reset(context, msg, locale.lang))
+        }),
+    },
+    Command {
+        name: "pause",
+        usage: "[duration]",
+        permission: Permission::Moderator,
+        cooldown: 2,
+        handler: Handler::Message(|context, msg, args, locale| {
+            Box::pin(pause(context, msg, args, locale))
+        }),
+    },
+    Command {
+        name: "resume",
+        usage: "",
+        permission: Permission::Moderator,
+        cooldown: 2,
+        handler: Handler::Message(|context, msg, _args, locale| {
+            Box::pin(resume(context, msg, locale.lang))
+        }),
+    },
+    Command {
+        name: "set",
+        usage: "<key> <value>",
+        permission: Permission::Admin,
+        cooldown: 2,
+        handler: Handler::Message(|context, msg, args, locale| {
+            Box::pin(set_config(context, msg, args, locale))
+        }),
+    },
+    Command {
+        name: "get",
+        usage: "<key>",
+        permission: Permission::Admin,
+        cooldown: 2,
+        handler: Handler::Message(|context, msg, args, locale| {
+            Box::pin(get_config(context, msg, args, locale))
+        }),
+    },
+    Command {
+        name: "config",
+        usage: "",
+        permission: Permission::Admin,
+        cooldown: 2,
+        handler: Handler::Slash(
+            |context, io, _args, locale| Box::pin(show_config(context, io, locale.lang)),
+            &[],
+        ),
+    },
+    Command {
+        name: "schedule",
+        usage: "<word> <HH:MM>",
+        permission: Permission::Admin,
+        cooldown: 2,
+        handler: Handler::Message(|context, msg, args, locale| {
+            Box::pin(schedule_post(context, msg, args, locale))
+        }),
+    },
+    Command {
+        name: "block",
+        usage: "<word|/regex/>",
+        permission: Permission::Admin,
+        cooldown: 2,
+        handler: Handler::Message(|context, msg, args, locale| {
+            Box::pin(block(context, msg, args, locale))
+        }),
+    },
+    Command {
+        name: "unblock",
+        usage: "<id|word>",
+        permission: Permission::Admin,
+        cooldown: 2,
+        handler: Handler::Message(|context, msg, args, locale| {
+            Box::pin(unblock(context, msg, args, locale))
+        }),
+    },
+    Command {
+        name: "blocked",
+        usage: "",
+        permission: Permission::Admin,
+        cooldown: 2,
+        handler: Handler::Message(|context, msg, _args, locale| {
+            Box::pin(send_blocklist(context, msg, locale.lang))
+        }),
+    },
+    Command {
+        name: "prefix",
+        usage: "[new prefix]",
+        permission: Permission::Everyone,
+        cooldown: 2,
+        handler: Handler::Message(|context, msg, args, locale| {
+            Box::pin(prefix(context, msg, args, locale))
+        }),
+    },
+    Command {
+        name: "settings",
+        usage: "[set <key> <value> | unset <key>]",
+        permission: Permission::Everyone,
+        cooldown: 2,
+        handler: Handler::Slash(
+            |context, io, args, locale| Box::pin(settings(context, io, args, locale)),
+            &[
+                SlashOption {
+                    name: "action",
+                    description: "Change a setting, or go back to the default",
+                    kind: OptionKind::Choice(&["set", "unset"]),
+                    required: false,
+                },
+                SlashOption {
+                    name: "key",
+                    description: "The setting",
+                    kind: OptionKind::Choice(guild_settings::KEYS),
+                    required: false,
+                },
+                SlashOption {
+                    name: "value",
+                    description: "Its new value",
+                    kind: OptionKind::Text,
+                    required: false,
+                },
+            ],
+        ),
+    },
+    Command {
+        name: "ignore",
+        usage: "@user",
+        permission: Permission::Admin,
+        cooldown: 2,
+        handler: Handler::Message(|context, msg, _args, locale| {
+            Box::pin(set_ignored(context, msg, true, locale))
+        }),
+    },
+    Command {
+        name: "unignore",
+        usage: "@user",
+        permission: Permission::Admin,
+        cooldown: 2,
+        handler: Handler::Message(|context, msg, _args, locale| {
+            Box::pin(set_ignored(context, msg, false, locale))
+        }),
+    },
+    Command {
+        name: "enable",
+        usage: "",
+        permission: Permission::Admin,
+        cooldown: 2,
+        handler: Handler::Message(|context, msg, _args, locale| {
+            Box::pin(set_tracking(context, msg, true, locale.lang))
+        }),
+    },
+    Command {
+        name: "disable",
+        usage: "",
+        permission: Permission::Admin,
+        cooldown: 2,
+        handler: Handler::Message(|context, msg, _args, locale| {
+            Box::pin(set_tracking(context, msg, false, locale.lang))
+        }),
+    },
+    Command {
+        name: "export",
+        usage: "",
+        permission: Permission::Admin,
+        cooldown: 60,
+        handler: Handler::Message(|context, msg, _args, locale| {
+            Box::pin(export_words(context, msg, locale.lang))
+        }),
+    },
+    Command {
+        name: "import",
+        usage: "",
+        permission: Permission::Admin,
+        cooldown: 30,
+        handler: Handler::Message(|context, msg, _args, locale| {
+            Box::pin(import_words(context, msg, locale))
+        }),
+    },
+    Command {
+        name: "debug",
+        usage: "",
+        permission: Permission::Owner,
+        cooldown: 10,
+        handler: Handler::Message(|context, msg, _args, locale| {
+            Box::pin(send_debug_dump(context, msg, locale.lang))
+        }),
+    },
+];
+
+/// One line of `!pino help` for each command, with who can use it if not everyone.
+fn help_lines(commands: &[Command], locale: Locale) -> Vec<String> {
+    let lang = locale.lang;
+
+    commands
+        .iter()
+        .map(|command| {
+            let usage = if command.usage.is_empty() {
+                format!("`{} {}`", locale.prefix, command.name)
+            } else {
+                format!("`{} {} {}`", locale.prefix, command.name, command.usage)
+            };
+
+            let description = tr(lang, &format!("help.{}", command.name), &[]);
+
+            match command.permission {
+                Permission::Everyone => format!("{} — {}", usage, description),
+                permission => tr(
+                    lang,
+                    "help.restricted",
+                    &[
+                        ("usage", &usage),
+                        ("description", &description),
+                        ("who", &permission.who(lang)),
+                    ],
+                ),
+            }
+        })
+        .collect()
+}
+
+/// Text of `!pino version`. `session` is how long ago pino connected and to
+/// how many servers, if it did.
+fn version_text(session: Option<(Duration, usize)>, words: &WordMap, lang: Lang) -> String {
+    let version = match option_env!("PINO_GIT_HASH") {
+        Some(hash) => format!("pino {} ({}) 🦜", env!("CARGO_PKG_VERSION"), hash),
+        None => format!("pino {} 🦜", env!("CARGO_PKG_VERSION")),
+    };
+    let session = match session {
+        Some((uptime, guilds)) => tr(
+            lang,
+            "version.uptime",
+            &[("uptime", &format_duration(uptime)), ("guilds", &guilds)],
+        ),
+        None => tr(lang, "version.not_connected", &[]),
+    };
+    let instances: usize = words.values().map(|instances| instances.len()).sum();
+    let tracking = tr(
+        lang,
+        "version.tracking",
+        &[("words", &words.len()), ("instances", &instances)],
+    );
+
+    format!("{}\n{}\n{}", version, session, tracking)
+}
+
+/// A duration like `3 days, 2 hours, 15 minutes`, skipping zero components.
+fn describe_uptime(uptime: Duration, lang: Lang) -> String {
+    let minutes = uptime.num_minutes().max(0);
+    let mut parts: Vec<_> = [
+        ("days", minutes / (24 * 60)),
+        ("hours", minutes / 60 % 24),
+        ("minutes", minutes % 60),
+    ]
+    .iter()
+    .filter(|(_unit, value)| *value > 0)
+    .map(|&(unit, value)| tr(lang, &format!("uptime.{}", unit), &[(unit, &value)]))
+    .collect();
+
+    if parts.is_empty() {
+        parts.push(tr(lang, "uptime.minutes", &[("minutes", &0)]));
+    }
+
+    parts.join(", ")
+}
+
+/// The reply to `!uptime`.
+fn uptime_text(uptime: Duration, messages: u64, posts: u64, lang: Lang) -> String {
+    tr(
+        lang,
+        "uptime.text",
+        &[
+            ("uptime", &describe_uptime(uptime, lang)),
+            ("messages", &messages),
+            ("posts", &posts),
+        ],
+    )
+}
+
+/// The prefix and the language of `guild`.
+async fn guild_locale(
+    context: &serenity::client::Context,
+    guild: Option<GuildId>,
+) -> (String, Lang) {
+    let data_read = context.data.read().await;
+    let default = crate::DEFAULT_PREFIX.get().map_or(PREFIX, String::as_str);
+    let cli = crate::DEFAULT_SETTINGS.get().cloned().unwrap_or_default();
+    let guild_settings = data_read
+        .get::<GuildSettings>()
+        .expect("GuildSettings to be in context")
+        .read()
+        .unwrap();
+
+    (
+        guild_settings.prefix(guild, default).to_owned(),
+        guild_settings.resolve(guild, &cli).language,
+    )
+}
+
+/// Run the command in the message, if any. Returns whether the message was a command.
+/// `bot_id` is pino's own id, to recognize commands starting with a mention.
+pub async fn dispatch(context: &serenity::client::Context, msg: &Message, bot_id: UserId) -> bool {
+    let (prefix, lang) = guild_locale(context, msg.guild_id).await;
+
+    let invocation =
+        match parse_mention(bot_id.get(), &msg.content).or_else(|| parse(&prefix, &msg.content)) {
+            Some(invocation) => invocation,
+            None => return false,
+        };
+
+    let command = match COMMANDS
+        .iter()
+        .find(|command| command.name == invocation.name)
+    {
+        Some(command) => command,
+        None => return false,
+    };
+
+    let locale = Locale {
+        lang,
+        prefix: &prefix,
+    };
+
+    if !may_run(context, msg, command, locale).await {
+        return true;
+    }
+
+    let span = info_span!("command", name = command.name);
+
+    async {
+        debug!("Running the command");
+        match command.handler {
+            Handler::Message(handler) => handler(context, msg, &invocation.args, locale).await,
+            Handler::Slash(handler, _options) => {
+                handler(context, msg, &invocation.args, locale).await
+            }
+        }
+    }
+    .instrument(span)
+    .await;
+
+    true
+}
+
+/// Check the cooldown of `command` for its author and whether they can use
+/// it, answering them if not, and count it. Returns whether to run it.
+async fn may_run(
+    context: &serenity::client::Context,
+    io: &dyn CommandIo,
+    command: &Command,
+    locale: Locale<'_>,
+) -> bool {
+    let lang = locale.lang;
+
+    let verdict = {
+        let data_read = context.data.read().await;
+        let verdict = data_read
+            .get::<UserCooldowns>()
+            .expect("UserCooldowns to be in context")
+            .write()
+            .unwrap()
+            .check(
+                ids::UserId(io.author_id().get()),
+                command.name,
+                Duration::seconds(command.cooldown),
+                Utc::now(),
+            );
+        verdict
+    };
+
+    match verdict {
+        Verdict::Allowed => {}
+        _ if is_allowed(Permission::Admin, io.permissions(context).await) => {}
+        Verdict::CoolingDown => {
+            if let Err(e) = io.react(context, COOLING_DOWN).await {
+                warn!("Error reacting to a command during its cooldown: {}", e);
+            }
+
+            return false;
+        }
+        Verdict::Ignored => return false,
+    }
+
+    let allowed = match command.permission {
+        Permission::Everyone => true,
+        Permission::Owner => is_owner(context, io).await,
+        permission => is_allowed(permission, io.permissions(context).await),
+    };
+
+    if !allowed {
+        let denied = tr(
+            lang,
+            "denied",
+            &[
+                ("who", &command.permission.who(lang)),
+                ("command", &format!("{} {}", locale.prefix, command.name)),
+            ],
+        );
+
+        if let Err(e) = io.reply_ephemeral(context, denied).await {
+            send_failed(context, io, "reply", &e).await;
+        }
+
+        return false;
+    }
+
+    let data_read = context.data.read().await;
+    data_read
+        .get::<MetricsRegistry>()
+        .expect("MetricsRegistry to be in context")
+        .command(command.name);
+
+    if command.permission != Permission::Everyone {
+        crate::record_audit(
+            &data_read,
+            audit::AuditEvent::Command {
+                command: command.name.to_owned(),
+                user_id: Some(io.author_id().get()),
+                channel_id: Some(io.channel_id().get()),
+                guild_id: io.guild_id().map(|id| id.get()),
+                timestamp: Utc::now(),
+            },
+        );
+    }
+
+    true
+}
+
+/// The command behind `/pino <name>`, if it's a slash command.
+fn slash_handler(name: &str) -> Option<(&'static Command, IoHandler)> {
+    COMMANDS.iter().find_map(|command| match command.handler {
+        Handler::Slash(handler, _options) if command.name == name => Some((command, handler)),
+        _ => None,
+    })
+}
+
+/// `/pino`, with a subcommand for each command that can be a slash command.
+pub fn slash_command() -> CreateCommand {
+    slash::command(COMMANDS.iter().filter_map(|command| match command.handler {
+        Handler::Slash(_handler, options) => Some((
+            command.name,
+            tr(Lang::En, &format!("help.{}", command.name), &[]),
+            options,
+        )),
+        Handler::Message(_handler) => None,
+    }))
+}
+
+/// Suggest the most said words of the guild starting with what is being
+/// typed in a word option, e.g. of `/pino forget`.
+pub async fn autocomplete(context: &serenity::client::Context, interaction: &CommandInteraction) {
+    let typed = match interaction.data.autocomplete() {
+        Some(option) => option.value,
+        None => return,
+    };
+
+    let normalization = crate::NORMALIZATION.get().copied().unwrap_or_default();
+    let typed = tracking::normalize_word(typed, normalization);
+
+    let response = {
+        let data_read = context.data.read().await;
+        let words = data_read
+            .get::<MessageMap>()
+            .expect("MessageMap to be in context")
+            .read()
+            .await;
+
+        let suggestions = tracking::starting_with(
+            words.guild(guild_key(interaction.guild_id)),
+            &typed,
+            slash::CHOICES_MAX,
+        );
+
+        slash::choices(suggestions.into_iter().map(|(word, _count)| word))
+    };
+
+    if let Err(e) = interaction.create_response(&context.http, response).await {
+        warn!("Error suggesting words: {}", e);
+    }
+}
+
+/// Run a slash command like `/pino top`, like the prefix command with the
+/// same name. The replies of the commands that aren't for everyone are only
+/// for whoever used them.
+pub async fn interaction(context: &serenity::client::Context, interaction: &CommandInteraction) {
+    let (command, handler, args) = match slash::arguments(&interaction.data)
+        .and_then(|(name, args)| Some((slash_handler(name)?, args)))
+    {
+        Some(((command, handler), args)) => (command, handler, args),
+        None => {
+            warn!("Unknown slash command {}", interaction.data.name);
+            return;
+        }
+    };
+
+    let (_prefix, lang) = guild_locale(context, interaction.guild_id).await;
+    let locale = Locale {
+        lang,
+        prefix: slash::PREFIX,
+    };
+    let io = SlashCommand::new(interaction, command.permission != Permission::Everyone);
+
+    let ran = may_run(context, &io, command, locale).await;
+
+    if ran {
+        let args: Vec<_> = args.iter().map(String::as_str).collect();
+        let span = info_span!("command", name = command.name);
+
+        async {
+            debug!("Running the slash command");
+            handler(context, &io, &args, locale).await;
+        }
+        .instrument(span)
+        .await;
+    }
+
+    // Discord shows the command as failed without an answer, e.g. after
+    // `/pino speak`, which talks in the channel instead
+    if !io.answered() {
+        let emoji = if ran { CONFIRM } else { COOLING_DOWN };
+
+        if let Err(e) = io.react(context, emoji).await {
+            send_failed(context, &io, "reply", &e).await;
+        }
+    }
+}
+
+/// List every command, in as many messages as needed.
+async fn send_help(context: &serenity::client::Context, msg: &Message, locale: Locale<'_>) {
+    for page in help_lines(COMMANDS, locale).chunks(HELP_PAGE_SIZE) {
+        reply(context, msg, page.join("\n")).await;
+    }
+}
+
+/// Reply with the version of pino and a few numbers about what it's doing.
+async fn send_version(context: &serenity::client::Context, msg: &Message, lang: Lang) {
+    let content = {
+        let data_read = context.data.read().await;
+        let session = data_read
+            .get::<ReadySession>()
+            .expect("ReadySession to be in context")
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|session| (Utc::now() - session.started, session.guilds()));
+        let words = data_read
+            .get::<MessageMap>()
+            .expect("MessageMap to be in context")
+            .read()
+            .await;
+
+        version_text(session, words.guild(guild_key(msg.guild_id)), lang)
+    };
+
+    reply(context, msg, content).await;
+}
+
+async fn send_uptime(context: &serenity::client::Context, msg: &Message, lang: Lang) {
+    let content = {
+        let data_read = context.data.read().await;
+        let startup_time = *data_read
+            .get::<StartupTime>()
+            .expect("StartupTime to be in context");
+        let activity = *data_read
+            .get::<ActivityCounters>()
+            .expect("ActivityCounters to be in context")
+            .read()
+            .unwrap();
+
+        uptime_text(
+            Utc::now() - startup_time,
+            activity.messages,
+            activity.posts,
+            lang,
+        )
+    };
+
+    reply(context, msg, content).await;
+}
+
+/// Returns true if the command can be used in the channel, replying otherwise.
+async fn check_cooldown(
+    context: &serenity::client::Context,
+    io: &dyn CommandIo,
+    command: &'static str,
+    cooldown: Duration,
+    lang: Lang,
+) -> bool {
+    let allowed = {
+        let data_read = context.data.read().await;
+        let mut cooldowns = data_read
+            .get::<CommandCooldowns>()
+            .expect("CommandCooldowns to be in context")
+            .write()
+            .unwrap();
+
+        cooldowns.try_use((io.channel_id(), command), Utc::now(), cooldown)
+    };
+
+    if !allowed {
+        reply(context, io, tr(lang, "cooldown", &[])).await;
+    }
+
+    allowed
+}
+
+/// Reply with an embed listing the most said words, or with `--trending`
+/// the ones growing the fastest.
+async fn send_top(
+    context: &serenity::client::Context,
+    io: &dyn CommandIo,
+    args: &[&str],
+    locale: Locale<'_>,
+) {
+    let lang = locale.lang;
+
+    let (trending, args) = split_trending_flag(args);
+    let n = match parse_top_amount(&args, locale) {
+        Ok(n) => n,
+        Err(e) => {
+            reply(context, io, e).await;
+            return;
+        }
+    };
+
+    if !check_cooldown(
+        context,
+        io,
+        "top",
+        Duration::seconds(TOP_COOLDOWN_SECONDS),
+        lang,
+    )
+    .await
+    {
+        return;
+    }
+
+    if trending {
+        send_trending(context, io, n, lang).await;
+        return;
+    }
+
+    let top = {
+        let data_read = context.data.read().await;
+        let words = data_read
+            .get::<MessageMap>()
+            .expect("MessageMap to be in context")
+            .read()
+            .await;
+        let blocklist = data_read
+            .get::<BlockedWords>()
+            .expect("BlockedWords to be in context")
+            .read()
+            .unwrap();
+
+        top_words(words.guild(guild_key(io.guild_id())), &blocklist, n)
+    };
+
+    if top.is_empty() {
+        reply(context, io, tr(lang, "no_words", &[])).await;
+        return;
+    }
+
+    let view = View::Top(n);
+    let sent = io
+        .reply_components(context, top_embed(&top, lang), components::buttons(view))
+        .await;
+
+    match sent {
+        Ok(message) => components::expire_later(messenger(context).await, io.channel_id(), message),
+        Err(e) => send_failed(context, io, "top words", &e).await,
+    }
+}
+
+/// The embed of `!pino top` listing `top`.
+fn top_embed(top: &[TopWord], lang: Lang) -> CreateEmbed {
+    let description = if top.is_empty() {
+        tr(lang, "no_words", &[])
+    } else {
+        top.iter()
+            .enumerate()
+            .map(|(i, top)| {
+                tr(
+                    lang,
+                    "top.line",
+                    &[
+                        ("rank", &(i + 1)),
+                        ("word", &top.word),
+                        ("count", &top.count),
+                        ("last_heard", &relative_time(top.last_heard)),
+                    ],
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    CreateEmbed::new()
+        .title(tr(lang, "top.title", &[]))
+        .description(description)
+}
+
+/// Reply with an embed listing the `n` words growing the fastest, like
+/// `--scoring trending` sees them.
+async fn send_trending(
+    context: &serenity::client::Context,
+    io: &dyn CommandIo,
+    n: usize,
+    lang: Lang,
+) {
+    let Selection { window, now, .. } = crate::word_selection(0, Scoring::Trending);
+    let shown_window = format_duration(Duration::seconds(window as i64));
+
+    let mut trends = {
+        let data_read = context.data.read().await;
+        let words = data_read
+            .get::<MessageMap>()
+            .expect("MessageMap to be in context")
+            .read()
+            .await;
+
+        trending::trends(words.guild(guild_key(io.guild_id())), now, window)
+    };
+    trends.truncate(n);
+
+    if trends.is_empty() {
+        let text = tr(lang, "top.no_trending", &[("window", &shown_window)]);
+        reply(context, io, text).await;
+        return;
+    }
+
+    let description = trends
+        .iter()
+        .enumerate()
+        .map(|(i, trend)| {
+            tr(
+                lang,
+                "top.trending_line",
+                &[
+                    ("rank", &(i + 1)),
+                    ("word", &trend.word),
+                    ("current", &trend.current),
+                    ("window", &shown_window),
+                    ("previous", &trend.previous),
+                ],
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let title = tr(lang, "top.trending_title", &[]);
+    let result = io
+        .reply_embed(
+            context,
+            None,
+            CreateEmbed::new().title(title).description(description),
+        )
+        .await;
+
+    if let Err(e) = result {
+        send_failed(context, io, "trending words", &e).await;
+    }
+}
+
+#[cfg(feature = "sqlite")]
+async fn send_db_event(context: &serenity::client::Context, event: db::Event) {
+    if let Some(db) = context.data.read().await.get::<DbWriter>() {
+        db.send(event);
+    }
+}
+
+/// Forget a single word. Needs the Manage Messages permission.
+async fn forget(
+    context: &serenity::client::Context,
+    io: &dyn CommandIo,
+    args: &[&str],
+    locale: Locale<'_>,
+) {
+    let lang = locale.lang;
+
+    let normalization = crate::NORMALIZATION.get().copied().unwrap_or_default();
+    let word = match args {
+        [word] => tracking::normalize_word(word, normalization),
+        _ => {
+            reply(context, io, usage(locale, "forget <word>")).await;
+            return;
+        }
+    };
+
+    let dropped = {
+        let data_read = context.data.read().await;
+        let mut words = data_read
+            .get::<MessageMap>()
+            .expect("MessageMap to be in context")
+            .write()
+            .await;
+        forget_pairs(&data_read, io.guild_id(), &word);
+
+        forget_word(&mut words, io.guild_id(), &word)
+    };
+
+    #[cfg(feature = "sqlite")]
+    send_db_event(
+        context,
+        db::Event::Forget(io.guild_id().map(|id| id.get()), word.clone()),
+    )
+    .await;
+
+    let content = if dropped == 0 {
+        tr(lang, "never_heard", &[("word", &word)])
+    } else {
+        tr(lang, "forget.done", &[("word", &word), ("count", &dropped)])
+    };
+
+    reply(context, io, content).await;
+}
+
+/// Forget every word, after the invoker confirms with a reaction.
+/// Needs the Manage Messages permission.
+async fn reset(context: &serenity::client::Context, msg: &Message, lang: Lang) {
+    let mut prompt = match msg
+        .channel_id
+        .say(
+            &context.http,
+            tr(
+                lang,
+                "reset.prompt",
+                &[
+                    ("user", &msg.author.mention()),
+                    ("emoji", &CONFIRM),
+                    ("seconds", &RESET_TIMEOUT_SECONDS),
+                ],
+            ),
+        )
+        .await
+    {
+        Ok(prompt) => prompt,
+        Err(e) => {
+            warn!("Error sending reset prompt: {}", e);
+            return;
+        }
+    };
+
+    let confirm = ReactionType::Unicode(CONFIRM.to_owned());
+
+    if let Err(e) = prompt.react(&context.http, confirm.clone()).await {
+        warn!("Error adding reset reaction: {}", e);
+    }
+
+    let confirmation = prompt
+        .await_reaction(context)
+        .author_id(msg.author.id)
+        .filter(move |reaction| reaction.emoji == confirm)
+        .timeout(Duration::seconds(RESET_TIMEOUT_SECONDS).to_std().unwrap())
+        .await;
+
+    if confirmation.is_none() {
+        // If the prompt has been deleted there's nothing left to edit
+        if let Err(e) = prompt
+            .edit(
+                &context,
+                EditMessage::new().content(tr(lang, "reset.cancelled", &[])),
+            )
+            .await
+        {
+            warn!("Reset prompt is gone: {}", e);
+        }
+
+        return;
+    }
+
+    // The prompt could have been deleted right after the confirmation was given
+    if let Err(e) = msg.channel_id.message(&context.http, prompt.id).await {
+        warn!("Reset prompt is gone, not resetting: {}", e);
+        return;
+    }
+
+    let forgotten: usize = {
+        let data_read = context.data.read().await;
+        let mut words = data_read
+            .get::<MessageMap>()
+            .expect("MessageMap to be in context")
+            .write()
+            .await;
+
+        // Only the words of this server, the others keep theirs
+        let forgotten = words.reset(guild_key(msg.guild_id));
+
+        if let Some(cooccurrences) = data_read.get::<Cooccurrences>() {
+            cooccurrences
+                .write()
+                .unwrap()
+                .remove(&guild_key(msg.guild_id));
+        }
+
+        forgotten
+    };
+
+    #[cfg(feature = "sqlite")]
+    send_db_event(context, db::Event::Reset(msg.guild_id.map(|id| id.get()))).await;
+
+    reply(
+        context,
+        msg,
+        tr(lang, "reset.done", &[("count", &forgotten)]),
+    )
+    .await;
+}
+
+/// Say the word pino would say next in this channel, right now. The timer of
+/// the next message starts over.
+async fn speak(context: &serenity::client::Context, io: &dyn CommandIo, lang: Lang) {
+    let paused = {
+        let data_read = context.data.read().await;
+        let paused = data_read
+            .get::<Paused>()
+            .expect("Paused to be in context")
+            .read()
+            .unwrap()
+            .is_paused(guild_key(io.guild_id()), Utc::now());
+        paused
+    };
+
+    if paused {
+        reply(context, io, tr(lang, "speak.paused", &[])).await;
+        return;
+    }
+
+    if !check_cooldown(
+        context,
+        io,
+        "speak",
+        Duration::seconds(SPEAK_COOLDOWN_SECONDS),
+        lang,
+    )
+    .await
+    {
+        return;
+    }
+
+    // Composed and delivered like the scheduled messages of the guild
+    let spoke = crate::speak_in(&*context.data.read().await, io.channel_id(), io.guild_id()).await;
+
+    if !spoke {
+        reply(context, io, tr(lang, "no_words", &[])).await;
+        return;
+    }
+
+    crate::spoke_in(&*context.data.read().await, io.guild_id(), Utc::now());
+}
+
+/// Reply with the last messages pino sent in this guild.
+async fn history(
+    context: &serenity::client::Context,
+    msg: &Message,
+    args: &[&str],
+    locale: Locale<'_>,
+) {
+    let lang = locale.lang;
+
+    let n = match parse_history_amount(args, locale) {
+        Ok(n) => n,
+        Err(e) => {
+            reply(context, msg, e).await;
+            return;
+        }
+    };
+
+    let lines: Vec<String> = {
+        let data_read = context.data.read().await;
+        let sent = data_read
+            .get::<SentMessages>()
+            .expect("SentMessages to be in context")
+            .read()
+            .unwrap();
+
+        sent.last(msg.guild_id.map(|id| id.get()), n)
+            .map(|message| message.describe(lang))
+            .collect()
+    };
+
+    if lines.is_empty() {
+        reply(context, msg, tr(lang, "history.empty", &[])).await;
+        return;
+    }
+
+    for page in lines.chunks(HISTORY_PAGE_SIZE) {
+        reply(context, msg, page.join("\n")).await;
+    }
+}
+
+/// Post a word in this channel at a given UTC time, within a day. Admin only.
+async fn schedule_post(
+    context: &serenity::client::Context,
+    msg: &Message,
+    args: &[&str],
+    locale: Locale<'_>,
+) {
+    let lang = locale.lang;
+
+    let (word, at) = match args {
+        [word, at] => (*word, *at),
+        _ => {
+            reply(context, msg, usage(locale, "schedule <word> <HH:MM>")).await;
+            return;
+        }
+    };
+
+    let at = match scheduled_posts::parse_time(at) {
+        Ok(at) => at,
+        Err(_) => {
+            reply(
+                context,
+                msg,
+                tr(lang, "schedule.invalid", &[("input", &at)]),
+            )
+            .await;
+            return;
+        }
+    };
+
+    let post = ScheduledPost::new(
+        word.to_owned(),
+        at,
+        Utc::now(),
+        ids::ChannelId(msg.channel_id.get()),
+        guild_key(msg.guild_id),
+    );
+    let due = post.due;
+
+    let added = {
+        let data_read = context.data.read().await;
+        let added = data_read
+            .get::<ScheduledPosts>()
+            .expect("ScheduledPosts to be in context")
+            .write()
+            .unwrap()
+            .add(post);
+        added
+    };
+
+    let content = match added {
+        Ok(()) => tr(
+            lang,
+            "schedule.done",
+            &[
+                ("word", &MessageBuilder::new().push_bold_safe(word).build()),
+                ("time", &relative_time(due)),
+            ],
+        ),
+        Err(_) => tr(
+            lang,
+            "schedule.full",
+            &[("max", &scheduled_posts::MAX_PENDING_PER_GUILD)],
+        ),
+    };
+
+    reply(context, msg, content).await;
+}
+
+/// Reply with when the next message is going to be sent.
+async fn when(context: &serenity::client::Context, msg: &Message, locale: Locale<'_>) {
+    let content = {
+        let data_read = context.data.read().await;
+        let next = data_read
+            .get::<Schedule>()
+            .expect("Schedule to be in context")
+            .read()
+            .unwrap()
+            .next_for(crate::schedule_target(msg.guild_id));
+        let pause = data_read
+            .get::<Paused>()
+            .expect("Paused to be in context")
+            .read()
+            .unwrap()
+            .get(guild_key(msg.guild_id));
+
+        describe_next(next, &pause, Utc::now(), locale)
+    };
+
+    reply(context, msg, content).await;
+}
+
+/// Stop sending messages in this server, for the given duration or until resumed.
+/// Needs the Manage Messages permission.
+async fn pause(
+    context: &serenity::client::Context,
+    msg: &Message,
+    args: &[&str],
+    locale: Locale<'_>,
+) {
+    let lang = locale.lang;
+
+    let duration = match args {
+        [] => None,
+        [duration] => match duration::parse_seconds(duration) {
+            Ok(seconds) => Some(Duration::seconds(seconds as i64)),
+            Err(_) => {
+                reply(
+                    context,
+                    msg,
+                    tr(lang, "pause.invalid", &[("input", duration)]),
+                )
+                .await;
+                return;
+            }
+        },
+        _ => {
+            reply(context, msg, usage(locale, "pause [duration]")).await;
+            return;
+        }
+    };
+
+    {
+        let data_read = context.data.read().await;
+        data_read
+            .get::<Paused>()
+            .expect("Paused to be in context")
+            .write()
+            .unwrap()
+            .pause(guild_key(msg.guild_id), Utc::now(), duration);
+    }
+
+    let content = match duration {
+        Some(duration) => tr(
+            lang,
+            "pause.for",
+            &[("duration", &format_duration(duration))],
+        ),
+        None => tr(
+            lang,
+            "pause.until_resumed",
+            &[("resume", &format!("{} resume", locale.prefix))],
+        ),
+    };
+
+    reply(context, msg, content).await;
+}
+
+/// Undo the pause of this server. Needs the Manage Messages permission.
+async fn resume(context: &serenity::client::Context, msg: &Message, lang: Lang) {
+    {
+        let data_read = context.data.read().await;
+        data_read
+            .get::<Paused>()
+            .expect("Paused to be in context")
+            .write()
+            .unwrap()
+            .resume(guild_key(msg.guild_id));
+    }
+
+    reply(context, msg, tr(lang, "resume.done", &[])).await;
+}
+
+/// Tell whether pino is paused in this server, and for how long.
+async fn status(context: &serenity::client::Context, msg: &Message, locale: Locale<'_>) {
+    let lang = locale.lang;
+
+    let content = {
+        let data_read = context.data.read().await;
+        let state = data_read
+            .get::<Paused>()
+            .expect("Paused to be in context")
+            .read()
+            .unwrap()
+            .get(guild_key(msg.guild_id));
+        let now = Utc::now();
+
+        if !state.is_paused(now) {
+            tr(lang, "status.active", &[])
+        } else if let Some(remaining) = state.remaining(now) {
+            tr(
+                lang,
+                "status.paused_for",
+                &[("duration", &format_duration(remaining))],
+            )
+        } else {
+            tr(
+                lang,
+                "status.paused",
+                &[("resume", &format!("{} resume", locale.prefix))],
+            )
+        }
+    };
+
+    reply(context, msg, content).await;
+}
+
+/// Change a setting of the send loop, effective from the next message. Admin only.
+async fn set_config(
+    context: &serenity::client::Context,
+    msg: &Message,
+    args: &[&str],
+    locale: Locale<'_>,
+) {
+    let lang = locale.lang;
+
+    let (key, value) = match args {
+        [key, value] => (*key, *value),
+        _ => {
+            reply(context, msg, usage(locale, "set <key> <value>")).await;
+            return;
+        }
+    };
+
+    let result = {
+        let data_read = context.data.read().await;
+        let mut config = data_read
+            .get::<Config>()
+            .expect("Config to be in context")
+            .write()
+            .unwrap();
+
+        config
+            .set(key, value)
+            .map(|()| config.get(key).expect("key to be valid after setting it"))
+    };
+
+    let content = match result {
+        Ok(value) => {
+            #[cfg(feature = "sqlite")]
+            send_db_event(context, db::Event::Setting(key.to_owned(), value.clone())).await;
+
+            tr(lang, "config.changed", &[("key", &key), ("value", &value)])
+        }
+        Err(e) => tr(
+            lang,
+            "config.failed",
+            &[("key", &key), ("error", &config_error(&e, lang))],
+        ),
+    };
+
+    reply(context, msg, content).await;
+}
+
+/// Reply with the value of a setting. Admin only.
+async fn get_config(
+    context: &serenity::client::Context,
+    msg: &Message,
+    args: &[&str],
+    locale: Locale<'_>,
+) {
+    let lang = locale.lang;
+
+    let key = match args {
+        [key] => *key,
+        _ => {
+            reply(context, msg, usage(locale, "get <key>")).await;
+            return;
+        }
+    };
+
+    let result = {
+        let data_read = context.data.read().await;
+        let config = data_read
+            .get::<Config>()
+            .expect("Config to be in context")
+            .read()
+            .unwrap();
+
+        config.get(key)
+    };
+
+    let content = match result {
+        Ok(value) => format!("{} = {}", key, value),
+        Err(e) => config_error(&e, lang),
+    };
+
+    reply(context, msg, content).await;
+}
+
+/// Reply with every setting, and with the options pino was started with in
+/// an embed. Admin only.
+async fn show_config(context: &serenity::client::Context, io: &dyn CommandIo, lang: Lang) {
+    let (entries, startup_options) = {
+        let data_read = context.data.read().await;
+        let config = data_read
+            .get::<Config>()
+            .expect("Config to be in context")
+            .read()
+            .unwrap();
+
+        (config.entries(), data_read.get::<StartupOptions>().cloned())
+    };
+
+    let lines = entries
+        .iter()
+        .map(|(key, value)| format!("{} = {}", key, value))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let content = MessageBuilder::new().push_codeblock(lines, None).build();
+
+    let result = match startup_options {
+        Some(startup_options) => {
+            let embed = CreateEmbed::new()
+                .title(tr(lang, "config.options", &[]))
+                .description(
+                    MessageBuilder::new()
+                        .push_codeblock(startup_options.as_str(), Some("json"))
+                        .build(),
+                );
+
+            io.reply_embed(context, Some(content), embed).await
+        }
+        None => io.reply_text(context, content).await,
+    };
+
+    if let Err(e) = result {
+        send_failed(context, io, "config", &e).await;
+    }
+}
+
+/// Why a setting of the send loop can't be changed, in the language of the guild.
+fn config_error(error: &ConfigError, lang: Lang) -> String {
+    match error {
+        ConfigError::UnknownKey(key) => tr(
+            lang,
+            "config.unknown_key",
+            &[
+                ("key", key),
+                ("keys", &crate::runtime_config::KEYS.join(", ")),
+            ],
+        ),
+        ConfigError::NotANumber { key, value } => tr(
+            lang,
+            "config.not_a_number",
+            &[("key", key), ("value", value)],
+        ),
+        ConfigError::NotADuration { key, value } => tr(
+            lang,
+            "config.not_a_duration",
+            &[("key", key), ("value", value)],
+        ),
+        ConfigError::IntervalOrder { low, high } => tr(
+            lang,
+            "config.interval_order",
+            &[("low", low), ("high", high)],
+        ),
+        ConfigError::MaxAgeTooShort {
+            max_age,
+            interval_high,
+        } => tr(
+            lang,
+            "config.max_age",
+            &[("max_age", max_age), ("interval_high", interval_high)],
+        ),
+    }
+}
+
+/// Why a setting of the guild can't be changed, in its language.
+fn setting_error(error: &SettingError, lang: Lang) -> String {
+    match error {
+        SettingError::UnknownKey(key) => tr(
+            lang,
+            "settings.unknown_key",
+            &[("key", key), ("keys", &guild_settings::KEYS.join(", "))],
+        ),
+        SettingError::MissingWord => tr(lang, "settings.missing_word", &[]),
+        SettingError::NotOnOff { key, value } => tr(
+            lang,
+            "settings.not_on_off",
+            &[("key", key), ("value", value)],
+        ),
+        SettingError::NotAWord => tr(lang, "settings.not_a_word", &[]),
+        SettingError::UnknownLanguage(value) => tr(
+            lang,
+            "settings.unknown_language",
+            &[("value", value), ("languages", &crate::i18n::codes())],
+        ),
+        SettingError::UnknownScoring(value) => {
+            tr(lang, "settings.unknown_scoring", &[("value", value)])
+        }
+    }
+}
+
+/// Lines of `!pino blocked`, split in messages of at most `BLOCKLIST_PAGE_SIZE` entries.
+fn blocklist_pages(blocklist: &Blocklist) -> Vec<String> {
+    let lines: Vec<_> = blocklist
+        .iter()
+        .map(|(id, pattern)| format!("**{}.** `{}`", id, pattern))
+        .collect();
+
+    lines
+        .chunks(BLOCKLIST_PAGE_SIZE)
+        .map(|page| page.join("\n"))
+        .collect()
+}
+
+/// Never learn a word, or the words matching a `/regex/`. Admin only.
+async fn block(
+    context: &serenity::client::Context,
+    msg: &Message,
+    args: &[&str],
+    locale: Locale<'_>,
+) {
+    let lang = locale.lang;
+
+    let pattern = match args {
+        [pattern] => match Pattern::parse(pattern) {
+            Ok(pattern) => pattern,
+            Err(e) => {
+                reply(context, msg, tr(lang, "block.invalid", &[("error", &e)])).await;
+                return;
+            }
+        },
+        _ => {
+            reply(context, msg, usage(locale, "block <word|/regex/>")).await;
+            return;
+        }
+    };
+
+    let content = block_pattern(context, msg.guild_id, pattern, lang).await;
+
+    reply(context, msg, content).await;
+}
+
+/// Block `pattern`, dropping right away every word it matches in every
+/// guild, since the blocklist is shared. Returns how it went, to tell the
+/// admin, counting only what `guild` dropped.
+async fn block_pattern(
+    context: &serenity::client::Context,
+    guild: Option<GuildId>,
+    pattern: Pattern,
+    lang: Lang,
+) -> String {
+    let (added, forgotten) = {
+        let data_read = context.data.read().await;
+        let mut words = data_read
+            .get::<MessageMap>()
+            .expect("MessageMap to be in context")
+            .write()
+            .await;
+        let mut blocklist = data_read
+            .get::<BlockedWords>()
+            .expect("BlockedWords to be in context")
+            .write()
+            .unwrap();
+        let added = blocklist.add(pattern.clone());
+
+        let forgotten = match added {
+            Some(_) => {
+                let blocked = |word: &str| blocklist.is_blocked(word);
+                let forgotten = words.forget_matching(blocked);
+
+                if let Some(cooccurrences) = data_read.get::<Cooccurrences>() {
+                    let mut cooccurrences = cooccurrences.write().unwrap();
+
+                    for (guild, word, _count) in &forgotten {
+                        if let Some(pairs) = cooccurrences.get_mut(guild) {
+                            cooccurrence::forget(pairs, word);
+                        }
+                    }
+                }
+
+                data_read
+                    .get::<WeeklySummaries>()
+                    .expect("WeeklySummaries to be in context")
+                    .write()
+                    .unwrap()
+                    .forget_matching(blocked);
+
+                forgotten
+            }
+            None => Vec::new(),
+        };
+
+        (added, forgotten)
+    };
+
+    let id = match added {
+        Some(id) => id,
+        None => return tr(lang, "block.already", &[("pattern", &pattern)]),
+    };
+
+    #[cfg(feature = "sqlite")]
+    {
+        send_db_event(context, db::Event::Block(id, pattern.to_string())).await;
+
+        for (guild, word, _count) in &forgotten {
+            send_db_event(
+                context,
+                db::Event::Forget(guild.map(|id| id.get()), word.clone()),
+            )
+            .await;
+        }
+    }
+
+    let dropped: usize = forgotten
+        .iter()
+        .filter(|(forgotten_guild, _word, _count)| *forgotten_guild == guild_key(guild))
+        .map(|(_guild, _word, count)| count)
+        .sum();
+
+    if dropped == 0 {
+        tr(lang, "block.done", &[("pattern", &pattern), ("id", &id)])
+    } else {
+        tr(
+            lang,
+            "block.done_dropping",
+            &[("pattern", &pattern), ("id", &id), ("count", &dropped)],
+        )
+    }
+}
+
+/// Remove an entry from the blocklist, by id or by pattern. Admin only.
+async fn unblock(
+    context: &serenity::client::Context,
+    msg: &Message,
+    args: &[&str],
+    locale: Locale<'_>,
+) {
+    let lang = locale.lang;
+
+    let id_or_pattern = match args {
+        [id_or_pattern] => *id_or_pattern,
+        _ => {
+            reply(context, msg, usage(locale, "unblock <id|word|/regex/>")).await;
+            return;
+        }
+    };
+
+    let removed = {
+        let data_read = context.data.read().await;
+        let mut blocklist = data_read
+            .get::<BlockedWords>()
+            .expect("BlockedWords to be in context")
+            .write()
+            .unwrap();
+
+        blocklist.remove(id_or_pattern)
+    };
+
+    let content = match removed {
+        Some((id, pattern)) => {
+            #[cfg(feature = "sqlite")]
+            send_db_event(context, db::Event::Unblock(id)).await;
+
+            tr(lang, "unblock.done", &[("pattern", &pattern), ("id", &id)])
+        }
+        None => tr(lang, "unblock.unknown", &[("input", &id_or_pattern)]),
+    };
+
+    reply(context, msg, content).await;
+}
+
+/// List the blocklist, in as many messages as needed. Admin only.
+async fn send_blocklist(context: &serenity::client::Context, msg: &Message, lang: Lang) {
+    let pages = {
+        let data_read = context.data.read().await;
+        let blocklist = data_read
+            .get::<BlockedWords>()
+            .expect("BlockedWords to be in context")
+            .read()
+            .unwrap();
+
+        blocklist_pages(&blocklist)
+    };
+
+    if pages.is_empty() {
+        reply(context, msg, tr(lang, "blocked.empty", &[])).await;
+        return;
+    }
+
+    for page in pages {
+        reply(context, msg, page).await;
+    }
+}
+
+/// Stop or start counting the words of the mentioned users. Admin only.
+/// The command is acknowledged with a reaction, so that the channel isn't told who's ignored.
+async fn set_ignored(
+    context: &serenity::client::Context,
+    msg: &Message,
+    ignored: bool,
+    locale: Locale<'_>,
+) {
+    let lang = locale.lang;
+
+    let guild_id = match msg.guild_id {
+        Some(guild_id) => guild_id,
+        None => {
+            reply(context, msg, tr(lang, "servers_only", &[])).await;
+            return;
+        }
+    };
+
+    if msg.mentions.is_empty() {
+        let name = if ignored { "ignore" } else { "unignore" };
+        reply(context, msg, usage(locale, &format!("{} @user", name))).await;
+        return;
+    }
+
+    {
+        let data_read = context.data.read().await;
+        let mut settings = data_read
+            .get::<GuildSettings>()
+            .expect("GuildSettings to be in context")
+            .write()
+            .unwrap();
+        let ignored_users = &mut settings.get_mut(guild_id).ignored_users;
+
+        for user in &msg.mentions {
+            if ignored {
+                ignored_users.insert(user.id);
+            } else {
+                ignored_users.remove(&user.id);
+            }
+        }
+    }
+
+    let done = ReactionType::Unicode(CONFIRM.to_owned());
+
+    let reacted = messenger(context)
+        .await
+        .react(msg.channel_id, msg.id, done)
+        .await;
+
+    if let Err(e) = reacted {
+        warn!("Error acknowledging ignore: {}", e);
+    }
+}
+
+/// Reply with the settings of the guild or, for administrators, change one.
+async fn settings(
+    context: &serenity::client::Context,
+    io: &dyn CommandIo,
+    args: &[&str],
+    locale: Locale<'_>,
+) {
+    let lang = locale.lang;
+
+    let guild_id = match io.guild_id() {
+        Some(guild_id) => guild_id,
+        None => {
+            reply(context, io, tr(lang, "servers_only", &[])).await;
+            return;
+        }
+    };
+    let cli = crate::DEFAULT_SETTINGS.get().cloned().unwrap_or_default();
+
+    let (key, value) = match args {
+        [] => {
+            let lines = {
+                let data_read = context.data.read().await;
+                let guild_settings = data_read
+                    .get::<GuildSettings>()
+                    .expect("GuildSettings to be in context")
+                    .read()
+                    .unwrap();
+                let guild = guild_settings
+                    .get(guild_id)
+                    .map(|config| config.settings.clone())
+                    .unwrap_or_default();
+
+                settings_lines(&guild, &guild_settings.defaults(Some(guild_id), &cli), lang)
+            };
+
+            let message = MessageBuilder::new()
+                .push_codeblock(lines.join("\n"), None)
+                .build();
+
+            reply(context, io, message).await;
+            return;
+        }
+        ["set", key, value @ ..] if !value.is_empty() => (*key, Some(value.join(" "))),
+        ["unset", key] => (*key, None),
+        _ => {
+            let content = tr(
+                lang,
+                "usage.either",
+                &[
+                    (
+                        "usage",
+                        &format!("{} settings set <key> <value>", locale.prefix),
+                    ),
+                    ("other", &format!("{} settings unset <key>", locale.prefix)),
+                ],
+            );
+
+            reply(context, io, content).await;
+            return;
+        }
+    };
+
+    if !is_allowed(Permission::Admin, io.permissions(context).await) {
+        reply(context, io, tr(lang, "settings.admin_only", &[])).await;
+        return;
+    }
+
+    let result = {
+        let data_read = context.data.read().await;
+        let mut guild_settings = data_read
+            .get::<GuildSettings>()
+            .expect("GuildSettings to be in context")
+            .write()
+            .unwrap();
+        let settings = &mut guild_settings.get_mut(guild_id).settings;
+
+        match &value {
+            Some(value) => settings.set(key, value),
+            None => settings.unset(key),
+        }
+        .map(|()| settings.get(key))
+    };
+
+    let value = match result {
+        Ok(value) => value,
+        Err(e) => {
+            reply(context, io, setting_error(&e, lang)).await;
+            return;
+        }
+    };
+
+    #[cfg(feature = "sqlite")]
+    send_db_event(
+        context,
+        db::Event::GuildSetting(guild_id.get(), key.to_owned(), value.clone()),
+    )
+    .await;
+
+    let content = match value {
+        Some(value) => tr(
+            lang,
+            "settings.changed",
+            &[
+                ("key", &key),
+                (
+                    "value",
+                    &MessageBuilder::new().push_mono_safe(value).build(),
+                ),
+            ],
+        ),
+        None => tr(lang, "settings.default", &[("key", &key)]),
+    };
+
+    reply(context, io, content).await;
+}
+
+/// Reply with the prefix of the guild or, for administrators, change it.
+async fn prefix(
+    context: &serenity::client::Context,
+    msg: &Message,
+    args: &[&str],
+    locale: Locale<'_>,
+) {
+    let lang = locale.lang;
+
+    let guild_id = match msg.guild_id {
+        Some(guild_id) => guild_id,
+        None => {
+            reply(context, msg, tr(lang, "servers_only", &[])).await;
+            return;
+        }
+    };
+
+    let new_prefix = match args {
+        [] => {
+            let content = {
+                let data_read = context.data.read().await;
+                let default = crate::DEFAULT_PREFIX.get().map_or(PREFIX, String::as_str);
+                let guild_settings = data_read
+                    .get::<GuildSettings>()
+                    .expect("GuildSettings to be in context")
+                    .read()
+                    .unwrap();
+
+                tr(
+                    lang,
+                    "prefix.show",
+                    &[("prefix", &guild_settings.prefix(Some(guild_id), default))],
+                )
+            };
+
+            reply(context, msg, content).await;
+            return;
+        }
+        [new_prefix] => *new_prefix,
+        _ => {
+            reply(context, msg, usage(locale, "prefix [new prefix]")).await;
+            return;
+        }
+    };
+
+    if !is_allowed(
+        Permission::Admin,
+        CommandIo::permissions(msg, context).await,
+    ) {
+        reply(context, msg, tr(lang, "prefix.admin_only", &[])).await;
+        return;
+    }
+
+    if let Err(e) = validate_prefix(new_prefix, lang) {
+        reply(context, msg, e).await;
+        return;
+    }
+
+    {
+        let data_read = context.data.read().await;
+        data_read
+            .get::<GuildSettings>()
+            .expect("GuildSettings to be in context")
+            .write()
+            .unwrap()
+            .get_mut(guild_id)
+            .prefix = Some(new_prefix.to_owned());
+    }
+
+    #[cfg(feature = "sqlite")]
+    send_db_event(
+        context,
+        db::Event::Prefix(guild_id.get(), new_prefix.to_owned()),
+    )
+    .await;
+
+    reply(
+        context,
+        msg,
+        tr(
+            lang,
+            "prefix.changed",
+            &[("help", &format!("{} help", new_prefix))],
+        ),
+    )
+    .await;
+}
+
+/// Start or stop counting the words said in the guild. Admin only.
+async fn set_tracking(
+    context: &serenity::client::Context,
+    msg: &Message,
+    enabled: bool,
+    lang: Lang,
+) {
+    let guild_id = match msg.guild_id {
+        Some(guild_id) => guild_id,
+        None => {
+            reply(context, msg, tr(lang, "servers_only", &[])).await;
+            return;
+        }
+    };
+
+    {
+        let data_read = context.data.read().await;
+        data_read
+            .get::<GuildSettings>()
+            .expect("GuildSettings to be in context")
+            .write()
+            .unwrap()
+            .get_mut(guild_id)
+            .tracking_enabled = enabled;
+    }
+
+    let content = if enabled {
+        tr(lang, "tracking.enabled", &[])
+    } else {
+        tr(lang, "tracking.disabled", &[])
+    };
+
+    reply(context, msg, content).await;
+}
+
+/// Reply with an embed describing how often a word has been said lately.
+async fn send_stats(
+    context: &serenity::client::Context,
+    io: &dyn CommandIo,
+    args: &[&str],
+    locale: Locale<'_>,
+) {
+    let lang = locale.lang;
+
+    let normalization = crate::NORMALIZATION.get().copied().unwrap_or_default();
+    let word = match args {
+        [word] => tracking::normalize_word(word, normalization),
+        _ => {
+            reply(context, io, usage(locale, "stats <word>")).await;
+            return;
+        }
+    };
+
+    let now = Utc::now();
+    let bucket = Duration::minutes(STATS_BUCKET_MINUTES);
+
+    let (found, blocked) = {
+        let data_read = context.data.read().await;
+        let max_age = data_read
+            .get::<Config>()
+            .expect("Config to be in context")
+            .read()
+            .unwrap()
+            .max_age;
+        let words = data_read
+            .get::<MessageMap>()
+            .expect("MessageMap to be in context")
+            .read()
+            .await;
+        let blocked = data_read
+            .get::<BlockedWords>()
+            .expect("BlockedWords to be in context")
+            .read()
+            .unwrap()
+            .is_blocked(&word);
+
+        // Enough buckets to cover every instance that hasn't expired
+        let count =
+            (max_age.div_ceil(bucket.num_seconds() as u64) as usize).clamp(1, STATS_MAX_BUCKETS);
+
+        let words = words.guild(guild_key(io.guild_id()));
+
+        let found = match words.get(&word) {
+            Some(instances) => Ok((
+                instances.len(),
+                instances.as_ref().first().copied(),
+                instances.as_ref().last().copied(),
+                stats::buckets(instances.as_ref(), now, bucket, count),
+            )),
+            None => Err(
+                stats::did_you_mean(&word, words.keys().map(|word| word.as_str()))
+                    .map(|suggestion| suggestion.to_owned()),
+            ),
+        };
+
+        (found, blocked)
+    };
+
+    let (count, first, last, buckets) = match found {
+        Ok(found) => found,
+        Err(suggestion) => {
+            let content = match suggestion {
+                Some(suggestion) => tr(
+                    lang,
+                    "stats.did_you_mean",
+                    &[("word", &word), ("suggestion", &suggestion)],
+                ),
+                None => tr(lang, "never_heard", &[("word", &word)]),
+            };
+
+            reply(context, io, content).await;
+            return;
+        }
+    };
+
+    let sparkline = MessageBuilder::new()
+        .push_codeblock(stats::sparkline(&buckets), None)
+        .push(tr(
+            lang,
+            "stats.sparkline",
+            &[("minutes", &STATS_BUCKET_MINUTES)],
+        ))
+        .build();
+
+    let heard = |time: Option<DateTime<Utc>>| {
+        time.map_or_else(|| tr(lang, "stats.never", &[]), relative_time)
+    };
+    let text = |key| tr(lang, key, &[]);
+
+    let result = io
+        .reply_embed(
+            context,
+            None,
+            CreateEmbed::new()
+                .title(tr(lang, "stats.title", &[("word", &word)]))
+                .description(sparkline)
+                .field(text("stats.times_heard"), count.to_string(), true)
+                .field(text("stats.first_heard"), heard(first), true)
+                .field(text("stats.last_heard"), heard(last), true)
+                .field(
+                    text("stats.blocked"),
+                    text(if blocked { "yes" } else { "no" }),
+                    true,
+                ),
+        )
+        .await;
+
+    if let Err(e) = result {
+        send_failed(context, io, "stats", &e).await;
+    }
+}
+
+/// Reply with a paginated list of every word. The invoker can flip pages with
+/// reactions until the leaderboard times out.
+async fn send_leaderboard(
+    context: &serenity::client::Context,
+    msg: &Message,
+    args: &[&str],
+    locale: Locale<'_>,
+) {
+    let lang = locale.lang;
+
+    let page = match parse_page(args, locale) {
+        Ok(page) => page,
+        Err(e) => {
+            reply(context, msg, e).await;
+            return;
+        }
+    };
+
+    let top = {
+        let data_read = context.data.read().await;
+        let words = data_read
+            .get::<MessageMap>()
+            .expect("MessageMap to be in context")
+            .read()
+            .await;
+        let blocklist = data_read
+            .get::<BlockedWords>()
+            .expect("BlockedWords to be in context")
+            .read()
+            .unwrap();
+
+        top_words(words.guild(guild_key(msg.guild_id)), &blocklist, usize::MAX)
+    };
+
+    if top.is_empty() {
+        reply(context, msg, tr(lang, "no_words", &[])).await;
+        return;
+    }
+
+    let pages = page_count(top.len());
+    let mut page = page.min(pages - 1);
+
+    let title = |page: usize| {
+        tr(
+            lang,
+            "leaderboard.title",
+            &[("page", &(page + 1)), ("pages", &pages)],
+        )
+    };
+
+    let mut message = match msg
+        .channel_id
+        .send_message(
+            &context.http,
+            CreateMessage::new().embed(
+                CreateEmbed::new()
+                    .title(title(page))
+                    .description(leaderboard_page(&top, page, lang)),
+            ),
+        )
+        .await
+    {
+        Ok(message) => message,
+        Err(e) => {
+            send_failed(context, msg, "leaderboard", &e).await;
+            return;
+        }
+    };
+
+    if pages == 1 {
+        return;
+    }
+
+    for emoji in &[PREVIOUS_PAGE, NEXT_PAGE] {
+        let reaction = ReactionType::Unicode(emoji.to_string());
+
+        if let Err(e) = message.react(&context.http, reaction).await {
+            warn!("Error adding leaderboard reactions: {}", e);
+            return;
+        }
+    }
+
+    let deadline = Utc::now() + Duration::seconds(LEADERBOARD_TIMEOUT_SECONDS);
+
+    // Until the deadline passes
+    while let Ok(remaining) = (deadline - Utc::now()).to_std() {
+        // Only the invoker can flip pages
+        let reaction = match message
+            .await_reaction(context)
+            .author_id(msg.author.id)
+            .timeout(remaining)
+            .await
+        {
+            Some(reaction) => reaction,
+            None => break,
+        };
+
+        let new_page = match &reaction.emoji {
+            ReactionType::Unicode(emoji) if emoji == PREVIOUS_PAGE => page.saturating_sub(1),
+            ReactionType::Unicode(emoji) if emoji == NEXT_PAGE => (page + 1).min(pages - 1),
+            _ => page,
+        };
+
+        // Remove the reaction, so that it can be clicked again
+        if let Err(e) = reaction.delete(&context.http).await {
+            warn!("Error removing leaderboard reaction: {}", e);
+        }
+
+        if new_page != page {
+            page = new_page;
+
+            if let Err(e) = message
+                .edit(
+                    &context,
+                    EditMessage::new().embed(
+                        CreateEmbed::new()
+                            .title(title(page))
+                            .description(leaderboard_page(&top, page, lang)),
+                    ),
+                )
+                .await
+            {
+                warn!("Error editing leaderboard: {}", e);
+            }
+        }
+    }
+
+    if let Err(e) = message.delete_reactions(&context.http).await {
+        warn!("Error removing leaderboard reactions: {}", e);
+    }
+}
+
+/// Reply with a bar chart of the tracked word instances grouped by word length.
+async fn send_histogram(context: &serenity::client::Context, msg: &Message) {
+    let chart = {
+        let data_read = context.data.read().await;
+        let words = data_read
+            .get::<MessageMap>()
+            .expect("MessageMap to be in context")
+            .read()
+            .await;
+
+        let buckets = histogram::length_histogram(
+            words
+                .guild(guild_key(msg.guild_id))
+                .iter()
+                .map(|(word, instances)| (word.as_str(), instances.len())),
+        );
+
+        histogram::render(&buckets)
+    };
+
+    let message = MessageBuilder::new().push_codeblock(chart, None).build();
+
+    let sent = messenger(context)
+        .await
+        .send_text(msg.channel_id, &message)
+        .await;
+
+    if let Err(e) = sent {
+        send_failed(context, msg, "histogram", &e).await;
+    }
+}
+
+/// Reply with the words said most often in the same messages as a word.
+async fn send_cooccurrence(
+    context: &serenity::client::Context,
+    msg: &Message,
+    args: &[&str],
+    locale: Locale<'_>,
+) {
+    let lang = locale.lang;
+
+    let normalization = crate::NORMALIZATION.get().copied().unwrap_or_default();
+    let word = match args {
+        [word] => tracking::normalize_word(word, normalization),
+        _ => {
+            reply(context, msg, usage(locale, "cooccurrence <word>")).await;
+            return;
+        }
+    };
+
+    let partners = {
+        let data_read = context.data.read().await;
+
+        data_read.get::<Cooccurrences>().map(|cooccurrences| {
+            cooccurrences
+                .read()
+                .unwrap()
+                .get(&guild_key(msg.guild_id))
+                .map_or_else(Vec::new, |pairs| {
+                    cooccurrence::partners(pairs, &word, COOCCURRENCE_COUNT)
+                })
+        })
+    };
+
+    let shown_word = MessageBuilder::new().push_bold_safe(&word).build();
+    let content = match partners {
+        None => tr(lang, "cooccurrence.disabled", &[]),
+        Some(partners) if partners.is_empty() => {
+            tr(lang, "cooccurrence.none", &[("word", &shown_word)])
+        }
+        Some(partners) => {
+            let partners = partners
+                .iter()
+                .map(|(partner, count)| {
+                    MessageBuilder::new()
+                        .push_safe(partner)
+                        .push(format!(" ({})", count))
+                        .build()
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            tr(
+                lang,
+                "cooccurrence.list",
+                &[("word", &shown_word), ("partners", &partners)],
+            )
+        }
+    };
+
+    reply(context, msg, content).await;
+}
+
+/// Reply with a grid of the messages by day of the week and hour.
+async fn send_heatmap(context: &serenity::client::Context, msg: &Message, lang: Lang) {
+    let chart = {
+        let data_read = context.data.read().await;
+
+        data_read
+            .get::<ActivityHeatmap>()
+            .map(|heatmap| heatmap::render(&heatmap.read().unwrap(), lang))
+    };
+
+    let message = match chart {
+        Some(chart) => MessageBuilder::new().push_codeblock(chart, None).build(),
+        None => tr(lang, "heatmap.disabled", &[]),
+    };
+
+    reply(context, msg, message).await;
+}
+
+/// Reply with the highlights of this week in the server.
+async fn send_summary(context: &serenity::client::Context, msg: &Message, lang: Lang) {
+    let guild_id = match msg.guild_id {
+        Some(guild_id) => guild_id,
+        None => {
+            reply(context, msg, tr(lang, "servers_only", &[])).await;
+            return;
+        }
+    };
+
+    let summary = context
+        .data
+        .read()
+        .await
+        .get::<WeeklySummaries>()
+        .expect("WeeklySummaries to be in context")
+        .read()
+        .unwrap()
+        .summary(ids::GuildId(guild_id.get()), week_start(Utc::now()));
+
+    let view = View::Summary(summary.start, TOP_WORDS);
+    let messenger = messenger(context).await;
+    let sent = messenger
+        .send_components(
+            msg.channel_id,
+            summary::embed(&summary, lang),
+            components::buttons(view),
+        )
+        .await;
+
+    match sent {
+        Ok(message) => components::expire_later(messenger, msg.channel_id, message),
+        Err(e) => send_failed(context, msg, "the server summary", &e).await,
+    }
+}
+
+/// The embed of a message with buttons showing `view` in `guild`, with the
+/// words it lists. `None` for a summary outside of guilds.
+async fn render(
+    context: &serenity::client::Context,
+    guild: Option<GuildId>,
+    view: View,
+    lang: Lang,
+) -> Option<(CreateEmbed, Vec<String>)> {
+    let data_read = context.data.read().await;
+
+    match view {
+        View::Top(n) => {
+            let words = data_read
+                .get::<MessageMap>()
+                .expect("MessageMap to be in context")
+                .read()
+                .await;
+            let top = top_words(
+                words.guild(guild_key(guild)),
+                &data_read
+                    .get::<BlockedWords>()
+                    .expect("BlockedWords to be in context")
+                    .read()
+                    .unwrap(),
+                n,
+            );
+            let words = top.iter().map(|top| top.word.clone()).collect();
+
+            Some((top_embed(&top, lang), words))
+        }
+        View::Summary(start, n) => {
+            let summary = data_read
+                .get::<WeeklySummaries>()
+                .expect("WeeklySummaries to be in context")
+                .read()
+                .unwrap()
+                .summary_with(ids::GuildId(guild?.get()), start, n);
+            let words = summary
+                .top_words
+                .iter()
+                .map(|(word, _count)| word.clone())
+                .collect();
+
+            Some((summary::embed(&summary, lang), words))
+        }
+    }
+}
+
+/// Answer a button or menu under a message of pino, e.g. 🔄 under
+/// `!pino top`. Blocking is for admins only, the others are told so privately.
+pub async fn component(context: &serenity::client::Context, interaction: &ComponentInteraction) {
+    let id = match ComponentId::decode(&interaction.data.custom_id) {
+        Some(id) => id,
+        None => {
+            warn!("Unknown component {}", interaction.data.custom_id);
+            return;
+        }
+    };
+
+    let guild = interaction.guild_id;
+    let (_prefix, lang) = guild_locale(context, guild).await;
+    let permissions = interaction
+        .member
+        .as_ref()
+        .and_then(|member| member.permissions)
+        .unwrap_or_else(Permissions::empty);
+    let blocking = matches!(id.action, Action::Block | Action::BlockChosen);
+    let update = |message| CreateInteractionResponse::UpdateMessage(message);
+    let ephemeral = || CreateInteractionResponseMessage::new().ephemeral(true);
+
+    let response = if components::expired(*interaction.message.timestamp, Utc::now()) {
+        // Still there if pino restarted before taking them away
+        update(CreateInteractionResponseMessage::new().components(Vec::new()))
+    } else if blocking && !is_allowed(Permission::Admin, permissions) {
+        let denied = tr(
+            lang,
+            "denied",
+            &[("who", &Permission::Admin.who(lang)), ("command", &"🚫")],
+        );
+
+        CreateInteractionResponse::Message(ephemeral().content(denied))
+    } else {
+        match id.action {
+            Action::Refresh | Action::More => {
+                let view = match id.action {
+                    Action::More => id.view.more(),
+                    _ => id.view,
+                };
+                let embed = match render(context, guild, view, lang).await {
+                    Some((embed, _words)) => embed,
+                    None => return,
+                };
+
+                update(
+                    CreateInteractionResponseMessage::new()
+                        .embed(embed)
+                        .components(components::buttons(view)),
+                )
+            }
+            Action::Block => {
+                let words = render(context, guild, id.view, lang)
+                    .await
+                    .map(|(_embed, words)| words)
+                    .unwrap_or_default();
+                let menu = components::block_menu(
+                    id.view,
+                    &words,
+                    tr(lang, "buttons.block_placeholder", &[]),
+                );
+
+                let message = if menu.is_empty() {
+                    ephemeral().content(tr(lang, "no_words", &[]))
+                } else {
+                    ephemeral().components(menu)
+                };
+
+                CreateInteractionResponse::Message(message)
+            }
+            Action::BlockChosen => {
+                let chosen = match &interaction.data.kind {
+                    ComponentInteractionDataKind::StringSelect { values } => values.clone(),
+                    _ => Vec::new(),
+                };
+
+                let mut lines = Vec::new();
+                for word in chosen {
+                    lines.push(block_pattern(context, guild, Pattern::Word(word), lang).await);
+                }
+
+                crate::record_audit(
+                    &*context.data.read().await,
+                    audit::AuditEvent::Command {
+                        command: "block".to_owned(),
+                        user_id: Some(interaction.user.id.get()),
+                        channel_id: Some(interaction.channel_id.get()),
+                        guild_id: guild.map(|id| id.get()),
+                        timestamp: Utc::now(),
+                    },
+                );
+
+                // The menu is replaced with what was blocked
+                update(
+                    CreateInteractionResponseMessage::new()
+                        .content(lines.join("\n"))
+                        .components(Vec::new()),
+                )
+            }
+        }
+    };
+
+    if let Err(e) = interaction.create_response(&context.http, response).await {
+        warn!("Error answering a button: {}", e);
+    }
+}
+
+/// The channels in `counts` that `keep` allows, the most active first. Ties
+/// are broken by channel id, so that the order doesn't depend on the map.
+fn most_active(
+    counts: &HashMap<ChannelId, u64>,
+    keep: impl Fn(ChannelId) -> bool,
+) -> Vec<(ChannelId, u64)> {
+    let mut channels: Vec<_> = counts
+        .iter()
+        .map(|(&channel, &count)| (channel, count))
+        .filter(|&(channel, _count)| keep(channel))
+        .collect();
+    channels.sort_unstable_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+
+    channels
+}
+
+/// Reply with how many messages were sent in each channel of the server since
+/// pino started, the most active first.
+async fn send_activity(context: &serenity::client::Context, msg: &Message, lang: Lang) {
+    let guild_id = match msg.guild_id {
+        Some(guild_id) => guild_id,
+        None => {
+            reply(context, msg, tr(lang, "servers_only", &[])).await;
+            return;
+        }
+    };
+
+    // Only this server's channels, the counts are of every server
+    let channels = match guild_id.channels(&context.http).await {
+        Ok(channels) => channels,
+        Err(e) => {
+            warn!("Error fetching the channels of the server: {}", e);
+            return;
+        }
+    };
+
+    let active = {
+        let data_read = context.data.read().await;
+        let counts = data_read
+            .get::<ChannelActivity>()
+            .expect("ChannelActivity to be in context")
+            .read()
+            .unwrap();
+
+        most_active(&counts, |channel| channels.contains_key(&channel))
+    };
+
+    if active.is_empty() {
+        reply(context, msg, tr(lang, "activity.empty", &[])).await;
+        return;
+    }
+
+    let lines: Vec<_> = active
+        .iter()
+        .map(|(channel, count)| {
+            tr(
+                lang,
+                "activity.line",
+                &[("channel", &channel.mention()), ("count", count)],
+            )
+        })
+        .collect();
+
+    for page in lines.chunks(ACTIVITY_PAGE_SIZE) {
+        reply(context, msg, page.join("\n")).await;
+    }
+}
+
+/// The word of `args` for the word of the week, normalized. `None` after
+/// replying why, if it can't be voted.
+async fn candidate(
+    context: &serenity::client::Context,
+    msg: &Message,
+    args: &[&str],
+    locale: Locale<'_>,
+    command: &str,
+) -> Option<String> {
+    let lang = locale.lang;
+
+    let normalization = crate::NORMALIZATION.get().copied().unwrap_or_default();
+    let word = match args {
+        [word] => tracking::normalize_word(word, normalization),
+        _ => {
+            reply(context, msg, usage(locale, &format!("{} <word>", command))).await;
+            return None;
+        }
+    };
+
+    let blocked = context
+        .data
+        .read()
+        .await
+        .get::<BlockedWords>()
+        .expect("BlockedWords to be in context")
+        .read()
+        .unwrap()
+        .is_blocked(&word);
+
+    if blocked || !crate::WORD_REGEX.get().unwrap().is_match(&word) {
+        reply(context, msg, tr(lang, "vote.invalid", &[("word", &word)])).await;
+        return None;
+    }
+
+    Some(word)
+}
+
+/// Add a word to the candidates for the word of the week of the server.
+async fn nominate(
+    context: &serenity::client::Context,
+    msg: &Message,
+    args: &[&str],
+    locale: Locale<'_>,
+) {
+    let lang = locale.lang;
+
+    let guild_id = match msg.guild_id {
+        Some(guild_id) => guild_id,
+        None => {
+            reply(context, msg, tr(lang, "servers_only", &[])).await;
+            return;
+        }
+    };
+    let word = match candidate(context, msg, args, locale, "nominate").await {
+        Some(word) => word,
+        None => return,
+    };
+
+    let nominated = context
+        .data
+        .read()
+        .await
+        .get::<WordOfTheWeek>()
+        .expect("WordOfTheWeek to be in context")
+        .write()
+        .unwrap()
+        .nominate(ids::GuildId(guild_id.get()), &word, week_start(Utc::now()));
+
+    let key = if nominated {
+        "vote.nominated"
+    } else {
+        "vote.already_nominated"
+    };
+
+    reply(context, msg, tr(lang, key, &[("word", &word)])).await;
+}
+
+/// Vote a nominated word as the word of the week of the server. Voting again
+/// moves the vote.
+async fn vote(
+    context: &serenity::client::Context,
+    msg: &Message,
+    args: &[&str],
+    locale: Locale<'_>,
+) {
+    let lang = locale.lang;
+
+    let guild_id = match msg.guild_id {
+        Some(guild_id) => guild_id,
+        None => {
+            reply(context, msg, tr(lang, "servers_only", &[])).await;
+            return;
+        }
+    };
+    let word = match candidate(context, msg, args, locale, "vote").await {
+        Some(word) => word,
+        None => return,
+    };
+
+    let voted = context
+        .data
+        .read()
+        .await
+        .get::<WordOfTheWeek>()
+        .expect("WordOfTheWeek to be in context")
+        .write()
+        .unwrap()
+        .vote(
+            ids::GuildId(guild_id.get()),
+            ids::UserId(msg.author.id.get()),
+            &word,
+            week_start(Utc::now()),
+        );
+
+    let content = match voted {
+        Ok(None) => tr(lang, "vote.voted", &[("word", &word)]),
+        Ok(Some(previous)) => tr(
+            lang,
+            "vote.moved",
+            &[("word", &word), ("previous", &previous)],
+        ),
+        Err(voting::VoteError::NotNominated) => tr(lang, "vote.not_nominated", &[("word", &word)]),
+    };
+
+    reply(context, msg, content).await;
+}
+
+/// Reply with the words nominated this week in the server and their votes.
+async fn send_votes(context: &serenity::client::Context, msg: &Message, lang: Lang) {
+    let guild_id = match msg.guild_id {
+        Some(guild_id) => guild_id,
+        None => {
+            reply(context, msg, tr(lang, "servers_only", &[])).await;
+            return;
+        }
+    };
+
+    let standings = context
+        .data
+        .read()
+        .await
+        .get::<WordOfTheWeek>()
+        .expect("WordOfTheWeek to be in context")
+        .read()
+        .unwrap()
+        .standings(ids::GuildId(guild_id.get()), week_start(Utc::now()));
+
+    if standings.is_empty() {
+        reply(context, msg, tr(lang, "vote.none", &[])).await;
+        return;
+    }
+
+    let lines: Vec<_> = standings
+        .iter()
+        .map(|(word, votes)| tr(lang, "vote.line", &[("word", word), ("count", votes)]))
+        .collect();
+
+    for page in lines.chunks(VOTES_PAGE_SIZE) {
+        reply(context, msg, page.join("\n")).await;
+    }
+}
+
+/// Reply with an image of the most said words, bigger the more they are said.
+/// Without a font, or if the image can't be drawn, the cloud is sent as text.
+async fn send_wordcloud(context: &serenity::client::Context, msg: &Message, lang: Lang) {
+    let (top, font) = {
+        let data_read = context.data.read().await;
+        let settings = data_read
+            .get::<WordcloudSettings>()
+            .expect("WordcloudSettings to be in context")
+            .clone();
+        let words = data_read
+            .get::<MessageMap>()
+            .expect("MessageMap to be in context")
+            .read()
+            .await;
+        let blocklist = data_read
+            .get::<BlockedWords>()
+            .expect("BlockedWords to be in context")
+            .read()
+            .unwrap();
+
+        (
+            top_words(
+                words.guild(guild_key(msg.guild_id)),
+                &blocklist,
+                settings.max_words,
+            ),
+            settings.font,
+        )
+    };
+
+    if top.is_empty() {
+        reply(context, msg, tr(lang, "no_words", &[])).await;
+        return;
+    }
+
+    let counts: Vec<_> = top
+        .into_iter()
+        .map(|top_word| (top_word.word, top_word.count))
+        .collect();
+
+    let png = match font {
+        Some(font) => {
+            let typing = messenger(context)
+                .await
+                .broadcast_typing(msg.channel_id)
+                .await;
+
+            if let Err(e) = typing {
+                debug!("Error showing pino as typing: {}", e);
+            }
+
+            let counts = counts.clone();
+            // Drawing takes a while, keep it off the thread of the handlers
+            let drawn = tokio::task::spawn_blocking(move || {
+                let words: Vec<_> = counts
+                    .iter()
+                    .map(|(word, count)| (word.as_str(), *count))
+                    .collect();
+
+                wordcloud::render(&words, &font)
+            })
+            .await
+            .context("drawing the word cloud")
+            .and_then(|drawn| drawn);
+
+            match drawn {
+                Ok(png) => Some(png),
+                Err(e) => {
+                    warn!("Error drawing the word cloud, sending it as text: {:#}", e);
+                    None
+                }
+            }
+        }
+        None => None,
+    };
+
+    let sent = match png {
+        Some(png) => {
+            msg.channel_id
+                .send_files(
+                    &context.http,
+                    vec![CreateAttachment::bytes(png, "wordcloud.png")],
+                    CreateMessage::new(),
+                )
+                .await
+        }
+        None => {
+            let words: Vec<_> = counts
+                .iter()
+                .map(|(word, count)| (word.as_str(), *count))
+                .collect();
+
+            msg.channel_id
+                .say(&context.http, wordcloud::text_cloud(&words))
+                .await
+        }
+    };
+
+    if let Err(e) = sent {
+        send_failed(context, msg, "word cloud", &e).await;
+    }
+}
+
+/// Whether someone with these permissions can use a command that needs `permission`.
+fn is_allowed(permission: Permission, permissions: Permissions) -> bool {
+    match permission {
+        Permission::Everyone => true,
+        Permission::Moderator => permissions.administrator() || permissions.manage_messages(),
+        Permission::Admin => permissions.administrator(),
+        // Not a permission in the guild, see `is_owner`
+        Permission::Owner => false,
+    }
+}
+
+/// Whether the author of the command owns the application. Nobody does if
+/// the owner couldn't be fetched at startup.
+async fn is_owner(context: &serenity::client::Context, io: &dyn CommandIo) -> bool {
+    context.data.read().await.get::<Owner>() == Some(&io.author_id())
+}
+
+/// Upload the words learned in this server as a JSON attachment. Admin only.
+async fn export_words(context: &serenity::client::Context, msg: &Message, lang: Lang) {
+    let snapshot = {
+        let data_read = context.data.read().await;
+        let words = data_read
+            .get::<MessageMap>()
+            .expect("MessageMap to be in context")
+            .read()
+            .await;
+
+        FrequencySnapshot::from_word_map(words.guild(guild_key(msg.guild_id)))
+    };
+
+    let json = match serde_json::to_vec(&snapshot) {
+        Ok(json) => json,
+        Err(e) => {
+            error!("Error serializing export: {}", e);
+            return;
+        }
+    };
+
+    let filename = format!("pino-export-{}.json", Utc::now().format("%Y-%m-%d"));
+    let content = tr(lang, "export.done", &[("count", &snapshot.words.len())]);
+
+    if let Err(e) = msg
+        .channel_id
+        .send_files(
+            &context.http,
+            vec![CreateAttachment::bytes(json, filename)],
+            CreateMessage::new().content(content),
+        )
+        .await
+    {
+        send_failed(context, msg, "export", &e).await;
+    }
+}
+
+/// Merge the words from the JSON attachment of the message into the words of this
+/// server. Admin only.
+async fn import_words(context: &serenity::client::Context, msg: &Message, locale: Locale<'_>) {
+    let lang = locale.lang;
+
+    let content = match import_attachment(context, msg, locale).await {
+        Ok(added) => tr(lang, "import.done", &[("count", &added)]),
+        Err(e) => tr(lang, "import.failed", &[("error", &e)]),
+    };
+
+    reply(context, msg, content).await;
+}
+
+/// Returns the amount of added word instances, or why nothing was imported.
+async fn import_attachment(
+    context: &serenity::client::Context,
+    msg: &Message,
+    locale: Locale<'_>,
+) -> Result<usize, String> {
+    let lang = locale.lang;
+
+    let attachment = msg.attachments.first().ok_or_else(|| {
+        tr(
+            lang,
+            "import.no_attachment",
+            &[("export", &format!("{} export", locale.prefix))],
+        )
+    })?;
+
+    // Check the advertised size before downloading anything
+    if attachment.size as usize > persistence::MAX_IMPORT_SIZE {
+        let error = ImportError::TooLarge(attachment.size as usize);
+        return Err(import_error(&error, lang));
+    }
+
+    let content = attachment
+        .download()
+        .await
+        .map_err(|e| tr(lang, "import.download", &[("error", &e)]))?;
+
+    let snapshot =
+        persistence::parse_import(&content, Utc::now()).map_err(|e| import_error(&e, lang))?;
+
+    let data_read = context.data.read().await;
+    let mut words = data_read
+        .get::<MessageMap>()
+        .expect("MessageMap to be in context")
+        .write()
+        .await;
+
+    Ok(snapshot.merge_into(words.guild_mut(guild_key(msg.guild_id))))
+}
+
+/// Why an export can't be imported, in the language of the guild.
+fn import_error(error: &ImportError, lang: Lang) -> String {
+    match error {
+        ImportError::TooLarge(size) => tr(
+            lang,
+            "import.too_large",
+            &[("size", size), ("max", &persistence::MAX_IMPORT_SIZE)],
+        ),
+        ImportError::Malformed(e) => tr(lang, "import.malformed", &[("error", e)]),
+        ImportError::UnsupportedVersion(version) => tr(
+            lang,
+            "import.version",
+            &[
+                ("version", version),
+                ("expected", &persistence::SNAPSHOT_VERSION),
+            ],
+        ),
+        ImportError::BadTimestamp(word, time) => tr(
+            lang,
+            "import.bad_timestamp",
+            &[("word", word), ("time", time)],
+        ),
+    }
+}
+
+/// Gather the state for `!pino debug`. Only read locks are taken.
+async fn debug_dump(data: &TypeMap, now: DateTime<Utc>) -> DebugDump {
+    let config = data
+        .get::<Config>()
+        .expect("Config to be in context")
+        .read()
+        .unwrap()
+        .entries()
+        .into_iter()
+        .collect();
+    let session = data
+        .get::<ReadySession>()
+        .expect("ReadySession to be in context")
+        .read()
+        .unwrap()
+        .clone();
+    let (words, instances, top) = {
+        let words = data
+            .get::<MessageMap>()
+            .expect("MessageMap to be in context")
+            .read()
+            .await;
+        // Every server added up, the dump is about the whole bot
+        let mut top: Vec<_> = crate::snapshots::counts(&words).into_iter().collect();
+        top.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        top.truncate(debug_dump::DUMP_TOP_WORDS);
+
+        (words.word_count(), words.instance_count(), top)
+    };
+    let recent_channel = data
+        .get::<RecentChannel>()
+        .expect("RecentChannel to be in context")
+        .read()
+        .await
+        .current();
+    let paused = data
+        .get::<Paused>()
+        .expect("Paused to be in context")
+        .read()
+        .unwrap()
+        .paused(now)
+        .map(|(guild, until)| debug_dump::PausedGuild {
+            guild_id: guild.map(ids::GuildId::get),
+            until,
+        })
+        .collect();
+    let next_send = data
+        .get::<Schedule>()
+        .expect("Schedule to be in context")
+        .read()
+        .unwrap()
+        .next()
+        .map(|(next, _guild)| next);
+    let (last_snapshot, keeps_snapshots) = {
+        let history = data
+            .get::<SnapshotHistory>()
+            .expect("SnapshotHistory to be in context")
+            .read()
+            .unwrap();
+
+        (history.latest(), history.is_enabled())
+    };
+
+    DebugDump {
+        taken: now,
+        command_line: debug_dump::redact_args(std::env::args()),
+        config,
+        guilds: session.as_ref().map(Session::guilds),
+        words,
+        instances,
+        top_words: top
+            .into_iter()
+            .map(|(word, count)| debug_dump::DumpWord { word, count })
+            .collect(),
+        recent_channel: recent_channel.map(|(channel_id, guild_id)| debug_dump::ChannelPointer {
+            channel_id: channel_id.get(),
+            guild_id: guild_id.map(|id| id.get()),
+        }),
+        paused,
+        next_send,
+        health: debug_dump::TaskHealth::check(
+            now,
+            session.map(|session| session.started),
+            next_send,
+            last_snapshot,
+            keeps_snapshots,
+        ),
+        task_restarts: data
+            .get::<TaskSupervisor>()
+            .map(|supervisor| supervisor.restarts())
+            .unwrap_or_default(),
+    }
+}
+
+/// DM the owner a JSON dump of the state of pino, in parts if it's too large. Owner only.
+async fn send_debug_dump(context: &serenity::client::Context, msg: &Message, lang: Lang) {
+    let dump = debug_dump(&*context.data.read().await, Utc::now()).await;
+
+    let json = match serde_json::to_vec_pretty(&dump) {
+        Ok(json) => json,
+        Err(e) => {
+            error!("Error serializing debug dump: {}", e);
+            return;
+        }
+    };
+
+    let channel = match msg.author.create_dm_channel(context).await {
+        Ok(channel) => channel,
+        Err(e) => {
+            warn!("Error opening DM for the debug dump: {}", e);
+            reply(context, msg, tr(lang, "debug.no_dm", &[])).await;
+            return;
+        }
+    };
+
+    let filename = format!("pino-debug-{}.json", dump.taken.format("%Y-%m-%d-%H%M%S"));
+    let parts = debug_dump::split_attachment(&json, debug_dump::MAX_ATTACHMENT_SIZE);
+    let count = parts.len();
+
+    for (i, part) in parts.into_iter().enumerate() {
+        let (name, content) = if count == 1 {
+            (filename.clone(), tr(lang, "debug.dump", &[]))
+        } else {
+            (
+                format!("{}.part{}", filename, i + 1),
+                tr(
+                    lang,
+                    "debug.dump_part",
+                    &[("part", &(i + 1)), ("parts", &count)],
+                ),
+            )
+        };
+
+        if let Err(e) = channel
+            .id
+            .send_files(
+                &context.http,
+                vec![CreateAttachment::bytes(part, name)],
+                CreateMessage::new().content(content),
+            )
+            .await
+        {
+            warn!("Error sending debug dump: {}", e);
+            return;
+        }
+    }
+
+    if msg.guild_id.is_some() {
+        reply(context, msg, tr(lang, "debug.sent", &[])).await;
+    }
+}
+
+/// How the commands talk on Discord.
+async fn messenger(context: &serenity::client::Context) -> Arc<dyn Messenger> {
+    context
+        .data
+        .read()
+        .await
+        .get::<Messaging>()
+        .expect("Messaging to be in context")
+        .clone()
+}
+
+/// Log an error of sending `what` in reply to a command, and count it for the
+/// alerts of the owner.
+async fn send_failed(
+    context: &serenity::client::Context,
+    io: &dyn CommandIo,
+    what: &str,
+    error: &serenity::Error,
+) {
+    warn!("Error sending {}: {}", what, error);
+
+    crate::report_error(
+        &*context.data.read().await,
+        io.guild_id(),
+        delivery::classify_serenity(error),
+        error,
+    );
+}
+
+async fn reply(
+    context: &serenity::client::Context,
+    io: &dyn CommandIo,
+    content: impl Into<String>,
+) {
+    if let Err(e) = io.reply_text(context, content.into()).await {
+        send_failed(context, io, "reply", &e).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use utils::SortedVec;
+
+    const EN: Locale = Locale {
+        lang: Lang::En,
+        prefix: PREFIX,
+    };
+
+    #[test]
+    fn parse_commands() {
+        assert_eq!(
+            Some(Invocation {
+                name: "top",
+                args: vec!["10"]
+            }),
+            parse(PREFIX, "!pino top 10")
+        );
+        assert_eq!(
+            Some(Invocation {
+                name: "top",
+                args: vec![]
+            }),
+            parse(PREFIX, "  !pino   top  ")
+        );
+        assert_eq!(
+            Some(Invocation {
+                name: "histogram",
+                args: vec![]
+            }),
+            parse(PREFIX, "!histogram")
+        );
+    }
+
+    #[test]
+    fn settings_listing() {
+        let cli = Settings {
+            default_word: Some("pino".to_owned()),
+            ..Settings::default()
+        };
+        let mut guild = Settings::default();
+        guild.set("emoji_mode", "on").unwrap();
+
+        assert_eq!(
+            vec![
+                "message_template = {word}",
+                "emoji_mode = on (this server)",
+                "default_word = pino",
+                "language = en",
+                "scoring = count",
+                "stop_words = --stop-words-file"
+            ],
+            settings_lines(&guild, &cli, Lang::En)
+        );
+    }
+
+    #[test]
+    fn parse_custom_prefixes() {
+        let top = Some(Invocation {
+            name: "top",
+            args: vec!["3"],
+        });
+
+        assert_eq!(top, parse("?pino", "?pino top 3"));
+        assert_eq!(top, parse("?pino", "?top 3"));
+        assert_eq!(None, parse("?pino", "!pino top 3"));
+        assert_eq!(None, parse("?pino", "!top 3"));
+
+        // no short form without a symbol
+        assert_eq!(top, parse("pino", "pino top 3"));
+        assert_eq!(None, parse("pino", "ptop 3"));
+        assert_eq!(None, parse("pino", "!top 3"));
+    }
+
+    #[test]
+    fn parse_mentions() {
+        let top = Some(Invocation {
+            name: "top",
+            args: vec!["3"],
+        });
+
+        assert_eq!(top, parse_mention(42, "<@42> top 3"));
+        assert_eq!(top, parse_mention(42, "  <@!42>   top 3"));
+        assert_eq!(None, parse_mention(42, "<@43> top 3"));
+        assert_eq!(None, parse_mention(42, "<@42>"));
+        assert_eq!(None, parse_mention(42, "<@42 top 3"));
+        assert_eq!(None, parse_mention(42, "<@&42> top 3"));
+        assert_eq!(None, parse_mention(42, "!pino top 3"));
+    }
+
+    #[test]
+    fn prefix_validation() {
+        assert!(validate_prefix("!pino", Lang::En).is_ok());
+        assert!(validate_prefix("?", Lang::En).is_ok());
+        assert!(validate_prefix("pinò", Lang::En).is_ok());
+        assert!(validate_prefix("", Lang::En).is_err());
+        assert!(validate_prefix("!pinot", Lang::En).is_err());
+        assert!(validate_prefix("! p", Lang::En).is_err());
+    }
+
+    #[test]
+    fn parse_not_commands() {
+        assert_eq!(None, parse(PREFIX, ""));
+        assert_eq!(None, parse(PREFIX, "!pino"));
+        assert_eq!(None, parse(PREFIX, "!"));
+        assert_eq!(None, parse(PREFIX, "ciao pino top"));
+        assert_eq!(None, parse(PREFIX, "pino top"));
+    }
+
+    #[test]
+    fn top_amount() {
+        assert_eq!(Ok(TOP_DEFAULT), parse_top_amount(&[], EN));
+        assert_eq!(Ok(3), parse_top_amount(&["3"], EN));
+        assert_eq!(Ok(TOP_MAX), parse_top_amount(&["1000"], EN));
+        assert!(parse_top_amount(&["0"], EN).is_err());
+        assert!(parse_top_amount(&["-1"], EN).is_err());
+        assert!(parse_top_amount(&["tanti"], EN).is_err());
+        assert_eq!(
+            Err("`tanti` is not a valid amount of words, try `?p top 10`".to_owned()),
+            parse_top_amount(
+                &["tanti"],
+                Locale {
+                    lang: Lang::En,
+                    prefix: "?p"
+                }
+            )
+        );
+
+        assert_eq!((false, vec!["3"]), split_trending_flag(&["3"]));
+        assert_eq!((true, vec!["3"]), split_trending_flag(&["--trending", "3"]));
+        assert_eq!((true, vec!["3"]), split_trending_flag(&["3", "--trending"]));
+        assert_eq!((true, vec![]), split_trending_flag(&["--trending"]));
+    }
+
+    #[test]
+    fn history_amount() {
+        assert_eq!(Ok(HISTORY_DEFAULT), parse_history_amount(&[], EN));
+        assert_eq!(Ok(3), parse_history_amount(&["3"], EN));
+        assert_eq!(
+            Ok(sent_log::LOG_CAPACITY),
+            parse_history_amount(&["1000"], EN)
+        );
+        assert!(parse_history_amount(&["0"], EN).is_err());
+        assert!(parse_history_amount(&["dieci"], EN).is_err());
+    }
+
+    #[test]
+    fn top() {
+        let now = Utc::now();
+        let mut words = WordMap::new();
+        words.insert("ciao".to_owned(), SortedVec::from_vec(vec![now, now]));
+        words.insert(
+            "gnocchi".to_owned(),
+            SortedVec::from_vec(vec![now - Duration::seconds(3), now - Duration::seconds(5)]),
+        );
+        words.insert("pino".to_owned(), SortedVec::from_vec(vec![now]));
+
+        let top = top_words(&words, &Blocklist::default(), 2);
+
+        assert_eq!(
+            vec![
+                TopWord {
+                    word: "ciao".to_owned(),
+                    count: 2,
+                    last_heard: now
+                },
+                TopWord {
+                    word: "gnocchi".to_owned(),
+                    count: 2,
+                    last_heard: now - Duration::seconds(3)
+                }
+            ],
+            top
+        );
+        assert!(top_words(&WordMap::new(), &Blocklist::default(), 5).is_empty());
+
+        // blocked words aren't listed, whatever blocked them
+        let mut blocklist = Blocklist::default();
+        blocklist.add(Pattern::parse("/^gn/").unwrap());
+        let top = top_words(&words, &blocklist, 2);
+        assert_eq!(
+            vec!["ciao", "pino"],
+            top.iter().map(|top| top.word.as_str()).collect::<Vec<_>>()
+        );
+        blocklist.block_until("ciao", now + Duration::hours(1));
+        assert_eq!(1, top_words(&words, &blocklist, 2).len());
+    }
+
+    #[test]
+    fn cooldown() {
+        let now = Utc::now();
+        let cooldown = Duration::seconds(10);
+        let mut cooldowns = Cooldowns::default();
+
+        assert!(cooldowns.try_use(1, now, cooldown));
+        assert!(!cooldowns.try_use(1, now + Duration::seconds(9), cooldown));
+        assert!(cooldowns.try_use(2, now + Duration::seconds(9), cooldown));
+        assert!(cooldowns.try_use(1, now + Duration::seconds(10), cooldown));
+    }
+
+    #[test]
+    fn relative() {
+        let time = chrono::TimeZone::timestamp_opt(&Utc, 1_600_000_000, 0).unwrap();
+        assert_eq!("<t:1600000000:R>", relative_time(time));
+    }
+
+    #[test]
+    fn next_message() {
+        let now = chrono::TimeZone::timestamp_opt(&Utc, 1_600_000_000, 0).unwrap();
+        let next = Some(now + Duration::seconds(90));
+        let mut pause = PauseState::default();
+
+        assert_eq!(
+            "Next squawk <t:1600000090:R> 🦜",
+            describe_next(next, &pause, now, EN)
+        );
+        assert_eq!(
+            "I'll squawk once someone talks here 🦜",
+            describe_next(None, &pause, now, EN)
+        );
+
+        pause.pause(now, Some(Duration::hours(1)));
+        assert_eq!(
+            "I'm paused, back <t:1600003600:R> 🤐",
+            describe_next(next, &pause, now, EN)
+        );
+        // expired pauses don't count
+        assert_eq!(
+            "Next squawk <t:1600000090:R> 🦜",
+            describe_next(next, &pause, now + Duration::hours(2), EN)
+        );
+
+        pause.pause(now, None);
+        assert_eq!(
+            "I'm paused until `!pino resume` 🤐",
+            describe_next(next, &pause, now, EN)
+        );
+        // the hint shows the prefix of the guild
+        assert_eq!(
+            "I'm paused until `?p resume` 🤐",
+            describe_next(
+                next,
+                &pause,
+                now,
+                Locale {
+                    lang: Lang::En,
+                    prefix: "?p"
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn pages() {
+        assert_eq!(1, page_count(0));
+        assert_eq!(1, page_count(10));
+        assert_eq!(2, page_count(11));
+
+        assert_eq!(Ok(0), parse_page(&[], EN));
+        assert_eq!(Ok(2), parse_page(&["3"], EN));
+        assert!(parse_page(&["0"], EN).is_err());
+        assert!(parse_page(&["primo"], EN).is_err());
+
+        let now = Utc::now();
+        let top: Vec<_> = (0..15)
+            .map(|i| TopWord {
+                word: format!("word{}", i),
+                count: 15 - i,
+                last_heard: now,
+            })
+            .collect();
+
+        let second = leaderboard_page(&top, 1, Lang::En);
+        assert_eq!(5, second.lines().count());
+        assert!(second.starts_with("**11.** word10 — 5 times"));
+        assert!(leaderboard_page(&top, 2, Lang::En).is_empty());
+    }
+
+    #[test]
+    fn forget() {
+        let now = Utc::now();
+        let guild = Some(GuildId::new(1));
+        let mut words = WordStore::new();
+        words.learn(guild_key(guild), "ciao".to_owned(), now);
+        words.learn(guild_key(guild), "ciao".to_owned(), now);
+        words.learn(None, "ciao".to_owned(), now);
+
+        assert_eq!(0, forget_word(&mut words, guild, "gnocchi"));
+        assert_eq!(2, forget_word(&mut words, guild, "CIAO"));
+        assert!(words.guild(guild_key(guild)).is_empty());
+        // only in the guild it was asked in
+        assert_eq!(1, words.guild(None)["ciao"].len());
+    }
+
+    #[test]
+    fn blocklist() {
+        let mut blocklist = Blocklist::default();
+        assert!(blocklist_pages(&blocklist).is_empty());
+
+        for i in 0..41 {
+            blocklist.add(Pattern::parse(&format!("word{}", i)).unwrap());
+        }
+        blocklist.add(Pattern::parse("/^gn/").unwrap());
+
+        let pages = blocklist_pages(&blocklist);
+        assert_eq!(3, pages.len());
+        assert_eq!(20, pages[0].lines().count());
+        assert!(pages[0].starts_with("**1.** `word0`"));
+        assert_eq!("**41.** `word40`\n**42.** `/^gn/`", pages[2]);
+    }
+
+    #[test]
+    fn help() {
+        fn noop<'a>(
+            _context: &'a serenity::client::Context,
+            _msg: &'a Message,
+            _args: &'a [&'a str],
+            _locale: Locale<'a>,
+        ) -> CommandFuture<'a> {
+            Box::pin(async {})
+        }
+
+        let commands = [
+            Command {
+                name: "top",
+                usage: "[n]",
+                permission: Permission::Everyone,
+                cooldown: 0,
+                handler: Handler::Message(noop),
+            },
+            Command {
+                name: "reset",
+                usage: "",
+                permission: Permission::Moderator,
+                cooldown: 0,
+                handler: Handler::Message(noop),
+            },
+        ];
+
+        assert_eq!(
+            vec![
+                "`!pino top [n]` — the most said words",
+                "`!pino reset` — forget everything, after confirming (only people who can manage messages)"
+            ],
+            help_lines(&commands, EN)
+        );
+        assert_eq!(
+            "`!pino reset` — dimentico tutto, dopo una conferma (solo per chi può gestire i messaggi)",
+            help_lines(
+                &commands,
+                Locale {
+                    lang: Lang::It,
+                    prefix: PREFIX
+                }
+            )[1]
+        );
+    }
+
+    #[test]
+    fn version() {
+        let now = Utc::now();
+        let mut words = WordMap::new();
+        words.insert("ciao".to_owned(), SortedVec::from_vec(vec![now, now]));
+        words.insert("pino".to_owned(), SortedVec::from_vec(vec![now]));
+
+        let text = version_text(Some((Duration::minutes(90), 3)), &words, Lang::En);
+        let lines: Vec<_> = text.lines().collect();
+
+        assert!(lines[0].starts_with(&format!("pino {}", env!("CARGO_PKG_VERSION"))));
+        assert_eq!("Up for 1h 30m in 3 servers", lines[1]);
+        assert_eq!("Tracking 2 words said 3 times", lines[2]);
+        assert_eq!(
+            "Not connected yet",
+            version_text(None, &WordMap::new(), Lang::En)
+                .lines()
+                .nth(1)
+                .unwrap()
+        );
+
+        words.remove("ciao");
+        assert_eq!(
+            "Conosco 1 parola, dette 1 volta in tutto",
+            version_text(None, &words, Lang::It).lines().nth(2).unwrap()
+        );
+    }
+
+    #[test]
+    fn uptime() {
+        let uptime = Duration::days(3) + Duration::hours(2) + Duration::minutes(15);
+        assert_eq!(
+            "Running for 3 days, 2 hours, 15 minutes\nRead 120 messages and posted 1 word",
+            uptime_text(uptime, 120, 1, Lang::En)
+        );
+        assert_eq!(
+            "1 day, 1 minute",
+            describe_uptime(Duration::minutes(24 * 60 + 1), Lang::En)
+        );
+        assert_eq!(
+            "0 minutes",
+            describe_uptime(Duration::seconds(59), Lang::En)
+        );
+        assert_eq!("2 ore", describe_uptime(Duration::hours(2), Lang::It));
+    }
+
+    #[test]
+    fn moderation_permissions() {
+        let moderator = Permissions::MANAGE_MESSAGES | Permissions::SEND_MESSAGES;
+
+        assert!(is_allowed(
+            Permission::Moderator,
+            Permissions::ADMINISTRATOR
+        ));
+        assert!(is_allowed(Permission::Moderator, moderator));
+        assert!(!is_allowed(
+            Permission::Moderator,
+            Permissions::SEND_MESSAGES
+        ));
+        assert!(!is_allowed(Permission::Moderator, Permissions::empty()));
+
+        assert!(is_allowed(Permission::Admin, Permissions::ADMINISTRATOR));
+        assert!(!is_allowed(Permission::Admin, moderator));
+        assert!(is_allowed(Permission::Everyone, Permissions::empty()));
+    }
+
+    #[test]
+    fn activity() {
+        let counts: HashMap<_, _> = vec![
+            (ChannelId::new(3), 5),
+            (ChannelId::new(1), 2),
+            (ChannelId::new(2), 5),
+            (ChannelId::new(4), 9),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(
+            vec![
+                (ChannelId::new(4), 9),
+                (ChannelId::new(2), 5),
+                (ChannelId::new(3), 5),
+                (ChannelId::new(1), 2),
+            ],
+            most_active(&counts, |_channel| true)
+        );
+        // another server's channels
+        assert_eq!(
+            vec![(ChannelId::new(2), 5), (ChannelId::new(1), 2)],
+            most_active(&counts, |channel| channel.get() < 3)
+        );
+        assert!(most_active(&HashMap::new(), |_channel| true).is_empty());
+    }
+
+    #[test]
+    fn command_names() {
+        let mut names: Vec<_> = COMMANDS.iter().map(|command| command.name).collect();
+        names.sort_unstable();
+        names.dedup();
+
+        assert_eq!(COMMANDS.len(), names.len());
+        assert!(names.contains(&"help"));
+    }
+
+    #[test]
+    fn slash_commands() {
+        let (top, _handler) = slash_handler("top").unwrap();
+        assert_eq!(Permission::Everyone, top.permission);
+        // their replies are only for whoever used them
+        let (forget, _handler) = slash_handler("forget").unwrap();
+        assert_eq!(Permission::Moderator, forget.permission);
+        let (config, _handler) = slash_handler("config").unwrap();
+        assert_eq!(Permission::Admin, config.permission);
+        // only prefix commands
+        assert!(slash_handler("reset").is_none());
+        assert!(slash_handler("nope").is_none());
+
+        let command = serde_json::to_value(slash_command()).unwrap();
+        let subcommands = command["options"].as_array().unwrap();
+        let names: Vec<_> = subcommands
+            .iter()
+            .map(|subcommand| subcommand["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            vec!["top", "stats", "speak", "forget", "config", "settings"],
+            names
+        );
+        // Discord refuses longer descriptions
+        assert!(subcommands
+            .iter()
+            .all(|subcommand| subcommand["description"].as_str().unwrap().len() <= 100));
+    }
+}
@@ -0,0 +1,270 @@
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use serenity::{
+    builder::{
+        CreateActionRow, CreateButton, CreateSelectMenu, CreateSelectMenuKind,
+        CreateSelectMenuOption,
+    },
+    model::{
+        application::ButtonStyle,
+        channel::ReactionType,
+        id::{ChannelId, MessageId},
+    },
+};
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::messenger::Messenger;
+
+/// For how long the buttons of a message work, then they are taken away.
+pub const LIFETIME_MINUTES: i64 = 10;
+/// How many words 📊 lists, the most a select menu can offer too.
+pub const MORE_WORDS: usize = 25;
+
+/// Discord refuses longer options in select menus.
+const OPTION_MAX_LENGTH: usize = 100;
+
+/// What a message with buttons shows, to show it again when they are used.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum View {
+    /// `!pino top` listing this many words.
+    Top(usize),
+    /// The summary of the week starting at the given time, listing this many
+    /// words.
+    Summary(DateTime<Utc>, usize),
+}
+
+impl View {
+    /// How many words are listed.
+    pub fn words(self) -> usize {
+        match self {
+            View::Top(words) | View::Summary(_, words) => words,
+        }
+    }
+
+    /// The same view listing [`MORE_WORDS`] words.
+    pub fn more(self) -> Self {
+        match self {
+            View::Top(_) => View::Top(MORE_WORDS),
+            View::Summary(start, _) => View::Summary(start, MORE_WORDS),
+        }
+    }
+}
+
+/// What a button or menu does.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Action {
+    /// 🔄: compute the message again.
+    Refresh,
+    /// 📊: list more words.
+    More,
+    /// 🚫: offer the listed words to block. Admin only.
+    Block,
+    /// The words chosen in the menu of [`Action::Block`] are blocked.
+    BlockChosen,
+}
+
+impl Action {
+    fn as_str(self) -> &'static str {
+        match self {
+            Action::Refresh => "refresh",
+            Action::More => "more",
+            Action::Block => "block",
+            Action::BlockChosen => "block-chosen",
+        }
+    }
+}
+
+/// What Discord sends back when a button or menu is used: its action and the
+/// view of its message, e.g. `pino:refresh:top:10`, since pino keeps nothing
+/// about the messages it sent.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ComponentId {
+    pub action: Action,
+    pub view: View,
+}
+
+impl ComponentId {
+    pub fn encode(&self) -> String {
+        let view = match self.view {
+            View::Top(words) => format!("top:{}", words),
+            View::Summary(start, words) => format!("summary:{}:{}", start.timestamp(), words),
+        };
+
+        format!("pino:{}:{}", self.action.as_str(), view)
+    }
+
+    /// `None` if it isn't the id of a component of pino.
+    pub fn decode(custom_id: &str) -> Option<Self> {
+        let mut parts = custom_id.split(':');
+
+        if parts.next()? != "pino" {
+            return None;
+        }
+
+        let action = match parts.next()? {
+            "refresh" => Action::Refresh,
+            "more" => Action::More,
+            "block" => Action::Block,
+            "block-chosen" => Action::BlockChosen,
+            _ => return None,
+        };
+
+        let view = match parts.next()? {
+            "top" => View::Top(parts.next()?.parse().ok()?),
+            "summary" => {
+                let start = Utc.timestamp_opt(parts.next()?.parse().ok()?, 0).single()?;
+
+                View::Summary(start, parts.next()?.parse().ok()?)
+            }
+            _ => return None,
+        };
+
+        if parts.next().is_some() {
+            return None;
+        }
+
+        Some(Self { action, view })
+    }
+}
+
+/// Whether the buttons of a message sent at `sent` don't work anymore, e.g.
+/// because pino restarted before taking them away.
+pub fn expired(sent: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+    now - sent >= Duration::minutes(LIFETIME_MINUTES)
+}
+
+/// 🔄, 🚫 and, if it can list more words, 📊 for a message showing `view`.
+pub fn buttons(view: View) -> Vec<CreateActionRow> {
+    let button = |action, emoji: &str| {
+        CreateButton::new(ComponentId { action, view }.encode())
+            .emoji(ReactionType::Unicode(emoji.to_owned()))
+            .style(ButtonStyle::Secondary)
+    };
+
+    let mut buttons = vec![
+        button(Action::Refresh, "🔄"),
+        button(Action::Block, "🚫").style(ButtonStyle::Danger),
+    ];
+
+    if view.words() < MORE_WORDS {
+        buttons.push(button(Action::More, "📊"));
+    }
+
+    vec![CreateActionRow::Buttons(buttons)]
+}
+
+/// A menu to choose which of `words` to block. Empty without words, since
+/// Discord refuses menus without options.
+pub fn block_menu(view: View, words: &[String], placeholder: String) -> Vec<CreateActionRow> {
+    let options: Vec<_> = words
+        .iter()
+        .filter(|word| word.chars().count() <= OPTION_MAX_LENGTH)
+        .take(MORE_WORDS)
+        .map(|word| CreateSelectMenuOption::new(word, word))
+        .collect();
+
+    if options.is_empty() {
+        return Vec::new();
+    }
+
+    let id = ComponentId {
+        action: Action::BlockChosen,
+        view,
+    };
+    let max = options.len() as u8;
+    let menu = CreateSelectMenu::new(id.encode(), CreateSelectMenuKind::String { options })
+        .placeholder(placeholder)
+        .max_values(max);
+
+    vec![CreateActionRow::SelectMenu(menu)]
+}
+
+/// Take the buttons away from `message` once they expire.
+pub fn expire_later(messenger: Arc<dyn Messenger>, channel: ChannelId, message: MessageId) {
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::minutes(LIFETIME_MINUTES).to_std().unwrap()).await;
+
+        // The message could have been deleted in the meantime
+        if let Err(e) = messenger.remove_components(channel, message).await {
+            warn!("Error taking the expired buttons away: {}", e);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn custom_ids() {
+        let start = Utc.with_ymd_and_hms(2021, 3, 15, 0, 0, 0).unwrap();
+        let ids = [
+            ComponentId {
+                action: Action::Refresh,
+                view: View::Top(10),
+            },
+            ComponentId {
+                action: Action::More,
+                view: View::Summary(start, 10),
+            },
+            ComponentId {
+                action: Action::BlockChosen,
+                view: View::Top(25),
+            },
+        ];
+
+        assert_eq!("pino:refresh:top:10", ids[0].encode());
+        assert_eq!("pino:more:summary:1615766400:10", ids[1].encode());
+        for id in &ids {
+            assert_eq!(Some(*id), ComponentId::decode(&id.encode()));
+            // Discord refuses longer ids
+            assert!(id.encode().len() <= 100);
+        }
+
+        assert_eq!(None, ComponentId::decode("other:refresh:top:10"));
+        assert_eq!(None, ComponentId::decode("pino:dance:top:10"));
+        assert_eq!(None, ComponentId::decode("pino:refresh:leaderboard:10"));
+        assert_eq!(None, ComponentId::decode("pino:refresh:top:ten"));
+        assert_eq!(None, ComponentId::decode("pino:refresh:top"));
+        assert_eq!(None, ComponentId::decode("pino:refresh:top:10:11"));
+        assert_eq!(None, ComponentId::decode(""));
+    }
+
+    #[test]
+    fn expiry() {
+        let sent = Utc::now();
+
+        assert!(!expired(sent, sent + Duration::minutes(9)));
+        assert!(expired(sent, sent + Duration::minutes(LIFETIME_MINUTES)));
+    }
+
+    #[test]
+    fn more_words() {
+        let buttons = |view| serde_json::to_value(buttons(view)).unwrap();
+
+        assert_eq!(
+            3,
+            buttons(View::Top(10))[0]["components"]
+                .as_array()
+                .unwrap()
+                .len()
+        );
+        // already listing them all
+        assert_eq!(
+            2,
+            buttons(View::Top(25))[0]["components"]
+                .as_array()
+                .unwrap()
+                .len()
+        );
+        assert_eq!(View::Top(MORE_WORDS), View::Top(10).more());
+
+        let words = ["pino".to_owned(), "a".repeat(101), "pizza".to_owned()];
+        let menu =
+            serde_json::to_value(block_menu(View::Top(10), &words, "Block".to_owned())).unwrap();
+        let options = menu[0]["components"][0]["options"].as_array().unwrap();
+        assert_eq!(2, options.len());
+        assert_eq!(2, menu[0]["components"][0]["max_values"]);
+        assert!(block_menu(View::Top(10), &[], "Block".to_owned()).is_empty());
+    }
+}
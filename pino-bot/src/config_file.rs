@@ -0,0 +1,478 @@
+use anyhow::{bail, Context};
+use serde::{de, Deserialize, Deserializer};
+use std::{
+    collections::BTreeMap,
+    fs,
+    net::SocketAddr,
+    num::NonZeroU64,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    duration, guild_settings, i18n::Lang, logging::LogFormat, reply_context::ReplyStyle,
+    selection::Scoring, self_echo::SelfEcho, sharding::ShardRange, tracking::Normalization,
+    Options,
+};
+
+/// The options of `--config`, named like the flags with underscores, e.g.
+/// `max_boost = 3`. Missing ones are left to the command line.
+#[derive(Deserialize, Debug, Default, PartialEq)]
+#[serde(default)]
+pub struct ConfigFile {
+    pub token: Option<String>,
+    pub token_file: Option<PathBuf>,
+    #[serde(deserialize_with = "seconds")]
+    pub interval_low: Option<u64>,
+    #[serde(deserialize_with = "seconds")]
+    pub interval_high: Option<u64>,
+    #[serde(deserialize_with = "seconds_or_zero")]
+    pub warmup: Option<u64>,
+    pub word_regex: Option<String>,
+    pub unicode_normalize: Option<Normalization>,
+    #[serde(deserialize_with = "seconds")]
+    pub max_age: Option<u64>,
+    pub max_boost: Option<usize>,
+    pub scoring: Option<Scoring>,
+    #[serde(deserialize_with = "seconds")]
+    pub trending_window: Option<u64>,
+    pub default_word: Option<String>,
+    pub state_file: Option<PathBuf>,
+    #[serde(deserialize_with = "seconds")]
+    pub save_interval: Option<u64>,
+    #[cfg(feature = "sqlite")]
+    pub db: Option<String>,
+    pub stop_words_file: Option<PathBuf>,
+    pub snapshot_history: Option<usize>,
+    pub webhook_url: Option<String>,
+    pub audit_webhook_url: Option<String>,
+    pub message_template: Option<String>,
+    pub prefix: Option<String>,
+    pub emoji_mode: Option<bool>,
+    pub emoji_map_file: Option<PathBuf>,
+    pub max_words: Option<usize>,
+    pub veto_count: Option<usize>,
+    pub error_alerts: Option<usize>,
+    pub heatmap: Option<bool>,
+    pub rate_limit_words: Option<usize>,
+    pub max_message_length: Option<usize>,
+    pub self_echo: Option<SelfEcho>,
+    #[serde(deserialize_with = "seconds_or_zero")]
+    pub max_slowmode: Option<u64>,
+    pub reply_style: Option<ReplyStyle>,
+    pub track_cooccurrence: Option<bool>,
+    pub global_slash_commands: Option<bool>,
+    pub wordcloud_max_words: Option<usize>,
+    pub wordcloud_font_file: Option<PathBuf>,
+    pub welcome_channel: Option<NonZeroU64>,
+    pub welcome_template: Option<String>,
+    pub summary_channel: Option<NonZeroU64>,
+    pub seed: Option<u64>,
+    pub language: Option<Lang>,
+    pub log_level: Option<String>,
+    pub log_format: Option<LogFormat>,
+    pub metrics_addr: Option<SocketAddr>,
+    pub health_addr: Option<SocketAddr>,
+    pub heartbeat_minutes: Option<u64>,
+    pub shards: Option<u32>,
+    pub shard_range: Option<ShardRange>,
+    pub dry_run: Option<bool>,
+    pub time_scale: Option<f64>,
+    /// `[guilds.<id>]` tables with the keys of `!pino settings`.
+    pub guilds: BTreeMap<String, BTreeMap<String, toml::Value>>,
+}
+
+/// A duration option, either as seconds (`max_age = 1800`) or like on the
+/// command line (`max_age = "30m"`).
+fn seconds<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+    duration::parse_seconds(&duration_text(deserializer)?)
+        .map(Some)
+        .map_err(de::Error::custom)
+}
+
+/// Like [`seconds`], for the options that zero turns off.
+fn seconds_or_zero<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+    duration::parse_seconds_or_zero(&duration_text(deserializer)?)
+        .map(Some)
+        .map_err(de::Error::custom)
+}
+
+/// A duration option as written on the command line.
+fn duration_text<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Seconds {
+        Number(u64),
+        Text(String),
+    }
+
+    Ok(match Seconds::deserialize(deserializer)? {
+        Seconds::Number(seconds) => seconds.to_string(),
+        Seconds::Text(text) => text,
+    })
+}
+
+/// Read a config file, along with the keys that mean nothing to pino. They
+/// are logged once the log options of the file are known.
+pub fn load(path: &Path) -> anyhow::Result<(ConfigFile, Vec<String>)> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("reading config file {}", path.display()))?;
+
+    parse(&content).with_context(|| format!("parsing config file {}", path.display()))
+}
+
+/// Parse a config file, along with the path of every key that isn't an option.
+pub fn parse(content: &str) -> Result<(ConfigFile, Vec<String>), toml::de::Error> {
+    let mut unknown = Vec::new();
+    let file: ConfigFile =
+        serde_ignored::deserialize(&mut toml::Deserializer::new(content), |path| {
+            unknown.push(path.to_string())
+        })?;
+
+    for (guild_id, table) in &file.guilds {
+        for key in table.keys() {
+            if !guild_settings::KEYS.contains(&key.as_str()) {
+                unknown.push(format!("guilds.{}.{}", guild_id, key));
+            }
+        }
+    }
+
+    Ok((file, unknown))
+}
+
+/// Use the value from the file if the option wasn't given at all.
+fn fill<T>(option: &mut Option<T>, file: Option<T>) {
+    if option.is_none() {
+        *option = file;
+    }
+}
+
+/// Use the value from the file instead of the default one, unless the option
+/// was given on the command line.
+fn replace_default<T>(option: &mut T, file: Option<T>, given: bool) {
+    if let (Some(value), false) = (file, given) {
+        *option = value;
+    }
+}
+
+impl ConfigFile {
+    /// Put the values of the file under the ones from the command line.
+    /// `given` tells whether a flag, like `max-boost`, was on the command line.
+    pub fn merge(self, options: &mut Options, given: impl Fn(&str) -> bool) {
+        fill(&mut options.token, self.token);
+        fill(&mut options.token_file, self.token_file);
+        fill(&mut options.default_word, self.default_word);
+        fill(&mut options.state_file, self.state_file);
+        #[cfg(feature = "sqlite")]
+        fill(&mut options.db, self.db);
+        fill(&mut options.stop_words_file, self.stop_words_file);
+        fill(&mut options.webhook_url, self.webhook_url);
+        fill(&mut options.audit_webhook_url, self.audit_webhook_url);
+        fill(&mut options.message_template, self.message_template);
+        fill(&mut options.emoji_map_file, self.emoji_map_file);
+        fill(&mut options.wordcloud_font_file, self.wordcloud_font_file);
+        fill(&mut options.welcome_channel, self.welcome_channel);
+        fill(&mut options.welcome_template, self.welcome_template);
+        fill(&mut options.summary_channel, self.summary_channel);
+        fill(&mut options.seed, self.seed);
+        fill(&mut options.max_words, self.max_words);
+        fill(&mut options.rate_limit_words, self.rate_limit_words);
+        fill(&mut options.language, self.language);
+        fill(&mut options.log_level, self.log_level);
+        fill(&mut options.metrics_addr, self.metrics_addr);
+        fill(&mut options.health_addr, self.health_addr);
+        fill(&mut options.shards, self.shards);
+        fill(&mut options.shard_range, self.shard_range);
+
+        replace_default(
+            &mut options.interval_low,
+            self.interval_low,
+            given("interval-low"),
+        );
+        replace_default(
+            &mut options.interval_high,
+            self.interval_high,
+            given("interval-high"),
+        );
+        replace_default(
+            &mut options.word_regex,
+            self.word_regex,
+            given("word-regex"),
+        );
+        replace_default(
+            &mut options.unicode_normalize,
+            self.unicode_normalize,
+            given("unicode-normalize"),
+        );
+        replace_default(&mut options.warmup, self.warmup, given("warmup"));
+        replace_default(&mut options.max_age, self.max_age, given("max-age"));
+        replace_default(&mut options.max_boost, self.max_boost, given("max-boost"));
+        replace_default(&mut options.scoring, self.scoring, given("scoring"));
+        replace_default(
+            &mut options.trending_window,
+            self.trending_window,
+            given("trending-window"),
+        );
+        replace_default(
+            &mut options.save_interval,
+            self.save_interval,
+            given("save-interval"),
+        );
+        replace_default(
+            &mut options.snapshot_history,
+            self.snapshot_history,
+            given("snapshot-history"),
+        );
+        replace_default(&mut options.prefix, self.prefix, given("prefix"));
+        replace_default(
+            &mut options.emoji_mode,
+            self.emoji_mode,
+            given("emoji-mode"),
+        );
+        replace_default(
+            &mut options.veto_count,
+            self.veto_count,
+            given("veto-count"),
+        );
+        replace_default(
+            &mut options.error_alerts,
+            self.error_alerts,
+            given("error-alerts"),
+        );
+        replace_default(&mut options.heatmap, self.heatmap, given("heatmap"));
+        replace_default(
+            &mut options.max_message_length,
+            self.max_message_length,
+            given("max-message-length"),
+        );
+        replace_default(&mut options.self_echo, self.self_echo, given("self-echo"));
+        replace_default(
+            &mut options.max_slowmode,
+            self.max_slowmode,
+            given("max-slowmode"),
+        );
+        replace_default(
+            &mut options.reply_style,
+            self.reply_style,
+            given("reply-style"),
+        );
+        replace_default(&mut options.dry_run, self.dry_run, given("dry-run"));
+        replace_default(
+            &mut options.time_scale,
+            self.time_scale,
+            given("time-scale"),
+        );
+        replace_default(
+            &mut options.track_cooccurrence,
+            self.track_cooccurrence,
+            given("track-cooccurrence"),
+        );
+        replace_default(
+            &mut options.global_slash_commands,
+            self.global_slash_commands,
+            given("global-slash-commands"),
+        );
+        replace_default(
+            &mut options.log_format,
+            self.log_format,
+            given("log-format"),
+        );
+        replace_default(
+            &mut options.heartbeat_minutes,
+            self.heartbeat_minutes,
+            given("heartbeat-minutes"),
+        );
+        replace_default(
+            &mut options.wordcloud_max_words,
+            self.wordcloud_max_words,
+            given("wordcloud-max-words"),
+        );
+    }
+
+    /// The settings of the `[guilds.<id>]` tables. Unknown keys are skipped,
+    /// [`parse`] already reported them.
+    pub fn guild_settings(&self) -> anyhow::Result<BTreeMap<u64, guild_settings::Settings>> {
+        let mut settings = BTreeMap::new();
+
+        for (guild_id, table) in &self.guilds {
+            let id = match guild_id.parse() {
+                Ok(id) => id,
+                Err(_) => bail!("`{}` is not a server id", guild_id),
+            };
+            let guild: &mut guild_settings::Settings = settings.entry(id).or_default();
+
+            for (key, value) in table {
+                let value = match value {
+                    toml::Value::String(value) => value.clone(),
+                    other => other.to_string(),
+                };
+
+                match guild.set(key, &value) {
+                    Ok(()) | Err(guild_settings::SettingError::UnknownKey(_)) => {}
+                    Err(e) => return Err(e).with_context(|| format!("in [guilds.{}]", guild_id)),
+                }
+            }
+        }
+
+        Ok(settings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use structopt::StructOpt;
+
+    /// The options from the command line `args`, with `file` merged in.
+    fn merged(args: &[&str], file: &str) -> Options {
+        let matches = Options::clap()
+            .get_matches_from(std::iter::once("pino-bot").chain(args.iter().copied()));
+        let mut options = Options::from_clap(&matches);
+        let (file, _unknown) = parse(file).unwrap();
+
+        file.merge(&mut options, |name| matches.occurrences_of(name) > 0);
+        options
+    }
+
+    #[test]
+    fn precedence() {
+        let file = r#"
+            max_boost = 3
+            default_word = "ciao"
+            emoji_mode = true
+            state_file = "pino.json"
+            unicode_normalize = "nfkc"
+            log_format = "json"
+            shard_range = "0..2/4"
+            self_echo = "halve"
+            max_slowmode = "10m"
+            reply_style = "reference"
+            scoring = "trending"
+            trending_window = "10m"
+        "#;
+
+        let options = merged(&[], file);
+        assert_eq!(3, options.max_boost);
+        assert_eq!(Some("ciao".to_owned()), options.default_word);
+        assert!(options.emoji_mode);
+        assert_eq!(Some(PathBuf::from("pino.json")), options.state_file);
+        assert_eq!(Normalization::Nfkc, options.unicode_normalize);
+        assert_eq!(LogFormat::Json, options.log_format);
+        assert_eq!(Some("0..2/4".parse().unwrap()), options.shard_range);
+        assert_eq!(SelfEcho::Halve, options.self_echo);
+        assert_eq!(600, options.max_slowmode);
+        assert_eq!(ReplyStyle::Reference, options.reply_style);
+        assert_eq!(Scoring::Trending, options.scoring);
+        assert_eq!(600, options.trending_window);
+        // missing from both
+        assert_eq!(600, options.interval_low);
+        assert_eq!("!pino", options.prefix);
+        assert_eq!(None, options.webhook_url);
+
+        let options = merged(
+            &[
+                "--max-boost",
+                "10",
+                "--default-word",
+                "pino",
+                "--prefix",
+                "?pino",
+            ],
+            file,
+        );
+        // the command line wins, even when it says the default value
+        assert_eq!(10, options.max_boost);
+        assert_eq!(Some("pino".to_owned()), options.default_word);
+        assert_eq!("?pino", options.prefix);
+        // the rest still comes from the file
+        assert!(options.emoji_mode);
+
+        let options = merged(&["--interval-low", "60"], "");
+        assert_eq!(60, options.interval_low);
+        assert_eq!(1200, options.interval_high);
+    }
+
+    #[test]
+    fn durations() {
+        let options = merged(
+            &["--interval-low", "1m30s"],
+            "interval_high = \"1h\"\nmax_age = 7200\nsave_interval = \"90\"\n",
+        );
+        assert_eq!(90, options.interval_low);
+        assert_eq!(3600, options.interval_high);
+        assert_eq!(7200, options.max_age);
+        assert_eq!(90, options.save_interval);
+        assert_eq!(300, options.warmup);
+
+        // the warmup can be turned off
+        assert_eq!(0, merged(&[], "warmup = 0").warmup);
+        assert_eq!(0, merged(&["--warmup", "0s"], "").warmup);
+
+        for file in &[
+            "max_age = \"soon\"",
+            "max_age = 0",
+            "interval_low = \"-5m\"",
+            "warmup = \"soon\"",
+        ] {
+            assert!(parse(file).is_err(), "{} should be invalid", file);
+        }
+
+        for value in &["0", "-1m", "1.5h"] {
+            assert!(
+                Options::from_iter_safe(&["pino-bot", "--max-age", value]).is_err(),
+                "{} should be invalid",
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_keys() {
+        let (file, unknown) = parse(
+            r#"
+            max_bost = 3
+            heatmap = true
+
+            [guilds.42]
+            emoji_mode = true
+            colour = "red"
+        "#,
+        )
+        .unwrap();
+
+        assert_eq!(Some(true), file.heatmap);
+        assert_eq!(vec!["max_bost", "guilds.42.colour"], unknown);
+
+        assert!(parse("max_boost = \"three\"").is_err());
+    }
+
+    #[test]
+    fn guild_tables() {
+        let (file, _unknown) = parse(
+            r#"
+            [guilds.42]
+            emoji_mode = true
+            language = "it"
+            colour = "red"
+
+            [guilds.7]
+            message_template = "Today: {word}"
+        "#,
+        )
+        .unwrap();
+
+        let settings = file.guild_settings().unwrap();
+        assert_eq!(vec![7, 42], settings.keys().copied().collect::<Vec<_>>());
+        assert_eq!(Some(true), settings[&42].emoji_mode);
+        assert_eq!(Some(Lang::It), settings[&42].language);
+        assert_eq!(
+            Some("Today: {word}".to_owned()),
+            settings[&7].message_template
+        );
+
+        let (file, _unknown) = parse("[guilds.42]\nmessage_template = \"no word\"").unwrap();
+        assert!(file.guild_settings().is_err());
+
+        let (file, _unknown) = parse("[guilds.pino]\nemoji_mode = true").unwrap();
+        assert!(file.guild_settings().is_err());
+    }
+}
@@ -0,0 +1,110 @@
+use pino_core::ids::GuildId;
+use std::collections::{BTreeSet, HashMap};
+
+/// How many messages each pair of different words appeared together in. The
+/// words of a pair are sorted, so that `(a, b)` and `(b, a)` are the same.
+pub type CooccurrenceMap = HashMap<(String, String), usize>;
+
+/// The pairs of each guild, the words said outside of any under `None`.
+pub type GuildCooccurrences = HashMap<Option<GuildId>, CooccurrenceMap>;
+
+/// Only the first this many distinct words of a message are paired, since the
+/// pairs grow with the square of the words.
+pub const MAX_WORDS_PER_MESSAGE: usize = 50;
+
+/// Count every pair of different words said in the same message. Repeating
+/// a word in a message doesn't count more.
+pub fn record(map: &mut CooccurrenceMap, words: &[String]) {
+    let mut distinct = BTreeSet::new();
+
+    for word in words {
+        if distinct.len() == MAX_WORDS_PER_MESSAGE {
+            break;
+        }
+
+        distinct.insert(word.as_str());
+    }
+
+    // sorted, so every pair comes out as (smaller, bigger)
+    let distinct: Vec<_> = distinct.into_iter().collect();
+
+    for (i, a) in distinct.iter().enumerate() {
+        for b in &distinct[i + 1..] {
+            *map.entry((a.to_string(), b.to_string())).or_insert(0) += 1;
+        }
+    }
+}
+
+/// The `n` words said together with `word` most often, with in how many
+/// messages. The most frequent first, alphabetically if tied.
+pub fn partners(map: &CooccurrenceMap, word: &str, n: usize) -> Vec<(String, usize)> {
+    let mut partners: Vec<_> = map
+        .iter()
+        .filter_map(|((a, b), &count)| {
+            if a == word {
+                Some((b.clone(), count))
+            } else if b == word {
+                Some((a.clone(), count))
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    partners.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    partners.truncate(n);
+
+    partners
+}
+
+/// Drop every pair with `word`, when it's forgotten.
+pub fn forget(map: &mut CooccurrenceMap, word: &str) {
+    map.retain(|(a, b), _count| a != word && b != word);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(message: &str) -> Vec<String> {
+        message.split_whitespace().map(str::to_owned).collect()
+    }
+
+    #[test]
+    fn pairs() {
+        let mut map = CooccurrenceMap::new();
+
+        record(&mut map, &words("pizza pino pizza"));
+        record(&mut map, &words("pino pizza ciao"));
+        record(&mut map, &words("pino"));
+        record(&mut map, &[]);
+
+        assert_eq!(3, map.len());
+        assert_eq!(2, map[&("pino".to_owned(), "pizza".to_owned())]);
+        assert_eq!(1, map[&("ciao".to_owned(), "pino".to_owned())]);
+        assert!(!map.contains_key(&("pizza".to_owned(), "pino".to_owned())));
+
+        assert_eq!(
+            vec![("pizza".to_owned(), 2), ("ciao".to_owned(), 1)],
+            partners(&map, "pino", 10)
+        );
+        assert_eq!(vec![("pino".to_owned(), 2)], partners(&map, "pizza", 1));
+        assert!(partners(&map, "gnocchi", 10).is_empty());
+
+        forget(&mut map, "pino");
+        assert_eq!(vec![("ciao".to_owned(), 1)], partners(&map, "pizza", 10));
+        assert!(partners(&map, "pino", 10).is_empty());
+    }
+
+    #[test]
+    fn long_messages() {
+        let mut map = CooccurrenceMap::new();
+        let many: Vec<_> = (0..200).map(|i| format!("word{}", i)).collect();
+
+        record(&mut map, &many);
+        assert_eq!(
+            MAX_WORDS_PER_MESSAGE * (MAX_WORDS_PER_MESSAGE - 1) / 2,
+            map.len()
+        );
+    }
+}
@@ -0,0 +1,676 @@
+use anyhow::Context;
+use chrono::{DateTime, TimeZone, Utc};
+use pino_core::{ids, store::WordStore};
+use rusqlite::{params, Connection};
+use std::{
+    collections::BTreeMap,
+    path::Path,
+    sync::mpsc::{self, Receiver, Sender},
+    thread::{self, JoinHandle},
+};
+use tracing::{error, info, warn};
+
+use crate::guild_settings::Settings;
+
+/// Migrations embedded in the binary. The index of the last applied migration
+/// is stored in the `user_version` pragma.
+const MIGRATIONS: &[&str] = &[
+    include_str!("../migrations/0001_init.sql"),
+    include_str!("../migrations/0002_blocklist.sql"),
+    include_str!("../migrations/0003_word_index.sql"),
+];
+
+/// Guild id of the settings that apply to every guild.
+const GLOBAL_SETTINGS: i64 = 0;
+
+/// Max amount of events written in a single transaction.
+const BATCH_SIZE: usize = 256;
+
+/// A single word said by someone, as stored in the database.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WordInstance {
+    pub word: String,
+    pub guild_id: Option<u64>,
+    pub channel_id: u64,
+    pub user_id: u64,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Events sent from the bot to the writer thread.
+#[derive(Debug)]
+pub enum Event {
+    Word(WordInstance),
+    /// Delete every instance older than (or equal to) the given time.
+    Expire(DateTime<Utc>),
+    /// Delete every instance of a word in a guild, or outside of any if `None`.
+    Forget(Option<u64>, String),
+    /// Delete every word instance of a guild, or outside of any if `None`.
+    Reset(Option<u64>),
+    /// Store the value of a global setting.
+    Setting(String, String),
+    /// Add an entry to the blocklist.
+    Block(u32, String),
+    /// Remove an entry from the blocklist.
+    Unblock(u32),
+    /// Store the command prefix of a guild.
+    Prefix(u64, String),
+    /// Store a setting of a guild, or go back to the default if `None`.
+    GuildSetting(u64, String, Option<String>),
+}
+
+pub struct Database {
+    conn: Connection,
+}
+
+impl Database {
+    /// Open (or create) the database described by `url`, in the form
+    /// `sqlite://path/to/file.db`, and bring its schema up to date.
+    pub fn open(url: &str) -> anyhow::Result<Self> {
+        let path = url
+            .strip_prefix("sqlite://")
+            .with_context(|| format!("unsupported database url '{}'", url))?;
+
+        Self::open_path(Path::new(path))
+    }
+
+    pub fn open_path(path: &Path) -> anyhow::Result<Self> {
+        let conn = Connection::open(path)
+            .with_context(|| format!("opening database {}", path.display()))?;
+
+        let mut db = Self { conn };
+        db.migrate()?;
+
+        Ok(db)
+    }
+
+    fn migrate(&mut self) -> anyhow::Result<()> {
+        let version: i64 = self
+            .conn
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .context("reading schema version")?;
+
+        for (i, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+            let tx = self.conn.transaction()?;
+            tx.execute_batch(migration)
+                .with_context(|| format!("applying migration {}", i + 1))?;
+            tx.pragma_update(None, "user_version", &((i + 1) as i64))?;
+            tx.commit()?;
+        }
+
+        Ok(())
+    }
+
+    /// Rebuild the in-memory words of every guild from the instances newer than
+    /// `older_than`.
+    pub fn load_words(&self, older_than: DateTime<Utc>) -> anyhow::Result<WordStore> {
+        let mut stmt = self.conn.prepare(
+            "SELECT word, guild_id, timestamp FROM word_instances
+             WHERE timestamp > ?1 ORDER BY timestamp",
+        )?;
+
+        let rows = stmt.query_map(params![older_than.timestamp_millis()], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                row.get::<_, Option<i64>>(1)?,
+                row.get::<_, i64>(2)?,
+            ))
+        })?;
+
+        let mut words = WordStore::new();
+
+        for row in rows {
+            let (word, guild_id, millis) = row?;
+            let time = Utc
+                .timestamp_millis_opt(millis)
+                .single()
+                .with_context(|| format!("invalid timestamp {} for word '{}'", millis, word))?;
+
+            // Rows come in chronological order, so every insert lands at the end.
+            words.learn(guild_id.map(|id| ids::GuildId(id as u64)), word, time);
+        }
+
+        Ok(words)
+    }
+
+    pub fn insert_words(&mut self, words: &[WordInstance]) -> anyhow::Result<()> {
+        let tx = self.conn.transaction()?;
+
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT INTO word_instances (word, guild_id, channel_id, user_id, timestamp)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?;
+
+            for instance in words {
+                stmt.execute(params![
+                    instance.word,
+                    instance.guild_id.map(|id| id as i64),
+                    instance.channel_id as i64,
+                    instance.user_id as i64,
+                    instance.timestamp.timestamp_millis(),
+                ])?;
+            }
+        }
+
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    /// Delete every instance older than (or equal to) `older_than`.
+    /// Returns the amount of deleted rows.
+    pub fn expire(&self, older_than: DateTime<Utc>) -> anyhow::Result<usize> {
+        let deleted = self.conn.execute(
+            "DELETE FROM word_instances WHERE timestamp <= ?1",
+            params![older_than.timestamp_millis()],
+        )?;
+
+        Ok(deleted)
+    }
+
+    pub fn forget(&self, guild_id: Option<u64>, word: &str) -> anyhow::Result<usize> {
+        let deleted = self.conn.execute(
+            "DELETE FROM word_instances WHERE word = ?1 AND guild_id IS ?2",
+            params![word, guild_id.map(|id| id as i64)],
+        )?;
+
+        Ok(deleted)
+    }
+
+    pub fn reset(&self, guild_id: Option<u64>) -> anyhow::Result<usize> {
+        let deleted = self.conn.execute(
+            "DELETE FROM word_instances WHERE guild_id IS ?1",
+            params![guild_id.map(|id| id as i64)],
+        )?;
+
+        Ok(deleted)
+    }
+
+    /// Every global setting, as key and value.
+    pub fn load_settings(&self) -> anyhow::Result<Vec<(String, String)>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT key, value FROM settings WHERE guild_id = ?1")?;
+
+        let settings = stmt
+            .query_map(params![GLOBAL_SETTINGS], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })?
+            .collect::<Result<_, _>>()?;
+
+        Ok(settings)
+    }
+
+    pub fn save_setting(&self, key: &str, value: &str) -> anyhow::Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO settings (guild_id, key, value) VALUES (?1, ?2, ?3)",
+            params![GLOBAL_SETTINGS, key, value],
+        )?;
+
+        Ok(())
+    }
+
+    /// The command prefix of every guild that changed it.
+    pub fn load_prefixes(&self) -> anyhow::Result<BTreeMap<u64, String>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT guild_id, value FROM settings WHERE key = 'prefix' AND guild_id != ?1",
+        )?;
+
+        let prefixes = stmt
+            .query_map(params![GLOBAL_SETTINGS], |row| {
+                Ok((row.get::<_, i64>(0)? as u64, row.get(1)?))
+            })?
+            .collect::<Result<_, _>>()?;
+
+        Ok(prefixes)
+    }
+
+    pub fn save_prefix(&self, guild_id: u64, prefix: &str) -> anyhow::Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO settings (guild_id, key, value) VALUES (?1, 'prefix', ?2)",
+            params![guild_id as i64, prefix],
+        )?;
+
+        Ok(())
+    }
+
+    /// The settings of every guild that changed any. Settings that aren't
+    /// valid anymore are skipped.
+    pub fn load_guild_settings(&self) -> anyhow::Result<BTreeMap<u64, Settings>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT guild_id, key, value FROM settings WHERE key != 'prefix' AND guild_id != ?1",
+        )?;
+
+        let rows = stmt
+            .query_map(params![GLOBAL_SETTINGS], |row| {
+                Ok((
+                    row.get::<_, i64>(0)? as u64,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, String>(2)?,
+                ))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut settings = BTreeMap::<u64, Settings>::new();
+
+        for (guild_id, key, value) in rows {
+            if let Err(e) = settings.entry(guild_id).or_default().set(&key, &value) {
+                warn!("Ignoring the setting {} of {}: {}", key, guild_id, e);
+            }
+        }
+
+        settings.retain(|_guild_id, settings| !settings.is_empty());
+
+        Ok(settings)
+    }
+
+    pub fn save_guild_setting(
+        &self,
+        guild_id: u64,
+        key: &str,
+        value: Option<&str>,
+    ) -> anyhow::Result<()> {
+        match value {
+            Some(value) => self.conn.execute(
+                "INSERT OR REPLACE INTO settings (guild_id, key, value) VALUES (?1, ?2, ?3)",
+                params![guild_id as i64, key, value],
+            )?,
+            None => self.conn.execute(
+                "DELETE FROM settings WHERE guild_id = ?1 AND key = ?2",
+                params![guild_id as i64, key],
+            )?,
+        };
+
+        Ok(())
+    }
+
+    /// Every blocklist entry by id.
+    pub fn load_blocklist(&self) -> anyhow::Result<BTreeMap<u32, String>> {
+        let mut stmt = self.conn.prepare("SELECT id, pattern FROM blocklist")?;
+
+        let entries = stmt
+            .query_map(params![], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<_, _>>()?;
+
+        Ok(entries)
+    }
+
+    pub fn block(&self, id: u32, pattern: &str) -> anyhow::Result<()> {
+        self.conn.execute(
+            "INSERT OR REPLACE INTO blocklist (id, pattern) VALUES (?1, ?2)",
+            params![id, pattern],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn unblock(&self, id: u32) -> anyhow::Result<()> {
+        self.conn
+            .execute("DELETE FROM blocklist WHERE id = ?1", params![id])?;
+
+        Ok(())
+    }
+
+    #[cfg(test)]
+    pub fn count_instances(&self) -> anyhow::Result<i64> {
+        let count =
+            self.conn
+                .query_row("SELECT COUNT(*) FROM word_instances", params![], |row| {
+                    row.get(0)
+                })?;
+
+        Ok(count)
+    }
+
+    /// Move the database to a dedicated thread, which writes the events sent through
+    /// the returned handle in batches. The thread exits once every handle is dropped.
+    pub fn spawn_writer(self) -> (DbHandle, JoinHandle<()>) {
+        let (sender, receiver) = mpsc::channel();
+
+        let thread = thread::spawn(move || self.write_events(receiver));
+
+        (DbHandle { sender }, thread)
+    }
+
+    fn write_events(mut self, receiver: Receiver<Event>) {
+        let mut batch = Vec::with_capacity(BATCH_SIZE);
+
+        // Block until there is something to do, then grab whatever else is queued
+        while let Ok(event) = receiver.recv() {
+            let mut next = Some(event);
+
+            while let Some(event) = next.take() {
+                match event {
+                    Event::Word(instance) => batch.push(instance),
+                    Event::Expire(older_than) => {
+                        self.flush(&mut batch);
+
+                        match self.expire(older_than) {
+                            Ok(deleted) => info!("Deleted {} expired rows", deleted),
+                            Err(e) => error!("Error expiring rows: {:#}", e),
+                        }
+                    }
+                    Event::Forget(guild_id, word) => {
+                        self.flush(&mut batch);
+
+                        if let Err(e) = self.forget(guild_id, &word) {
+                            error!("Error deleting the word '{}': {:#}", word, e);
+                        }
+                    }
+                    Event::Reset(guild_id) => {
+                        // Queued words of the guild were said before the reset
+                        batch.retain(|instance| instance.guild_id != guild_id);
+
+                        if let Err(e) = self.reset(guild_id) {
+                            error!("Error deleting every word: {:#}", e);
+                        }
+                    }
+                    Event::Setting(key, value) => {
+                        if let Err(e) = self.save_setting(&key, &value) {
+                            error!("Error saving the setting {}: {:#}", key, e);
+                        }
+                    }
+                    Event::Prefix(guild_id, prefix) => {
+                        if let Err(e) = self.save_prefix(guild_id, &prefix) {
+                            error!("Error saving the prefix of {}: {:#}", guild_id, e);
+                        }
+                    }
+                    Event::GuildSetting(guild_id, key, value) => {
+                        if let Err(e) = self.save_guild_setting(guild_id, &key, value.as_deref()) {
+                            error!("Error saving the setting {} of {}: {:#}", key, guild_id, e);
+                        }
+                    }
+                    Event::Block(id, pattern) => {
+                        if let Err(e) = self.block(id, &pattern) {
+                            error!("Error blocking '{}': {:#}", pattern, e);
+                        }
+                    }
+                    Event::Unblock(id) => {
+                        if let Err(e) = self.unblock(id) {
+                            error!("Error removing blocklist entry {}: {:#}", id, e);
+                        }
+                    }
+                }
+
+                if batch.len() < BATCH_SIZE {
+                    next = receiver.try_recv().ok();
+                }
+            }
+
+            self.flush(&mut batch);
+        }
+    }
+
+    fn flush(&mut self, batch: &mut Vec<WordInstance>) {
+        if batch.is_empty() {
+            return;
+        }
+
+        if let Err(e) = self.insert_words(batch) {
+            error!(
+                "Error writing {} words to the database: {:#}",
+                batch.len(),
+                e
+            );
+        }
+
+        batch.clear();
+    }
+}
+
+/// Cheap handle to send events to the database writer. Sending never blocks.
+#[derive(Clone)]
+pub struct DbHandle {
+    sender: Sender<Event>,
+}
+
+impl DbHandle {
+    pub fn send(&self, event: Event) {
+        if self.sender.send(event).is_err() {
+            warn!("Database writer is gone, dropping event");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use std::{fs, path::PathBuf};
+
+    fn temp_db(name: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("pino-{}-{}.db", std::process::id(), name));
+        let _ = fs::remove_file(&path);
+        path
+    }
+
+    fn instance(word: &str, timestamp: DateTime<Utc>) -> WordInstance {
+        WordInstance {
+            word: word.to_owned(),
+            guild_id: Some(1),
+            channel_id: 2,
+            user_id: 3,
+            timestamp,
+        }
+    }
+
+    #[test]
+    fn migrations_are_idempotent() {
+        let path = temp_db("migrations");
+
+        Database::open_path(&path).unwrap();
+        Database::open_path(&path).unwrap();
+        let url = format!("sqlite://{}", path.display());
+        let db = Database::open(&url).unwrap();
+
+        let schema = |kind: &str, name: &str| -> i64 {
+            db.conn
+                .query_row(
+                    "SELECT COUNT(*) FROM sqlite_master WHERE type = ?1 AND name = ?2",
+                    params![kind, name],
+                    |row| row.get(0),
+                )
+                .unwrap()
+        };
+        assert_eq!(1, schema("index", "word_instances_word"));
+        assert_eq!(0, schema("table", "opt_outs"));
+        drop(db);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn bad_url() {
+        assert!(Database::open("postgres://localhost/pino").is_err());
+    }
+
+    #[test]
+    fn restart_recovery() {
+        let path = temp_db("restart");
+        let now = Utc::now();
+
+        let (handle, thread) = Database::open_path(&path).unwrap().spawn_writer();
+        handle.send(Event::Word(instance("ciao", now - Duration::seconds(10))));
+        handle.send(Event::Word(instance("ciao", now)));
+        handle.send(Event::Word(instance(
+            "gnocchi",
+            now - Duration::seconds(500),
+        )));
+        drop(handle);
+        thread.join().unwrap();
+
+        // "restart"
+        let db = Database::open_path(&path).unwrap();
+        let words = db.load_words(now - Duration::seconds(100)).unwrap();
+
+        let words = words.guild(Some(ids::GuildId(1)));
+        assert_eq!(1, words.len());
+        assert_eq!(2, words["ciao"].len());
+        assert_eq!(3, db.count_instances().unwrap());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn expiry() {
+        let path = temp_db("expiry");
+        let now = Utc::now();
+
+        let (handle, thread) = Database::open_path(&path).unwrap().spawn_writer();
+        for i in 0..1000 {
+            handle.send(Event::Word(instance("ciao", now - Duration::seconds(i))));
+        }
+        handle.send(Event::Expire(now - Duration::seconds(100)));
+        drop(handle);
+        thread.join().unwrap();
+
+        let db = Database::open_path(&path).unwrap();
+        assert_eq!(100, db.count_instances().unwrap());
+        assert_eq!(
+            100,
+            db.load_words(now - Duration::days(1))
+                .unwrap()
+                .guild(Some(ids::GuildId(1)))["ciao"]
+                .len()
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn forget_and_reset() {
+        let path = temp_db("forget");
+        let now = Utc::now();
+        let guild = Some(ids::GuildId(1));
+        let other = WordInstance {
+            guild_id: Some(4),
+            ..instance("ciao", now)
+        };
+
+        let (handle, thread) = Database::open_path(&path).unwrap().spawn_writer();
+        handle.send(Event::Word(instance("ciao", now)));
+        handle.send(Event::Word(instance("gnocchi", now)));
+        handle.send(Event::Word(other.clone()));
+        handle.send(Event::Forget(Some(1), "ciao".to_owned()));
+        drop(handle);
+        thread.join().unwrap();
+
+        let db = Database::open_path(&path).unwrap();
+        let words = db.load_words(now - Duration::days(1)).unwrap();
+        assert!(!words.guild(guild).contains_key("ciao"));
+        assert!(words.guild(guild).contains_key("gnocchi"));
+        // only forgotten where it was asked
+        assert!(words.guild(Some(ids::GuildId(4))).contains_key("ciao"));
+
+        let (handle, thread) = db.spawn_writer();
+        handle.send(Event::Reset(Some(1)));
+        drop(handle);
+        thread.join().unwrap();
+
+        let db = Database::open_path(&path).unwrap();
+        assert_eq!(1, db.count_instances().unwrap());
+        let words = db.load_words(now - Duration::days(1)).unwrap();
+        assert!(words.guild(guild).is_empty());
+        assert_eq!(1, words.guild(Some(ids::GuildId(4))).len());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn settings() {
+        let path = temp_db("settings");
+
+        let (handle, thread) = Database::open_path(&path).unwrap().spawn_writer();
+        handle.send(Event::Setting("max_age".to_owned(), "60".to_owned()));
+        handle.send(Event::Setting("max_boost".to_owned(), "1".to_owned()));
+        handle.send(Event::Setting("max_age".to_owned(), "90".to_owned()));
+        drop(handle);
+        thread.join().unwrap();
+
+        let db = Database::open_path(&path).unwrap();
+        let mut settings = db.load_settings().unwrap();
+        settings.sort();
+
+        assert_eq!(
+            vec![
+                ("max_age".to_owned(), "90".to_owned()),
+                ("max_boost".to_owned(), "1".to_owned())
+            ],
+            settings
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn prefixes() {
+        let path = temp_db("prefixes");
+
+        let (handle, thread) = Database::open_path(&path).unwrap().spawn_writer();
+        handle.send(Event::Setting("max_age".to_owned(), "60".to_owned()));
+        handle.send(Event::Prefix(1, "?pino".to_owned()));
+        handle.send(Event::Prefix(2, "pino".to_owned()));
+        handle.send(Event::Prefix(1, "%".to_owned()));
+        drop(handle);
+        thread.join().unwrap();
+
+        let db = Database::open_path(&path).unwrap();
+
+        assert_eq!(
+            vec![(1, "%".to_owned()), (2, "pino".to_owned())],
+            db.load_prefixes().unwrap().into_iter().collect::<Vec<_>>()
+        );
+        // global settings don't see the prefixes
+        assert_eq!(1, db.load_settings().unwrap().len());
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn guild_settings() {
+        let path = temp_db("guild-settings");
+
+        let (handle, thread) = Database::open_path(&path).unwrap().spawn_writer();
+        handle.send(Event::Prefix(1, "?pino".to_owned()));
+        let setting = |guild_id, key: &str, value: Option<&str>| {
+            Event::GuildSetting(guild_id, key.to_owned(), value.map(str::to_owned))
+        };
+        handle.send(setting(1, "emoji_mode", Some("on")));
+        handle.send(setting(1, "default_word", Some("ciao")));
+        handle.send(setting(1, "default_word", None));
+        handle.send(setting(2, "default_word", Some("pino")));
+        handle.send(setting(3, "emoji_mode", Some("maybe")));
+        drop(handle);
+        thread.join().unwrap();
+
+        let db = Database::open_path(&path).unwrap();
+        let settings = db.load_guild_settings().unwrap();
+
+        assert_eq!(vec![1, 2], settings.keys().copied().collect::<Vec<_>>());
+        assert_eq!(Some(true), settings[&1].emoji_mode);
+        assert_eq!(None, settings[&1].default_word);
+        assert_eq!(Some("pino".to_owned()), settings[&2].default_word);
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn blocklist() {
+        let path = temp_db("blocklist");
+
+        let (handle, thread) = Database::open_path(&path).unwrap().spawn_writer();
+        handle.send(Event::Block(1, "ciao".to_owned()));
+        handle.send(Event::Block(2, "/^gn/".to_owned()));
+        handle.send(Event::Unblock(1));
+        drop(handle);
+        thread.join().unwrap();
+
+        let db = Database::open_path(&path).unwrap();
+        let blocklist = db.load_blocklist().unwrap();
+
+        assert_eq!(1, blocklist.len());
+        assert_eq!("/^gn/", blocklist[&2]);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,211 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Words listed in the dump, most said first.
+pub const DUMP_TOP_WORDS: usize = 20;
+/// Dumps bigger than this are sent in parts, to stay below the upload limit of Discord.
+pub const MAX_ATTACHMENT_SIZE: usize = 8_000_000;
+
+/// Arguments whose value must never end up in a dump.
+const SECRET_ARGS: &[&str] = &["--token", "--webhook-url"];
+const REDACTED: &str = "[redacted]";
+
+/// A background task is considered stuck if it's late by this many minutes.
+const HEALTH_GRACE_MINUTES: i64 = 2;
+
+/// Everything `!pino debug` tells the owner about the state of pino.
+#[derive(Serialize, Debug)]
+pub struct DebugDump {
+    pub taken: DateTime<Utc>,
+    /// Arguments pino was started with, secrets redacted.
+    pub command_line: Vec<String>,
+    pub config: BTreeMap<&'static str, String>,
+    /// Servers pino is connected to, `None` before the `ready` event.
+    pub guilds: Option<usize>,
+    /// Every server has its own words, these are added up.
+    pub words: usize,
+    pub instances: usize,
+    /// Every server added up too.
+    pub top_words: Vec<DumpWord>,
+    pub recent_channel: Option<ChannelPointer>,
+    /// The servers that paused pino.
+    pub paused: Vec<PausedGuild>,
+    /// The earliest message of any guild, `None` if nobody talked yet.
+    pub next_send: Option<DateTime<Utc>>,
+    pub health: TaskHealth,
+    /// How many times each background task was restarted after panicking.
+    pub task_restarts: BTreeMap<&'static str, usize>,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+pub struct DumpWord {
+    pub word: String,
+    pub count: usize,
+}
+
+/// A server that paused pino, `None` for direct messages.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct PausedGuild {
+    pub guild_id: Option<u64>,
+    /// When the pause expires by itself, `None` if paused until resumed.
+    pub until: Option<DateTime<Utc>>,
+}
+
+/// The channel pino is going to talk in.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct ChannelPointer {
+    pub channel_id: u64,
+    pub guild_id: Option<u64>,
+}
+
+/// Whether the background tasks look alive.
+#[derive(Serialize, Debug, PartialEq)]
+pub struct TaskHealth {
+    pub connected: bool,
+    /// The next message should have been sent a while ago.
+    pub send_loop_late: bool,
+    /// No word counts were taken lately, although they should be.
+    pub snapshots_late: bool,
+}
+
+impl TaskHealth {
+    /// `started` is when pino connected, `last_snapshot` is `None` if no
+    /// snapshot is kept or none was taken yet.
+    pub fn check(
+        now: DateTime<Utc>,
+        started: Option<DateTime<Utc>>,
+        next_send: Option<DateTime<Utc>>,
+        last_snapshot: Option<DateTime<Utc>>,
+        keeps_snapshots: bool,
+    ) -> Self {
+        let grace = Duration::minutes(HEALTH_GRACE_MINUTES);
+        // The first snapshot is taken a minute after starting
+        let since = last_snapshot.or(started).unwrap_or(now);
+
+        Self {
+            connected: started.is_some(),
+            send_loop_late: next_send.is_some_and(|next_send| now - next_send > grace),
+            snapshots_late: keeps_snapshots && now - since > grace,
+        }
+    }
+}
+
+/// The command line arguments with the values of the secret ones replaced,
+/// both in the `--token <value>` and in the `--token=<value>` form.
+pub fn redact_args(args: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut redact_next = false;
+
+    args.into_iter()
+        .map(|arg| {
+            if redact_next {
+                redact_next = false;
+                return REDACTED.to_owned();
+            }
+
+            if SECRET_ARGS.contains(&arg.as_str()) {
+                redact_next = true;
+                return arg;
+            }
+
+            match arg.split_once('=') {
+                Some((name, _value)) if SECRET_ARGS.contains(&name) => {
+                    format!("{}={}", name, REDACTED)
+                }
+                _ => arg,
+            }
+        })
+        .collect()
+}
+
+/// Split a dump in attachments of at most `max_size` bytes. Only the
+/// concatenation of the parts is valid JSON.
+pub fn split_attachment(content: &[u8], max_size: usize) -> Vec<&[u8]> {
+    content.chunks(max_size.max(1)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dump(command_line: Vec<String>) -> DebugDump {
+        let now = Utc::now();
+
+        DebugDump {
+            taken: now,
+            command_line,
+            config: vec![("max_boost", "10".to_owned())].into_iter().collect(),
+            guilds: Some(2),
+            words: 1,
+            instances: 3,
+            top_words: vec![DumpWord {
+                word: "ciao".to_owned(),
+                count: 3,
+            }],
+            recent_channel: Some(ChannelPointer {
+                channel_id: 10,
+                guild_id: Some(20),
+            }),
+            paused: vec![PausedGuild {
+                guild_id: Some(20),
+                until: None,
+            }],
+            next_send: Some(now),
+            health: TaskHealth::check(now, Some(now), Some(now), None, true),
+            task_restarts: vec![("send loop", 1)].into_iter().collect(),
+        }
+    }
+
+    #[test]
+    fn serialization() {
+        let args = vec![
+            "pino-bot",
+            "--token",
+            "secret-token",
+            "--webhook-url=https://discord.com/api/webhooks/secret",
+            "--max-boost",
+            "10",
+        ];
+        let dump = dump(redact_args(args.into_iter().map(String::from)));
+
+        let json = serde_json::to_string(&dump).unwrap();
+        assert!(!json.contains("secret"));
+        assert!(json.contains("--token"));
+        assert!(json.contains("--webhook-url=[redacted]"));
+        assert!(json.contains("--max-boost"));
+
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!("ciao", value["top_words"][0]["word"]);
+        assert_eq!(20, value["recent_channel"]["guild_id"]);
+        assert_eq!(20, value["paused"][0]["guild_id"]);
+        assert_eq!("10", value["config"]["max_boost"]);
+        assert_eq!(1, value["task_restarts"]["send loop"]);
+    }
+
+    #[test]
+    fn health() {
+        let now = Utc::now();
+        let late = now - Duration::minutes(5);
+
+        let healthy = TaskHealth::check(now, Some(late), Some(now), Some(now), true);
+        assert!(healthy.connected);
+        assert!(!healthy.send_loop_late);
+        assert!(!healthy.snapshots_late);
+
+        let stuck = TaskHealth::check(now, Some(late), Some(late), Some(late), true);
+        assert!(stuck.send_loop_late);
+        assert!(stuck.snapshots_late);
+
+        // nothing to be late for
+        assert!(!TaskHealth::check(now, Some(late), Some(now), None, false).snapshots_late);
+        assert!(!TaskHealth::check(now, Some(late), None, Some(now), true).send_loop_late);
+        assert!(!TaskHealth::check(now, None, Some(now), None, true).connected);
+    }
+
+    #[test]
+    fn splitting() {
+        assert_eq!(vec![b"abc" as &[u8]], split_attachment(b"abc", 3));
+        assert_eq!(vec![b"ab" as &[u8], b"c"], split_attachment(b"abc", 2));
+        assert!(split_attachment(b"", 2).is_empty());
+    }
+}
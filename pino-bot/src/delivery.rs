@@ -0,0 +1,138 @@
+use serde::Serialize;
+use serenity::http::HttpError;
+use std::time::Duration;
+
+/// How many times a message is sent again in the same cycle, when Discord
+/// might accept it later.
+pub const MAX_RETRIES: u32 = 3;
+/// Wait before the first retry, doubled at each one. All the retries fit in
+/// the time the tasks get to stop at shutdown.
+const FIRST_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Discord's codes for "Unknown Channel", "Unknown Guild", "Missing Access"
+/// and "Missing Permissions", see
+/// https://discord.com/developers/docs/topics/opcodes-and-status-codes#json
+const LOST_CHANNEL_CODES: &[isize] = &[10003, 10004, 50001, 50013];
+
+/// Why a message couldn't be sent.
+#[derive(Serialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Failure {
+    /// Rate limits, network errors and Discord outages, it might work later.
+    Retryable,
+    /// Pino was kicked from the channel, lost its permissions or the channel
+    /// was deleted. It won't work until someone talks there again.
+    LostChannel,
+    /// Anything else, like a message Discord refuses.
+    Other,
+}
+
+/// What to do after a failed send.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Next {
+    /// Send again after waiting.
+    Retry(Duration),
+    /// Move to the next recent channel.
+    Rotate,
+    GiveUp,
+}
+
+/// Classify the error of a send, e.g. from [`crate::send_and_record`].
+pub fn classify(error: &anyhow::Error) -> Failure {
+    match error.downcast_ref::<serenity::Error>() {
+        Some(error) => classify_serenity(error),
+        None => Failure::Other,
+    }
+}
+
+pub fn classify_serenity(error: &serenity::Error) -> Failure {
+    match error {
+        serenity::Error::Http(HttpError::UnsuccessfulRequest(response)) => {
+            classify_response(response.status_code.as_u16(), response.error.code)
+        }
+        serenity::Error::Http(HttpError::Request(_)) | serenity::Error::Io(_) => Failure::Retryable,
+        _ => Failure::Other,
+    }
+}
+
+/// Classify an error response of Discord by its HTTP status and JSON code.
+pub fn classify_response(status: u16, code: isize) -> Failure {
+    if LOST_CHANNEL_CODES.contains(&code) || status == 403 || status == 404 {
+        Failure::LostChannel
+    } else if status == 429 || (500..600).contains(&status) {
+        Failure::Retryable
+    } else {
+        Failure::Other
+    }
+}
+
+/// Wait before the retry number `retry`, counting from 0.
+pub fn backoff(retry: u32) -> Duration {
+    FIRST_BACKOFF * 2u32.pow(retry)
+}
+
+/// What to do after `failure`, when the message has been sent again
+/// `retries` times already.
+pub fn next_step(failure: Failure, retries: u32) -> Next {
+    match failure {
+        Failure::Retryable if retries < MAX_RETRIES => Next::Retry(backoff(retries)),
+        Failure::LostChannel => Next::Rotate,
+        Failure::Retryable | Failure::Other => Next::GiveUp,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serenity::model::ModelError;
+    use std::io;
+
+    #[test]
+    fn classification() {
+        assert_eq!(Failure::LostChannel, classify_response(403, 50013));
+        assert_eq!(Failure::LostChannel, classify_response(404, 10003));
+        assert_eq!(Failure::LostChannel, classify_response(403, 0));
+        assert_eq!(Failure::Retryable, classify_response(429, 0));
+        assert_eq!(Failure::Retryable, classify_response(502, 0));
+        // e.g. a message too long
+        assert_eq!(Failure::Other, classify_response(400, 50035));
+
+        let timeout = serenity::Error::Io(io::Error::new(io::ErrorKind::TimedOut, "timed out"));
+        assert_eq!(Failure::Retryable, classify_serenity(&timeout));
+        assert_eq!(
+            Failure::Other,
+            classify_serenity(&serenity::Error::Model(ModelError::MessageTooLong(2100)))
+        );
+        assert_eq!(
+            Failure::Other,
+            classify_serenity(&serenity::Error::Http(HttpError::InvalidWebhook))
+        );
+
+        // through anyhow, like send_and_record returns them
+        assert_eq!(Failure::Retryable, classify(&anyhow::Error::new(timeout)));
+        assert_eq!(
+            Failure::Other,
+            classify(&anyhow::anyhow!("executing webhook"))
+        );
+    }
+
+    #[test]
+    fn steps() {
+        let delays: Vec<_> = (0..MAX_RETRIES)
+            .map(|retries| next_step(Failure::Retryable, retries))
+            .collect();
+        assert_eq!(
+            vec![
+                Next::Retry(Duration::from_secs(1)),
+                Next::Retry(Duration::from_secs(2)),
+                Next::Retry(Duration::from_secs(4)),
+            ],
+            delays
+        );
+        assert_eq!(Next::GiveUp, next_step(Failure::Retryable, MAX_RETRIES));
+
+        assert_eq!(Next::Rotate, next_step(Failure::LostChannel, 0));
+        assert_eq!(Next::Rotate, next_step(Failure::LostChannel, MAX_RETRIES));
+        assert_eq!(Next::GiveUp, next_step(Failure::Other, 0));
+    }
+}
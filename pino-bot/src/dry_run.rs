@@ -0,0 +1,358 @@
+use chrono::{DateTime, Duration, Utc};
+use pino_core::{
+    blocklist::Blocklist,
+    selection::Selection,
+    tracking::{self, Normalization},
+    WordMap,
+};
+use rand::rngs::StdRng;
+use regex::Regex;
+use serenity::async_trait;
+use std::{
+    collections::{HashMap, HashSet},
+    io::{BufRead, Write},
+    sync::Mutex,
+    time::Instant,
+};
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+
+use crate::{
+    guild_settings::Resolved,
+    pipeline::{self, Outlet, Post},
+    runtime_config::RuntimeConfig,
+    schedule::{self, Scheduler, Wakeup},
+};
+
+/// A line read by `--dry-run`: `[#channel] [@user] message text`.
+#[derive(Debug, PartialEq)]
+pub struct Line<'a> {
+    pub channel: Option<&'a str>,
+    pub user: Option<&'a str>,
+    pub content: &'a str,
+}
+
+impl<'a> Line<'a> {
+    pub fn parse(line: &'a str) -> Self {
+        let mut rest = line.trim_start();
+        let mut tag = |symbol: char| {
+            let name = rest.strip_prefix(symbol)?;
+            let (name, after) = name.split_once(char::is_whitespace).unwrap_or((name, ""));
+            rest = after.trim_start();
+
+            Some(name).filter(|name| !name.is_empty())
+        };
+
+        let channel = tag('#');
+        let user = tag('@');
+
+        Line {
+            channel,
+            user,
+            content: rest,
+        }
+    }
+}
+
+/// Time as seen by the dry run: it starts now and runs `scale` times faster.
+struct Clock {
+    real_start: Instant,
+    start: DateTime<Utc>,
+    scale: f64,
+}
+
+impl Clock {
+    fn now(&self) -> DateTime<Utc> {
+        let elapsed = self.real_start.elapsed().mul_f64(self.scale);
+
+        Duration::from_std(elapsed)
+            .ok()
+            .and_then(|elapsed| self.start.checked_add_signed(elapsed))
+            .unwrap_or(DateTime::<Utc>::MAX_UTC)
+    }
+
+    /// How long to really wait for `duration` to pass.
+    fn real(&self, duration: std::time::Duration) -> std::time::Duration {
+        duration.div_f64(self.scale)
+    }
+}
+
+/// Prints the post of a cycle, with when and where it would have been sent.
+struct Terminal<'a, W> {
+    out: &'a Mutex<W>,
+    at: DateTime<Utc>,
+    channel: Option<&'a str>,
+}
+
+#[async_trait]
+impl<W: Write + Send> Outlet for Terminal<'_, W> {
+    async fn deliver(&self, post: &Post) {
+        let at = self.at.format("%Y-%m-%d %H:%M:%S");
+        let mut out = self.out.lock().unwrap();
+        let written = match self.channel {
+            Some(channel) => writeln!(out, "[{}] #{}: {}", at, channel, post.message),
+            None => writeln!(out, "[{}] {}", at, post.message),
+        };
+
+        if let Err(e) = written.and_then(|_| out.flush()) {
+            warn!("Error printing '{}': {}", post.word, e);
+        }
+    }
+}
+
+/// Everything `--dry-run` needs, from the options.
+pub struct DryRun {
+    pub word_regex: Regex,
+    pub normalization: Normalization,
+    pub stop_words: HashSet<String>,
+    pub emoji_map: HashMap<String, String>,
+    pub settings: Resolved,
+    pub config: RuntimeConfig,
+    pub max_words: Option<usize>,
+    pub max_message_length: usize,
+    /// `--trending-window`, in seconds.
+    pub trending_window: u64,
+    /// `--time-scale`.
+    pub time_scale: f64,
+    pub rng: StdRng,
+}
+
+/// Learn from the lines of `input` and write to `out` what pino would send,
+/// through the same steps as with Discord. Once the input is over, pino
+/// speaks one last time and the dry run ends.
+pub async fn run<R, W>(mut dry_run: DryRun, input: R, out: &Mutex<W>)
+where
+    R: BufRead + Send + 'static,
+    W: Write + Send,
+{
+    let (lines, mut received) = mpsc::unbounded_channel();
+
+    // Reading blocks, but only this thread
+    std::thread::spawn(move || {
+        for line in input.lines() {
+            if lines.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    let clock = Clock {
+        real_start: Instant::now(),
+        start: Utc::now(),
+        scale: dry_run.time_scale,
+    };
+    let blocklist = Blocklist::default();
+    let mut words = WordMap::new();
+    let mut channel: Option<String> = None;
+    let mut scheduler = Scheduler::new(
+        clock.now(),
+        schedule::next_delay(&mut dry_run.rng, &dry_run.config),
+    );
+    let mut input_over = false;
+
+    loop {
+        let remaining = clock.real(scheduler.remaining(clock.now()));
+
+        tokio::select! {
+            line = received.recv(), if !input_over => match line {
+                Some(Ok(line)) => {
+                    let line = Line::parse(&line);
+
+                    if let Some(name) = line.channel {
+                        channel = Some(name.to_owned());
+                    }
+
+                    if tracking::is_too_long(line.content, dry_run.max_message_length) {
+                        debug!(
+                            "Skipping a message longer than {} characters from {}",
+                            dry_run.max_message_length,
+                            line.user.unwrap_or("someone")
+                        );
+                        continue;
+                    }
+
+                    let heard: Vec<_> = tracking::extract_words(
+                        line.content,
+                        &dry_run.word_regex,
+                        dry_run.normalization,
+                        &dry_run.stop_words,
+                        &blocklist,
+                    )
+                    .collect();
+
+                    debug!(
+                        "Heard {} words from {} in {}",
+                        heard.len(),
+                        line.user.unwrap_or("someone"),
+                        line.channel.unwrap_or("the same channel")
+                    );
+
+                    let now = clock.now();
+                    for word in heard {
+                        tracking::learn(&mut words, word, now);
+                    }
+                }
+                Some(Err(e)) => {
+                    warn!("Error reading the input: {}", e);
+                    input_over = true;
+                }
+                None => input_over = true,
+            },
+            _ = tokio::time::sleep(remaining) => {
+                let now = clock.now();
+                let delay = schedule::next_delay(&mut dry_run.rng, &dry_run.config);
+
+                if !scheduler.on_wakeup(Wakeup::Timer, now, delay) {
+                    continue;
+                }
+
+                let post = pipeline::compose(
+                    &words,
+                    &blocklist,
+                    &mut dry_run.rng,
+                    &Selection {
+                        scoring: dry_run.settings.scoring,
+                        max_boost: dry_run.config.max_boost,
+                        window: dry_run.trending_window,
+                        now,
+                    },
+                    &dry_run.settings,
+                    &dry_run.emoji_map,
+                );
+
+                match post {
+                    Some(post) => {
+                        let terminal = Terminal {
+                            out,
+                            at: now,
+                            channel: channel.as_deref(),
+                        };
+                        terminal.deliver(&post).await;
+
+                        let older_than = now - Duration::seconds(dry_run.config.max_age as i64);
+                        pipeline::forget_old(&mut words, older_than, dry_run.max_words);
+                    }
+                    None => info!("Nothing to say yet"),
+                }
+
+                if input_over {
+                    break;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pino_core::selection::Scoring;
+    use rand::SeedableRng;
+
+    #[test]
+    fn lines() {
+        assert_eq!(
+            Line {
+                channel: Some("general"),
+                user: Some("axel"),
+                content: "ciao ciao gnocchi",
+            },
+            Line::parse("#general @axel ciao ciao gnocchi")
+        );
+        assert_eq!(
+            Line {
+                channel: None,
+                user: Some("axel"),
+                content: "ciao",
+            },
+            Line::parse("  @axel   ciao")
+        );
+        assert_eq!(
+            Line {
+                channel: Some("general"),
+                user: None,
+                content: "",
+            },
+            Line::parse("#general")
+        );
+        // only at the start, and in this order
+        assert_eq!(
+            Line {
+                channel: None,
+                user: None,
+                content: "ciao #general",
+            },
+            Line::parse("ciao #general")
+        );
+        assert_eq!(
+            Line {
+                channel: None,
+                user: Some("axel"),
+                content: "#general ciao",
+            },
+            Line::parse("@axel #general ciao")
+        );
+    }
+
+    fn dry_run(default_word: Option<&str>) -> DryRun {
+        DryRun {
+            word_regex: Regex::new("^[a-z]+$").unwrap(),
+            normalization: Normalization::None,
+            stop_words: HashSet::new(),
+            emoji_map: HashMap::new(),
+            settings: Resolved {
+                message_template: "**{word}**".to_owned(),
+                emoji_mode: false,
+                default_word: default_word.map(str::to_owned),
+                language: Default::default(),
+                scoring: Scoring::Count,
+                stop_words: None,
+            },
+            config: RuntimeConfig {
+                interval_low: 1,
+                interval_high: 1,
+                max_age: 60,
+                max_boost: 0,
+            },
+            max_words: None,
+            max_message_length: 20,
+            trending_window: 60,
+            // a second of pino is a millisecond
+            time_scale: 1000.0,
+            rng: StdRng::seed_from_u64(1),
+        }
+    }
+
+    async fn printed(dry_run: DryRun, input: &'static str) -> String {
+        let out = Mutex::new(Vec::new());
+        run(dry_run, std::io::Cursor::new(input), &out).await;
+
+        String::from_utf8(out.into_inner().unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn says_the_most_said_word() {
+        let printed = printed(dry_run(None), "#general @axel ciao ciao gnocchi\n").await;
+
+        assert!(
+            printed.ends_with("] #general: **ciao**\n"),
+            "printed {:?}",
+            printed
+        );
+    }
+
+    #[tokio::test]
+    async fn long_messages() {
+        let printed = printed(dry_run(None), "pizza\nciao ciao ciao ciao ciao ciao ciao\n").await;
+
+        assert!(printed.ends_with("] **pizza**\n"), "printed {:?}", printed);
+    }
+
+    #[tokio::test]
+    async fn nothing_heard() {
+        assert_eq!("", printed(dry_run(None), "").await);
+        assert!(printed(dry_run(Some("pino")), "")
+            .await
+            .ends_with("] **pino**\n"));
+    }
+}
@@ -0,0 +1,91 @@
+use anyhow::Context;
+use std::{collections::HashMap, fs, path::Path};
+
+/// Put between regional indicators, or Discord would show pairs of them as flags.
+const SEPARATOR: char = '\u{200b}';
+
+/// Read the word to emoji map from a JSON object, e.g. `{"pizza": "🍕"}`.
+pub fn load(path: &Path) -> anyhow::Result<HashMap<String, String>> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("reading emoji map file {}", path.display()))?;
+
+    parse(&content).with_context(|| format!("parsing emoji map file {}", path.display()))
+}
+
+/// Words are lowercased, like the ones in the word map.
+pub fn parse(content: &str) -> anyhow::Result<HashMap<String, String>> {
+    let map: HashMap<String, String> = serde_json::from_str(content)?;
+
+    Ok(map
+        .into_iter()
+        .map(|(word, emoji)| (word.to_lowercase(), emoji))
+        .collect())
+}
+
+/// The emoji of the word in the map or, if it has none, the word spelled out
+/// in regional indicators. Characters that aren't letters are left out.
+pub fn word_to_emoji(word: &str, map: &HashMap<String, String>) -> String {
+    let word = word.to_lowercase();
+
+    if let Some(emoji) = map.get(&word) {
+        return emoji.clone();
+    }
+
+    let letters: Vec<String> = word
+        .chars()
+        .filter_map(|c| regional_indicator(strip_accent(c)))
+        .map(String::from)
+        .collect();
+
+    letters.join(&SEPARATOR.to_string())
+}
+
+/// The regional indicator of an ASCII letter, like 🇦 for `a`.
+fn regional_indicator(letter: char) -> Option<char> {
+    if !letter.is_ascii_lowercase() {
+        return None;
+    }
+
+    std::char::from_u32('🇦' as u32 + (letter as u32 - 'a' as u32))
+}
+
+/// The letters accepted by the default word regex, without accent.
+fn strip_accent(c: char) -> char {
+    match c {
+        'à' | 'á' => 'a',
+        'è' | 'é' => 'e',
+        'ì' | 'í' => 'i',
+        'ò' | 'ó' => 'o',
+        'ù' | 'ú' => 'u',
+        c => c,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mapped_words() {
+        let map = parse(r#"{"Pizza": "🍕", "gnocchi": "🥟"}"#).unwrap();
+
+        assert_eq!("🍕", word_to_emoji("pizza", &map));
+        assert_eq!("🍕", word_to_emoji("PIZZA", &map));
+        assert_eq!("🥟", word_to_emoji("gnocchi", &map));
+
+        assert!(parse("[\"pizza\"]").is_err());
+    }
+
+    #[test]
+    fn spelled_words() {
+        let map = HashMap::new();
+
+        assert_eq!("🇨\u{200b}🇮\u{200b}🇦\u{200b}🇴", word_to_emoji("ciao", &map));
+        assert_eq!(
+            "🇵\u{200b}🇪\u{200b}🇷\u{200b}🇨\u{200b}🇭\u{200b}🇪",
+            word_to_emoji("perché", &map)
+        );
+        assert_eq!("🇿", word_to_emoji("z'", &map));
+        assert_eq!("", word_to_emoji("'", &map));
+    }
+}
@@ -0,0 +1,320 @@
+use chrono::{DateTime, Duration, Utc};
+use serenity::model::id::UserId;
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    sync::{Arc, Mutex},
+};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+use crate::{
+    delivery::Failure,
+    i18n::{tr, Lang},
+    messenger::Messenger,
+};
+
+/// How far back the errors are counted.
+const WINDOW_HOURS: i64 = 1;
+/// How long the owner isn't told again about the same errors.
+const COOLDOWN_HOURS: i64 = 24;
+
+/// The same errors happened too often: the owner should know.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Alert {
+    pub guild: Option<u64>,
+    pub failure: Failure,
+    /// Errors in the last hour, this one included.
+    pub count: usize,
+    pub last_error: String,
+}
+
+/// The recent errors of a guild and class.
+#[derive(Debug, Default)]
+struct Tally {
+    /// Oldest first, only the ones in the window.
+    times: VecDeque<DateTime<Utc>>,
+    notified: Option<DateTime<Utc>>,
+}
+
+/// Counts the errors by guild and class, and tells when one of them happened
+/// more than `threshold` times in an hour. Each is alerted at most once a day.
+#[derive(Debug)]
+pub struct Aggregator {
+    threshold: usize,
+    tallies: HashMap<(Option<u64>, Failure), Tally>,
+}
+
+impl Aggregator {
+    pub fn new(threshold: usize) -> Self {
+        Self {
+            threshold,
+            tallies: HashMap::new(),
+        }
+    }
+
+    /// Count an error in `guild`, `None` when it isn't in one. Returns the
+    /// alert to send, if any.
+    pub fn record(
+        &mut self,
+        guild: Option<u64>,
+        failure: Failure,
+        error: &str,
+        now: DateTime<Utc>,
+    ) -> Option<Alert> {
+        let tally = self.tallies.entry((guild, failure)).or_default();
+        let since = now - Duration::hours(WINDOW_HOURS);
+
+        while tally.times.front().is_some_and(|&time| time <= since) {
+            tally.times.pop_front();
+        }
+        tally.times.push_back(now);
+
+        if tally.times.len() <= self.threshold {
+            return None;
+        }
+
+        let cooling_down = tally
+            .notified
+            .is_some_and(|notified| now - notified < Duration::hours(COOLDOWN_HOURS));
+
+        if cooling_down {
+            return None;
+        }
+
+        tally.notified = Some(now);
+
+        Some(Alert {
+            guild,
+            failure,
+            count: tally.times.len(),
+            last_error: error.to_owned(),
+        })
+    }
+}
+
+/// Cheap handle to the [`Aggregator`], for every place where sending can
+/// fail. Alerts go to the task that DMs the owner, see [`notify_owner`].
+#[derive(Clone)]
+pub struct Reporter {
+    aggregator: Arc<Mutex<Aggregator>>,
+    alerts: mpsc::UnboundedSender<Alert>,
+}
+
+impl Reporter {
+    /// A reporter alerting after `threshold` errors, with where its alerts
+    /// are received.
+    pub fn new(threshold: usize) -> (Self, mpsc::UnboundedReceiver<Alert>) {
+        let (alerts, received) = mpsc::unbounded_channel();
+        let reporter = Self {
+            aggregator: Arc::new(Mutex::new(Aggregator::new(threshold))),
+            alerts,
+        };
+
+        (reporter, received)
+    }
+
+    pub fn report(&self, guild: Option<u64>, failure: Failure, error: &dyn fmt::Display) {
+        let alert =
+            self.aggregator
+                .lock()
+                .unwrap()
+                .record(guild, failure, &error.to_string(), Utc::now());
+
+        if let Some(alert) = alert {
+            warn!(
+                "{} errors of class {:?} in guild {:?} in the last hour, telling the owner",
+                alert.count, alert.failure, alert.guild
+            );
+
+            if self.alerts.send(alert).is_err() {
+                info!("Nobody is there to tell the owner");
+            }
+        }
+    }
+}
+
+/// The DM telling the owner about `alert`.
+pub fn compose(alert: &Alert, lang: Lang) -> String {
+    let place = match alert.guild {
+        Some(guild) => tr(lang, "alert.guild", &[("guild", &guild)]),
+        None => tr(lang, "alert.no_guild", &[]),
+    };
+    let what = match alert.failure {
+        Failure::Retryable => tr(lang, "alert.retryable", &[]),
+        Failure::LostChannel => tr(lang, "alert.lost_channel", &[]),
+        Failure::Other => tr(lang, "alert.other", &[]),
+    };
+
+    tr(
+        lang,
+        "alert.errors",
+        &[
+            ("place", &place),
+            ("what", &what),
+            ("count", &alert.count),
+            ("error", &alert.last_error),
+        ],
+    )
+}
+
+/// DM the owner about `alert`. Failing is only logged: reporting it would
+/// make more alerts that fail the same way.
+pub async fn notify_owner(messenger: &dyn Messenger, owner: UserId, alert: &Alert, lang: Lang) {
+    if let Err(e) = messenger.direct_message(owner, &compose(alert, lang)).await {
+        warn!("Error telling the owner about repeated errors: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messenger::{Recorded, Recorder};
+
+    /// Record `count` errors, one every `every`, returning the alerts.
+    fn stream(
+        aggregator: &mut Aggregator,
+        key: (Option<u64>, Failure),
+        start: DateTime<Utc>,
+        every: Duration,
+        count: i32,
+    ) -> Vec<Alert> {
+        (0..count)
+            .filter_map(|i| {
+                aggregator.record(key.0, key.1, "Missing Permissions", start + every * i)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn threshold() {
+        let now = Utc::now();
+        let mut aggregator = Aggregator::new(3);
+        let key = (Some(1), Failure::LostChannel);
+
+        // 3 errors are fine, the 4th isn't
+        let alerts = stream(&mut aggregator, key, now, Duration::minutes(1), 3);
+        assert!(alerts.is_empty());
+        assert_eq!(
+            Some(Alert {
+                guild: Some(1),
+                failure: Failure::LostChannel,
+                count: 4,
+                last_error: "Missing Access".to_owned(),
+            }),
+            aggregator.record(
+                Some(1),
+                Failure::LostChannel,
+                "Missing Access",
+                now + Duration::minutes(3)
+            )
+        );
+
+        // once a day
+        let later = now + Duration::minutes(4);
+        assert!(stream(&mut aggregator, key, later, Duration::minutes(10), 100).is_empty());
+    }
+
+    #[test]
+    fn window() {
+        let now = Utc::now();
+        let mut aggregator = Aggregator::new(3);
+        let key = (Some(1), Failure::LostChannel);
+
+        // never more than 3 in an hour
+        assert!(stream(&mut aggregator, key, now, Duration::minutes(20), 50).is_empty());
+
+        // a burst is
+        let later = now + Duration::days(1);
+        let alerts = stream(&mut aggregator, key, later, Duration::minutes(1), 4);
+        assert_eq!(1, alerts.len());
+        assert_eq!(4, alerts[0].count);
+    }
+
+    #[test]
+    fn keys() {
+        let now = Utc::now();
+        let mut aggregator = Aggregator::new(2);
+        let minute = Duration::minutes(1);
+
+        // each guild and class is counted and alerted on its own
+        let mut alerts = Vec::new();
+        for key in &[
+            (Some(1), Failure::LostChannel),
+            (Some(2), Failure::LostChannel),
+            (Some(1), Failure::Retryable),
+            (None, Failure::Other),
+        ] {
+            assert!(stream(&mut aggregator, *key, now, minute, 2).is_empty());
+            alerts.extend(stream(&mut aggregator, *key, now + minute * 2, minute, 5));
+        }
+
+        let keys: Vec<_> = alerts
+            .iter()
+            .map(|alert| (alert.guild, alert.failure, alert.count))
+            .collect();
+        assert_eq!(
+            vec![
+                (Some(1), Failure::LostChannel, 3),
+                (Some(2), Failure::LostChannel, 3),
+                (Some(1), Failure::Retryable, 3),
+                (None, Failure::Other, 3),
+            ],
+            keys
+        );
+    }
+
+    #[test]
+    fn cooldown() {
+        let now = Utc::now();
+        let mut aggregator = Aggregator::new(1);
+        let key = (Some(1), Failure::Other);
+        let hour = Duration::hours(1);
+
+        // errors twice an hour for two days and a bit
+        let alerts = stream(&mut aggregator, key, now, hour / 2, 2 * 24 * 2 + 2);
+        assert_eq!(3, alerts.len());
+    }
+
+    #[tokio::test]
+    async fn reporting() {
+        let (reporter, mut alerts) = Reporter::new(1);
+
+        reporter.report(Some(1), Failure::Other, &"Invalid Form Body");
+        assert!(alerts.try_recv().is_err());
+        reporter.report(Some(1), Failure::Other, &"Invalid Form Body");
+        let alert = alerts.try_recv().unwrap();
+        assert_eq!(2, alert.count);
+
+        let recorder = Recorder::default();
+        let owner = UserId::new(42);
+        notify_owner(&recorder, owner, &alert, Lang::En).await;
+        assert_eq!(
+            vec![Recorded::Direct(
+                owner,
+                "⚠️ In server 1, Discord refused my messages: 2 errors in the last hour. \
+                 The last one: Invalid Form Body\nI won't tell you about it again for a day."
+                    .to_owned()
+            )],
+            recorder.recorded()
+        );
+    }
+
+    #[tokio::test]
+    async fn failing_to_notify() {
+        let (reporter, mut alerts) = Reporter::new(1);
+        let alert = Alert {
+            guild: None,
+            failure: Failure::LostChannel,
+            count: 2,
+            last_error: "Cannot send messages to this user".to_owned(),
+        };
+
+        // nothing is reported about the DM itself
+        for _ in 0..10 {
+            notify_owner(&Recorder::failing(), UserId::new(42), &alert, Lang::It).await;
+        }
+        assert!(alerts.try_recv().is_err());
+        assert!(reporter.aggregator.lock().unwrap().tallies.is_empty());
+    }
+}
@@ -0,0 +1,271 @@
+use serenity::model::id::{GuildId, UserId};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::guild_settings::{Resolved, Settings};
+
+/// Settings of a single guild.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GuildConfig {
+    /// Whether words said in the guild are counted. Commands work regardless.
+    pub tracking_enabled: bool,
+    /// Users whose messages are not counted.
+    pub ignored_users: HashSet<UserId>,
+    /// Overrides the default command prefix.
+    pub prefix: Option<String>,
+    /// Overrides the settings from the command line and `configured`.
+    pub settings: Settings,
+    /// From the `[guilds.<id>]` table of the config file. Overrides the
+    /// settings from the command line and is never persisted.
+    pub configured: Settings,
+}
+
+impl Default for GuildConfig {
+    fn default() -> Self {
+        Self {
+            tracking_enabled: true,
+            ignored_users: HashSet::new(),
+            prefix: None,
+            settings: Settings::default(),
+            configured: Settings::default(),
+        }
+    }
+}
+
+/// Settings of every guild. Guilds that never changed anything use the defaults.
+#[derive(Debug, Default)]
+pub struct GuildConfigs {
+    configs: HashMap<GuildId, GuildConfig>,
+}
+
+impl GuildConfigs {
+    /// The config of the guild, if it changed anything.
+    pub fn get(&self, guild_id: GuildId) -> Option<&GuildConfig> {
+        self.configs.get(&guild_id)
+    }
+
+    pub fn get_mut(&mut self, guild_id: GuildId) -> &mut GuildConfig {
+        self.configs.entry(guild_id).or_default()
+    }
+
+    /// Whether words said in the guild should be counted. Direct messages always are.
+    pub fn is_tracking(&self, guild_id: Option<GuildId>) -> bool {
+        guild_id
+            .and_then(|guild_id| self.configs.get(&guild_id))
+            .is_none_or(|config| config.tracking_enabled)
+    }
+
+    /// Whether the user is ignored in the guild. Nobody is ignored in direct messages.
+    pub fn is_ignored(&self, guild_id: Option<GuildId>, user_id: UserId) -> bool {
+        guild_id
+            .and_then(|guild_id| self.configs.get(&guild_id))
+            .is_some_and(|config| config.ignored_users.contains(&user_id))
+    }
+
+    /// The command prefix used in the guild. Direct messages always use the default.
+    pub fn prefix<'a>(&'a self, guild_id: Option<GuildId>, default: &'a str) -> &'a str {
+        guild_id
+            .and_then(|guild_id| self.configs.get(&guild_id))
+            .and_then(|config| config.prefix.as_deref())
+            .unwrap_or(default)
+    }
+
+    /// The prefix of every guild that changed it, to be persisted.
+    pub fn prefixes(&self) -> BTreeMap<u64, String> {
+        self.configs
+            .iter()
+            .filter_map(|(guild_id, config)| Some((guild_id.get(), config.prefix.clone()?)))
+            .collect()
+    }
+
+    /// The settings of the guild, on top of the ones from the config file and
+    /// the command line. Direct messages only use the command line.
+    pub fn resolve(&self, guild_id: Option<GuildId>, cli: &Settings) -> Resolved {
+        let defaults = self.defaults(guild_id, cli);
+
+        guild_id
+            .and_then(|guild_id| self.configs.get(&guild_id))
+            .map_or_else(
+                || Settings::default().resolve(&defaults),
+                |config| config.settings.resolve(&defaults),
+            )
+    }
+
+    /// What the guild goes back to when unsetting a setting: the config file,
+    /// or else the command line.
+    pub fn defaults(&self, guild_id: Option<GuildId>, cli: &Settings) -> Settings {
+        guild_id
+            .and_then(|guild_id| self.configs.get(&guild_id))
+            .map_or_else(|| cli.clone(), |config| config.configured.on_top_of(cli))
+    }
+
+    /// The settings of every guild that changed any, to be persisted.
+    pub fn settings(&self) -> BTreeMap<u64, Settings> {
+        self.configs
+            .iter()
+            .filter(|(_guild_id, config)| !config.settings.is_empty())
+            .map(|(guild_id, config)| (guild_id.get(), config.settings.clone()))
+            .collect()
+    }
+
+    /// Restore the persisted settings.
+    pub fn load_settings(&mut self, settings: &BTreeMap<u64, Settings>) {
+        for (&guild_id, settings) in settings {
+            self.get_mut(GuildId::new(guild_id)).settings = settings.clone();
+        }
+    }
+
+    /// Use the settings from the config file.
+    pub fn load_configured(&mut self, settings: &BTreeMap<u64, Settings>) {
+        for (&guild_id, settings) in settings {
+            self.get_mut(GuildId::new(guild_id)).configured = settings.clone();
+        }
+    }
+
+    /// Restore the persisted prefixes.
+    pub fn load_prefixes(&mut self, prefixes: &BTreeMap<u64, String>) {
+        for (&guild_id, prefix) in prefixes {
+            self.get_mut(GuildId::new(guild_id)).prefix = Some(prefix.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracking_toggle() {
+        let mut configs = GuildConfigs::default();
+        let guild = GuildId::new(1);
+        let other = GuildId::new(2);
+
+        assert!(configs.is_tracking(Some(guild)));
+        assert!(configs.is_tracking(None));
+
+        configs.get_mut(guild).tracking_enabled = false;
+        assert!(!configs.is_tracking(Some(guild)));
+        assert!(configs.is_tracking(Some(other)));
+        assert!(configs.is_tracking(None));
+
+        configs.get_mut(guild).tracking_enabled = true;
+        assert!(configs.is_tracking(Some(guild)));
+    }
+
+    #[test]
+    fn ignored_users() {
+        let mut configs = GuildConfigs::default();
+        let guild = GuildId::new(1);
+        let user = UserId::new(3);
+
+        assert!(!configs.is_ignored(Some(guild), user));
+
+        configs.get_mut(guild).ignored_users.insert(user);
+        assert!(configs.is_ignored(Some(guild), user));
+        assert!(!configs.is_ignored(Some(GuildId::new(2)), user));
+        assert!(!configs.is_ignored(None, user));
+        assert!(!configs.is_ignored(Some(guild), UserId::new(4)));
+
+        configs.get_mut(guild).ignored_users.remove(&user);
+        assert!(!configs.is_ignored(Some(guild), user));
+    }
+
+    #[test]
+    fn prefixes() {
+        let mut configs = GuildConfigs::default();
+        let guild = GuildId::new(1);
+
+        assert_eq!("!pino", configs.prefix(Some(guild), "!pino"));
+
+        configs.get_mut(guild).prefix = Some("?pino".to_owned());
+        configs.get_mut(GuildId::new(2)).tracking_enabled = false;
+        assert_eq!("?pino", configs.prefix(Some(guild), "!pino"));
+        assert_eq!("!pino", configs.prefix(Some(GuildId::new(2)), "!pino"));
+        assert_eq!("!pino", configs.prefix(None, "!pino"));
+
+        let persisted = configs.prefixes();
+        assert_eq!(
+            vec![(1, "?pino".to_owned())],
+            persisted.clone().into_iter().collect::<Vec<_>>()
+        );
+
+        let mut loaded = GuildConfigs::default();
+        loaded.load_prefixes(&persisted);
+        assert_eq!("?pino", loaded.prefix(Some(guild), "!pino"));
+    }
+
+    #[test]
+    fn settings() {
+        let mut configs = GuildConfigs::default();
+        let guild = GuildId::new(1);
+        let cli = Settings {
+            default_word: Some("pino".to_owned()),
+            ..Settings::default()
+        };
+
+        configs
+            .get_mut(guild)
+            .settings
+            .set("default_word", "ciao")
+            .unwrap();
+        configs.get_mut(GuildId::new(2)).tracking_enabled = false;
+
+        let word = |configs: &GuildConfigs, guild_id| configs.resolve(guild_id, &cli).default_word;
+        assert_eq!(Some("ciao".to_owned()), word(&configs, Some(guild)));
+        assert_eq!(
+            Some("pino".to_owned()),
+            word(&configs, Some(GuildId::new(2)))
+        );
+        assert_eq!(Some("pino".to_owned()), word(&configs, None));
+
+        let persisted = configs.settings();
+        assert_eq!(vec![1], persisted.keys().copied().collect::<Vec<_>>());
+
+        let mut loaded = GuildConfigs::default();
+        loaded.load_settings(&persisted);
+        assert_eq!(Some("ciao".to_owned()), word(&loaded, Some(guild)));
+    }
+
+    #[test]
+    fn configured_settings() {
+        let mut configs = GuildConfigs::default();
+        let guild = GuildId::new(1);
+        let cli = Settings {
+            default_word: Some("pino".to_owned()),
+            emoji_mode: Some(false),
+            ..Settings::default()
+        };
+        let mut configured = Settings::default();
+        configured.set("default_word", "gnocchi").unwrap();
+        configured.set("emoji_mode", "on").unwrap();
+
+        configs.load_configured(&vec![(1, configured)].into_iter().collect());
+
+        let resolved = configs.resolve(Some(guild), &cli);
+        assert_eq!(Some("gnocchi".to_owned()), resolved.default_word);
+        assert!(resolved.emoji_mode);
+        assert!(!configs.resolve(Some(GuildId::new(2)), &cli).emoji_mode);
+
+        // the settings of the guild win, and the file is back after unsetting
+        configs
+            .get_mut(guild)
+            .settings
+            .set("default_word", "ciao")
+            .unwrap();
+        assert_eq!(
+            Some("ciao".to_owned()),
+            configs.resolve(Some(guild), &cli).default_word
+        );
+        configs
+            .get_mut(guild)
+            .settings
+            .unset("default_word")
+            .unwrap();
+        assert_eq!(
+            Some("gnocchi".to_owned()),
+            configs.resolve(Some(guild), &cli).default_word
+        );
+
+        // nothing from the file gets persisted
+        assert!(configs.settings().is_empty());
+    }
+}
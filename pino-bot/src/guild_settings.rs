@@ -0,0 +1,408 @@
+use pino_core::selection::Scoring;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, fmt};
+
+use crate::{i18n::Lang, template};
+
+/// Every key accepted by [`Settings::set`] and [`Settings::unset`].
+pub const KEYS: &[&str] = &[
+    "message_template",
+    "emoji_mode",
+    "default_word",
+    "language",
+    "scoring",
+    "stop_words",
+];
+
+/// Template used when neither the guild nor the command line set one.
+pub const DEFAULT_TEMPLATE: &str = "{word}";
+
+/// Behaviour that can be set on the command line and changed by each guild
+/// with `!pino settings set`. `None` means not set at this level.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct Settings {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_template: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub emoji_mode: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_word: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<Lang>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scoring: Option<Scoring>,
+    /// Instead of the ones of `--stop-words-file`, which can't be set on the
+    /// command line.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_words: Option<HashSet<String>>,
+}
+
+/// The settings that apply to a guild, after looking at every level.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Resolved {
+    pub message_template: String,
+    pub emoji_mode: bool,
+    /// Said when no words have been heard. `None` to stay quiet.
+    pub default_word: Option<String>,
+    /// Of the replies to the commands.
+    pub language: Lang,
+    /// How the word to say is picked.
+    pub scoring: Scoring,
+    /// Words that aren't learned. `None` for the ones of `--stop-words-file`.
+    pub stop_words: Option<HashSet<String>>,
+}
+
+/// Why a value can't be set.
+#[derive(Debug, PartialEq)]
+pub enum SettingError {
+    UnknownKey(String),
+    /// The message template doesn't contain `{word}`.
+    MissingWord,
+    NotOnOff {
+        key: String,
+        value: String,
+    },
+    NotAWord,
+    UnknownLanguage(String),
+    UnknownScoring(String),
+}
+
+impl fmt::Display for SettingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingError::UnknownKey(key) => write!(
+                f,
+                "Unknown setting `{}`, valid ones are {}",
+                key,
+                KEYS.join(", ")
+            ),
+            SettingError::MissingWord => write!(f, "The message template must contain {{word}}"),
+            SettingError::NotOnOff { key, value } => write!(
+                f,
+                "`{}` is not a valid value for {}, use `on` or `off`",
+                value, key
+            ),
+            SettingError::NotAWord => write!(f, "The default word must be a single word"),
+            SettingError::UnknownLanguage(value) => write!(
+                f,
+                "`{}` is not a supported language, use {}",
+                value,
+                crate::i18n::codes()
+            ),
+            SettingError::UnknownScoring(value) => write!(
+                f,
+                "`{}` is not a way to score words, use count or trending",
+                value
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SettingError {}
+
+/// The value set by the guild, or else the one from the command line, or else the built-in one.
+pub fn layered<T: Clone>(guild: &Option<T>, cli: &Option<T>, built_in: T) -> T {
+    guild.as_ref().or(cli.as_ref()).cloned().unwrap_or(built_in)
+}
+
+impl Settings {
+    /// Resolve the settings of a guild, `self`, on top of the ones from the command line.
+    pub fn resolve(&self, cli: &Settings) -> Resolved {
+        Resolved {
+            message_template: layered(
+                &self.message_template,
+                &cli.message_template,
+                DEFAULT_TEMPLATE.to_owned(),
+            ),
+            emoji_mode: layered(&self.emoji_mode, &cli.emoji_mode, false),
+            default_word: layered(
+                &self.default_word.clone().map(Some),
+                &cli.default_word.clone().map(Some),
+                None,
+            ),
+            language: layered(&self.language, &cli.language, Lang::default()),
+            scoring: layered(&self.scoring, &cli.scoring, Scoring::default()),
+            stop_words: layered(
+                &self.stop_words.clone().map(Some),
+                &cli.stop_words.clone().map(Some),
+                None,
+            ),
+        }
+    }
+
+    /// The values set in `self`, or else the ones of `base`.
+    pub fn on_top_of(&self, base: &Settings) -> Settings {
+        Settings {
+            message_template: self
+                .message_template
+                .clone()
+                .or_else(|| base.message_template.clone()),
+            emoji_mode: self.emoji_mode.or(base.emoji_mode),
+            default_word: self
+                .default_word
+                .clone()
+                .or_else(|| base.default_word.clone()),
+            language: self.language.or(base.language),
+            scoring: self.scoring.or(base.scoring),
+            stop_words: self.stop_words.clone().or_else(|| base.stop_words.clone()),
+        }
+    }
+
+    /// Whether nothing is set, so there's nothing to persist.
+    pub fn is_empty(&self) -> bool {
+        *self == Settings::default()
+    }
+
+    /// Set a value, if it's valid for the key.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), SettingError> {
+        let value = value.trim();
+
+        match key {
+            "message_template" => {
+                template::validate(value).map_err(|_| SettingError::MissingWord)?;
+                self.message_template = Some(value.to_owned());
+            }
+            "emoji_mode" => {
+                self.emoji_mode = Some(match value {
+                    "on" | "true" => true,
+                    "off" | "false" => false,
+                    _ => {
+                        return Err(SettingError::NotOnOff {
+                            key: key.to_owned(),
+                            value: value.to_owned(),
+                        })
+                    }
+                });
+            }
+            "default_word" => {
+                if value.is_empty() || value.chars().any(char::is_whitespace) {
+                    return Err(SettingError::NotAWord);
+                }
+
+                self.default_word = Some(value.to_owned());
+            }
+            "language" => {
+                let language = value
+                    .parse()
+                    .map_err(|_| SettingError::UnknownLanguage(value.to_owned()))?;
+                self.language = Some(language);
+            }
+            "scoring" => {
+                let scoring = value
+                    .parse()
+                    .map_err(|_| SettingError::UnknownScoring(value.to_owned()))?;
+                self.scoring = Some(scoring);
+            }
+            "stop_words" => {
+                // `none` to learn every word
+                self.stop_words = Some(
+                    value
+                        .split(|c: char| c == ',' || c.is_whitespace())
+                        .filter(|word| !word.is_empty() && *word != "none")
+                        .map(str::to_lowercase)
+                        .collect(),
+                );
+            }
+            _ => return Err(SettingError::UnknownKey(key.to_owned())),
+        }
+
+        Ok(())
+    }
+
+    /// Go back to the value from the command line.
+    pub fn unset(&mut self, key: &str) -> Result<(), SettingError> {
+        match key {
+            "message_template" => self.message_template = None,
+            "emoji_mode" => self.emoji_mode = None,
+            "default_word" => self.default_word = None,
+            "language" => self.language = None,
+            "scoring" => self.scoring = None,
+            "stop_words" => self.stop_words = None,
+            _ => return Err(SettingError::UnknownKey(key.to_owned())),
+        }
+
+        Ok(())
+    }
+
+    /// The value of a key, if it's set.
+    pub fn get(&self, key: &str) -> Option<String> {
+        match key {
+            "message_template" => self.message_template.clone(),
+            "emoji_mode" => self
+                .emoji_mode
+                .map(|enabled| if enabled { "on" } else { "off" }.to_owned()),
+            "default_word" => self.default_word.clone(),
+            "language" => self.language.map(|language| language.to_string()),
+            "scoring" => self.scoring.map(|scoring| scoring.to_string()),
+            "stop_words" => self.stop_words.as_ref().map(list_words),
+            _ => None,
+        }
+    }
+
+    /// Every key that is set, with its value.
+    #[cfg(test)]
+    pub fn entries(&self) -> Vec<(&'static str, String)> {
+        KEYS.iter()
+            .filter_map(|&key| Some((key, self.get(key)?)))
+            .collect()
+    }
+}
+
+impl Resolved {
+    /// The value of a key, as shown by `!pino settings`.
+    pub fn get(&self, key: &str) -> Option<String> {
+        let value = match key {
+            "message_template" => self.message_template.clone(),
+            "emoji_mode" => if self.emoji_mode { "on" } else { "off" }.to_owned(),
+            "default_word" => self
+                .default_word
+                .clone()
+                .unwrap_or_else(|| "none".to_owned()),
+            "language" => self.language.to_string(),
+            "scoring" => self.scoring.to_string(),
+            "stop_words" => self
+                .stop_words
+                .as_ref()
+                .map_or_else(|| "--stop-words-file".to_owned(), list_words),
+            _ => return None,
+        };
+
+        Some(value)
+    }
+}
+
+/// Stop words as they are set, in alphabetical order.
+fn list_words(words: &HashSet<String>) -> String {
+    if words.is_empty() {
+        return "none".to_owned();
+    }
+
+    let mut words: Vec<_> = words.iter().map(String::as_str).collect();
+    words.sort_unstable();
+
+    words.join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layers() {
+        assert_eq!(1, layered(&Some(1), &Some(2), 3));
+        assert_eq!(2, layered(&None, &Some(2), 3));
+        assert_eq!(3, layered(&None, &None, 3));
+
+        let cli = Settings {
+            message_template: Some("**{word}**".to_owned()),
+            emoji_mode: Some(false),
+            default_word: None,
+            language: None,
+            scoring: Some(Scoring::Trending),
+            stop_words: None,
+        };
+        let mut guild = Settings::default();
+
+        assert_eq!(
+            Resolved {
+                message_template: "**{word}**".to_owned(),
+                emoji_mode: false,
+                default_word: None,
+                language: Lang::En,
+                scoring: Scoring::Trending,
+                stop_words: None,
+            },
+            guild.resolve(&cli)
+        );
+        assert_eq!(
+            DEFAULT_TEMPLATE,
+            guild.resolve(&Settings::default()).message_template
+        );
+
+        guild.set("emoji_mode", "on").unwrap();
+        guild.set("default_word", "ciao").unwrap();
+        let resolved = guild.resolve(&cli);
+        assert!(resolved.emoji_mode);
+        assert_eq!(Some("ciao".to_owned()), resolved.default_word);
+        assert_eq!("**{word}**", resolved.message_template);
+
+        guild.unset("emoji_mode").unwrap();
+        assert!(!guild.resolve(&cli).emoji_mode);
+        assert_eq!(
+            Some("off".to_owned()),
+            guild.resolve(&cli).get("emoji_mode")
+        );
+        assert_eq!(
+            Some("none".to_owned()),
+            Settings::default().resolve(&cli).get("default_word")
+        );
+
+        guild.set("scoring", "count").unwrap();
+        guild.set("stop_words", "Il, lo la").unwrap();
+        let resolved = guild.resolve(&cli);
+        assert_eq!(Scoring::Count, resolved.scoring);
+        assert_eq!(Some("il, la, lo".to_owned()), resolved.get("stop_words"));
+        assert!(resolved.stop_words.unwrap().contains("il"));
+        // every word is learned
+        guild.set("stop_words", "none").unwrap();
+        assert_eq!(Some(HashSet::new()), guild.resolve(&cli).stop_words);
+        guild.unset("stop_words").unwrap();
+        assert_eq!(
+            Some("--stop-words-file".to_owned()),
+            guild.resolve(&cli).get("stop_words")
+        );
+    }
+
+    #[test]
+    fn validation() {
+        let mut settings = Settings::default();
+
+        assert_eq!(
+            Err(SettingError::MissingWord),
+            settings.set("message_template", "no word")
+        );
+        assert!(settings.set("emoji_mode", "maybe").is_err());
+        assert_eq!(
+            Err(SettingError::NotAWord),
+            settings.set("default_word", "two words")
+        );
+        assert_eq!(
+            Err(SettingError::UnknownLanguage("de".to_owned())),
+            settings.set("language", "de")
+        );
+        assert_eq!(
+            Err(SettingError::UnknownScoring("random".to_owned())),
+            settings.set("scoring", "random")
+        );
+        assert!(settings.set("max_boost", "3").is_err());
+        assert!(settings.unset("max_boost").is_err());
+        assert!(settings.is_empty());
+
+        settings.set("language", "it").unwrap();
+        assert_eq!(Lang::It, settings.resolve(&Settings::default()).language);
+        settings.unset("language").unwrap();
+
+        settings.set("message_template", " Today: {word} ").unwrap();
+        settings.set("emoji_mode", "off").unwrap();
+        assert_eq!(
+            vec![
+                ("message_template", "Today: {word}".to_owned()),
+                ("emoji_mode", "off".to_owned())
+            ],
+            settings.entries()
+        );
+    }
+
+    #[test]
+    fn serialization() {
+        let settings = Settings {
+            emoji_mode: Some(true),
+            ..Settings::default()
+        };
+
+        let json = serde_json::to_string(&settings).unwrap();
+        assert_eq!(r#"{"emoji_mode":true}"#, json);
+        assert_eq!(settings, serde_json::from_str(&json).unwrap());
+        assert_eq!(Settings::default(), serde_json::from_str("{}").unwrap());
+    }
+}
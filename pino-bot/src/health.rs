@@ -0,0 +1,334 @@
+use chrono::{DateTime, Duration, Utc};
+use hyper::{Body, Method, Request, Response, StatusCode};
+use serde::Serialize;
+use serenity::gateway::ConnectionStage;
+use std::{
+    collections::BTreeMap,
+    net::TcpListener,
+    ops::Range,
+    sync::{Arc, RwLock},
+};
+use tokio::sync::watch;
+
+use crate::{http_server, supervisor::Supervisor};
+
+/// What pino knows about its connections to Discord, one per shard, from
+/// the gateway events.
+#[derive(Debug, Clone)]
+pub struct Gateway {
+    /// When each shard of this process last connected or resumed, `None`
+    /// while it's disconnected.
+    shards: BTreeMap<u32, Option<DateTime<Utc>>>,
+    /// When Discord last sent anything, on any shard.
+    last_event: Option<DateTime<Utc>>,
+}
+
+impl Gateway {
+    /// Nothing connected yet, out of `shards`.
+    pub fn new(shards: Range<u32>) -> Self {
+        Self {
+            shards: shards.map(|shard| (shard, None)).collect(),
+            last_event: None,
+        }
+    }
+
+    /// On `ready` and `resume` of `shard`.
+    pub fn connected(&mut self, shard: u32, now: DateTime<Utc>) {
+        self.shards.insert(shard, Some(now));
+        self.last_event = Some(now);
+    }
+
+    /// On every change of the stage of `shard`: anything but
+    /// [`ConnectionStage::Connected`] is a lost connection.
+    pub fn stage(&mut self, shard: u32, stage: ConnectionStage, now: DateTime<Utc>) {
+        let connected = self.shards.get(&shard).copied().flatten().is_some();
+
+        match stage {
+            ConnectionStage::Connected if !connected => self.connected(shard, now),
+            ConnectionStage::Connected => {}
+            _ => {
+                self.shards.insert(shard, None);
+            }
+        }
+    }
+
+    /// On every event from the gateway.
+    pub fn event(&mut self, now: DateTime<Utc>) {
+        self.last_event = Some(now);
+    }
+
+    /// Whether every shard is connected.
+    pub fn is_connected(&self) -> bool {
+        self.shards.values().all(Option::is_some)
+    }
+
+    /// Why pino isn't ready at `now`, if it isn't. It's not when a shard is
+    /// disconnected, or when the last event is more than `heartbeat` old.
+    pub fn not_ready(&self, now: DateTime<Utc>, heartbeat: Duration) -> Option<String> {
+        if let Some((shard, _)) = self.shards.iter().find(|(_, since)| since.is_none()) {
+            return Some(format!("shard {} not connected to Discord", shard));
+        }
+
+        match self.last_event {
+            Some(last) if now - last <= heartbeat => None,
+            _ => Some(format!(
+                "no events from Discord in {} minutes",
+                heartbeat.num_minutes()
+            )),
+        }
+    }
+}
+
+/// What the probes look at.
+#[derive(Clone)]
+pub struct Probes {
+    pub started: DateTime<Utc>,
+    pub gateway: Arc<RwLock<Gateway>>,
+    pub supervisor: Supervisor,
+    /// `--heartbeat-minutes`.
+    pub heartbeat: Duration,
+}
+
+/// Body of `/healthz`.
+#[derive(Serialize)]
+struct Liveness {
+    status: &'static str,
+    uptime_seconds: i64,
+    /// Background tasks that panicked and wait to be restarted.
+    tasks_down: Vec<&'static str>,
+    restarts: BTreeMap<&'static str, usize>,
+}
+
+/// Body of `/readyz`.
+#[derive(Serialize)]
+struct Readiness {
+    status: &'static str,
+    uptime_seconds: i64,
+    connected: bool,
+    /// When each shard connected, by id.
+    shards: BTreeMap<u32, Option<DateTime<Utc>>>,
+    last_event: Option<DateTime<Utc>>,
+    reason: Option<String>,
+}
+
+/// 200 and `ok` when healthy, 503 and `unavailable` otherwise.
+fn status(healthy: bool) -> (StatusCode, &'static str) {
+    if healthy {
+        (StatusCode::OK, "ok")
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, "unavailable")
+    }
+}
+
+fn json<T: Serialize>(status: StatusCode, body: &T) -> Response<Body> {
+    http_server::response(
+        status,
+        "application/json",
+        serde_json::to_string(body).unwrap(),
+    )
+}
+
+impl Probes {
+    /// Alive if every background task is running.
+    fn liveness(&self, now: DateTime<Utc>) -> Response<Body> {
+        let tasks_down = self.supervisor.down();
+        let (code, status) = status(tasks_down.is_empty());
+
+        json(
+            code,
+            &Liveness {
+                status,
+                uptime_seconds: (now - self.started).num_seconds(),
+                tasks_down,
+                restarts: self.supervisor.restarts(),
+            },
+        )
+    }
+
+    /// Ready if connected to Discord, and hearing from it.
+    fn readiness(&self, now: DateTime<Utc>) -> Response<Body> {
+        let gateway = self.gateway.read().unwrap().clone();
+        let reason = gateway.not_ready(now, self.heartbeat);
+        let (code, status) = status(reason.is_none());
+
+        json(
+            code,
+            &Readiness {
+                status,
+                uptime_seconds: (now - self.started).num_seconds(),
+                connected: gateway.is_connected(),
+                shards: gateway.shards,
+                last_event: gateway.last_event,
+                reason,
+            },
+        )
+    }
+
+    /// Answer `GET /healthz` and `GET /readyz`.
+    async fn respond(self, request: Request<Body>) -> Response<Body> {
+        if request.method() != Method::GET {
+            return http_server::response(
+                StatusCode::METHOD_NOT_ALLOWED,
+                "text/plain",
+                "Only GET\n".to_owned(),
+            );
+        }
+
+        match request.uri().path() {
+            "/healthz" => self.liveness(Utc::now()),
+            "/readyz" => self.readiness(Utc::now()),
+            _ => http_server::response(
+                StatusCode::NOT_FOUND,
+                "text/plain",
+                "Not found, try /healthz or /readyz\n".to_owned(),
+            ),
+        }
+    }
+}
+
+/// Serve the probes on `listener` until shutdown.
+pub async fn serve(
+    listener: TcpListener,
+    probes: Probes,
+    stop: watch::Receiver<bool>,
+) -> anyhow::Result<()> {
+    let handler = move |request| probes.clone().respond(request);
+
+    http_server::serve(listener, handler, stop).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shutdown::Shutdown;
+
+    #[test]
+    fn events() {
+        let mut gateway = Gateway::new(0..1);
+        let heartbeat = Duration::minutes(10);
+        let now = Utc::now();
+
+        assert!(gateway.not_ready(now, heartbeat).is_some());
+
+        gateway.connected(0, now);
+        assert_eq!(None, gateway.not_ready(now, heartbeat));
+        assert_eq!(
+            None,
+            gateway.not_ready(now + Duration::minutes(10), heartbeat)
+        );
+
+        // too quiet
+        let later = now + Duration::minutes(11);
+        assert!(gateway
+            .not_ready(later, heartbeat)
+            .unwrap()
+            .contains("no events"));
+        gateway.event(later);
+        assert_eq!(None, gateway.not_ready(later, heartbeat));
+
+        gateway.stage(0, ConnectionStage::Resuming, later);
+        assert!(gateway
+            .not_ready(later, heartbeat)
+            .unwrap()
+            .contains("not connected"));
+
+        let reconnected = later + Duration::minutes(1);
+        gateway.stage(0, ConnectionStage::Connected, reconnected);
+        assert_eq!(Some(reconnected), gateway.shards[&0]);
+        assert_eq!(None, gateway.not_ready(reconnected, heartbeat));
+
+        // already connected, it's not a new connection
+        gateway.stage(0, ConnectionStage::Connected, reconnected + heartbeat);
+        assert_eq!(Some(reconnected), gateway.shards[&0]);
+    }
+
+    #[test]
+    fn shards() {
+        let mut gateway = Gateway::new(2..4);
+        let heartbeat = Duration::minutes(10);
+        let now = Utc::now();
+
+        gateway.connected(2, now);
+        assert!(!gateway.is_connected());
+        assert_eq!(
+            Some("shard 3 not connected to Discord".to_owned()),
+            gateway.not_ready(now, heartbeat)
+        );
+
+        gateway.stage(3, ConnectionStage::Connected, now);
+        assert!(gateway.is_connected());
+        assert_eq!(None, gateway.not_ready(now, heartbeat));
+
+        gateway.stage(2, ConnectionStage::Disconnected, now);
+        assert!(!gateway.is_connected());
+    }
+
+    async fn get(addr: std::net::SocketAddr, path: &str) -> (u16, serde_json::Value) {
+        let response = reqwest::get(format!("http://{}{}", addr, path))
+            .await
+            .unwrap();
+
+        (
+            response.status().as_u16(),
+            response.json().await.unwrap_or_default(),
+        )
+    }
+
+    #[tokio::test]
+    async fn probes() {
+        let supervisor = Supervisor::new(std::time::Duration::from_secs(60));
+        let probes = Probes {
+            started: Utc::now() - Duration::minutes(5),
+            gateway: Arc::new(RwLock::new(Gateway::new(0..1))),
+            supervisor: supervisor.clone(),
+            heartbeat: Duration::minutes(10),
+        };
+        let gateway = probes.gateway.clone();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut shutdown = Shutdown::default();
+
+        shutdown.spawn(move |stop| async move {
+            serve(listener, probes, stop).await.unwrap();
+        });
+
+        let (status, body) = get(addr, "/healthz").await;
+        assert_eq!(200, status);
+        assert_eq!("ok", body["status"]);
+        assert_eq!(300, body["uptime_seconds"]);
+
+        let (status, body) = get(addr, "/readyz").await;
+        assert_eq!(503, status);
+        assert_eq!(false, body["connected"]);
+        assert!(body["shards"]["0"].is_null());
+        assert!(body["last_event"].is_null());
+
+        gateway.write().unwrap().connected(0, Utc::now());
+        let (status, body) = get(addr, "/readyz").await;
+        assert_eq!(200, status);
+        assert_eq!("ok", body["status"]);
+        assert!(body["shards"]["0"].is_string());
+        assert!(body["last_event"].is_string());
+
+        gateway
+            .write()
+            .unwrap()
+            .stage(0, ConnectionStage::Disconnected, Utc::now());
+        assert_eq!(503, get(addr, "/readyz").await.0);
+
+        // a background task that panicked is waiting for its restart
+        supervisor.spawn(&mut shutdown, "broken", |_stop| async {
+            panic!("broken task");
+        });
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+
+        let (status, body) = get(addr, "/healthz").await;
+        assert_eq!(503, status);
+        assert_eq!(serde_json::json!(["broken"]), body["tasks_down"]);
+        assert_eq!(1, body["restarts"]["broken"]);
+
+        assert_eq!(404, get(addr, "/metrics").await.0);
+
+        assert!(shutdown.stop(std::time::Duration::from_secs(5)).await);
+    }
+}
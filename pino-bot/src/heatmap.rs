@@ -0,0 +1,114 @@
+use chrono::{DateTime, Datelike, Timelike, Utc};
+
+use crate::{
+    i18n::{tr, Lang},
+    stats,
+};
+
+/// Messages by day of the week (Monday first) and hour of the day, in UTC.
+pub type Heatmap = [[u64; 24]; 7];
+
+/// Count a message sent at `time`.
+pub fn record(heatmap: &mut Heatmap, time: DateTime<Utc>) {
+    let day = time.weekday().num_days_from_monday() as usize;
+    heatmap[day][time.hour() as usize] += 1;
+}
+
+/// Day and hour with the most messages, the earliest in the week if tied.
+/// `None` if nothing was counted yet.
+pub fn peak(heatmap: &Heatmap) -> Option<(usize, usize)> {
+    let mut peak = None;
+    let mut max = 0;
+
+    for (day, hours) in heatmap.iter().enumerate() {
+        for (hour, &count) in hours.iter().enumerate() {
+            if count > max {
+                max = count;
+                peak = Some((day, hour));
+            }
+        }
+    }
+
+    peak
+}
+
+/// One row of blocks per day and one column per hour, scaled to the busiest
+/// hour of the week, followed by the busiest hour.
+pub fn render(heatmap: &Heatmap, lang: Lang) -> String {
+    let max = heatmap.iter().flatten().copied().max().unwrap_or(0) as usize;
+    let day_name = |day: usize| tr(lang, &format!("day.{}", day), &[]);
+    let mut out = format!("{:<4}{:<6}{:<6}{:<6}{}\n", "", 0, 6, 12, 18);
+
+    for (day, hours) in heatmap.iter().enumerate() {
+        let hours: Vec<usize> = hours.iter().map(|&count| count as usize).collect();
+        out.push_str(&format!(
+            "{} {}\n",
+            day_name(day),
+            stats::sparkline_scaled(&hours, max)
+        ));
+    }
+
+    match peak(heatmap) {
+        Some((day, hour)) => out.push_str(&tr(
+            lang,
+            "heatmap.busiest",
+            &[
+                ("day", &day_name(day)),
+                ("hour", &format!("{:02}", hour)),
+                ("count", &max),
+            ],
+        )),
+        None => out.push_str(&tr(lang, "heatmap.empty", &[])),
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, TimeZone};
+
+    #[test]
+    fn recording() {
+        let mut heatmap = [[0; 24]; 7];
+        assert_eq!(None, peak(&heatmap));
+
+        // a Sunday
+        let date = NaiveDate::from_ymd_opt(2021, 3, 14).unwrap();
+        let sunday = Utc.from_utc_datetime(&date.and_hms_opt(21, 30, 0).unwrap());
+        record(&mut heatmap, sunday);
+        record(&mut heatmap, sunday);
+        let monday = sunday + chrono::Duration::hours(3);
+        record(&mut heatmap, monday);
+
+        assert_eq!(2, heatmap[6][21]);
+        assert_eq!(1, heatmap[0][0]);
+        assert_eq!(Some((6, 21)), peak(&heatmap));
+    }
+
+    #[test]
+    fn rendering() {
+        let mut heatmap = [[0; 24]; 7];
+        heatmap[4][21] = 8;
+        heatmap[4][20] = 1;
+
+        let chart = render(&heatmap, Lang::En);
+        let lines: Vec<_> = chart.lines().collect();
+
+        assert_eq!(9, lines.len());
+        assert_eq!("    0     6     12    18", lines[0]);
+        assert_eq!(format!("Mon {}", " ".repeat(24)), lines[1]);
+        assert_eq!(
+            format!("Fri {}▁█{}", " ".repeat(20), " ".repeat(2)),
+            lines[5]
+        );
+        assert_eq!("Busiest: Fri 21:00 UTC, 8 messages", lines[8]);
+
+        assert!(render(&[[0; 24]; 7], Lang::En).ends_with("No messages yet"));
+
+        let chart = render(&heatmap, Lang::It);
+        assert!(chart.contains("\nVen "));
+        assert!(chart.ends_with("Più attivo: Ven 21:00 UTC, 8 messaggi"));
+    }
+}
@@ -0,0 +1,99 @@
+/// Number of buckets: words of length 1 to 9 get their own bucket, the last one
+/// collects every word with 10 or more characters.
+pub const BUCKETS: usize = 10;
+
+/// Widest bar in the rendered chart. Keeps the whole code block well below
+/// Discord's 2000 characters limit.
+const MAX_BAR_WIDTH: usize = 40;
+
+/// Group words by their length (in characters), summing up the amount of
+/// instances of every word in the matching bucket.
+pub fn length_histogram<'a, I>(words: I) -> [usize; BUCKETS]
+where
+    I: IntoIterator<Item = (&'a str, usize)>,
+{
+    let mut buckets = [0; BUCKETS];
+
+    for (word, instances) in words {
+        let len = word.chars().count();
+
+        if len == 0 {
+            continue;
+        }
+
+        buckets[len.min(BUCKETS) - 1] += instances;
+    }
+
+    buckets
+}
+
+/// Render the histogram as a text-art bar chart, one line per bucket.
+/// Bars are scaled relative to the biggest bucket.
+pub fn render(buckets: &[usize; BUCKETS]) -> String {
+    let max = buckets.iter().copied().max().unwrap_or(0);
+    let mut out = String::new();
+
+    for (i, &count) in buckets.iter().enumerate() {
+        let label = if i + 1 == BUCKETS {
+            format!("{}+", BUCKETS)
+        } else {
+            format!("{}", i + 1)
+        };
+
+        let width = if max == 0 {
+            0
+        } else {
+            // Round up so that non-empty buckets always get at least one block
+            let (count, max) = (count as u128, max as u128);
+            (count * MAX_BAR_WIDTH as u128).div_ceil(max) as usize
+        };
+
+        out.push_str(&format!(
+            "{:>3} | {:<width$} {}\n",
+            label,
+            "█".repeat(width),
+            count,
+            width = MAX_BAR_WIDTH
+        ));
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn buckets() {
+        let words = vec![
+            ("a", 2),
+            ("ciao", 3),
+            ("pino", 1),
+            ("precipitevolissimevolmente", 4),
+        ];
+        let histogram = length_histogram(words);
+
+        assert_eq!([2, 0, 0, 4, 0, 0, 0, 0, 0, 4], histogram);
+    }
+
+    #[test]
+    fn counts_characters() {
+        let histogram = length_histogram(vec![("perché", 1)]);
+        assert_eq!(1, histogram[5]);
+    }
+
+    #[test]
+    fn render_fits() {
+        let mut buckets = [0; BUCKETS];
+        buckets[0] = usize::MAX;
+        buckets[1] = 1;
+
+        let chart = render(&buckets);
+
+        assert_eq!(BUCKETS, chart.lines().count());
+        assert!(chart.chars().count() < 2000);
+        // smallest non-empty bucket is still visible
+        assert!(chart.lines().nth(1).unwrap().contains('█'));
+    }
+}
@@ -0,0 +1,51 @@
+use hyper::{
+    header::CONTENT_TYPE,
+    service::{make_service_fn, service_fn},
+    Body, Request, Response, Server, StatusCode,
+};
+use std::{convert::Infallible, future::Future, net::TcpListener};
+use tokio::sync::watch;
+
+use crate::shutdown;
+
+/// A response with `status`, and `body` of type `content_type`.
+pub fn response(status: StatusCode, content_type: &str, body: String) -> Response<Body> {
+    let mut response = Response::new(Body::from(body));
+    *response.status_mut() = status;
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, content_type.parse().unwrap());
+
+    response
+}
+
+/// Answer the requests on `listener` with `handler` until shutdown. The
+/// listener is bound when starting, so that a busy address stops pino right
+/// away.
+pub async fn serve<H, F>(
+    listener: TcpListener,
+    handler: H,
+    mut stop: watch::Receiver<bool>,
+) -> anyhow::Result<()>
+where
+    H: Fn(Request<Body>) -> F + Clone + Send + Sync + 'static,
+    F: Future<Output = Response<Body>> + Send + 'static,
+{
+    let service = make_service_fn(move |_connection| {
+        let handler = handler.clone();
+
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let response = handler(request);
+                async move { Ok::<_, Infallible>(response.await) }
+            }))
+        }
+    });
+
+    Server::from_tcp(listener)?
+        .serve(service)
+        .with_graceful_shutdown(async move { shutdown::requested(&mut stop).await })
+        .await?;
+
+    Ok(())
+}
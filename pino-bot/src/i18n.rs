@@ -0,0 +1,982 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashSet, fmt, str::FromStr, sync::Mutex};
+use tracing::warn;
+
+/// A language pino can reply in.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Lang {
+    #[default]
+    En,
+    It,
+}
+
+/// Every language, as accepted by `--language` and `!pino settings set language`.
+pub const LANGS: &[Lang] = &[Lang::En, Lang::It];
+
+impl Lang {
+    pub fn code(self) -> &'static str {
+        match self {
+            Lang::En => "en",
+            Lang::It => "it",
+        }
+    }
+
+    fn table(self) -> Table {
+        match self {
+            Lang::En => EN,
+            Lang::It => IT,
+        }
+    }
+}
+
+impl fmt::Display for Lang {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+impl FromStr for Lang {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        LANGS
+            .iter()
+            .copied()
+            .find(|lang| lang.code().eq_ignore_ascii_case(s))
+            .ok_or_else(|| format!("`{}` is not a supported language, use {}", s, codes()))
+    }
+}
+
+/// The codes of every language, e.g. `en, it`.
+pub fn codes() -> String {
+    LANGS
+        .iter()
+        .map(|lang| lang.code())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Arguments of a template, by name.
+pub type Args<'a> = &'a [(&'a str, &'a (dyn fmt::Display + Sync))];
+
+/// Templates of a language, by key.
+type Table = &'static [(&'static str, &'static str)];
+
+/// Keys already reported as missing, so that each is logged once.
+static MISSING: Lazy<Mutex<HashSet<(Lang, String)>>> = Lazy::new(Default::default);
+
+/// The text of `key` in `lang`, with the arguments filled in. See [`render`]
+/// for the syntax of the templates. Keys missing in `lang` fall back to
+/// English, keys missing in English too are returned as they are.
+pub fn tr(lang: Lang, key: &str, args: Args) -> String {
+    match template(lang, lang.table(), key) {
+        Some(template) => render(template, args),
+        None => key.to_owned(),
+    }
+}
+
+/// The template of `key` in `table`, or else the English one.
+fn template(lang: Lang, table: Table, key: &str) -> Option<&'static str> {
+    if let Some(template) = find(table, key) {
+        return Some(template);
+    }
+
+    if report_missing(lang, key) {
+        warn!("Missing translation of '{}' in {}", key, lang);
+    }
+
+    match lang {
+        Lang::En => None,
+        _ => find(EN, key),
+    }
+}
+
+fn find(table: Table, key: &str) -> Option<&'static str> {
+    table
+        .iter()
+        .find(|(candidate, _template)| *candidate == key)
+        .map(|(_key, template)| *template)
+}
+
+/// Returns true the first time a key is reported missing in a language.
+fn report_missing(lang: Lang, key: &str) -> bool {
+    MISSING.lock().unwrap().insert((lang, key.to_owned()))
+}
+
+/// Fill in a template. `{name}` is replaced with the argument called `name`,
+/// `{name|one|other}` with `one` if that argument is 1 and with `other`
+/// otherwise, which is how both English and Italian make plurals.
+/// `{{` and `}}` are literal braces, placeholders without an argument are kept.
+pub fn render(template: &str, args: Args) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find(['{', '}']) {
+        out.push_str(&rest[..start]);
+        let brace = &rest[start..];
+
+        if brace.starts_with("{{") || brace.starts_with("}}") {
+            out.push_str(&brace[..1]);
+            rest = &brace[2..];
+            continue;
+        }
+
+        let end = match brace.find('}').filter(|_| brace.starts_with('{')) {
+            Some(end) => end,
+            None => {
+                // Stray brace
+                out.push_str(&brace[..1]);
+                rest = &brace[1..];
+                continue;
+            }
+        };
+
+        let placeholder = &brace[1..end];
+        match expand(placeholder, args) {
+            Some(value) => out.push_str(&value),
+            None => out.push_str(&brace[..=end]),
+        }
+        rest = &brace[end + 1..];
+    }
+
+    out.push_str(rest);
+    out
+}
+
+/// The value of a placeholder, without the braces. `None` if there's no such argument.
+fn expand(placeholder: &str, args: Args) -> Option<String> {
+    let mut parts = placeholder.split('|');
+    let name = parts.next()?;
+    let value = args
+        .iter()
+        .find(|(candidate, _value)| *candidate == name)?
+        .1
+        .to_string();
+
+    match (parts.next(), parts.next(), parts.next()) {
+        (None, _, _) => Some(value),
+        (Some(one), Some(other), None) => Some(if value == "1" { one } else { other }.to_owned()),
+        _ => None,
+    }
+}
+
+static EN: Table = &[
+    // Commands
+    ("help.help", "this list"),
+    ("help.version", "version, uptime and how much I remember"),
+    ("help.uptime", "how long I've been running and how much I did"),
+    ("help.histogram", "bar chart of the tracked words grouped by length"),
+    ("help.heatmap", "when people talk the most, by day and hour"),
+    (
+        "help.server-summary",
+        "this week's top words, most active member and busiest hour",
+    ),
+    (
+        "help.cooccurrence",
+        "the words said most often in the same messages as a word",
+    ),
+    ("help.activity", "how many messages were sent in each channel"),
+    ("help.nominate", "candidate a word for the word of the week"),
+    ("help.vote", "vote the word of the week, among the nominated ones"),
+    ("help.votes", "the words nominated this week and their votes"),
+    ("help.wordcloud", "picture of the most said words, bigger the more they're said"),
+    ("help.top", "the most said words"),
+    ("help.stats", "how often a word has been said lately"),
+    ("help.leaderboard", "every word, a page at a time"),
+    ("help.speak", "say the next word right now"),
+    ("help.when", "when the next word is coming"),
+    ("help.history", "the last messages I sent in this server"),
+    ("help.status", "whether I'm paused, and for how long"),
+    ("help.forget", "forget a word"),
+    ("help.reset", "forget everything, after confirming"),
+    ("help.pause", "stop talking, e.g. for `30m` or until resumed"),
+    ("help.resume", "start talking again"),
+    ("help.set", "change a setting without restarting"),
+    ("help.get", "current value of a setting"),
+    (
+        "help.config",
+        "every setting with its current value, and the options pino was started with",
+    ),
+    ("help.schedule", "post a word in this channel at a given UTC time"),
+    ("help.block", "never learn a word, or the words matching a regex"),
+    ("help.unblock", "remove an entry from the blocklist"),
+    ("help.blocked", "list the blocklist"),
+    ("help.prefix", "show or change the prefix of the commands in this server"),
+    ("help.settings", "show or change how I talk in this server"),
+    ("help.ignore", "stop counting someone's words in this server"),
+    ("help.unignore", "start counting someone's words again"),
+    ("help.enable", "start counting the words said in this server"),
+    ("help.disable", "stop counting the words said in this server"),
+    ("help.export", "upload the learned words as a JSON file"),
+    ("help.import", "merge the words from an attached export file"),
+    ("help.debug", "DM a dump of my state, to find out what's wrong"),
+    ("help.restricted", "{usage} — {description} (only {who})"),
+    ("who.everyone", "everyone"),
+    ("who.moderator", "people who can manage messages"),
+    ("who.admin", "administrators"),
+    ("who.owner", "the owner of pino"),
+    // Replies shared by several commands
+    ("usage", "Usage: `{usage}`"),
+    ("usage.either", "Usage: `{usage}` or `{other}`"),
+    ("denied", "Only {who} can use `{command}`"),
+    ("servers_only", "This only works in servers"),
+    ("cooldown", "Slow down, I'm still catching my breath 🦜"),
+    ("no_words", "I haven't heard anything yet, say something! 🦜"),
+    ("never_heard", "I never heard '{word}' 🦜"),
+    // Replies of single commands
+    (
+        "version.uptime",
+        "Up for {uptime} in {guilds} {guilds|server|servers}",
+    ),
+    ("version.not_connected", "Not connected yet"),
+    (
+        "version.tracking",
+        "Tracking {words} {words|word|words} said {instances} {instances|time|times}",
+    ),
+    (
+        "uptime.text",
+        "Running for {uptime}\nRead {messages} {messages|message|messages} and posted {posts} {posts|word|words}",
+    ),
+    ("uptime.days", "{days} {days|day|days}"),
+    ("uptime.hours", "{hours} {hours|hour|hours}"),
+    ("uptime.minutes", "{minutes} {minutes|minute|minutes}"),
+    ("top.title", "What I hear the most 🦜"),
+    (
+        "top.line",
+        "**{rank}.** {word} — {count} {count|time|times}, last heard {last_heard}",
+    ),
+    (
+        "top.invalid",
+        "`{input}` is not a valid amount of words, try `{example}`",
+    ),
+    ("top.trending_title", "What's catching on 📈"),
+    (
+        "top.trending_line",
+        "**{rank}.** {word} — {current} {current|time|times} in the last {window}, {previous} before",
+    ),
+    ("top.no_trending", "Nothing was said in the last {window} 🤐"),
+    (
+        "history.invalid",
+        "`{input}` is not a valid amount of messages, try `{example}`",
+    ),
+    ("history.empty", "I haven't said anything yet 🦜"),
+    ("history.channel", "in {channel}"),
+    ("history.webhook", "through the webhook"),
+    ("history.scheduled", "scheduled"),
+    ("history.forced", "on demand"),
+    ("history.planned", "planned"),
+    ("leaderboard.invalid", "`{input}` is not a valid page, try `{example}`"),
+    ("leaderboard.title", "Leaderboard 🦜 (page {page}/{pages})"),
+    ("leaderboard.line", "**{rank}.** {word} — {count} {count|time|times}"),
+    ("next.active", "Next squawk {time} 🦜"),
+    ("next.waiting", "I'll squawk once someone talks here 🦜"),
+    ("next.paused_until", "I'm paused, back {time} 🤐"),
+    ("next.paused", "I'm paused until `{resume}` 🤐"),
+    (
+        "forget.done",
+        "Forgot '{word}', dropping {count} {count|instance|instances} 🦜",
+    ),
+    (
+        "reset.prompt",
+        "{user} do you really want me to forget everything? React with {emoji} within {seconds} seconds to confirm",
+    ),
+    ("reset.cancelled", "No confirmation, I'll keep my memory 🦜"),
+    (
+        "reset.done",
+        "Forgot {count} word {count|instance|instances}, tabula rasa 🦜",
+    ),
+    ("speak.paused", "I'm paused, ask a moderator to resume me 🤐"),
+    (
+        "schedule.invalid",
+        "`{input}` is not a valid time, use the 24 hour UTC format like `18:30`",
+    ),
+    ("schedule.full", "There are already {max} posts waiting in this server"),
+    ("schedule.done", "I'll post {word} here {time} 🦜"),
+    (
+        "pause.invalid",
+        "`{input}` is not a valid duration, try `30m` or `2h`",
+    ),
+    ("pause.for", "I'll be quiet for {duration} 🤐"),
+    ("pause.until_resumed", "I'll be quiet until `{resume}` 🤐"),
+    ("resume.done", "Back in business 🦜"),
+    ("status.active", "Active 🦜"),
+    ("status.paused_for", "Paused for another {duration} 🤐"),
+    ("status.paused", "Paused until `{resume}` 🤐"),
+    ("config.changed", "{key} is now {value} 🦜"),
+    ("config.failed", "Can't change {key}: {error}"),
+    (
+        "config.unknown_key",
+        "unknown setting `{key}`, valid ones are {keys}",
+    ),
+    (
+        "config.not_a_number",
+        "`{value}` is not a valid value for {key}, it must be a positive whole number",
+    ),
+    (
+        "config.not_a_duration",
+        "`{value}` is not a valid value for {key}, it must be a duration like `10m`, `1h30m` or a number of seconds",
+    ),
+    (
+        "config.interval_order",
+        "interval_low ({low}) can't be greater than interval_high ({high})",
+    ),
+    (
+        "config.max_age",
+        "max_age ({max_age}) can't be shorter than interval_high ({interval_high}), or words would be forgotten before pino speaks",
+    ),
+    ("config.options", "Started with"),
+    ("block.invalid", "That's not a valid regex: {error}"),
+    ("block.already", "`{pattern}` is already blocked"),
+    ("buttons.block_placeholder", "Words to block"),
+    ("block.done", "Blocked `{pattern}` as entry {id} 🙉"),
+    (
+        "block.done_dropping",
+        "Blocked `{pattern}` as entry {id}, dropping {count} {count|instance|instances} 🙉",
+    ),
+    ("unblock.done", "Unblocked `{pattern}` (entry {id}) 🦜"),
+    ("unblock.unknown", "`{input}` is not in the blocklist"),
+    ("blocked.empty", "Nothing is blocked 🦜"),
+    ("settings.this_server", "{key} = {value} (this server)"),
+    ("settings.admin_only", "Only administrators can change my settings"),
+    ("settings.changed", "{key} is now {value} in this server 🦜"),
+    ("settings.default", "{key} is back to the default 🦜"),
+    (
+        "settings.unknown_key",
+        "Unknown setting `{key}`, valid ones are {keys}",
+    ),
+    ("settings.missing_word", "The message template must contain {{word}}"),
+    (
+        "settings.not_on_off",
+        "`{value}` is not a valid value for {key}, use `on` or `off`",
+    ),
+    ("settings.not_a_word", "The default word must be a single word"),
+    (
+        "settings.unknown_language",
+        "`{value}` is not a supported language, use {languages}",
+    ),
+    (
+        "settings.unknown_scoring",
+        "`{value}` is not a way to score words, use count or trending",
+    ),
+    (
+        "prefix.show",
+        "My prefix here is `{prefix}`, mentioning me works too 🦜",
+    ),
+    ("prefix.admin_only", "Only administrators can change my prefix"),
+    (
+        "prefix.changed",
+        "Got it, try `{help}`. Mentioning me always works too 🦜",
+    ),
+    ("prefix.length", "The prefix must be 1 to {max} characters long"),
+    ("prefix.spaces", "The prefix can't contain spaces"),
+    ("tracking.enabled", "I'm all ears again 🦜"),
+    (
+        "tracking.disabled",
+        "I'll stop listening here, commands still work 🙉",
+    ),
+    (
+        "stats.did_you_mean",
+        "I never heard '{word}', did you mean '{suggestion}'? 🦜",
+    ),
+    ("stats.title", "Stats for '{word}' 🦜"),
+    (
+        "stats.sparkline",
+        "Mentions every {minutes} minutes, most recent on the right",
+    ),
+    ("stats.times_heard", "Times heard"),
+    ("stats.first_heard", "First heard"),
+    ("stats.last_heard", "Last heard"),
+    ("stats.blocked", "Blocked"),
+    ("stats.never", "never"),
+    ("yes", "yes"),
+    ("no", "no"),
+    (
+        "heatmap.disabled",
+        "I'm not counting messages, start me with `--heatmap` 🦜",
+    ),
+    (
+        "heatmap.busiest",
+        "Busiest: {day} {hour}:00 UTC, {count} {count|message|messages}",
+    ),
+    ("heatmap.empty", "No messages yet"),
+    ("activity.line", "{channel}: {count} {count|message|messages}"),
+    ("activity.empty", "No messages in this server yet"),
+    ("vote.invalid", "**{word}** can't be the word of the week"),
+    ("vote.nominated", "**{word}** is in the running for the word of the week 🦜"),
+    ("vote.already_nominated", "**{word}** was already nominated"),
+    (
+        "vote.not_nominated",
+        "Nobody nominated **{word}** this week, try `!pino nominate {word}`",
+    ),
+    ("vote.voted", "You voted **{word}**"),
+    ("vote.moved", "You voted **{word}** instead of **{previous}**"),
+    ("vote.none", "Nothing nominated this week yet"),
+    ("vote.line", "**{word}**: {count} {count|vote|votes}"),
+    (
+        "vote.winner",
+        "🏆 The word of the week is **{word}**, with {count} {count|vote|votes}!",
+    ),
+    ("summary.title", "Week of {date}"),
+    ("summary.words", "Top words"),
+    ("summary.word_line", "{rank}. **{word}** ({count})"),
+    ("summary.user", "Most active member"),
+    (
+        "summary.user_line",
+        "{user}, {count} {count|message|messages}",
+    ),
+    ("summary.hour", "Busiest hour"),
+    (
+        "summary.hour_line",
+        "{hour}:00 UTC, {count} {count|message|messages}",
+    ),
+    ("summary.messages", "Messages"),
+    ("summary.none", "Nothing yet"),
+    (
+        "cooccurrence.disabled",
+        "I'm not counting which words are said together, start me with `--track-cooccurrence` 🦜",
+    ),
+    (
+        "cooccurrence.none",
+        "Nothing has been said together with {word} yet",
+    ),
+    ("cooccurrence.list", "Said most often with {word}: {partners}"),
+    (
+        "welcome.word",
+        "Welcome {user}! You joined while everyone is talking about **{word}** 🦜",
+    ),
+    ("welcome.quiet", "Welcome {user}! 🦜"),
+    ("day.0", "Mon"),
+    ("day.1", "Tue"),
+    ("day.2", "Wed"),
+    ("day.3", "Thu"),
+    ("day.4", "Fri"),
+    ("day.5", "Sat"),
+    ("day.6", "Sun"),
+    ("export.done", "Here are the {count} {count|word|words} I know 🦜"),
+    (
+        "import.done",
+        "Imported {count} new word {count|instance|instances} 🦜",
+    ),
+    ("import.failed", "Import failed: {error}"),
+    (
+        "import.no_attachment",
+        "attach a file exported with `{export}` to the command",
+    ),
+    ("import.download", "can't download the attachment: {error}"),
+    (
+        "import.too_large",
+        "the file is too large ({size} bytes, at most {max} are allowed)",
+    ),
+    ("import.malformed", "the file is not a valid export: {error}"),
+    (
+        "import.version",
+        "unsupported export version {version} (expected {expected})",
+    ),
+    (
+        "import.bad_timestamp",
+        "the word '{word}' has an impossible timestamp {time}",
+    ),
+    ("debug.no_dm", "I can't DM you the dump 🤐"),
+    ("debug.dump", "Here's how I'm doing 🦜"),
+    ("debug.dump_part", "Here's how I'm doing, part {part} of {parts} 🦜"),
+    ("debug.sent", "Sent you a DM 🦜"),
+    // Owner alerts
+    (
+        "alert.errors",
+        "⚠️ {place}, {what}: {count} errors in the last hour. The last one: {error}\nI won't tell you about it again for a day.",
+    ),
+    ("alert.guild", "In server {guild}"),
+    ("alert.no_guild", "Through the webhook or in DMs"),
+    ("alert.retryable", "Discord was unreachable or rate limited me"),
+    (
+        "alert.lost_channel",
+        "I couldn't post because a channel or a permission is missing, like Send Messages",
+    ),
+    ("alert.other", "Discord refused my messages"),
+];
+
+static IT: Table = &[
+    // Commands
+    ("help.help", "questa lista"),
+    ("help.version", "versione, da quanto sono attivo e quanto ricordo"),
+    ("help.uptime", "da quanto sono in funzione e quanto ho fatto"),
+    (
+        "help.histogram",
+        "grafico a barre delle parole raggruppate per lunghezza",
+    ),
+    ("help.heatmap", "quando si parla di più, per giorno e ora"),
+    (
+        "help.server-summary",
+        "le parole più dette della settimana, il membro più attivo e l'ora più attiva",
+    ),
+    (
+        "help.cooccurrence",
+        "le parole dette più spesso negli stessi messaggi di una parola",
+    ),
+    ("help.activity", "quanti messaggi sono stati inviati in ogni canale"),
+    ("help.nominate", "candida una parola a parola della settimana"),
+    ("help.vote", "vota la parola della settimana, tra quelle candidate"),
+    ("help.votes", "le parole candidate questa settimana e i loro voti"),
+    (
+        "help.wordcloud",
+        "immagine delle parole più dette, più grandi quanto più sono dette",
+    ),
+    ("help.top", "le parole più dette"),
+    ("help.stats", "quanto spesso è stata detta una parola di recente"),
+    ("help.leaderboard", "tutte le parole, una pagina alla volta"),
+    ("help.speak", "dico subito la prossima parola"),
+    ("help.when", "quando arriva la prossima parola"),
+    ("help.history", "gli ultimi messaggi che ho mandato in questo server"),
+    ("help.status", "se sono in pausa, e per quanto"),
+    ("help.forget", "dimentico una parola"),
+    ("help.reset", "dimentico tutto, dopo una conferma"),
+    ("help.pause", "smetto di parlare, ad esempio per `30m` o fino a nuovo ordine"),
+    ("help.resume", "ricomincio a parlare"),
+    ("help.set", "cambia un'impostazione senza riavviarmi"),
+    ("help.get", "valore attuale di un'impostazione"),
+    (
+        "help.config",
+        "tutte le impostazioni con il loro valore, e le opzioni con cui è stato avviato pino",
+    ),
+    (
+        "help.schedule",
+        "posto una parola in questo canale a un orario UTC",
+    ),
+    (
+        "help.block",
+        "non imparo mai una parola, o le parole che corrispondono a una regex",
+    ),
+    ("help.unblock", "toglie una voce dalla lista dei bloccati"),
+    ("help.blocked", "la lista dei bloccati"),
+    (
+        "help.prefix",
+        "mostra o cambia il prefisso dei comandi in questo server",
+    ),
+    ("help.settings", "mostra o cambia come parlo in questo server"),
+    (
+        "help.ignore",
+        "smetto di contare le parole di qualcuno in questo server",
+    ),
+    ("help.unignore", "ricomincio a contare le parole di qualcuno"),
+    (
+        "help.enable",
+        "comincio a contare le parole dette in questo server",
+    ),
+    ("help.disable", "smetto di contare le parole dette in questo server"),
+    ("help.export", "carica le parole imparate in un file JSON"),
+    ("help.import", "unisce le parole di un file esportato allegato"),
+    (
+        "help.debug",
+        "manda in privato un dump del mio stato, per capire cosa non va",
+    ),
+    ("help.restricted", "{usage} — {description} (solo per {who})"),
+    ("who.everyone", "tutti"),
+    ("who.moderator", "chi può gestire i messaggi"),
+    ("who.admin", "gli amministratori"),
+    ("who.owner", "il proprietario di pino"),
+    // Replies shared by several commands
+    ("usage", "Uso: `{usage}`"),
+    ("usage.either", "Uso: `{usage}` oppure `{other}`"),
+    ("denied", "`{command}` è solo per {who}"),
+    ("servers_only", "Funziona solo nei server"),
+    ("cooldown", "Piano, sto ancora riprendendo fiato 🦜"),
+    ("no_words", "Non ho ancora sentito niente, dite qualcosa! 🦜"),
+    ("never_heard", "Non ho mai sentito '{word}' 🦜"),
+    // Replies of single commands
+    ("version.uptime", "Attivo da {uptime} in {guilds} server"),
+    ("version.not_connected", "Non ancora connesso"),
+    (
+        "version.tracking",
+        "Conosco {words} {words|parola|parole}, dette {instances} {instances|volta|volte} in tutto",
+    ),
+    (
+        "uptime.text",
+        "In funzione da {uptime}\nHo letto {messages} {messages|messaggio|messaggi} e scritto {posts} {posts|parola|parole}",
+    ),
+    ("uptime.days", "{days} {days|giorno|giorni}"),
+    ("uptime.hours", "{hours} {hours|ora|ore}"),
+    ("uptime.minutes", "{minutes} {minutes|minuto|minuti}"),
+    ("top.title", "Quello che sento di più 🦜"),
+    (
+        "top.line",
+        "**{rank}.** {word} — {count} {count|volta|volte}, l'ultima {last_heard}",
+    ),
+    (
+        "top.invalid",
+        "`{input}` non è un numero di parole valido, prova `{example}`",
+    ),
+    ("top.trending_title", "Quello che sta prendendo piede 📈"),
+    (
+        "top.trending_line",
+        "**{rank}.** {word} — {current} {current|volta|volte} negli ultimi {window}, {previous} prima",
+    ),
+    ("top.no_trending", "Negli ultimi {window} non ho sentito niente 🤐"),
+    (
+        "history.invalid",
+        "`{input}` non è un numero di messaggi valido, prova `{example}`",
+    ),
+    ("history.empty", "Non ho ancora detto niente 🦜"),
+    ("history.channel", "in {channel}"),
+    ("history.webhook", "tramite il webhook"),
+    ("history.scheduled", "di routine"),
+    ("history.forced", "su richiesta"),
+    ("history.planned", "programmato"),
+    (
+        "leaderboard.invalid",
+        "`{input}` non è una pagina valida, prova `{example}`",
+    ),
+    ("leaderboard.title", "Classifica 🦜 (pagina {page}/{pages})"),
+    ("leaderboard.line", "**{rank}.** {word} — {count} {count|volta|volte}"),
+    ("next.active", "Prossimo verso {time} 🦜"),
+    ("next.waiting", "Farò il verso appena qualcuno parla qui 🦜"),
+    ("next.paused_until", "Sono in pausa, torno {time} 🤐"),
+    ("next.paused", "Sono in pausa fino a `{resume}` 🤐"),
+    (
+        "forget.done",
+        "Ho dimenticato '{word}', {count} {count|volta|volte} in meno 🦜",
+    ),
+    (
+        "reset.prompt",
+        "{user} vuoi davvero che dimentichi tutto? Reagisci con {emoji} entro {seconds} secondi per confermare",
+    ),
+    ("reset.cancelled", "Nessuna conferma, mi tengo la memoria 🦜"),
+    (
+        "reset.done",
+        "Ho dimenticato {count} {count|parola|parole}, tabula rasa 🦜",
+    ),
+    (
+        "speak.paused",
+        "Sono in pausa, chiedi a un moderatore di riattivarmi 🤐",
+    ),
+    (
+        "schedule.invalid",
+        "`{input}` non è un orario valido, usa il formato 24 ore UTC come `18:30`",
+    ),
+    (
+        "schedule.full",
+        "Ci sono già {max} post in attesa in questo server",
+    ),
+    ("schedule.done", "Posterò {word} qui {time} 🦜"),
+    (
+        "pause.invalid",
+        "`{input}` non è una durata valida, prova `30m` o `2h`",
+    ),
+    ("pause.for", "Starò zitto per {duration} 🤐"),
+    ("pause.until_resumed", "Starò zitto fino a `{resume}` 🤐"),
+    ("resume.done", "Eccomi di nuovo 🦜"),
+    ("status.active", "Attivo 🦜"),
+    ("status.paused_for", "In pausa per altri {duration} 🤐"),
+    ("status.paused", "In pausa fino a `{resume}` 🤐"),
+    ("config.changed", "{key} ora è {value} 🦜"),
+    ("config.failed", "Non posso cambiare {key}: {error}"),
+    (
+        "config.unknown_key",
+        "l'impostazione `{key}` non esiste, quelle valide sono {keys}",
+    ),
+    (
+        "config.not_a_number",
+        "`{value}` non è un valore valido per {key}, deve essere un numero intero positivo",
+    ),
+    (
+        "config.not_a_duration",
+        "`{value}` non è un valore valido per {key}, deve essere una durata come `10m`, `1h30m` o un numero di secondi",
+    ),
+    (
+        "config.interval_order",
+        "interval_low ({low}) non può essere maggiore di interval_high ({high})",
+    ),
+    (
+        "config.max_age",
+        "max_age ({max_age}) non può essere minore di interval_high ({interval_high}), o le parole sarebbero dimenticate prima che pino parli",
+    ),
+    ("config.options", "Avviato con"),
+    ("block.invalid", "Non è una regex valida: {error}"),
+    ("block.already", "`{pattern}` è già bloccato"),
+    ("buttons.block_placeholder", "Parole da bloccare"),
+    ("block.done", "Ho bloccato `{pattern}` come voce {id} 🙉"),
+    (
+        "block.done_dropping",
+        "Ho bloccato `{pattern}` come voce {id}, {count} {count|volta|volte} in meno 🙉",
+    ),
+    ("unblock.done", "Ho sbloccato `{pattern}` (voce {id}) 🦜"),
+    ("unblock.unknown", "`{input}` non è nella lista dei bloccati"),
+    ("blocked.empty", "Non c'è niente di bloccato 🦜"),
+    ("settings.this_server", "{key} = {value} (questo server)"),
+    (
+        "settings.admin_only",
+        "Solo gli amministratori possono cambiare le mie impostazioni",
+    ),
+    ("settings.changed", "{key} ora è {value} in questo server 🦜"),
+    ("settings.default", "{key} è tornato al valore predefinito 🦜"),
+    (
+        "settings.unknown_key",
+        "L'impostazione `{key}` non esiste, quelle valide sono {keys}",
+    ),
+    (
+        "settings.missing_word",
+        "Il modello dei messaggi deve contenere {{word}}",
+    ),
+    (
+        "settings.not_on_off",
+        "`{value}` non è un valore valido per {key}, usa `on` o `off`",
+    ),
+    (
+        "settings.not_a_word",
+        "La parola predefinita deve essere una parola sola",
+    ),
+    (
+        "settings.unknown_language",
+        "`{value}` non è una lingua supportata, usa {languages}",
+    ),
+    (
+        "settings.unknown_scoring",
+        "`{value}` non è un modo di scegliere le parole, usa count o trending",
+    ),
+    (
+        "prefix.show",
+        "Qui il mio prefisso è `{prefix}`, funziona anche menzionarmi 🦜",
+    ),
+    (
+        "prefix.admin_only",
+        "Solo gli amministratori possono cambiare il mio prefisso",
+    ),
+    (
+        "prefix.changed",
+        "Fatto, prova `{help}`. Menzionarmi funziona sempre 🦜",
+    ),
+    (
+        "prefix.length",
+        "Il prefisso deve essere lungo da 1 a {max} caratteri",
+    ),
+    ("prefix.spaces", "Il prefisso non può contenere spazi"),
+    ("tracking.enabled", "Sono di nuovo tutt'orecchi 🦜"),
+    (
+        "tracking.disabled",
+        "Smetto di ascoltare qui, i comandi funzionano ancora 🙉",
+    ),
+    (
+        "stats.did_you_mean",
+        "Non ho mai sentito '{word}', intendevi '{suggestion}'? 🦜",
+    ),
+    ("stats.title", "Statistiche di '{word}' 🦜"),
+    (
+        "stats.sparkline",
+        "Menzioni ogni {minutes} minuti, le più recenti a destra",
+    ),
+    ("stats.times_heard", "Volte sentita"),
+    ("stats.first_heard", "Prima volta"),
+    ("stats.last_heard", "Ultima volta"),
+    ("stats.blocked", "Bloccata"),
+    ("stats.never", "mai"),
+    ("yes", "sì"),
+    ("no", "no"),
+    (
+        "heatmap.disabled",
+        "Non sto contando i messaggi, avviami con `--heatmap` 🦜",
+    ),
+    (
+        "heatmap.busiest",
+        "Più attivo: {day} {hour}:00 UTC, {count} {count|messaggio|messaggi}",
+    ),
+    ("heatmap.empty", "Ancora nessun messaggio"),
+    ("activity.line", "{channel}: {count} {count|messaggio|messaggi}"),
+    ("activity.empty", "Ancora nessun messaggio in questo server"),
+    ("vote.invalid", "**{word}** non può essere la parola della settimana"),
+    ("vote.nominated", "**{word}** è candidata a parola della settimana 🦜"),
+    ("vote.already_nominated", "**{word}** era già candidata"),
+    (
+        "vote.not_nominated",
+        "Nessuno ha candidato **{word}** questa settimana, prova `!pino nominate {word}`",
+    ),
+    ("vote.voted", "Hai votato **{word}**"),
+    ("vote.moved", "Hai votato **{word}** invece di **{previous}**"),
+    ("vote.none", "Ancora nessuna parola candidata questa settimana"),
+    ("vote.line", "**{word}**: {count} {count|voto|voti}"),
+    (
+        "vote.winner",
+        "🏆 La parola della settimana è **{word}**, con {count} {count|voto|voti}!",
+    ),
+    ("summary.title", "Settimana del {date}"),
+    ("summary.words", "Parole più dette"),
+    ("summary.word_line", "{rank}. **{word}** ({count})"),
+    ("summary.user", "Membro più attivo"),
+    (
+        "summary.user_line",
+        "{user}, {count} {count|messaggio|messaggi}",
+    ),
+    ("summary.hour", "Ora più attiva"),
+    (
+        "summary.hour_line",
+        "{hour}:00 UTC, {count} {count|messaggio|messaggi}",
+    ),
+    ("summary.messages", "Messaggi"),
+    ("summary.none", "Ancora niente"),
+    (
+        "cooccurrence.disabled",
+        "Non sto contando quali parole vengono dette insieme, avviami con `--track-cooccurrence` 🦜",
+    ),
+    (
+        "cooccurrence.none",
+        "Non è ancora stato detto niente insieme a {word}",
+    ),
+    ("cooccurrence.list", "Dette più spesso con {word}: {partners}"),
+    (
+        "welcome.word",
+        "Benvenuto {user}! Sei arrivato mentre tutti parlano di **{word}** 🦜",
+    ),
+    ("welcome.quiet", "Benvenuto {user}! 🦜"),
+    ("day.0", "Lun"),
+    ("day.1", "Mar"),
+    ("day.2", "Mer"),
+    ("day.3", "Gio"),
+    ("day.4", "Ven"),
+    ("day.5", "Sab"),
+    ("day.6", "Dom"),
+    ("export.done", "Ecco {count} {count|parola|parole} che conosco 🦜"),
+    (
+        "import.done",
+        "Ho importato {count} {count|nuova parola|nuove parole} 🦜",
+    ),
+    ("import.failed", "Importazione fallita: {error}"),
+    (
+        "import.no_attachment",
+        "allega al comando un file esportato con `{export}`",
+    ),
+    ("import.download", "non riesco a scaricare l'allegato: {error}"),
+    (
+        "import.too_large",
+        "il file è troppo grande ({size} byte, al massimo {max})",
+    ),
+    (
+        "import.malformed",
+        "il file non è un'esportazione valida: {error}",
+    ),
+    (
+        "import.version",
+        "la versione {version} delle esportazioni non è supportata (mi aspettavo la {expected})",
+    ),
+    (
+        "import.bad_timestamp",
+        "la parola '{word}' ha un orario impossibile {time}",
+    ),
+    ("debug.no_dm", "Non riesco a mandarti il dump in privato 🤐"),
+    ("debug.dump", "Ecco come sto 🦜"),
+    ("debug.dump_part", "Ecco come sto, parte {part} di {parts} 🦜"),
+    ("debug.sent", "Ti ho scritto in privato 🦜"),
+    // Owner alerts
+    (
+        "alert.errors",
+        "⚠️ {place}, {what}: {count} errori nell'ultima ora. L'ultimo: {error}\nNon te ne riparlerò per un giorno.",
+    ),
+    ("alert.guild", "Nel server {guild}"),
+    ("alert.no_guild", "Tramite il webhook o in privato"),
+    ("alert.retryable", "Discord non era raggiungibile o mi ha limitato"),
+    (
+        "alert.lost_channel",
+        "non ho potuto scrivere perché manca un canale o un permesso, come Invia messaggi",
+    ),
+    ("alert.other", "Discord ha rifiutato i miei messaggi"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn placeholders() {
+        assert_eq!(
+            "I never heard 'ciao' 🦜",
+            tr(Lang::En, "never_heard", &[("word", &"ciao")])
+        );
+        assert_eq!(
+            "Non ho mai sentito 'ciao' 🦜",
+            tr(Lang::It, "never_heard", &[("word", &"ciao")])
+        );
+
+        assert_eq!(
+            "ciao ciao 3",
+            render("{word} {word} {count}", &[("count", &3), ("word", &"ciao")])
+        );
+        // missing arguments, escaped and stray braces
+        assert_eq!("{word} and", render("{word} and", &[]));
+        assert_eq!("{word} } {", render("{{word}} } {", &[("word", &"ciao")]));
+        assert_eq!("{wor", render("{wor", &[("wor", &"ciao")]));
+    }
+
+    #[test]
+    fn plurals() {
+        let count = |count: usize| tr(Lang::It, "reset.done", &[("count", &count)]);
+
+        assert_eq!("Ho dimenticato 1 parola, tabula rasa 🦜", count(1));
+        assert_eq!("Ho dimenticato 3 parole, tabula rasa 🦜", count(3));
+        assert_eq!("Ho dimenticato 0 parole, tabula rasa 🦜", count(0));
+
+        assert_eq!(
+            "1 time, 11 times",
+            render(
+                "{a} {a|time|times}, {b} {b|time|times}",
+                &[("a", &1), ("b", &11)]
+            )
+        );
+        // malformed plurals are kept
+        assert_eq!("{a|one}", render("{a|one}", &[("a", &1)]));
+    }
+
+    #[test]
+    fn fallback() {
+        let partial: Table = &[("yes", "sì")];
+
+        assert_eq!(Some("sì"), template(Lang::It, partial, "yes"));
+        assert_eq!(
+            Some("Nothing is blocked 🦜"),
+            template(Lang::It, partial, "blocked.empty")
+        );
+        // logged only the first time
+        assert!(!report_missing(Lang::It, "blocked.empty"));
+        assert!(report_missing(Lang::It, "stats.never"));
+        assert!(!report_missing(Lang::It, "stats.never"));
+
+        assert_eq!("nonexistent", tr(Lang::It, "nonexistent", &[]));
+        assert_eq!("nonexistent", tr(Lang::En, "nonexistent", &[]));
+    }
+
+    /// Names of the placeholders of a template.
+    fn placeholder_names(template: &str) -> BTreeSet<&str> {
+        template
+            .split('{')
+            .skip(1)
+            .filter_map(|part| part.split('}').next())
+            .filter_map(|placeholder| placeholder.split('|').next())
+            .filter(|name| !name.is_empty())
+            .collect()
+    }
+
+    #[test]
+    fn tables() {
+        let keys = |table: Table| table.iter().map(|(key, _)| *key).collect::<BTreeSet<_>>();
+
+        assert_eq!(EN.len(), keys(EN).len(), "duplicate keys");
+        assert_eq!(IT.len(), keys(IT).len(), "duplicate keys");
+        assert_eq!(keys(EN), keys(IT));
+
+        for (key, template) in IT {
+            assert_eq!(
+                placeholder_names(find(EN, key).unwrap()),
+                placeholder_names(template),
+                "placeholders of {}",
+                key
+            );
+        }
+    }
+
+    #[test]
+    fn languages() {
+        assert_eq!(Ok(Lang::It), "it".parse());
+        assert_eq!(Ok(Lang::En), "EN".parse());
+        assert!("de".parse::<Lang>().is_err());
+        assert_eq!("\"it\"", serde_json::to_string(&Lang::It).unwrap());
+    }
+}
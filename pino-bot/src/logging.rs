@@ -0,0 +1,171 @@
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+use tracing::Subscriber;
+use tracing_subscriber::{fmt::MakeWriter, EnvFilter};
+
+/// Logged when neither `--log-level` nor `RUST_LOG` say otherwise.
+const DEFAULT_FILTER: &str = "info";
+
+/// How the log lines are written.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// One readable line per event.
+    #[default]
+    Text,
+    /// One JSON object per event, for log collectors.
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!("`{}` is not a log format, use text or json", s)),
+        }
+    }
+}
+
+impl fmt::Display for LogFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LogFormat::Text => write!(f, "text"),
+            LogFormat::Json => write!(f, "json"),
+        }
+    }
+}
+
+/// What is logged: `--log-level` if given, e.g. `debug` or
+/// `info,serenity=warn`, else `RUST_LOG`, else `info`.
+pub fn filter(level: Option<&str>) -> Result<EnvFilter, String> {
+    let directives = match level {
+        Some(level) => level.to_owned(),
+        None => std::env::var(EnvFilter::DEFAULT_ENV).unwrap_or_else(|_| DEFAULT_FILTER.to_owned()),
+    };
+
+    EnvFilter::try_new(&directives).map_err(|e| format!("`{}`: {}", directives, e))
+}
+
+/// A subscriber writing the events that pass `filter` to `writer`, without
+/// colors, which would end up as escape codes in log files.
+pub fn subscriber<W>(
+    filter: EnvFilter,
+    format: LogFormat,
+    writer: W,
+) -> Box<dyn Subscriber + Send + Sync>
+where
+    W: for<'writer> MakeWriter<'writer> + Send + Sync + 'static,
+{
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(writer)
+        .with_ansi(false);
+
+    match format {
+        LogFormat::Text => Box::new(builder.finish()),
+        LogFormat::Json => Box::new(builder.json().finish()),
+    }
+}
+
+/// Log to stdout from now on, or to stderr when stdout is taken by what
+/// `--dry-run` prints. The filter comes from [`filter`].
+pub fn init(filter: EnvFilter, format: LogFormat, stderr: bool) -> anyhow::Result<()> {
+    let subscriber = if stderr {
+        subscriber(filter, format, std::io::stderr)
+    } else {
+        subscriber(filter, format, std::io::stdout)
+    };
+    tracing::subscriber::set_global_default(subscriber)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io,
+        sync::{Arc, Mutex},
+    };
+    use tracing::{info, info_span, warn};
+
+    /// Collects what's logged.
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl io::Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'writer> MakeWriter<'writer> for Buffer {
+        type Writer = Buffer;
+
+        fn make_writer(&'writer self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    /// Log a few events with a subscriber, returning the output.
+    fn logged(level: &str, format: LogFormat) -> String {
+        let buffer = Buffer::default();
+        let subscriber = subscriber(filter(Some(level)).unwrap(), format, buffer.clone());
+
+        tracing::subscriber::with_default(subscriber, || {
+            let span = info_span!("message", guild = 42_u64);
+            let _entered = span.enter();
+
+            info!(words = 3, "Learned the words");
+            warn!("Error sending reply");
+        });
+
+        let output = buffer.0.lock().unwrap().clone();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn text() {
+        let output = logged("info", LogFormat::Text);
+        let lines: Vec<_> = output.lines().collect();
+
+        assert_eq!(2, lines.len());
+        assert!(lines[0].contains("INFO"));
+        assert!(lines[0].contains("message{guild=42}"));
+        assert!(lines[0].contains("Learned the words words=3"));
+        assert!(lines[1].contains("WARN"));
+
+        assert_eq!(1, logged("warn", LogFormat::Text).lines().count());
+    }
+
+    #[test]
+    fn json() {
+        let output = logged("info", LogFormat::Json);
+        let events: Vec<serde_json::Value> = output
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(2, events.len());
+        assert_eq!("INFO", events[0]["level"]);
+        assert_eq!("Learned the words", events[0]["fields"]["message"]);
+        assert_eq!(3, events[0]["fields"]["words"]);
+        assert_eq!(42, events[0]["span"]["guild"]);
+        assert_eq!("WARN", events[1]["level"]);
+    }
+
+    #[test]
+    fn options() {
+        assert_eq!(Ok(LogFormat::Json), "JSON".parse());
+        assert!("xml".parse::<LogFormat>().is_err());
+        assert!(filter(Some("info,serenity=warn")).is_ok());
+        assert!(filter(Some("info,serenity=loud")).is_err());
+    }
+}
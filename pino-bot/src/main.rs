@@ -1,197 +1,3308 @@
-use anyhow::Context;
+use anyhow::{bail, Context};
 use chrono::{DateTime, Duration, Utc};
+use cli::{default_to_run, Cli, Options};
+use messenger::Messenger;
 use once_cell::sync::OnceCell;
+use persistence::FrequencySnapshot;
+use pino_core::{
+    blocklist, budget, command_guard, duration, ids, pause, rate_limit, runtime_config, schedule,
+    scheduled_posts, selection, store::WordStore, tracking, trending, voting, WordMap,
+};
+use pipeline::Outlet;
 use rand::prelude::*;
 use regex::Regex;
 use serenity::{
     async_trait,
-    model::{channel::Message, id::ChannelId},
+    gateway::{ConnectionStage, GatewayError, ShardStageUpdateEvent},
+    http::Http,
+    model::{
+        application::{Command, Interaction},
+        channel::{Message, Reaction, ReactionType},
+        event::{Event, ResumedEvent},
+        gateway::Ready,
+        guild::{Guild, Member, UnavailableGuild},
+        id::{ChannelId, GuildId, MessageId, UserId},
+        mention::Mentionable,
+    },
     prelude::*,
     utils::MessageBuilder,
 };
 use std::{
-    collections::HashMap,
-    sync::{Arc, RwLock},
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt,
+    path::Path,
+    sync::{Arc, Mutex, RwLock},
 };
 use structopt::StructOpt;
-use tokio::runtime::{self};
-use utils::SortedVec;
+use tokio::sync::Notify;
+use tracing::{debug, error, info, info_span, warn, Instrument};
 
-static WORD_REGEX: OnceCell<Regex> = OnceCell::new();
+mod audit;
+mod channel_check;
+mod cli;
+mod command_io;
+mod commands;
+mod components;
+mod config_file;
+mod cooccurrence;
+#[cfg(feature = "sqlite")]
+mod db;
+mod debug_dump;
+mod delivery;
+mod dry_run;
+mod emoji;
+mod error_report;
+mod guild_config;
+mod guild_settings;
+mod health;
+mod heatmap;
+mod histogram;
+mod http_server;
+mod i18n;
+mod logging;
+mod messenger;
+mod metrics;
+mod offline;
+mod persistence;
+mod pipeline;
+mod recent_channels;
+mod reload;
+mod reply_context;
+mod self_echo;
+mod sent_log;
+mod sharding;
+mod shutdown;
+mod slash;
+mod snapshots;
+mod stats;
+mod stop_words;
+mod summary;
+mod supervisor;
+mod template;
+mod timestamp;
+mod token;
+mod validation;
+mod veto;
+mod webhook;
+mod welcome;
+mod wordcloud;
 
-#[derive(StructOpt, Debug)]
-#[structopt(name = "basic")]
-struct Options {
-    /// The discord token to use
-    #[structopt(long)]
-    pub token: String,
-    /// Min interval between messages
-    #[structopt(long, default_value = "600")]
-    pub interval_low: u64,
-    /// Max interval between bessages
-    #[structopt(long, default_value = "1200")]
-    pub interval_high: u64,
-    /// Words are separated by a whitespace
-    #[structopt(long, default_value = "^[a-zA-ZàáèéìíòóùúÀÁÈÉÌÍÒÓÙÚ']+$")]
-    pub word_regex: String,
-    /// Instances of words older than this are deleted to save space and forget dead memes.
-    #[structopt(long, default_value = "1800")]
-    pub max_age: u64,
-    /// Max random boost to word count. If set to 3, a word said 8 times might be texted even if there's a word texted 10 times.
-    #[structopt(long, default_value = "10")]
-    pub max_boost: usize,
-    /// If no words have been said, the bot will print this word as default. Leave blank to not print anything by default.
-    #[structopt(long)]
-    pub default_word: Option<String>,
-}
-
-type WordMap = HashMap<String, SortedVec<DateTime<Utc>>>;
+static WORD_REGEX: OnceCell<Regex> = OnceCell::new();
+/// Applied to the words before matching them with `WORD_REGEX`.
+static NORMALIZATION: OnceCell<tracking::Normalization> = OnceCell::new();
+/// Prefix of the commands in guilds that didn't change it.
+static DEFAULT_PREFIX: OnceCell<String> = OnceCell::new();
+/// `--webhook-url` was given: every guild shares the timer of the webhook.
+static WEBHOOK_MODE: OnceCell<bool> = OnceCell::new();
+/// `--webhook-url`, if given.
+static WEBHOOK_URL: OnceCell<String> = OnceCell::new();
+/// `--max-message-length`.
+static MAX_MESSAGE_LENGTH: OnceCell<usize> = OnceCell::new();
+/// `--max-slowmode`, in seconds.
+static MAX_SLOWMODE: OnceCell<u64> = OnceCell::new();
+/// `--trending-window`, in seconds.
+static TRENDING_WINDOW: OnceCell<u64> = OnceCell::new();
+/// `--rate-limit-words`, if given.
+static RATE_LIMIT_WORDS: OnceCell<usize> = OnceCell::new();
+/// `--global-slash-commands`.
+static GLOBAL_SLASH_COMMANDS: OnceCell<bool> = OnceCell::new();
+/// Settings from the command line, used by guilds that didn't change them.
+static DEFAULT_SETTINGS: OnceCell<guild_settings::Settings> = OnceCell::new();
 
+/// The words heard in each guild. The lock is async: the send loop and the
+/// commands await while holding it, which a std lock would block the executor for.
 struct MessageMap;
 
 impl TypeMapKey for MessageMap {
-    type Value = Arc<RwLock<WordMap>>;
+    type Value = Arc<tokio::sync::RwLock<WordStore>>;
+}
+
+/// How pino talks on Discord: serenity's `Http`, or a recorder in the tests.
+struct Messaging;
+
+impl TypeMapKey for Messaging {
+    type Value = Arc<dyn Messenger>;
+}
+
+/// What pino knows of the guilds it's in, to check its permissions before
+/// posting. Not there in the tests.
+struct DiscordCache;
+
+impl TypeMapKey for DiscordCache {
+    type Value = Arc<serenity::cache::Cache>;
+}
+
+struct Config;
+
+impl TypeMapKey for Config {
+    type Value = Arc<RwLock<runtime_config::RuntimeConfig>>;
 }
 
 struct RecentChannel;
 
 impl TypeMapKey for RecentChannel {
-    type Value = Arc<RwLock<Option<ChannelId>>>;
+    type Value = Arc<tokio::sync::RwLock<recent_channels::RecentChannels>>;
+}
+
+/// When pino first connected, and to how many guilds it's connected now.
+#[derive(Debug, Clone)]
+struct Session {
+    started: DateTime<Utc>,
+    /// Guilds by the shard of this process they're on.
+    shard_guilds: BTreeMap<u32, usize>,
+    /// Pino's own id, so that it doesn't listen to itself.
+    bot_id: UserId,
+}
+
+impl Session {
+    /// Guilds on every shard of this process.
+    fn guilds(&self) -> usize {
+        self.shard_guilds.values().sum()
+    }
+}
+
+/// `None` until the `ready` event.
+struct ReadySession;
+
+impl TypeMapKey for ReadySession {
+    type Value = Arc<RwLock<Option<Session>>>;
+}
+
+/// The owner of the application, who can use `!pino debug` and hears about
+/// repeated errors. Missing if it couldn't be fetched.
+struct Owner;
+
+impl TypeMapKey for Owner {
+    type Value = UserId;
+}
+
+/// Where the errors of sending are counted, to tell the owner about the
+/// repeated ones. Missing without an owner or with `--error-alerts 0`.
+struct ErrorReports;
+
+impl TypeMapKey for ErrorReports {
+    type Value = error_report::Reporter;
+}
+
+/// The last message saying each word, to reply to. Only there with
+/// `--reply-style reference`.
+struct WordMentions;
+
+impl TypeMapKey for WordMentions {
+    type Value = Arc<RwLock<reply_context::LastMentions>>;
+}
+
+/// Where the posts, the admin commands and the errors are sent, with
+/// `--audit-webhook-url`.
+struct AuditLog;
+
+impl TypeMapKey for AuditLog {
+    type Value = audit::Auditor;
+}
+
+/// When `main` started, for `!uptime`.
+struct StartupTime;
+
+impl TypeMapKey for StartupTime {
+    type Value = DateTime<Utc>;
+}
+
+/// What pino did since it started.
+#[derive(Debug, Default, Clone, Copy)]
+struct Activity {
+    /// Messages of other users, commands included.
+    messages: u64,
+    /// Words posted, in any way.
+    posts: u64,
+}
+
+/// The options pino was started with, config file included, as JSON without
+/// the secrets.
+struct StartupOptions;
+
+impl TypeMapKey for StartupOptions {
+    type Value = Arc<String>;
+}
+
+/// Becomes true when pino is shutting down, so that nothing new is learned.
+struct ShuttingDown;
+
+impl TypeMapKey for ShuttingDown {
+    type Value = tokio::sync::watch::Receiver<bool>;
+}
+
+struct TaskSupervisor;
+
+impl TypeMapKey for TaskSupervisor {
+    type Value = supervisor::Supervisor;
+}
+
+struct ActivityCounters;
+
+impl TypeMapKey for ActivityCounters {
+    type Value = Arc<RwLock<Activity>>;
+}
+
+/// Messages of other users in each channel since pino started, for
+/// `!pino activity`.
+struct ChannelActivity;
+
+impl TypeMapKey for ChannelActivity {
+    type Value = Arc<RwLock<HashMap<ChannelId, u64>>>;
+}
+
+/// The connection to Discord, for `/readyz`. Always updated, served only
+/// with `--health-addr`.
+struct GatewayHealth;
+
+impl TypeMapKey for GatewayHealth {
+    type Value = Arc<RwLock<health::Gateway>>;
+}
+
+/// Always updated, served only with `--metrics-addr`.
+struct MetricsRegistry;
+
+impl TypeMapKey for MetricsRegistry {
+    type Value = Arc<metrics::Metrics>;
+}
+
+struct SentMessages;
+
+impl TypeMapKey for SentMessages {
+    type Value = Arc<RwLock<sent_log::SentLog>>;
+}
+
+struct SnapshotHistory;
+
+impl TypeMapKey for SnapshotHistory {
+    type Value = Arc<RwLock<snapshots::History>>;
+}
+
+struct GuildSettings;
+
+impl TypeMapKey for GuildSettings {
+    type Value = Arc<RwLock<guild_config::GuildConfigs>>;
+}
+
+/// When each guild gets its next message. DMs are under `None`, and so is
+/// everything with `--webhook-url`, which talks in a single place.
+struct Schedule;
+
+impl TypeMapKey for Schedule {
+    type Value = Arc<RwLock<schedule::Deadlines<Option<ids::GuildId>>>>;
+}
+
+struct ScheduledPosts;
+
+impl TypeMapKey for ScheduledPosts {
+    type Value = Arc<RwLock<scheduled_posts::ScheduledPosts>>;
+}
+
+/// Wakes up the send loop after the schedule changed, e.g. someone made pino
+/// speak or talked in a new guild, so that it checks what to wait for again.
+struct ScheduleChanged;
+
+impl TypeMapKey for ScheduleChanged {
+    type Value = Arc<Notify>;
+}
+
+/// The random choices of the send loop and of `!pino speak`, from `--seed`.
+struct Random;
+
+impl TypeMapKey for Random {
+    type Value = Arc<Mutex<StdRng>>;
+}
+
+/// Whether each guild let pino talk, see `!pino pause`.
+struct Paused;
+
+impl TypeMapKey for Paused {
+    type Value = Arc<RwLock<pause::Pauses>>;
+}
+
+struct BlockedWords;
+
+impl TypeMapKey for BlockedWords {
+    type Value = Arc<RwLock<blocklist::Blocklist>>;
+}
+
+struct VetoVotes;
+
+impl TypeMapKey for VetoVotes {
+    type Value = Arc<RwLock<veto::Vetoes>>;
+}
+
+/// Only there with `--heatmap`.
+struct ActivityHeatmap;
+
+impl TypeMapKey for ActivityHeatmap {
+    type Value = Arc<RwLock<heatmap::Heatmap>>;
+}
+
+/// The words recently learned from each user. Only there with
+/// `--rate-limit-words`.
+struct RateLimitMap;
+
+impl TypeMapKey for RateLimitMap {
+    type Value = Arc<RwLock<rate_limit::RateLimits>>;
+}
+
+/// Which messages repeating pino are learned. Not there with
+/// `--self-echo full`.
+struct SelfEchoes;
+
+impl TypeMapKey for SelfEchoes {
+    type Value = Arc<RwLock<self_echo::EchoFilter>>;
+}
+
+/// Only there with `--track-cooccurrence`.
+struct Cooccurrences;
+
+impl TypeMapKey for Cooccurrences {
+    type Value = Arc<RwLock<cooccurrence::GuildCooccurrences>>;
+}
+
+/// What was said this week and the last one in each guild, for
+/// `!pino server-summary` and `--summary-channel`.
+struct WeeklySummaries;
+
+impl TypeMapKey for WeeklySummaries {
+    type Value = Arc<RwLock<pino_core::summary::WeeklyStats>>;
+}
+
+/// The words nominated for the word of the week in each guild, for
+/// `!pino vote`. The winner is posted with the weekly summary.
+struct WordOfTheWeek;
+
+impl TypeMapKey for WordOfTheWeek {
+    type Value = Arc<RwLock<voting::Elections>>;
+}
+
+/// Only there with `--welcome-channel`.
+struct WelcomeSettings;
+
+impl TypeMapKey for WelcomeSettings {
+    type Value = welcome::Welcome;
+}
+
+struct WordcloudSettings;
+
+impl TypeMapKey for WordcloudSettings {
+    type Value = wordcloud::Settings;
+}
+
+/// `--emoji-map-file`, for the guilds in emoji mode.
+struct EmojiMap;
+
+impl TypeMapKey for EmojiMap {
+    type Value = Arc<HashMap<String, String>>;
+}
+
+struct StopWords;
+
+impl TypeMapKey for StopWords {
+    type Value = Arc<RwLock<HashSet<String>>>;
+}
+
+#[cfg(feature = "sqlite")]
+struct DbWriter;
+
+#[cfg(feature = "sqlite")]
+impl TypeMapKey for DbWriter {
+    type Value = db::DbHandle;
+}
+
+/// Notes when Discord last sent anything, for `/readyz`.
+struct GatewayWatcher(Arc<RwLock<health::Gateway>>);
+
+#[async_trait]
+impl RawEventHandler for GatewayWatcher {
+    async fn raw_event(&self, _context: serenity::client::Context, _event: Event) {
+        self.0.write().unwrap().event(Utc::now());
+    }
 }
 
 struct Reader;
 
 #[async_trait]
 impl EventHandler for Reader {
-    async fn message(&self, context: serenity::client::Context, msg: Message) {
-        // skip if own message
-        if msg.author.id == context.http.get_current_user().await.unwrap().id {
-            return; // do nothing if we sent the message
+    async fn ready(&self, context: serenity::client::Context, ready: Ready) {
+        let shard = context.shard_id.0;
+        let reconnected = on_ready(
+            &*context.data.read().await,
+            shard,
+            ready.user.id,
+            ready.guilds.len(),
+            Utc::now(),
+        );
+
+        if reconnected {
+            info!(
+                "Shard {} reconnected as {} to {} servers with a new session {} 🦜",
+                shard,
+                ready.user.name,
+                ready.guilds.len(),
+                ready.session_id
+            );
+        } else {
+            info!(
+                "Shard {} connected as {} to {} servers, session {} 🦜",
+                shard,
+                ready.user.name,
+                ready.guilds.len(),
+                ready.session_id
+            );
+
+            register_slash_commands(&context, &ready).await;
         }
+    }
 
-        let regex = WORD_REGEX.get().unwrap();
+    async fn resume(&self, context: serenity::client::Context, _event: ResumedEvent) {
+        let shard = context.shard_id.0;
+        info!("Shard {} resumed the session with Discord", shard);
 
-        // iterate over words defined by the regex
-        let word_iterator = msg
-            .content
-            .split_whitespace()
-            .filter(|word| regex.is_match(word))
-            .map(|word| word.to_lowercase());
+        on_resume(&*context.data.read().await, shard, Utc::now());
+    }
 
-        {
+    async fn shard_stage_update(
+        &self,
+        context: serenity::client::Context,
+        event: ShardStageUpdateEvent,
+    ) {
+        let shard = event.shard_id.0;
+
+        if event.old == ConnectionStage::Connected {
+            warn!(
+                "Shard {} lost the connection to Discord ({} -> {}), reconnecting",
+                shard, event.old, event.new
+            );
+        } else {
+            info!(
+                "Shard {} connection to Discord: {} -> {}",
+                shard, event.old, event.new
+            );
+        }
+
+        context
+            .data
+            .read()
+            .await
+            .get::<GatewayHealth>()
+            .expect("GatewayHealth to be in context")
+            .write()
+            .unwrap()
+            .stage(shard, event.new, Utc::now());
+    }
+
+    async fn guild_delete(
+        &self,
+        context: serenity::client::Context,
+        incomplete: UnavailableGuild,
+        _full: Option<Guild>,
+    ) {
+        // Outages make guilds unavailable, pino is still in them
+        if incomplete.unavailable {
+            return;
+        }
+
+        info!("Left guild {}", incomplete.id);
+        left_guild(&*context.data.read().await, incomplete.id).await;
+    }
+
+    async fn guild_member_addition(&self, context: serenity::client::Context, new_member: Member) {
+        if new_member.user.bot {
+            return;
+        }
+
+        let welcome = match context.data.read().await.get::<WelcomeSettings>().cloned() {
+            Some(welcome) => welcome,
+            None => return,
+        };
+
+        // Members of other servers aren't greeted in the channel
+        let channel_guild = match welcome.channel.to_channel(&context).await {
+            Ok(channel) => channel.guild().map(|channel| channel.guild_id),
+            Err(e) => {
+                warn!("Error fetching the welcome channel: {}", e);
+                return;
+            }
+        };
+
+        if channel_guild != Some(new_member.guild_id) {
+            return;
+        }
+
+        let (paused, settings, word) = {
             let data_read = context.data.read().await;
-            let recent_channel_lock = data_read
-                .get::<RecentChannel>()
-                .expect("RecentChannel to be in context")
-                .clone();
+            let paused = data_read
+                .get::<Paused>()
+                .expect("Paused to be in context")
+                .read()
+                .unwrap()
+                .is_paused(guild_key(Some(new_member.guild_id)), Utc::now());
+            let settings = data_read
+                .get::<GuildSettings>()
+                .expect("GuildSettings to be in context")
+                .read()
+                .unwrap()
+                .resolve(Some(new_member.guild_id), DEFAULT_SETTINGS.get().unwrap());
+            let word = commands::top_words(
+                data_read
+                    .get::<MessageMap>()
+                    .expect("MessageMap to be in context")
+                    .read()
+                    .await
+                    .guild(guild_key(Some(new_member.guild_id))),
+                &data_read
+                    .get::<BlockedWords>()
+                    .expect("BlockedWords to be in context")
+                    .read()
+                    .unwrap(),
+                1,
+            )
+            .pop()
+            .map(|top| top.word);
+
+            (paused, settings, word)
+        };
+
+        if paused {
+            return;
+        }
+
+        let word = word.or(settings.default_word);
+        let content = welcome.message(
+            settings.language,
+            &new_member.mention().to_string(),
+            word.as_deref(),
+        );
+
+        let messenger = context
+            .data
+            .read()
+            .await
+            .get::<Messaging>()
+            .expect("Messaging to be in context")
+            .clone();
+
+        match messenger.send_text(welcome.channel, &content).await {
+            Ok(_) => info!("Welcomed a new member 🦜"),
+            Err(e) => {
+                warn!("Error welcoming a new member: {}", e);
+                report_error(
+                    &*context.data.read().await,
+                    Some(new_member.guild_id),
+                    delivery::classify_serenity(&e),
+                    &e,
+                );
+            }
+        }
+    }
 
-            // Set most current channel. Pino will reply there.
-            recent_channel_lock.write().unwrap().replace(msg.channel_id);
+    async fn reaction_add(&self, context: serenity::client::Context, reaction: Reaction) {
+        let sent = match vetoed_message(&context, &reaction).await {
+            Some(sent) => sent,
+            None => return,
+        };
+
+        let user = match reaction.user(&context).await {
+            Ok(user) => user,
+            Err(e) => {
+                warn!("Error fetching the user of a veto: {}", e);
+                return;
+            }
+        };
+
+        // pino and other bots don't vote
+        if user.bot {
+            return;
         }
 
-        let message_map_lock = {
+        let vetoed = {
             let data_read = context.data.read().await;
-            data_read
-                .get::<MessageMap>()
-                .expect("MessageMap to be in context")
-                .clone()
+            let vetoed = data_read
+                .get::<VetoVotes>()
+                .expect("VetoVotes to be in context")
+                .write()
+                .unwrap()
+                .vote(
+                    reaction.message_id.get(),
+                    sent.time,
+                    user.id.get(),
+                    Utc::now(),
+                );
+
+            if vetoed {
+                data_read
+                    .get::<BlockedWords>()
+                    .expect("BlockedWords to be in context")
+                    .write()
+                    .unwrap()
+                    .block_until(
+                        &sent.word,
+                        Utc::now() + Duration::minutes(veto::VETO_BLOCK_MINUTES),
+                    );
+            }
+
+            vetoed
         };
 
-        let mut message_map = message_map_lock.write().unwrap();
+        if !vetoed {
+            return;
+        }
 
-        let time = msg.timestamp;
+        info!("'{}' was vetoed, blocking it for a while 🤐", sent.word);
 
-        for word in word_iterator {
-            if let Some(value) = message_map.get_mut(&word) {
-                value.insert(time);
-            } else {
-                message_map.insert(word, SortedVec::from_vec(vec![time]));
+        // The word is blocked even if the message can't be deleted
+        if let Err(e) = reaction
+            .channel_id
+            .delete_message(&context.http, reaction.message_id)
+            .await
+        {
+            warn!("Error deleting a vetoed message: {}", e);
+        }
+    }
+
+    async fn reaction_remove(&self, context: serenity::client::Context, reaction: Reaction) {
+        if vetoed_message(&context, &reaction).await.is_none() {
+            return;
+        }
+
+        let user_id = match reaction.user(&context).await {
+            Ok(user) => user.id,
+            Err(e) => {
+                warn!("Error fetching the user of a veto: {}", e);
+                return;
             }
+        };
+
+        let data_read = context.data.read().await;
+        data_read
+            .get::<VetoVotes>()
+            .expect("VetoVotes to be in context")
+            .write()
+            .unwrap()
+            .unvote(reaction.message_id.get(), user_id.get());
+    }
+
+    async fn message(&self, context: serenity::client::Context, msg: Message) {
+        let span = info_span!(
+            "message",
+            guild = tracing::field::Empty,
+            channel = msg.channel_id.get(),
+            shard = context.shard_id.0,
+            author = msg.author.id.get(),
+        );
+
+        if let Some(guild_id) = msg.guild_id {
+            span.record("guild", guild_id.get());
         }
+
+        handle_message(context, msg).instrument(span).await;
     }
-}
 
-#[tokio::main(max_threads = 1)]
-async fn main() -> anyhow::Result<()> {
-    let options = Options::from_args();
+    async fn interaction_create(
+        &self,
+        context: serenity::client::Context,
+        interaction: Interaction,
+    ) {
+        let (command, autocomplete) = match interaction {
+            Interaction::Command(command) => (command, false),
+            Interaction::Autocomplete(command) => (command, true),
+            Interaction::Component(component) => {
+                let span = info_span!(
+                    "component",
+                    guild = tracing::field::Empty,
+                    channel = component.channel_id.get(),
+                    shard = context.shard_id.0,
+                    author = component.user.id.get(),
+                );
 
-    println!("Starting PinoBot 🦜");
+                if let Some(guild_id) = component.guild_id {
+                    span.record("guild", guild_id.get());
+                }
 
-    WORD_REGEX
-        .set(Regex::new(&options.word_regex).context("compiling regex")?)
-        .unwrap();
+                commands::component(&context, &component)
+                    .instrument(span)
+                    .await;
+                return;
+            }
+            _ => return,
+        };
 
-    let mut client = Client::builder(&options.token)
-        .event_handler(Reader)
-        .await
-        .expect("creating client");
+        let span = info_span!(
+            "interaction",
+            guild = tracing::field::Empty,
+            channel = command.channel_id.get(),
+            shard = context.shard_id.0,
+            author = command.user.id.get(),
+        );
 
-    {
-        let mut data = client.data.write().await;
-        data.insert::<MessageMap>(Arc::new(RwLock::new(HashMap::new())));
-        data.insert::<RecentChannel>(Arc::new(RwLock::new(None)));
+        if let Some(guild_id) = command.guild_id {
+            span.record("guild", guild_id.get());
+        }
+
+        if autocomplete {
+            commands::autocomplete(&context, &command)
+                .instrument(span)
+                .await;
+        } else {
+            commands::interaction(&context, &command)
+                .instrument(span)
+                .await;
+        }
     }
+}
 
-    let cache_and_http = client.cache_and_http.clone();
-    let data = client.data.clone();
+/// Register `/pino` in the guilds of the shard, or for every guild at once
+/// with `--global-slash-commands`, which Discord takes up to an hour to show.
+async fn register_slash_commands(context: &serenity::client::Context, ready: &Ready) {
+    let command = commands::slash_command();
 
-    tokio::spawn(async move {
-        let mut rng = rand::rngs::StdRng::seed_from_u64(69);
+    if GLOBAL_SLASH_COMMANDS.get().copied().unwrap_or_default() {
+        // Global commands aren't per shard, the first one registers them
+        if context.shard_id.0 == 0 {
+            if let Err(e) = Command::set_global_commands(&context.http, vec![command]).await {
+                warn!("Error registering the global slash commands: {}", e);
+            }
+        }
 
-        loop {
-            let time: u64 = rng.gen_range(options.interval_low..=options.interval_high);
+        return;
+    }
 
-            println!("Sending message in {} seconds", time);
+    for guild in &ready.guilds {
+        if let Err(e) = guild
+            .id
+            .set_commands(&context.http, vec![command.clone()])
+            .await
+        {
+            warn!(
+                "Error registering the slash commands in guild {}: {}",
+                guild.id, e
+            );
+        }
+    }
+}
 
-            tokio::time::delay_for(Duration::seconds(time as i64).to_std().unwrap()).await;
+/// Record a `ready` event of `shard`, which comes once per shard and again
+/// after every reconnection that couldn't resume its session. Only the
+/// session is updated: the words, the recent channels and the pause are kept.
+/// Returns whether the shard was already connected before.
+fn on_ready(data: &TypeMap, shard: u32, bot_id: UserId, guilds: usize, now: DateTime<Utc>) -> bool {
+    data.get::<GatewayHealth>()
+        .expect("GatewayHealth to be in data/context")
+        .write()
+        .unwrap()
+        .connected(shard, now);
 
-            // Send message
-            let data_read = data.read().await;
+    let reconnected = {
+        let mut session = data
+            .get::<ReadySession>()
+            .expect("ReadySession to be in data/context")
+            .write()
+            .unwrap();
 
-            let mut boost = || rng.gen_range(0..=options.max_boost);
+        // Reconnections and other shards don't reset the uptime
+        let session = session.get_or_insert_with(|| Session {
+            started: now,
+            shard_guilds: BTreeMap::new(),
+            bot_id,
+        });
+        session.bot_id = bot_id;
 
-            let maybe_word = {
-                let words = data_read.get::<MessageMap>().unwrap().read().unwrap();
-                let maybe_word = words
-                    .iter()
-                    .max_by_key(|(_word, instances)| instances.len() + boost())
-                    .map(|(word, _)| word.to_owned());
+        session.shard_guilds.insert(shard, guilds).is_some()
+    };
 
-                maybe_word.or(options.default_word.clone())
-            };
+    data.get::<Schedule>()
+        .expect("Schedule to be in data/context")
+        .write()
+        .unwrap()
+        .ready(now);
 
-            if let Some(word) = maybe_word {
-                let recent_channel = data_read
-                    .get::<RecentChannel>()
-                    .expect("RecentChannel to be in data/context");
+    if reconnected {
+        data.get::<MetricsRegistry>()
+            .expect("MetricsRegistry to be in data/context")
+            .gateway_reconnect(shard);
+    }
 
-                let locked_channel = *recent_channel.read().expect("locking recent channel");
+    reconnected
+}
 
-                if let Some(channel) = locked_channel.clone() {
-                    let message = MessageBuilder::new().push(&word).build();
+/// Record a `resume` event of `shard`: its session survived the
+/// reconnection.
+fn on_resume(data: &TypeMap, shard: u32, now: DateTime<Utc>) {
+    data.get::<GatewayHealth>()
+        .expect("GatewayHealth to be in data/context")
+        .write()
+        .unwrap()
+        .connected(shard, now);
+    data.get::<MetricsRegistry>()
+        .expect("MetricsRegistry to be in data/context")
+        .gateway_reconnect(shard);
+}
 
-                    if let Err(e) = channel.clone().say(&cache_and_http.http, message).await {
-                        println!("Error sending message: {}", e);
-                    } else {
-                        println!("Send message '{}' to channel '{:?}' 🦜", word, channel);
-                    }
-                } else {
-                    println!("Most recent channel is None, type some text to update it!");
-                }
+/// Run the command in a message, or learn its words. The content is never
+/// logged.
+async fn handle_message(context: serenity::client::Context, msg: Message) {
+    let bot_id = {
+        let data_read = context.data.read().await;
+        let bot_id = data_read
+            .get::<ReadySession>()
+            .expect("ReadySession to be in context")
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|session| session.bot_id);
+        bot_id
+    };
 
-                // Clean up old words
-                let older_than = Utc::now() - Duration::seconds(options.max_age as i64);
+    let bot_id = match bot_id {
+        Some(bot_id) => bot_id,
+        // Pino doesn't know who it is until it's ready
+        None => return,
+    };
 
-                let mut words = data_read.get::<MessageMap>().unwrap().write().unwrap();
-                // Remove words older than older_than
-                for val in words.values_mut() {
-                    val.remove_le(&older_than);
-                }
-                // Remove entries with empty vectors to save space
-                words.retain(|_k, vec| vec.len() != 0);
+    if is_own_message(&msg, bot_id) {
+        return; // do nothing if we sent the message
+    }
+
+    let shutting_down = {
+        let data_read = context.data.read().await;
+        shutdown::is_requested(
+            data_read
+                .get::<ShuttingDown>()
+                .expect("ShuttingDown to be in context"),
+        )
+    };
+
+    // The state is being saved for the last time
+    if shutting_down {
+        return;
+    }
+
+    {
+        let data_read = context.data.read().await;
+        data_read
+            .get::<ActivityCounters>()
+            .expect("ActivityCounters to be in context")
+            .write()
+            .unwrap()
+            .messages += 1;
+        *data_read
+            .get::<ChannelActivity>()
+            .expect("ChannelActivity to be in context")
+            .write()
+            .unwrap()
+            .entry(msg.channel_id)
+            .or_default() += 1;
+        data_read
+            .get::<MetricsRegistry>()
+            .expect("MetricsRegistry to be in context")
+            .message_seen(context.shard_id.0);
+    }
+
+    if commands::dispatch(&context, &msg, bot_id).await {
+        return;
+    }
+
+    let ignored = {
+        let data_read = context.data.read().await;
+        let ignored = data_read
+            .get::<GuildSettings>()
+            .expect("GuildSettings to be in context")
+            .read()
+            .unwrap()
+            .is_ignored(msg.guild_id, msg.author.id);
+        ignored
+    };
+
+    // Ignored users don't even move pino to their channel
+    if ignored {
+        return;
+    }
+
+    {
+        let data_read = context.data.read().await;
+        let recent_channel_lock = data_read
+            .get::<RecentChannel>()
+            .expect("RecentChannel to be in context")
+            .clone();
+
+        let checked = check_channel(
+            &data_read,
+            msg.channel_id,
+            msg.guild_id,
+            channel_check::Style::Text,
+            MAX_SLOWMODE.get().copied().unwrap_or(u64::MAX),
+        );
+
+        // Set most current channel. Pino will reply there. The webhook posts
+        // in its own channel, this one is only logged.
+        match checked {
+            Err(unfit) if !WEBHOOK_MODE.get().copied().unwrap_or_default() => {
+                debug!("Not moving to channel {}: {}", msg.channel_id, unfit)
             }
+            _ => recent_channel_lock
+                .write()
+                .await
+                .touch(msg.channel_id, msg.guild_id),
         }
-    });
 
-    client.start().await.context("starting client")
+        heard_in(&data_read, msg.guild_id, Utc::now());
+    }
+
+    let tracking = {
+        let data_read = context.data.read().await;
+        let tracking = data_read
+            .get::<GuildSettings>()
+            .expect("GuildSettings to be in context")
+            .read()
+            .unwrap()
+            .is_tracking(msg.guild_id);
+        tracking
+    };
+
+    if !tracking {
+        return;
+    }
+
+    let max_length = MAX_MESSAGE_LENGTH.get().copied().unwrap_or(usize::MAX);
+
+    if tracking::is_too_long(&msg.content, max_length) {
+        debug!(
+            "Skipping a message longer than {} characters from {} in channel {}",
+            max_length, msg.author.id, msg.channel_id
+        );
+        return;
+    }
+
+    {
+        let data_read = context.data.read().await;
+
+        if let Some(heatmap) = data_read.get::<ActivityHeatmap>() {
+            heatmap::record(
+                &mut heatmap.write().unwrap(),
+                timestamp::to_utc(msg.timestamp),
+            );
+        }
+    }
+
+    let message_map_lock = {
+        let data_read = context.data.read().await;
+        data_read
+            .get::<MessageMap>()
+            .expect("MessageMap to be in context")
+            .clone()
+    };
+
+    let stop_words_lock = {
+        let data_read = context.data.read().await;
+        data_read
+            .get::<StopWords>()
+            .expect("StopWords to be in context")
+            .clone()
+    };
+    // The guild can have its own instead of the ones of --stop-words-file
+    let guild_stop_words = context
+        .data
+        .read()
+        .await
+        .get::<GuildSettings>()
+        .expect("GuildSettings to be in context")
+        .read()
+        .unwrap()
+        .resolve(msg.guild_id, DEFAULT_SETTINGS.get().unwrap())
+        .stop_words;
+
+    let blocklist_lock = {
+        let data_read = context.data.read().await;
+        data_read
+            .get::<BlockedWords>()
+            .expect("BlockedWords to be in context")
+            .clone()
+    };
+
+    #[cfg(feature = "sqlite")]
+    let db = context.data.read().await.get::<DbWriter>().cloned();
+
+    let cooccurrences = context.data.read().await.get::<Cooccurrences>().cloned();
+    let weekly = context
+        .data
+        .read()
+        .await
+        .get::<WeeklySummaries>()
+        .expect("WeeklySummaries to be in context")
+        .clone();
+    let rate_limits = context.data.read().await.get::<RateLimitMap>().cloned();
+    let echoes = context.data.read().await.get::<SelfEchoes>().cloned();
+    let mentions = context.data.read().await.get::<WordMentions>().cloned();
+    let (sent_log, max_age) = {
+        let data_read = context.data.read().await;
+        let sent_log = data_read
+            .get::<SentMessages>()
+            .expect("SentMessages to be in context")
+            .clone();
+        let max_age = data_read
+            .get::<Config>()
+            .expect("Config to be in context")
+            .read()
+            .unwrap()
+            .max_age;
+        (sent_log, max_age)
+    };
+    let metrics = context
+        .data
+        .read()
+        .await
+        .get::<MetricsRegistry>()
+        .expect("MetricsRegistry to be in context")
+        .clone();
+
+    // Awaited before taking the std locks, which can't be held across it
+    let mut message_map = message_map_lock.write().await;
+
+    let regex = WORD_REGEX.get().unwrap();
+    let global_stop_words = stop_words_lock.read().unwrap();
+    let stop_words = guild_stop_words.as_ref().unwrap_or(&global_stop_words);
+    let blocklist = blocklist_lock.read().unwrap();
+
+    let time = timestamp::to_utc(msg.timestamp);
+
+    let normalization = NORMALIZATION.get().copied().unwrap_or_default();
+
+    let tokenize = |content: &str| -> Vec<_> {
+        tracking::extract_words(content, regex, normalization, stop_words, &blocklist).collect()
+    };
+    let mut words = tokenize(&msg.content);
+
+    if let Some(echoes) = &echoes {
+        let learn = echoes.write().unwrap().learn(
+            &sent_log.read().unwrap(),
+            msg.guild_id.map(|id| id.get()),
+            &words,
+            time - Duration::seconds(max_age as i64),
+            tokenize,
+        );
+
+        if !learn {
+            debug!(
+                "Skipping a message from {} in channel {} repeating pino",
+                msg.author.id, msg.channel_id
+            );
+            return;
+        }
+    }
+
+    if let (Some(rate_limits), Some(&limit)) = (&rate_limits, RATE_LIMIT_WORDS.get()) {
+        let allowed = rate_limit::take(
+            &mut rate_limits.write().unwrap(),
+            ids::UserId(msg.author.id.get()),
+            words.len(),
+            limit,
+            time,
+        );
+
+        if allowed < words.len() {
+            warn!(
+                "Throttled, learning {} of {} words (limit {} every {} seconds)",
+                allowed,
+                words.len(),
+                limit,
+                rate_limit::WINDOW_SECONDS
+            );
+            words.truncate(allowed);
+        }
+    }
+
+    metrics.words_counted(msg.guild_id.map(|id| id.get()), words.len(), time);
+
+    if let Some(mentions) = &mentions {
+        mentions
+            .write()
+            .unwrap()
+            .record(msg.guild_id, &words, msg.channel_id, msg.id, time);
+    }
+
+    if let Some(guild_id) = msg.guild_id {
+        weekly.write().unwrap().record(
+            ids::GuildId(guild_id.get()),
+            ids::UserId(msg.author.id.get()),
+            &words,
+            time,
+        );
+    }
+
+    if let Some(cooccurrences) = &cooccurrences {
+        let mut cooccurrences = cooccurrences.write().unwrap();
+        cooccurrence::record(
+            cooccurrences.entry(guild_key(msg.guild_id)).or_default(),
+            &words,
+        );
+    }
+
+    for word in words {
+        #[cfg(feature = "sqlite")]
+        if let Some(db) = &db {
+            db.send(db::Event::Word(db::WordInstance {
+                word: word.clone(),
+                guild_id: msg.guild_id.map(|id| id.get()),
+                channel_id: msg.channel_id.get(),
+                user_id: msg.author.id.get(),
+                timestamp: time,
+            }));
+        }
+
+        message_map.learn(guild_key(msg.guild_id), word, time);
+    }
+}
+
+/// Post the summary of the week starting at `start` in `channel`, for the
+/// server of the channel. Nothing is posted while the server paused pino.
+async fn post_summary(http: &Http, data: &TypeMap, channel: ChannelId, start: DateTime<Utc>) {
+    let guild_id = match channel.to_channel(http).await {
+        Ok(channel) => match channel.guild() {
+            Some(channel) => channel.guild_id,
+            None => {
+                warn!("The summary channel isn't in a server, skipping the weekly summary");
+                return;
+            }
+        },
+        Err(e) => {
+            warn!("Error fetching the summary channel: {}", e);
+            return;
+        }
+    };
+
+    let paused = data
+        .get::<Paused>()
+        .expect("Paused to be in data/context")
+        .read()
+        .unwrap()
+        .is_paused(guild_key(Some(guild_id)), Utc::now());
+
+    if paused {
+        info!("Paused, skipping the weekly summary");
+        return;
+    }
+
+    let lang = data
+        .get::<GuildSettings>()
+        .expect("GuildSettings to be in data/context")
+        .read()
+        .unwrap()
+        .resolve(Some(guild_id), DEFAULT_SETTINGS.get().unwrap())
+        .language;
+    let summary = data
+        .get::<WeeklySummaries>()
+        .expect("WeeklySummaries to be in data/context")
+        .read()
+        .unwrap()
+        .summary(ids::GuildId(guild_id.get()), start);
+
+    let winner = data
+        .get::<WordOfTheWeek>()
+        .expect("WordOfTheWeek to be in data/context")
+        .write()
+        .unwrap()
+        .close(ids::GuildId(guild_id.get()), start);
+    let messenger = data
+        .get::<Messaging>()
+        .expect("Messaging to be in data/context");
+
+    // Chosen by the owner, the slowmode doesn't matter once a week
+    let checked = check_channel(
+        data,
+        channel,
+        Some(guild_id),
+        channel_check::Style::Embed,
+        u64::MAX,
+    );
+
+    match checked {
+        Err(unfit) => warn!(
+            "Can't post the weekly summary in channel {}: {}",
+            channel, unfit
+        ),
+        Ok(()) => match messenger
+            .send_components(
+                channel,
+                summary::embed(&summary, lang),
+                components::buttons(components::View::Summary(
+                    summary.start,
+                    pino_core::summary::TOP_WORDS,
+                )),
+            )
+            .await
+        {
+            Ok(message) => {
+                info!("Posted the weekly summary 🦜");
+                components::expire_later(messenger.clone(), channel, message);
+            }
+            Err(e) => {
+                warn!("Error posting the weekly summary: {}", e);
+                report_error(data, Some(guild_id), delivery::classify_serenity(&e), &e);
+            }
+        },
+    }
+
+    if let Some((word, votes)) = winner {
+        post_word_of_the_week(&**messenger, channel, lang, &word, votes).await;
+    }
+}
+
+/// Announce the word of the week in `channel` and pin it.
+async fn post_word_of_the_week(
+    messenger: &dyn Messenger,
+    channel: ChannelId,
+    lang: i18n::Lang,
+    word: &str,
+    votes: usize,
+) {
+    let content = i18n::tr(lang, "vote.winner", &[("word", &word), ("count", &votes)]);
+    let message = match messenger.send_text(channel, &content).await {
+        Ok(message) => message,
+        Err(e) => {
+            warn!("Error posting the word of the week: {}", e);
+            return;
+        }
+    };
+
+    match messenger.pin(channel, message).await {
+        Ok(()) => info!("Pinned the word of the week '{}'", word),
+        Err(e) => warn!("Error pinning the word of the week: {}", e),
+    }
+}
+
+/// Posts the word of the send loop where pino last heard someone, or through
+/// the webhook. Nothing is posted while the guild of the target paused pino.
+struct DiscordOutlet<'a> {
+    data: &'a TypeMap,
+    webhook_url: Option<&'a str>,
+    /// The username of the webhook would give the word away.
+    emoji_mode: bool,
+    target: Option<(ChannelId, Option<GuildId>)>,
+    /// Scheduled by the send loop, or forced with `!pino speak`.
+    kind: sent_log::SendKind,
+}
+
+#[async_trait]
+impl Outlet for DiscordOutlet<'_> {
+    async fn deliver(&self, post: &pipeline::Post) {
+        let word = &post.word;
+        let paused = self
+            .data
+            .get::<Paused>()
+            .expect("Paused to be in data/context")
+            .read()
+            .unwrap()
+            .is_paused(
+                guild_key(self.target.and_then(|(_channel, guild)| guild)),
+                Utc::now(),
+            );
+
+        if paused {
+            info!("Paused, keeping '{}' to myself 🤐", word);
+        } else if let Some(url) = self.webhook_url {
+            let destination = Destination::Webhook {
+                url,
+                username: if self.emoji_mode {
+                    None
+                } else {
+                    webhook::username_for(word)
+                },
+                guild_id: self.target.and_then(|(_channel, guild)| guild),
+            };
+
+            if let Err(e) =
+                send_and_record(self.data, destination, word, &post.message, self.kind).await
+            {
+                error!("Error posting through the webhook: {:#}", e);
+            } else {
+                info!("Posted message '{}' through the webhook 🦜", word);
+            }
+        } else if let Some(target) = self.target {
+            send_with_retries(self.data, target, word, &post.message, self.kind).await;
+        } else {
+            warn!("Most recent channel is None, type some text to update it!");
+        }
+    }
+}
+
+/// `guild` as pino-core knows it, e.g. to find its words in the [`MessageMap`].
+pub(crate) fn guild_key(guild: Option<GuildId>) -> Option<ids::GuildId> {
+    guild.map(|id| ids::GuildId(id.get()))
+}
+
+/// The timer of `guild` in the [`Schedule`].
+fn schedule_target(guild: Option<GuildId>) -> Option<ids::GuildId> {
+    if WEBHOOK_MODE.get().copied().unwrap_or_default() {
+        None
+    } else {
+        guild_key(guild)
+    }
+}
+
+/// Someone talked in `guild`: it gets messages from now on, if it didn't
+/// already.
+fn heard_in(data: &TypeMap, guild: Option<GuildId>, now: DateTime<Utc>) {
+    let target = schedule_target(guild);
+    let config = data
+        .get::<Config>()
+        .expect("Config to be in data/context")
+        .read()
+        .unwrap()
+        .clone();
+    let rng = data.get::<Random>().expect("Random to be in data/context");
+    let added = data
+        .get::<Schedule>()
+        .expect("Schedule to be in data/context")
+        .write()
+        .unwrap()
+        .hear(target, now, &mut *rng.lock().unwrap(), &config);
+
+    if added {
+        info!("Talking in guild {:?} from now on", target);
+        data.get::<ScheduleChanged>()
+            .expect("ScheduleChanged to be in data/context")
+            .notify_one();
+    }
+}
+
+/// Say what pino would say next in `channel` of `guild` right now, composed
+/// and delivered like in a send cycle: with the settings of the guild, and
+/// through the webhook if there's one. Returns false if there's nothing to say.
+async fn speak_in(data: &TypeMap, channel: ChannelId, guild: Option<GuildId>) -> bool {
+    let max_boost = data
+        .get::<Config>()
+        .expect("Config to be in data/context")
+        .read()
+        .unwrap()
+        .max_boost;
+    let settings = data
+        .get::<GuildSettings>()
+        .expect("GuildSettings to be in data/context")
+        .read()
+        .unwrap()
+        .resolve(guild, DEFAULT_SETTINGS.get().unwrap());
+    let emoji_map = data
+        .get::<EmojiMap>()
+        .expect("EmojiMap to be in data/context");
+
+    let post = {
+        let words = data.get::<MessageMap>().unwrap().read().await;
+        let blocklist = data.get::<BlockedWords>().unwrap().read().unwrap();
+        let rng = data.get::<Random>().expect("Random to be in data/context");
+
+        pipeline::compose(
+            words.guild(guild_key(guild)),
+            &blocklist,
+            &mut *rng.lock().unwrap(),
+            &word_selection(max_boost, settings.scoring),
+            &settings,
+            emoji_map,
+        )
+    };
+
+    let post = match post {
+        Some(post) => post,
+        None => return false,
+    };
+
+    let outlet = DiscordOutlet {
+        data,
+        webhook_url: WEBHOOK_URL.get().map(String::as_str),
+        emoji_mode: settings.emoji_mode,
+        target: Some((channel, guild)),
+        kind: sent_log::SendKind::Forced,
+    };
+    outlet.deliver(&post).await;
+
+    true
+}
+
+/// Pino just spoke in `guild` on demand, so its timer starts over.
+fn spoke_in(data: &TypeMap, guild: Option<GuildId>, now: DateTime<Utc>) {
+    let config = data
+        .get::<Config>()
+        .expect("Config to be in data/context")
+        .read()
+        .unwrap()
+        .clone();
+    let rng = data.get::<Random>().expect("Random to be in data/context");
+
+    data.get::<Schedule>()
+        .expect("Schedule to be in data/context")
+        .write()
+        .unwrap()
+        .spoke(
+            schedule_target(guild),
+            now,
+            &mut *rng.lock().unwrap(),
+            &config,
+        );
+    data.get::<ScheduleChanged>()
+        .expect("ScheduleChanged to be in data/context")
+        .notify_one();
+}
+
+/// Pino left `guild`: forget where it talked there and stop its timer.
+async fn left_guild(data: &TypeMap, guild: GuildId) {
+    data.get::<RecentChannel>()
+        .expect("RecentChannel to be in data/context")
+        .write()
+        .await
+        .forget_guild(guild);
+
+    if !WEBHOOK_MODE.get().copied().unwrap_or_default() {
+        data.get::<Schedule>()
+            .expect("Schedule to be in data/context")
+            .write()
+            .unwrap()
+            .remove(guild_key(Some(guild)));
+    }
+}
+
+/// Wake up the send loop: if it's the turn of a guild, post the most said
+/// word where pino last heard someone there, then forget the old words.
+async fn send_cycle(
+    data: &TypeMap,
+    emoji_map: &HashMap<String, String>,
+    webhook_url: Option<&str>,
+    max_words: Option<usize>,
+) {
+    // Settings might have changed while waiting
+    let config = data
+        .get::<Config>()
+        .expect("Config to be in data/context")
+        .read()
+        .unwrap()
+        .clone();
+
+    let rng = data.get::<Random>().expect("Random to be in data/context");
+    let due = data
+        .get::<Schedule>()
+        .expect("Schedule to be in data/context")
+        .write()
+        .unwrap()
+        .turn(Utc::now(), &mut *rng.lock().unwrap(), &config);
+
+    let target = match due {
+        Some(schedule::Due::Send(target)) => target,
+        Some(schedule::Due::Idle(target)) => {
+            info!(
+                "Nobody talked in guild {:?} for a while, waiting for someone to",
+                target
+            );
+            return;
+        }
+        None => return,
+    };
+
+    let locked_channel = {
+        let recent = data
+            .get::<RecentChannel>()
+            .expect("RecentChannel to be in data/context")
+            .read()
+            .await;
+
+        // The webhook posts in its own channel, the guild is only logged
+        if webhook_url.is_some() {
+            recent.current()
+        } else {
+            let target = target.map(|id| GuildId::new(id.get()));
+            recent.current_in(target).map(|channel| (channel, target))
+        }
+    };
+
+    // The guild pino is going to talk in decides how, and with which words
+    let guild = locked_channel.and_then(|(_channel, guild)| guild);
+    let settings = data
+        .get::<GuildSettings>()
+        .expect("GuildSettings to be in data/context")
+        .read()
+        .unwrap()
+        .resolve(guild, DEFAULT_SETTINGS.get().unwrap());
+
+    let post = {
+        let words = data.get::<MessageMap>().unwrap().read().await;
+        let blocklist = data.get::<BlockedWords>().unwrap().read().unwrap();
+
+        pipeline::compose(
+            words.guild(guild_key(guild)),
+            &blocklist,
+            &mut *rng.lock().unwrap(),
+            &word_selection(config.max_boost, settings.scoring),
+            &settings,
+            emoji_map,
+        )
+    };
+
+    if let Some(post) = post {
+        let outlet = DiscordOutlet {
+            data,
+            webhook_url,
+            emoji_mode: settings.emoji_mode,
+            target: locked_channel,
+            kind: sent_log::SendKind::Scheduled,
+        };
+        outlet.deliver(&post).await;
+
+        // Clean up old words
+        async {
+            let older_than = Utc::now() - Duration::seconds(config.max_age as i64);
+
+            let mut words = data.get::<MessageMap>().unwrap().write().await;
+            let (expired, evicted) = words.forget_old(older_than, max_words);
+
+            info!("Forgot {} expired word instances", expired);
+            data.get::<MetricsRegistry>()
+                .expect("MetricsRegistry to be in data/context")
+                .words_expired(expired);
+
+            if evicted > 0 {
+                info!(
+                    "Forgot {} words to stay below {} words",
+                    evicted,
+                    max_words.unwrap_or_default()
+                );
+            }
+
+            if let Some(rate_limits) = data.get::<RateLimitMap>() {
+                rate_limit::prune(&mut rate_limits.write().unwrap(), Utc::now());
+            }
+
+            if let Some(mentions) = data.get::<WordMentions>() {
+                mentions.write().unwrap().expire(older_than);
+            }
+
+            #[cfg(feature = "sqlite")]
+            if let Some(db) = data.get::<DbWriter>() {
+                db.send(db::Event::Expire(older_than));
+            }
+        }
+        .instrument(info_span!("cleanup"))
+        .await;
+    }
+}
+
+/// Whether pino sent the message itself.
+fn is_own_message(msg: &Message, bot_id: UserId) -> bool {
+    msg.author.id == bot_id
+}
+
+/// Where pino posts a message.
+enum Destination<'a> {
+    Channel(ChannelId, Option<GuildId>),
+    /// In the channel, as a reply to the message if Discord still has it.
+    Reply(ChannelId, Option<GuildId>, MessageId),
+    /// The guild of the webhook isn't known, the message is logged under `guild_id`.
+    Webhook {
+        url: &'a str,
+        username: Option<&'a str>,
+        guild_id: Option<GuildId>,
+    },
+}
+
+/// Post a message, returning the ids of its guild, channel and message when
+/// they are known.
+async fn post(
+    messenger: &dyn Messenger,
+    destination: Destination<'_>,
+    content: &str,
+) -> anyhow::Result<(Option<u64>, Option<u64>, Option<u64>)> {
+    let (channel_id, guild_id, reply_to) = match destination {
+        Destination::Channel(channel_id, guild_id) => (channel_id, guild_id, None),
+        Destination::Reply(channel_id, guild_id, message) => (channel_id, guild_id, Some(message)),
+        Destination::Webhook {
+            url,
+            username,
+            guild_id,
+        } => {
+            webhook::post_via_webhook(url, content, username).await?;
+            return Ok((guild_id.map(|id| id.get()), None, None));
+        }
+    };
+
+    let message = reply_context::send(messenger, channel_id, content, reply_to).await?;
+
+    Ok((
+        guild_id.map(|id| id.get()),
+        Some(channel_id.get()),
+        Some(message.get()),
+    ))
+}
+
+/// Count an error of sending in `guild`, so that the owner hears about it if
+/// it keeps happening.
+fn report_error(
+    data: &TypeMap,
+    guild: Option<GuildId>,
+    failure: delivery::Failure,
+    error: &dyn fmt::Display,
+) {
+    if let Some(reporter) = data.get::<ErrorReports>() {
+        reporter.report(guild.map(|id| id.get()), failure, error);
+    }
+
+    record_audit(
+        data,
+        audit::AuditEvent::Error {
+            failure,
+            error: error.to_string(),
+            guild_id: guild.map(|id| id.get()),
+            timestamp: Utc::now(),
+        },
+    );
+}
+
+/// Tell the audit log about `event`, if there is one.
+fn record_audit(data: &TypeMap, event: audit::AuditEvent) {
+    if let Some(auditor) = data.get::<AuditLog>() {
+        auditor.record(event);
+    }
+}
+
+/// Post a message showing `word` and log it in the history of sent messages.
+async fn send_and_record(
+    data: &TypeMap,
+    destination: Destination<'_>,
+    word: &str,
+    content: &str,
+    kind: sent_log::SendKind,
+) -> anyhow::Result<()> {
+    let messenger = data
+        .get::<Messaging>()
+        .expect("Messaging to be in data/context");
+    let metrics = data
+        .get::<MetricsRegistry>()
+        .expect("MetricsRegistry to be in data/context");
+
+    let guild = match destination {
+        Destination::Channel(_channel, guild) | Destination::Reply(_channel, guild, _) => guild,
+        Destination::Webhook { guild_id, .. } => guild_id,
+    };
+
+    let (guild_id, channel_id, message_id) = match post(&**messenger, destination, content).await {
+        Ok(ids) => ids,
+        Err(e) => {
+            let failure = delivery::classify(&e);
+            metrics.send_error(failure);
+            report_error(data, guild, failure, &format_args!("{:#}", e));
+            return Err(e);
+        }
+    };
+
+    data.get::<SentMessages>()
+        .expect("SentMessages to be in data/context")
+        .write()
+        .unwrap()
+        .push(
+            guild_id,
+            sent_log::SentMessage {
+                time: Utc::now(),
+                word: word.to_owned(),
+                content: content.to_owned(),
+                channel_id,
+                message_id,
+                kind,
+            },
+        );
+    data.get::<ActivityCounters>()
+        .expect("ActivityCounters to be in data/context")
+        .write()
+        .unwrap()
+        .posts += 1;
+    metrics.message_sent();
+    record_audit(
+        data,
+        audit::AuditEvent::Post {
+            word: word.to_owned(),
+            channel_id,
+            guild_id,
+            timestamp: Utc::now(),
+        },
+    );
+
+    Ok(())
+}
+
+/// Post a scheduled message in the most recent channel. Sends again when
+/// Discord might accept it later, and moves to the next recent channel when
+/// pino can't post in one anymore.
+async fn send_with_retries(
+    data: &TypeMap,
+    mut target: (ChannelId, Option<GuildId>),
+    word: &str,
+    content: &str,
+    kind: sent_log::SendKind,
+) {
+    let mut retries = 0;
+
+    loop {
+        let (channel, guild) = target;
+        let max_slowmode = MAX_SLOWMODE.get().copied().unwrap_or(u64::MAX);
+
+        if let Err(unfit) = check_channel(
+            data,
+            channel,
+            guild,
+            channel_check::Style::Text,
+            max_slowmode,
+        ) {
+            warn!("Can't post in channel '{:?}': {}", channel, unfit);
+
+            match next_channel(data, channel, guild).await {
+                Some(next) => {
+                    target = (next, guild);
+                    retries = 0;
+                    continue;
+                }
+                None => return,
+            }
+        }
+
+        let destination = match reply_target(data, channel, guild, word) {
+            Some(message) => Destination::Reply(channel, guild, message),
+            None => Destination::Channel(channel, guild),
+        };
+
+        let e = match send_and_record(data, destination, word, content, kind).await {
+            Ok(()) => {
+                info!("Send message '{}' to channel '{:?}' 🦜", word, channel);
+                return;
+            }
+            Err(e) => e,
+        };
+
+        match delivery::next_step(delivery::classify(&e), retries) {
+            delivery::Next::Retry(delay) => {
+                warn!(
+                    "Error sending message, trying again in {} seconds: {:#}",
+                    delay.as_secs(),
+                    e
+                );
+                tokio::time::sleep(delay).await;
+                retries += 1;
+            }
+            delivery::Next::Rotate => {
+                warn!("Can't post in channel '{:?}' anymore: {:#}", channel, e);
+
+                match next_channel(data, channel, guild).await {
+                    Some(next) => {
+                        target = (next, guild);
+                        retries = 0;
+                    }
+                    None => return,
+                }
+            }
+            delivery::Next::GiveUp => {
+                error!("Error sending message: {:#}", e);
+                return;
+            }
+        }
+    }
+}
+
+/// The message to reply to when posting `word` in `channel`, with
+/// `--reply-style reference`: the last one saying it there within `max_age`.
+fn reply_target(
+    data: &TypeMap,
+    channel: ChannelId,
+    guild: Option<GuildId>,
+    word: &str,
+) -> Option<MessageId> {
+    let mentions = data.get::<WordMentions>()?;
+    let max_age = data
+        .get::<Config>()
+        .expect("Config to be in data/context")
+        .read()
+        .unwrap()
+        .max_age;
+    let since = Utc::now() - Duration::seconds(max_age as i64);
+
+    let reply_to = mentions
+        .read()
+        .unwrap()
+        .reply_to(guild, word, channel, since);
+    reply_to
+}
+
+/// Forget `channel` of `guild`, which pino can't post in, returning the next
+/// recent channel of the guild to try, if any.
+async fn next_channel(
+    data: &TypeMap,
+    channel: ChannelId,
+    guild: Option<GuildId>,
+) -> Option<ChannelId> {
+    let next = data
+        .get::<RecentChannel>()
+        .expect("RecentChannel to be in data/context")
+        .write()
+        .await
+        .forget(channel, guild);
+
+    match next {
+        Some(next) => info!("Moving to channel '{:?}'", next),
+        None => warn!("No other recent channel in the guild, type some text to add one!"),
+    }
+
+    next
+}
+
+/// Whether pino can post `style` in `channel` of `guild`, as far as the
+/// cache knows, see [`channel_check::check`]. Channels it knows nothing
+/// about, like DMs and threads, are tried anyway: sending will tell.
+fn check_channel(
+    data: &TypeMap,
+    channel: ChannelId,
+    guild: Option<GuildId>,
+    style: channel_check::Style,
+    max_slowmode: u64,
+) -> Result<(), channel_check::Unfit> {
+    let (cache, guild) = match (data.get::<DiscordCache>(), guild) {
+        (Some(cache), Some(guild)) => (cache, guild),
+        _ => return Ok(()),
+    };
+    let bot_id = cache.current_user().id;
+
+    let found = cache.guild(guild).and_then(|guild| {
+        let channel = guild.channels.get(&channel)?;
+        let member = guild.members.get(&bot_id)?;
+
+        Some((
+            guild.user_permissions_in(channel, member),
+            channel.rate_limit_per_user.unwrap_or(0),
+        ))
+    });
+
+    match found {
+        Some((permissions, slowmode)) => {
+            channel_check::check(permissions, slowmode, style, max_slowmode)
+        }
+        None => Ok(()),
+    }
+}
+
+/// The message a 👎 reaction is on, if it's a recent message of pino that
+/// showed a word.
+async fn vetoed_message(
+    context: &serenity::client::Context,
+    reaction: &Reaction,
+) -> Option<sent_log::SentMessage> {
+    match &reaction.emoji {
+        ReactionType::Unicode(emoji) if emoji.starts_with(veto::VETO_EMOJI) => {}
+        _ => return None,
+    }
+
+    let data_read = context.data.read().await;
+    let sent = data_read
+        .get::<SentMessages>()
+        .expect("SentMessages to be in context")
+        .read()
+        .unwrap()
+        .find(
+            reaction.guild_id.map(|id| id.get()),
+            reaction.message_id.get(),
+        )
+        .cloned()?;
+
+    // Messages logged before words were recorded can't be vetoed
+    if sent.word.is_empty() {
+        None
+    } else {
+        Some(sent)
+    }
+}
+
+/// How the word to say is picked now, by the `scoring` of the guild, with the
+/// boost of the runtime config. Without options, like in the tests, with the
+/// default window.
+pub fn word_selection(max_boost: usize, scoring: selection::Scoring) -> selection::Selection {
+    let window = TRENDING_WINDOW.get().copied().unwrap_or(5 * 60);
+
+    selection::Selection {
+        scoring,
+        max_boost,
+        window,
+        now: Utc::now(),
+    }
+}
+
+/// The source of every random choice: seeded with `--seed`, or else from
+/// the OS, so that no two runs wait the same times.
+fn make_rng(seed: Option<u64>) -> StdRng {
+    match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    }
+}
+
+/// Load the persisted state, starting fresh if the state file is missing or corrupt.
+fn load_state(path: &Path) -> Option<FrequencySnapshot> {
+    match persistence::load(path) {
+        Ok(snapshot) => {
+            if snapshot.is_none() {
+                info!("State file {} not found, starting fresh", path.display());
+            }
+
+            snapshot
+        }
+        Err(e) => {
+            warn!(
+                "Error loading state file {}: {:#}. Starting fresh",
+                path.display(),
+                e
+            );
+            None
+        }
+    }
+}
+
+/// Apply the persisted settings to the config from the command line. If they
+/// aren't valid anymore, they are all ignored.
+fn apply_settings(
+    base: &runtime_config::RuntimeConfig,
+    settings: &[(String, String)],
+) -> runtime_config::RuntimeConfig {
+    if settings.is_empty() {
+        return base.clone();
+    }
+
+    match base.with_settings(
+        settings
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str())),
+    ) {
+        Ok(config) => {
+            info!("Using {} settings changed at runtime", settings.len());
+            config
+        }
+        Err(e) => {
+            warn!("Ignoring the settings changed at runtime: {}", e);
+            base.clone()
+        }
+    }
+}
+
+/// Read the config file and the stop words again on SIGHUP and apply them,
+/// or nothing if any of them is broken.
+#[cfg(unix)]
+fn reload_files(sources: &reload::Sources, live: &reload::Live<'_>) {
+    let loaded = match sources.load() {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            warn!("Error reloading, keeping the current config: {:#}", e);
+            return;
+        }
+    };
+    let stop_words = loaded.stop_words.as_ref().map(HashSet::len);
+
+    match reload::apply(loaded, live) {
+        Ok(changes) if changes.is_empty() => info!("Reloaded, the settings didn't change"),
+        Ok(changes) => {
+            for change in &changes {
+                info!("Reloaded {}", change);
+            }
+        }
+        Err(e) => {
+            warn!("Error reloading, keeping the current config: {}", e);
+            return;
+        }
+    }
+
+    if let Some(count) = stop_words {
+        info!("Reloaded {} stop words", count);
+    }
+}
+
+/// Everything that is saved to the state file.
+#[derive(Clone)]
+struct PersistedState {
+    message_map: Arc<tokio::sync::RwLock<WordStore>>,
+    config: Arc<RwLock<runtime_config::RuntimeConfig>>,
+    /// The config from the command line and the config file, which SIGHUP
+    /// reloads. Only the settings that differ from it are saved.
+    base_config: Arc<RwLock<runtime_config::RuntimeConfig>>,
+    blocklist: Arc<RwLock<blocklist::Blocklist>>,
+    guild_settings: Arc<RwLock<guild_config::GuildConfigs>>,
+    sent_log: Arc<RwLock<sent_log::SentLog>>,
+}
+
+/// Save the state to disk. The locks are only held while taking the snapshot,
+/// not while writing.
+async fn save_state(path: &Path, state: &PersistedState) {
+    let (snapshot, count) = {
+        let words = state.message_map.read().await;
+        let mut snapshot = FrequencySnapshot::from_store(&words);
+        snapshot.settings = state
+            .config
+            .read()
+            .unwrap()
+            .changes_from(&state.base_config.read().unwrap());
+        snapshot.blocklist = state.blocklist.read().unwrap().to_map();
+        let guild_settings = state.guild_settings.read().unwrap();
+        snapshot.prefixes = guild_settings.prefixes();
+        snapshot.guild_settings = guild_settings.settings();
+        snapshot.sent = state.sent_log.read().unwrap().clone();
+        (snapshot, words.word_count())
+    };
+
+    match persistence::save(path, &snapshot) {
+        Ok(()) => info!("Saved {} words to {}", count, path.display()),
+        Err(e) => error!("Error saving state file {}: {:#}", path.display(), e),
+    }
+}
+
+/// What pino needs to hear. Reading messages needs the privileged message
+/// content intent, which must be enabled in the developer portal.
+const INTENTS: GatewayIntents = GatewayIntents::GUILDS
+    .union(GatewayIntents::GUILD_MESSAGES)
+    .union(GatewayIntents::GUILD_MESSAGE_REACTIONS)
+    .union(GatewayIntents::DIRECT_MESSAGES)
+    .union(GatewayIntents::MESSAGE_CONTENT);
+
+/// Also needed with `--welcome-channel`, to hear about new members.
+const WELCOME_INTENTS: GatewayIntents = GatewayIntents::GUILD_MEMBERS;
+
+/// Explain the errors of the client that need action on the developer portal.
+fn start_error(error: serenity::Error) -> anyhow::Error {
+    match error {
+        serenity::Error::Gateway(GatewayError::DisallowedGatewayIntents) => anyhow::anyhow!(
+            "Discord refused a privileged intent: pino needs the message content one to read the words, \
+             and the server members one with --welcome-channel. Enable them in the developer portal, \
+             under Bot > Privileged Gateway Intents"
+        ),
+        error => anyhow::Error::new(error).context("starting client"),
+    }
+}
+
+#[tokio::main(worker_threads = 1)]
+async fn main() -> anyhow::Result<()> {
+    let startup_time = Utc::now();
+    let matches = Cli::clap().get_matches_from(default_to_run(std::env::args_os().collect()));
+
+    match Cli::from_clap(&matches) {
+        Cli::Run(options) => {
+            let matches = matches.subcommand_matches("run").unwrap();
+            run(options, matches, startup_time).await
+        }
+        Cli::Validate { config } => offline::check(&config, &mut std::io::stdout()),
+        Cli::Export {
+            state_file,
+            out,
+            guild,
+        } => offline::export(
+            &state_file,
+            &out,
+            guild.map(ids::GuildId),
+            &mut std::io::stdout(),
+        )
+        .map(drop),
+        Cli::Import {
+            state_file,
+            input,
+            guild,
+        } => offline::import(
+            &state_file,
+            &input,
+            guild.map(ids::GuildId),
+            Utc::now(),
+            &mut std::io::stdout(),
+        )
+        .map(drop),
+        Cli::Stats {
+            state_file,
+            top,
+            guild,
+        } => offline::stats(
+            &state_file,
+            top,
+            guild.map(ids::GuildId),
+            &mut std::io::stdout(),
+        ),
+    }
+}
+
+/// `pino-bot run`: connect to Discord, or read stdin with `--dry-run`.
+async fn run(
+    mut options: Options,
+    matches: &structopt::clap::ArgMatches<'_>,
+    startup_time: DateTime<Utc>,
+) -> anyhow::Result<()> {
+    let (configured_guilds, unknown_keys) = match options.config.clone() {
+        Some(path) => {
+            let (file, unknown) = config_file::load(&path)?;
+            let guilds = file.guild_settings().context("invalid config file")?;
+
+            file.merge(&mut options, |name| matches.occurrences_of(name) > 0);
+            (guilds, unknown)
+        }
+        None => (BTreeMap::new(), Vec::new()),
+    };
+
+    let validated = match options.validate() {
+        Ok(validated) => validated,
+        Err(problems) => {
+            let problems: Vec<_> = problems
+                .iter()
+                .map(|problem| format!("\n  {}", problem))
+                .collect();
+            bail!("invalid options:{}", problems.concat());
+        }
+    };
+
+    // The config file can set the log options, nothing is logged before
+    logging::init(validated.log_filter, options.log_format, options.dry_run)?;
+
+    info!("Starting PinoBot 🦜");
+
+    if let Some(path) = &options.config {
+        if !unknown_keys.is_empty() {
+            warn!(
+                "Ignoring unknown keys in {}: {}",
+                path.display(),
+                unknown_keys.join(", ")
+            );
+        }
+    }
+
+    for warning in &validated.warnings {
+        warn!("{}", warning);
+    }
+
+    let startup_options =
+        serde_json::to_string_pretty(&options).context("serializing the options")?;
+
+    WORD_REGEX.set(validated.word_regex).unwrap();
+    NORMALIZATION.set(options.unicode_normalize).unwrap();
+    DEFAULT_PREFIX.set(options.prefix.clone()).unwrap();
+    GLOBAL_SLASH_COMMANDS
+        .set(options.global_slash_commands)
+        .unwrap();
+    MAX_MESSAGE_LENGTH.set(options.max_message_length).unwrap();
+    MAX_SLOWMODE.set(options.max_slowmode).unwrap();
+    TRENDING_WINDOW.set(options.trending_window).unwrap();
+
+    let base_config = runtime_config::RuntimeConfig {
+        interval_low: options.interval_low,
+        interval_high: options.interval_high,
+        max_age: options.max_age,
+        max_boost: options.max_boost,
+    };
+
+    DEFAULT_SETTINGS
+        .set(guild_settings::Settings {
+            message_template: options.message_template.clone(),
+            emoji_mode: Some(options.emoji_mode),
+            default_word: options.default_word.clone(),
+            language: options.language,
+            scoring: Some(options.scoring),
+            stop_words: None,
+        })
+        .unwrap();
+
+    let stop_words = match &options.stop_words_file {
+        Some(path) => stop_words::load(path)?,
+        None => HashSet::new(),
+    };
+
+    let emoji_map = match &options.emoji_map_file {
+        Some(path) => emoji::load(path)?,
+        None => HashMap::new(),
+    };
+
+    if options.dry_run {
+        let dry_run = dry_run::DryRun {
+            word_regex: WORD_REGEX.get().unwrap().clone(),
+            normalization: options.unicode_normalize,
+            stop_words,
+            emoji_map,
+            settings: guild_config::GuildConfigs::default()
+                .resolve(None, DEFAULT_SETTINGS.get().unwrap()),
+            config: base_config,
+            max_words: options.max_words,
+            max_message_length: options.max_message_length,
+            trending_window: options.trending_window,
+            time_scale: options.time_scale,
+            rng: make_rng(options.seed),
+        };
+
+        info!("Dry run, reading `[#channel] [@user] message` lines from stdin");
+        dry_run::run(
+            dry_run,
+            std::io::BufReader::new(std::io::stdin()),
+            &Mutex::new(std::io::stdout()),
+        )
+        .await;
+
+        return Ok(());
+    }
+
+    let token = token::resolve(options.token_file.as_deref(), options.token.as_deref())?;
+
+    let sharding = sharding::Sharding::new(options.shards, options.shard_range);
+    let gateway = Arc::new(RwLock::new(health::Gateway::new(sharding.shards())));
+
+    let intents = if options.welcome_channel.is_some() {
+        INTENTS | WELCOME_INTENTS
+    } else {
+        INTENTS
+    };
+
+    let mut client = Client::builder(&token, intents)
+        .event_handler(Reader)
+        .raw_event_handler(GatewayWatcher(gateway.clone()))
+        .await
+        .expect("creating client");
+
+    let owner = match client.http.get_current_application_info().await {
+        Ok(info) => info.owner.map(|owner| owner.id),
+        Err(e) => {
+            warn!(
+                "Error fetching the owner of the application, debug dumps and error alerts are disabled: {}",
+                e
+            );
+            None
+        }
+    };
+
+    #[cfg(feature = "sqlite")]
+    let database = match &options.db {
+        Some(url) => Some(db::Database::open(url).context("opening database")?),
+        None => None,
+    };
+
+    let snapshot = options.state_file.as_deref().and_then(load_state);
+
+    let settings: Vec<_> = snapshot
+        .as_ref()
+        .map(|snapshot| snapshot.settings.clone().into_iter().collect())
+        .unwrap_or_default();
+
+    #[cfg(feature = "sqlite")]
+    let settings = match &database {
+        Some(database) => database
+            .load_settings()
+            .context("loading settings from the database")?,
+        None => settings,
+    };
+
+    let config = apply_settings(&base_config, &settings);
+    let base_config = Arc::new(RwLock::new(base_config));
+
+    let blocklist = snapshot
+        .as_ref()
+        .map(|snapshot| snapshot.blocklist.clone())
+        .unwrap_or_default();
+
+    #[cfg(feature = "sqlite")]
+    let blocklist = match &database {
+        Some(database) => database
+            .load_blocklist()
+            .context("loading the blocklist from the database")?,
+        None => blocklist,
+    };
+
+    let blocklist = blocklist::Blocklist::load(&blocklist);
+
+    let prefixes = snapshot
+        .as_ref()
+        .map(|snapshot| snapshot.prefixes.clone())
+        .unwrap_or_default();
+
+    #[cfg(feature = "sqlite")]
+    let prefixes = match &database {
+        Some(database) => database
+            .load_prefixes()
+            .context("loading the prefixes from the database")?,
+        None => prefixes,
+    };
+
+    let settings_by_guild = snapshot
+        .as_ref()
+        .map(|snapshot| snapshot.guild_settings.clone())
+        .unwrap_or_default();
+
+    #[cfg(feature = "sqlite")]
+    let settings_by_guild = match &database {
+        Some(database) => database
+            .load_guild_settings()
+            .context("loading the settings of the guilds from the database")?,
+        None => settings_by_guild,
+    };
+
+    let mut guild_settings = guild_config::GuildConfigs::default();
+    guild_settings.load_prefixes(&prefixes);
+    guild_settings.load_settings(&settings_by_guild);
+    guild_settings.load_configured(&configured_guilds);
+
+    let sent_log = snapshot
+        .as_ref()
+        .map(|snapshot| snapshot.sent.clone())
+        .unwrap_or_default();
+
+    let word_map = match (snapshot, &options.state_file) {
+        (Some(snapshot), Some(path)) => {
+            let older_than = Utc::now() - Duration::seconds(config.max_age as i64);
+            let words = snapshot.into_store(older_than);
+
+            info!(
+                "Loaded {} words from {}",
+                words.word_count(),
+                path.display()
+            );
+
+            words
+        }
+        _ => WordStore::new(),
+    };
+
+    #[cfg(feature = "sqlite")]
+    let word_map = match &database {
+        Some(database) => {
+            let older_than = Utc::now() - Duration::seconds(config.max_age as i64);
+            let words = database
+                .load_words(older_than)
+                .context("loading words from the database")?;
+
+            info!("Loaded {} words from the database", words.word_count());
+
+            words
+        }
+        None => word_map,
+    };
+
+    let message_map = Arc::new(tokio::sync::RwLock::new(word_map));
+    let config = Arc::new(RwLock::new(config));
+    let blocklist = Arc::new(RwLock::new(blocklist));
+    let sent_log = Arc::new(RwLock::new(sent_log));
+    let guild_settings = Arc::new(RwLock::new(guild_settings));
+
+    let stop_words = Arc::new(RwLock::new(stop_words));
+    let emoji_map = Arc::new(emoji_map);
+
+    let wordcloud = wordcloud::Settings {
+        max_words: options.wordcloud_max_words,
+        font: match &options.wordcloud_font_file {
+            Some(path) => Some(wordcloud::load_font(path)?),
+            None => None,
+        },
+    };
+
+    let schedule_changed = Arc::new(Notify::new());
+
+    let rng = Arc::new(Mutex::new(make_rng(options.seed)));
+    let scheduler = Arc::new(RwLock::new(schedule::Deadlines::with_warmup(
+        Duration::seconds(options.warmup as i64),
+    )));
+
+    let snapshot_history = Arc::new(RwLock::new(snapshots::History::new(
+        options.snapshot_history,
+    )));
+
+    // Bound now, so that a busy address stops pino before it connects
+    let metrics_listener = match options.metrics_addr {
+        Some(addr) => Some(
+            std::net::TcpListener::bind(addr)
+                .with_context(|| format!("listening for metrics scrapes on {}", addr))?,
+        ),
+        None => None,
+    };
+    let health_listener = match options.health_addr {
+        Some(addr) => Some(
+            std::net::TcpListener::bind(addr)
+                .with_context(|| format!("listening for health probes on {}", addr))?,
+        ),
+        None => None,
+    };
+    let metrics = Arc::new(metrics::Metrics::default());
+
+    let mut shutdown = shutdown::Shutdown::default();
+    let supervisor = supervisor::Supervisor::default();
+    #[cfg(feature = "sqlite")]
+    let mut db_writer = None;
+
+    {
+        let mut data = client.data.write().await;
+        data.insert::<ShuttingDown>(shutdown.subscribe());
+        data.insert::<StartupOptions>(Arc::new(startup_options));
+        data.insert::<TaskSupervisor>(supervisor.clone());
+        data.insert::<MessageMap>(message_map.clone());
+        data.insert::<Config>(config.clone());
+        data.insert::<RecentChannel>(Arc::new(tokio::sync::RwLock::new(
+            recent_channels::RecentChannels::default(),
+        )));
+        data.insert::<StopWords>(stop_words.clone());
+        data.insert::<BlockedWords>(blocklist.clone());
+        data.insert::<SentMessages>(sent_log.clone());
+        data.insert::<StartupTime>(startup_time);
+        data.insert::<Messaging>(client.http.clone());
+        data.insert::<DiscordCache>(client.cache.clone());
+
+        if let Some(url) = options.audit_webhook_url.clone() {
+            data.insert::<AuditLog>(audit::Auditor::new(url));
+        }
+        data.insert::<ActivityCounters>(Arc::new(RwLock::new(Activity::default())));
+        data.insert::<ChannelActivity>(Arc::new(RwLock::new(HashMap::new())));
+        data.insert::<MetricsRegistry>(metrics.clone());
+        data.insert::<GatewayHealth>(gateway.clone());
+        data.insert::<ReadySession>(Arc::new(RwLock::new(None)));
+        data.insert::<VetoVotes>(Arc::new(RwLock::new(veto::Vetoes::new(options.veto_count))));
+        data.insert::<SnapshotHistory>(snapshot_history.clone());
+        data.insert::<GuildSettings>(guild_settings.clone());
+        data.insert::<Schedule>(scheduler.clone());
+        data.insert::<ScheduleChanged>(schedule_changed.clone());
+        data.insert::<Random>(rng.clone());
+        data.insert::<Paused>(Arc::new(RwLock::new(pause::Pauses::default())));
+        data.insert::<ScheduledPosts>(Arc::new(RwLock::new(
+            scheduled_posts::ScheduledPosts::default(),
+        )));
+        data.insert::<commands::CommandCooldowns>(Arc::new(RwLock::new(
+            commands::Cooldowns::default(),
+        )));
+        data.insert::<commands::UserCooldowns>(Arc::new(RwLock::new(
+            command_guard::CommandGuard::default(),
+        )));
+
+        data.insert::<WordcloudSettings>(wordcloud);
+        data.insert::<EmojiMap>(emoji_map.clone());
+
+        WEBHOOK_MODE.set(options.webhook_url.is_some()).unwrap();
+        if let Some(url) = &options.webhook_url {
+            WEBHOOK_URL.set(url.clone()).unwrap();
+        }
+
+        if let Some(limit) = options.rate_limit_words {
+            RATE_LIMIT_WORDS.set(limit).unwrap();
+            data.insert::<RateLimitMap>(Arc::new(RwLock::new(rate_limit::RateLimits::new())));
+        }
+
+        if options.reply_style == reply_context::ReplyStyle::Reference {
+            let mentions = reply_context::LastMentions::default();
+            data.insert::<WordMentions>(Arc::new(RwLock::new(mentions)));
+        }
+
+        if options.self_echo != self_echo::SelfEcho::Full {
+            let filter = self_echo::EchoFilter::new(options.self_echo);
+            data.insert::<SelfEchoes>(Arc::new(RwLock::new(filter)));
+        }
+
+        if let Some(channel) = options.welcome_channel {
+            data.insert::<WelcomeSettings>(welcome::Welcome {
+                channel: ChannelId::from(channel),
+                template: options.welcome_template.clone(),
+            });
+        }
+
+        if options.track_cooccurrence {
+            data.insert::<Cooccurrences>(Arc::new(RwLock::new(
+                cooccurrence::GuildCooccurrences::new(),
+            )));
+        }
+
+        data.insert::<WeeklySummaries>(Arc::new(RwLock::new(
+            pino_core::summary::WeeklyStats::default(),
+        )));
+        data.insert::<WordOfTheWeek>(Arc::new(RwLock::new(voting::Elections::default())));
+
+        if options.heatmap {
+            data.insert::<ActivityHeatmap>(Arc::new(RwLock::new([[0; 24]; 7])));
+        }
+
+        if let Some(owner) = owner {
+            data.insert::<Owner>(owner);
+        }
+
+        #[cfg(feature = "sqlite")]
+        if let Some(database) = database {
+            // The writer thread runs until the handle is removed on shutdown
+            let (handle, thread) = database.spawn_writer();
+            data.insert::<DbWriter>(handle);
+            db_writer = Some(thread);
+        }
+    }
+
+    {
+        let message_map = message_map.clone();
+
+        supervisor.spawn(&mut shutdown, "snapshots", move |mut stop| {
+            let message_map = message_map.clone();
+            let snapshot_history = snapshot_history.clone();
+
+            async move {
+                loop {
+                    tokio::select! {
+                        _ = tokio::time::sleep(Duration::minutes(1).to_std().unwrap()) => {}
+                        _ = shutdown::requested(&mut stop) => break,
+                    }
+
+                    let counts = snapshots::counts(&*message_map.read().await);
+                    snapshot_history.write().unwrap().push(Utc::now(), counts);
+                }
+            }
+        });
+    }
+
+    if let Some(listener) = metrics_listener {
+        let message_map = message_map.clone();
+
+        info!(
+            "Serving metrics on http://{}/metrics",
+            listener.local_addr()?
+        );
+
+        supervisor.spawn(&mut shutdown, "metrics", move |stop| {
+            let listener = listener.try_clone();
+            let metrics = metrics.clone();
+            let message_map = message_map.clone();
+
+            async move {
+                let result = match listener {
+                    Ok(listener) => metrics::serve(listener, metrics, message_map, stop).await,
+                    Err(e) => Err(e.into()),
+                };
+
+                if let Err(e) = result {
+                    error!("Error serving metrics: {:#}", e);
+                }
+            }
+        });
+    }
+
+    if let Some(listener) = health_listener {
+        let probes = health::Probes {
+            started: startup_time,
+            gateway,
+            supervisor: supervisor.clone(),
+            heartbeat: Duration::minutes(options.heartbeat_minutes as i64),
+        };
+
+        info!(
+            "Serving health probes on http://{}/healthz and /readyz",
+            listener.local_addr()?
+        );
+
+        supervisor.spawn(&mut shutdown, "health probes", move |stop| {
+            let listener = listener.try_clone();
+            let probes = probes.clone();
+
+            async move {
+                let result = match listener {
+                    Ok(listener) => health::serve(listener, probes, stop).await,
+                    Err(e) => Err(e.into()),
+                };
+
+                if let Err(e) = result {
+                    error!("Error serving health probes: {:#}", e);
+                }
+            }
+        });
+    }
+
+    #[cfg(unix)]
+    if options.config.is_some() || options.stop_words_file.is_some() {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let sources = reload::Sources {
+            config_file: options.config.clone(),
+            stop_words_file: options.stop_words_file.clone(),
+            given: runtime_config::KEYS
+                .iter()
+                .copied()
+                .filter(|key| matches.occurrences_of(key.replace('_', "-")) > 0)
+                .collect(),
+        };
+
+        // Shared, so that a restarted task keeps listening on the same stream
+        let hangup = signal(SignalKind::hangup()).context("listening for SIGHUP")?;
+        let hangup = Arc::new(tokio::sync::Mutex::new(hangup));
+        let base_config = base_config.clone();
+        let config = config.clone();
+
+        supervisor.spawn(&mut shutdown, "reload", move |mut stop| {
+            let sources = sources.clone();
+            let base_config = base_config.clone();
+            let config = config.clone();
+            let stop_words = stop_words.clone();
+            let hangup = hangup.clone();
+
+            async move {
+                let mut hangup = hangup.lock().await;
+
+                loop {
+                    tokio::select! {
+                        received = hangup.recv() => {
+                            if received.is_none() {
+                                break;
+                            }
+                        }
+                        _ = shutdown::requested(&mut stop) => break,
+                    }
+
+                    let live = reload::Live {
+                        base: &base_config,
+                        config: &config,
+                        stop_words: &stop_words,
+                    };
+                    reload_files(&sources, &live);
+                }
+            }
+        });
+    }
+
+    let persisted = options.state_file.clone().map(|path| {
+        let state = PersistedState {
+            message_map: message_map.clone(),
+            config: config.clone(),
+            base_config: base_config.clone(),
+            blocklist: blocklist.clone(),
+            guild_settings,
+            sent_log,
+        };
+
+        (path, state)
+    });
+
+    if let Some((path, state)) = persisted.clone() {
+        let save_interval = options.save_interval;
+
+        supervisor.spawn(&mut shutdown, "periodic saves", move |mut stop| {
+            let path = path.clone();
+            let state = state.clone();
+
+            async move {
+                loop {
+                    tokio::select! {
+                        _ = tokio::time::sleep(
+                            Duration::seconds(save_interval as i64).to_std().unwrap(),
+                        ) => {}
+                        _ = shutdown::requested(&mut stop) => break,
+                    }
+
+                    save_state(&path, &state).await;
+                }
+            }
+        });
+    }
+
+    {
+        let trigger = shutdown.trigger();
+        let shard_manager = client.shard_manager.clone();
+
+        tokio::spawn(async move {
+            if let Err(e) = shutdown::signal().await {
+                error!("Error listening for ctrl-c: {}", e);
+                return;
+            }
+
+            info!("Shutting down 🦜");
+            trigger.fire();
+            // Makes `client.start` return
+            shard_manager.shutdown_all().await;
+        });
+    }
+
+    let http = client.http.clone();
+    let data = client.data.clone();
+
+    if let (Some(owner), true) = (owner, options.error_alerts > 0) {
+        let (reporter, alerts) = error_report::Reporter::new(options.error_alerts);
+        data.write().await.insert::<ErrorReports>(reporter);
+
+        // Shared, so that a restarted task keeps the alerts not sent yet
+        let alerts = Arc::new(tokio::sync::Mutex::new(alerts));
+        let http = http.clone();
+        let lang = DEFAULT_SETTINGS.get().unwrap().language.unwrap_or_default();
+
+        supervisor.spawn(&mut shutdown, "error alerts", move |mut stop| {
+            let alerts = alerts.clone();
+            let http = http.clone();
+
+            async move {
+                let mut alerts = alerts.lock().await;
+
+                loop {
+                    let alert = tokio::select! {
+                        alert = alerts.recv() => match alert {
+                            Some(alert) => alert,
+                            None => break,
+                        },
+                        _ = shutdown::requested(&mut stop) => break,
+                    };
+
+                    error_report::notify_owner(&*http, owner, &alert, lang).await;
+                }
+            }
+        });
+    }
+
+    {
+        let data = data.clone();
+
+        supervisor.spawn(&mut shutdown, "planned posts", move |mut stop| {
+            let data = data.clone();
+
+            async move {
+                loop {
+                    tokio::select! {
+                        _ = tokio::time::sleep(Duration::minutes(1).to_std().unwrap()) => {}
+                        _ = shutdown::requested(&mut stop) => break,
+                    }
+
+                    let data_read = data.read().await;
+                    let now = Utc::now();
+
+                    let due = {
+                        let pauses = data_read
+                            .get::<Paused>()
+                            .expect("Paused to be in data/context")
+                            .read()
+                            .unwrap();
+
+                        data_read
+                            .get::<ScheduledPosts>()
+                            .expect("ScheduledPosts to be in data/context")
+                            .write()
+                            .unwrap()
+                            .take_due(now, &pauses)
+                    };
+
+                    for post in due {
+                        let message = MessageBuilder::new().push(&post.word).build();
+                        let destination = Destination::Channel(
+                            ChannelId::new(post.channel_id.get()),
+                            post.guild_id.map(|id| GuildId::new(id.get())),
+                        );
+
+                        if let Err(e) = send_and_record(
+                            &data_read,
+                            destination,
+                            &post.word,
+                            &message,
+                            sent_log::SendKind::Planned,
+                        )
+                        .await
+                        {
+                            error!("Error sending planned post: {:#}", e);
+                        } else {
+                            info!("Posted planned word '{}' 🦜", post.word);
+                        }
+                    }
+                }
+            }
+        });
+    }
+
+    if let Some(channel) = options.summary_channel {
+        let http = http.clone();
+        let data = data.clone();
+        let channel = ChannelId::from(channel);
+
+        supervisor.spawn(&mut shutdown, "weekly summary", move |mut stop| {
+            let http = http.clone();
+            let data = data.clone();
+
+            async move {
+                loop {
+                    let due = pino_core::summary::next_week(Utc::now());
+
+                    tokio::select! {
+                        _ = tokio::time::sleep(
+                            (due - Utc::now()).to_std().unwrap_or_default(),
+                        ) => {}
+                        _ = shutdown::requested(&mut stop) => break,
+                    }
+
+                    post_summary(
+                        &http,
+                        &*data.read().await,
+                        channel,
+                        due - Duration::weeks(1),
+                    )
+                    .await;
+                }
+            }
+        });
+    }
+
+    // Shared with every restart of the send loop
+    let options = Arc::new(options);
+
+    supervisor.spawn(&mut shutdown, "send loop", move |mut stop| {
+        let data = data.clone();
+        let options = options.clone();
+        let emoji_map = emoji_map.clone();
+        let scheduler = scheduler.clone();
+        let schedule_changed = schedule_changed.clone();
+
+        async move {
+            loop {
+                let remaining = scheduler.read().unwrap().remaining(Utc::now());
+
+                match remaining {
+                    Some(remaining) => info!("Sending message in {} seconds", remaining.as_secs()),
+                    None => info!("Nobody talked yet, waiting for someone to"),
+                }
+
+                // Without guilds there's nothing to wait for but a change
+                let due = async {
+                    match remaining {
+                        Some(remaining) => tokio::time::sleep(remaining).await,
+                        None => std::future::pending().await,
+                    }
+                };
+
+                tokio::select! {
+                    _ = due => {}
+                    _ = schedule_changed.notified() => continue,
+                    _ = shutdown::requested(&mut stop) => break,
+                }
+
+                send_cycle(
+                    &*data.read().await,
+                    &emoji_map,
+                    options.webhook_url.as_deref(),
+                    options.max_words,
+                )
+                .instrument(info_span!("send_cycle"))
+                .await;
+            }
+        }
+    });
+
+    // The background tasks above run once, whatever the number of shards
+    let result = match sharding {
+        sharding::Sharding::Single => client.start().await,
+        sharding::Sharding::All(total) => client.start_shards(total).await,
+        // Serenity's range includes its end
+        sharding::Sharding::Range(range) => {
+            client
+                .start_shard_range(range.start..range.end - 1, range.total)
+                .await
+        }
+    }
+    .map_err(start_error);
+
+    if !shutdown.stop(shutdown::TASKS_TIMEOUT).await {
+        warn!("Some background tasks didn't stop in time");
+    }
+
+    if let Some((path, state)) = &persisted {
+        save_state(path, state).await;
+    }
+
+    #[cfg(feature = "sqlite")]
+    if let Some(thread) = db_writer {
+        // Dropping the last handle lets the thread write what's queued and exit
+        client.data.write().await.remove::<DbWriter>();
+
+        if thread.join().is_err() {
+            error!("The database writer panicked");
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn own_messages() {
+        let pino = UserId::new(42);
+        let mut msg = Message::default();
+
+        msg.author.id = pino;
+        assert!(is_own_message(&msg, pino));
+
+        msg.author.id = UserId::new(7);
+        assert!(!is_own_message(&msg, pino));
+    }
+
+    #[tokio::test]
+    async fn repeated_ready() {
+        let mut data = TypeMap::new();
+        let metrics = Arc::new(metrics::Metrics::default());
+        let recent = Arc::new(tokio::sync::RwLock::new(
+            recent_channels::RecentChannels::default(),
+        ));
+        let paused = Arc::new(RwLock::new(pause::Pauses::default()));
+        let schedule = Arc::new(RwLock::new(schedule::Deadlines::with_warmup(
+            Duration::minutes(10),
+        )));
+
+        data.insert::<GatewayHealth>(Arc::new(RwLock::new(health::Gateway::new(0..2))));
+        data.insert::<ReadySession>(Arc::new(RwLock::new(None)));
+        data.insert::<MetricsRegistry>(metrics.clone());
+        data.insert::<RecentChannel>(recent.clone());
+        data.insert::<Paused>(paused.clone());
+        data.insert::<Schedule>(schedule.clone());
+
+        let first = Utc::now();
+        let channel = (ChannelId::new(7), Some(GuildId::new(1)));
+        recent.write().await.touch(channel.0, channel.1);
+        paused
+            .write()
+            .unwrap()
+            .pause(guild_key(channel.1), first, None);
+
+        assert!(!on_ready(&data, 0, UserId::new(42), 3, first));
+
+        let later = first + Duration::minutes(5);
+        // another shard connecting isn't a reconnection
+        assert!(!on_ready(&data, 1, UserId::new(42), 2, later));
+        assert!(on_ready(&data, 0, UserId::new(43), 4, later));
+        assert!(on_ready(&data, 0, UserId::new(43), 4, later));
+
+        let session = data
+            .get::<ReadySession>()
+            .unwrap()
+            .read()
+            .unwrap()
+            .clone()
+            .unwrap();
+        assert_eq!(first, session.started);
+        assert_eq!(UserId::new(43), session.bot_id);
+        assert_eq!(6, session.guilds());
+        // the warmup is counted from the first one
+        assert!(schedule.read().unwrap().warming_up(later));
+        assert!(!schedule
+            .read()
+            .unwrap()
+            .warming_up(first + Duration::minutes(10)));
+
+        on_resume(&data, 1, later);
+        let text = metrics.render(&WordStore::new());
+        let lines: Vec<_> = text.lines().collect();
+        assert!(lines.contains(&"pino_gateway_reconnects_total{shard=\"0\"} 2"));
+        assert!(lines.contains(&"pino_gateway_reconnects_total{shard=\"1\"} 1"));
+
+        let gateway = data.get::<GatewayHealth>().unwrap().read().unwrap().clone();
+        assert_eq!(None, gateway.not_ready(later, Duration::minutes(10)));
+
+        // what pino was doing survives
+        assert_eq!(Some(channel), recent.read().await.current());
+        assert!(paused
+            .read()
+            .unwrap()
+            .is_paused(guild_key(channel.1), later));
+    }
+
+    /// What a send cycle needs, with pino talking to `recorder`. Guilds get a
+    /// message a minute after someone talks there, and then every minute.
+    fn send_data(recorder: Arc<messenger::Recorder>) -> TypeMap {
+        DEFAULT_SETTINGS.get_or_init(guild_settings::Settings::default);
+
+        let config = runtime_config::RuntimeConfig {
+            interval_low: 60,
+            interval_high: 60,
+            max_age: 3600,
+            max_boost: 0,
+        };
+        let mut data = TypeMap::new();
+
+        data.insert::<Messaging>(recorder);
+        data.insert::<Config>(Arc::new(RwLock::new(config)));
+        data.insert::<Schedule>(Arc::new(RwLock::new(schedule::Deadlines::default())));
+        data.insert::<ScheduleChanged>(Arc::new(Notify::new()));
+        data.insert::<Random>(Arc::new(Mutex::new(StdRng::seed_from_u64(1))));
+        data.insert::<RecentChannel>(Arc::new(tokio::sync::RwLock::new(
+            recent_channels::RecentChannels::default(),
+        )));
+        data.insert::<GuildSettings>(Arc::new(RwLock::new(guild_config::GuildConfigs::default())));
+        data.insert::<MessageMap>(Arc::new(tokio::sync::RwLock::new(WordStore::new())));
+        data.insert::<BlockedWords>(Arc::new(RwLock::new(blocklist::Blocklist::default())));
+        data.insert::<MetricsRegistry>(Arc::new(metrics::Metrics::default()));
+        data.insert::<Paused>(Arc::new(RwLock::new(pause::Pauses::default())));
+        data.insert::<SentMessages>(Arc::new(RwLock::new(sent_log::SentLog::default())));
+        data.insert::<ActivityCounters>(Arc::new(RwLock::new(Activity::default())));
+        data.insert::<EmojiMap>(Arc::new(HashMap::new()));
+
+        data
+    }
+
+    async fn hear(data: &TypeMap, guild: Option<GuildId>, words: &[&str], time: DateTime<Utc>) {
+        let mut map = data.get::<MessageMap>().unwrap().write().await;
+
+        for word in words {
+            map.learn(guild_key(guild), word.to_string(), time);
+        }
+    }
+
+    async fn cycle(data: &TypeMap) {
+        send_cycle(data, &HashMap::new(), None, None).await;
+    }
+
+    /// Someone talked in `channel` a while ago, so it's its guild's turn.
+    async fn talk(data: &TypeMap, channel: ChannelId, guild: Option<GuildId>) {
+        data.get::<RecentChannel>()
+            .unwrap()
+            .write()
+            .await
+            .touch(channel, guild);
+        // the delay is a minute, plus up to 15 seconds
+        heard_in(data, guild, Utc::now() - Duration::minutes(2));
+    }
+
+    #[tokio::test]
+    async fn send_cycles() {
+        let recorder = Arc::new(messenger::Recorder::default());
+        let data = send_data(recorder.clone());
+        let channel = ChannelId::new(7);
+        let guild = GuildId::new(1);
+
+        // nowhere to talk yet
+        hear(&data, Some(guild), &["ciao", "ciao", "pizza"], Utc::now()).await;
+        cycle(&data).await;
+        assert!(recorder.recorded().is_empty());
+
+        data.get::<GuildSettings>()
+            .unwrap()
+            .write()
+            .unwrap()
+            .get_mut(guild)
+            .settings
+            .set("message_template", "{word} ({count})")
+            .unwrap();
+        talk(&data, channel, Some(guild)).await;
+
+        cycle(&data).await;
+        assert_eq!(vec![(channel, "ciao (2)".to_owned())], recorder.texts());
+
+        let sent = data
+            .get::<SentMessages>()
+            .unwrap()
+            .read()
+            .unwrap()
+            .last(Some(guild.get()), 1)
+            .next()
+            .cloned()
+            .unwrap();
+        assert_eq!("ciao", sent.word);
+        assert_eq!(Some(channel.get()), sent.channel_id);
+        assert_eq!(Some(1), sent.message_id);
+        assert_eq!(
+            1,
+            data.get::<ActivityCounters>()
+                .unwrap()
+                .read()
+                .unwrap()
+                .posts
+        );
+
+        // not due for another minute, and speaking on demand only starts over
+        cycle(&data).await;
+        spoke_in(&data, Some(guild), Utc::now());
+        cycle(&data).await;
+        assert_eq!(1, recorder.texts().len());
+
+        // another guild has its own timer, its own channel, its own settings
+        // and its own words
+        let other = ChannelId::new(8);
+        hear(&data, Some(GuildId::new(2)), &["pizza"], Utc::now()).await;
+        talk(&data, other, Some(GuildId::new(2))).await;
+        cycle(&data).await;
+        cycle(&data).await;
+        assert_eq!(
+            vec![
+                (channel, "ciao (2)".to_owned()),
+                (other, "pizza".to_owned())
+            ],
+            recorder.texts()
+        );
+
+        left_guild(&data, guild).await;
+        let schedule = data.get::<Schedule>().unwrap().read().unwrap();
+        assert_eq!(None, schedule.next_for(guild_key(Some(guild))));
+        assert_eq!(1, schedule.len());
+    }
+
+    #[tokio::test]
+    async fn speaking_on_demand() {
+        let recorder = Arc::new(messenger::Recorder::default());
+        let data = send_data(recorder.clone());
+        let channel = ChannelId::new(7);
+        let guild = GuildId::new(1);
+
+        assert!(!speak_in(&data, channel, Some(guild)).await);
+
+        hear(&data, Some(guild), &["ciao", "ciao", "pizza"], Utc::now()).await;
+        data.get::<GuildSettings>()
+            .unwrap()
+            .write()
+            .unwrap()
+            .get_mut(guild)
+            .settings
+            .set("message_template", "{word} ({count})")
+            .unwrap();
+
+        // the same message as a scheduled one
+        assert!(speak_in(&data, channel, Some(guild)).await);
+        assert_eq!(vec![(channel, "ciao (2)".to_owned())], recorder.texts());
+
+        let sent = data
+            .get::<SentMessages>()
+            .unwrap()
+            .read()
+            .unwrap()
+            .last(Some(guild.get()), 1)
+            .next()
+            .cloned()
+            .unwrap();
+        assert_eq!(sent_log::SendKind::Forced, sent.kind);
+    }
+
+    #[tokio::test]
+    async fn idle_guilds() {
+        let recorder = Arc::new(messenger::Recorder::default());
+        let data = send_data(recorder.clone());
+        let guild = Some(GuildId::new(1));
+
+        hear(&data, guild, &["ciao"], Utc::now()).await;
+        data.get::<RecentChannel>()
+            .unwrap()
+            .write()
+            .await
+            .touch(ChannelId::new(7), guild);
+        // longer ago than max_age
+        heard_in(&data, guild, Utc::now() - Duration::hours(2));
+
+        cycle(&data).await;
+        assert!(recorder.recorded().is_empty());
+        assert!(data.get::<Schedule>().unwrap().read().unwrap().is_empty());
+
+        // back once someone talks
+        talk(&data, ChannelId::new(7), guild).await;
+        cycle(&data).await;
+        assert_eq!(1, recorder.texts().len());
+    }
+
+    #[tokio::test]
+    async fn repeated_errors() {
+        let mut data = send_data(Arc::new(messenger::Recorder::failing()));
+        let (reporter, mut alerts) = error_report::Reporter::new(1);
+        data.insert::<ErrorReports>(reporter);
+        let guild = Some(GuildId::new(1));
+
+        hear(&data, guild, &["ciao"], Utc::now()).await;
+        talk(&data, ChannelId::new(7), guild).await;
+        cycle(&data).await;
+        assert!(alerts.try_recv().is_err());
+
+        // due again
+        data.get::<Schedule>().unwrap().write().unwrap().reschedule(
+            guild_key(guild),
+            Utc::now() - Duration::minutes(2),
+            Duration::minutes(1),
+        );
+        cycle(&data).await;
+
+        let alert = alerts.try_recv().unwrap();
+        assert_eq!(Some(1), alert.guild);
+        assert_eq!(delivery::Failure::Other, alert.failure);
+        assert_eq!(2, alert.count);
+    }
+
+    #[tokio::test]
+    async fn quiet_send_cycles() {
+        let recorder = Arc::new(messenger::Recorder::default());
+        let data = send_data(recorder.clone());
+        let now = Utc::now();
+
+        talk(&data, ChannelId::new(7), None).await;
+        hear(&data, None, &["pizza"], now - Duration::hours(2)).await;
+        hear(&data, None, &["ciao"], now).await;
+        data.get::<Paused>()
+            .unwrap()
+            .write()
+            .unwrap()
+            .pause(None, now, None);
+
+        cycle(&data).await;
+        assert!(recorder.recorded().is_empty());
+
+        // the words older than max_age are forgotten all the same
+        let words = data.get::<MessageMap>().unwrap().read().await;
+        assert_eq!(vec!["ciao"], words.guild(None).keys().collect::<Vec<_>>());
+    }
+
+    /// Pausing pino in a guild leaves the others alone.
+    #[tokio::test]
+    async fn paused_guilds() {
+        let recorder = Arc::new(messenger::Recorder::default());
+        let data = send_data(recorder.clone());
+        let (paused, other) = (Some(GuildId::new(1)), Some(GuildId::new(2)));
+
+        data.get::<Paused>()
+            .unwrap()
+            .write()
+            .unwrap()
+            .pause(guild_key(paused), Utc::now(), None);
+        hear(&data, paused, &["ciao"], Utc::now()).await;
+        hear(&data, other, &["pizza"], Utc::now()).await;
+        talk(&data, ChannelId::new(7), paused).await;
+        talk(&data, ChannelId::new(8), other).await;
+
+        cycle(&data).await;
+        cycle(&data).await;
+        assert_eq!(
+            vec![(ChannelId::new(8), "pizza".to_owned())],
+            recorder.texts()
+        );
+    }
+
+    #[tokio::test]
+    async fn replying_send_cycles() {
+        let recorder = Arc::new(messenger::Recorder::default());
+        let mut data = send_data(recorder.clone());
+        let channel = ChannelId::new(7);
+        let guild = Some(GuildId::new(1));
+        let mut mentions = reply_context::LastMentions::default();
+        let words = vec!["pizza".to_owned()];
+        mentions.record(guild, &words, channel, MessageId::new(42), Utc::now());
+        data.insert::<WordMentions>(Arc::new(RwLock::new(mentions)));
+
+        hear(&data, guild, &["pizza"], Utc::now()).await;
+        talk(&data, channel, guild).await;
+        cycle(&data).await;
+        assert_eq!(
+            vec![messenger::Recorded::Reply(
+                channel,
+                MessageId::new(42),
+                "pizza".to_owned()
+            )],
+            recorder.recorded()
+        );
+
+        // said somewhere else, it's posted on its own
+        let other = ChannelId::new(8);
+        let other_guild = Some(GuildId::new(2));
+        hear(&data, other_guild, &["pizza"], Utc::now()).await;
+        talk(&data, other, other_guild).await;
+        cycle(&data).await;
+        assert_eq!(
+            Some(&messenger::Recorded::Text(other, "pizza".to_owned())),
+            recorder.recorded().last()
+        );
+    }
+
+    #[tokio::test]
+    async fn word_of_the_week() {
+        let recorder = messenger::Recorder::default();
+        let channel = ChannelId::new(7);
+
+        post_word_of_the_week(&recorder, channel, i18n::Lang::En, "pizza", 3).await;
+
+        assert_eq!(
+            vec![
+                messenger::Recorded::Text(
+                    channel,
+                    "🏆 The word of the week is **pizza**, with 3 votes!".to_owned()
+                ),
+                messenger::Recorded::Pin(channel, MessageId::new(1)),
+            ],
+            recorder.recorded()
+        );
+    }
 }
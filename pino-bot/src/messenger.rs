@@ -0,0 +1,369 @@
+use serenity::{
+    async_trait,
+    builder::{CreateActionRow, CreateAllowedMentions, CreateEmbed, CreateMessage, EditMessage},
+    http::Http,
+    model::{
+        channel::ReactionType,
+        id::{ChannelId, MessageId, UserId},
+    },
+};
+
+/// Everything pino says on Discord goes through here, so that the send loop,
+/// the commands and the weekly summary can run without Discord in the tests.
+#[async_trait]
+pub trait Messenger: Send + Sync {
+    /// Send `content` in `channel`, returning the id of the message.
+    async fn send_text(&self, channel: ChannelId, content: &str) -> serenity::Result<MessageId>;
+
+    /// Send `content` in `channel` as a reply to `message`, without pinging
+    /// its author.
+    async fn send_reply(
+        &self,
+        channel: ChannelId,
+        message: MessageId,
+        content: &str,
+    ) -> serenity::Result<MessageId>;
+
+    async fn send_embed(
+        &self,
+        channel: ChannelId,
+        embed: CreateEmbed,
+    ) -> serenity::Result<MessageId>;
+
+    /// Send `embed` in `channel` with buttons or menus under it.
+    async fn send_components(
+        &self,
+        channel: ChannelId,
+        embed: CreateEmbed,
+        components: Vec<CreateActionRow>,
+    ) -> serenity::Result<MessageId>;
+
+    /// Take away the buttons and menus of `message`, e.g. once they expire.
+    async fn remove_components(
+        &self,
+        channel: ChannelId,
+        message: MessageId,
+    ) -> serenity::Result<()>;
+
+    async fn react(
+        &self,
+        channel: ChannelId,
+        message: MessageId,
+        reaction: ReactionType,
+    ) -> serenity::Result<()>;
+
+    /// Show pino as typing in `channel` for a few seconds, or until it sends
+    /// something.
+    async fn broadcast_typing(&self, channel: ChannelId) -> serenity::Result<()>;
+
+    async fn pin(&self, channel: ChannelId, message: MessageId) -> serenity::Result<()>;
+
+    /// Send `content` to `user` in private, returning the id of the message.
+    async fn direct_message(&self, user: UserId, content: &str) -> serenity::Result<MessageId>;
+}
+
+#[async_trait]
+impl Messenger for Http {
+    async fn send_text(&self, channel: ChannelId, content: &str) -> serenity::Result<MessageId> {
+        Ok(channel.say(self, content).await?.id)
+    }
+
+    async fn send_reply(
+        &self,
+        channel: ChannelId,
+        message: MessageId,
+        content: &str,
+    ) -> serenity::Result<MessageId> {
+        let reply = CreateMessage::new()
+            .content(content)
+            .reference_message((channel, message))
+            .allowed_mentions(CreateAllowedMentions::new().replied_user(false));
+
+        Ok(channel.send_message(self, reply).await?.id)
+    }
+
+    async fn send_embed(
+        &self,
+        channel: ChannelId,
+        embed: CreateEmbed,
+    ) -> serenity::Result<MessageId> {
+        let message = CreateMessage::new().embed(embed);
+
+        Ok(channel.send_message(self, message).await?.id)
+    }
+
+    async fn send_components(
+        &self,
+        channel: ChannelId,
+        embed: CreateEmbed,
+        components: Vec<CreateActionRow>,
+    ) -> serenity::Result<MessageId> {
+        let message = CreateMessage::new().embed(embed).components(components);
+
+        Ok(channel.send_message(self, message).await?.id)
+    }
+
+    async fn remove_components(
+        &self,
+        channel: ChannelId,
+        message: MessageId,
+    ) -> serenity::Result<()> {
+        let edit = EditMessage::new().components(Vec::new());
+
+        channel.edit_message(self, message, edit).await.map(drop)
+    }
+
+    async fn react(
+        &self,
+        channel: ChannelId,
+        message: MessageId,
+        reaction: ReactionType,
+    ) -> serenity::Result<()> {
+        channel.create_reaction(self, message, reaction).await
+    }
+
+    async fn broadcast_typing(&self, channel: ChannelId) -> serenity::Result<()> {
+        channel.broadcast_typing(self).await
+    }
+
+    async fn pin(&self, channel: ChannelId, message: MessageId) -> serenity::Result<()> {
+        channel.pin(self, message).await
+    }
+
+    async fn direct_message(&self, user: UserId, content: &str) -> serenity::Result<MessageId> {
+        let message = CreateMessage::new().content(content);
+
+        Ok(user.direct_message(self, message).await?.id)
+    }
+}
+
+/// What a [`Recorder`] was asked to do.
+#[cfg(test)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Recorded {
+    Text(ChannelId, String),
+    /// A reply to the message.
+    Reply(ChannelId, MessageId, String),
+    Embed(ChannelId, Box<CreateEmbed>),
+    Components(ChannelId, Box<CreateEmbed>, Vec<CreateActionRow>),
+    ComponentsRemoved(ChannelId, MessageId),
+    Reaction(ChannelId, MessageId, ReactionType),
+    Typing(ChannelId),
+    Pin(ChannelId, MessageId),
+    Direct(UserId, String),
+}
+
+/// Keeps everything sent in memory instead of sending it. Messages get the
+/// ids 1, 2, 3 and so on.
+#[cfg(test)]
+#[derive(Debug, Default)]
+pub struct Recorder {
+    recorded: std::sync::Mutex<Vec<Recorded>>,
+    messages: std::sync::atomic::AtomicU64,
+    /// Fail everything instead, recording nothing.
+    failing: bool,
+    /// Fail only the replies, like Discord when the message was deleted.
+    refusing_replies: bool,
+}
+
+#[cfg(test)]
+impl Recorder {
+    /// A recorder failing everything, like Discord when pino lost its
+    /// permissions.
+    pub fn failing() -> Self {
+        Self {
+            failing: true,
+            ..Self::default()
+        }
+    }
+
+    /// A recorder failing the replies only.
+    pub fn refusing_replies() -> Self {
+        Self {
+            refusing_replies: true,
+            ..Self::default()
+        }
+    }
+
+    /// Everything recorded so far, oldest first.
+    pub fn recorded(&self) -> Vec<Recorded> {
+        self.recorded.lock().unwrap().clone()
+    }
+
+    /// The texts sent so far, with their channel.
+    pub fn texts(&self) -> Vec<(ChannelId, String)> {
+        self.recorded()
+            .into_iter()
+            .filter_map(|recorded| match recorded {
+                Recorded::Text(channel, content) => Some((channel, content)),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Keep `recorded`, `None` if failing.
+    fn record(&self, recorded: Recorded) -> Option<()> {
+        if self.failing {
+            return None;
+        }
+
+        self.recorded.lock().unwrap().push(recorded);
+        Some(())
+    }
+
+    fn message(&self, recorded: Recorded) -> Option<MessageId> {
+        use std::sync::atomic::Ordering;
+
+        self.record(recorded)?;
+        Some(MessageId::new(
+            self.messages.fetch_add(1, Ordering::Relaxed) + 1,
+        ))
+    }
+}
+
+/// What a failing [`Recorder`] returns.
+#[cfg(test)]
+fn failure() -> serenity::Error {
+    serenity::Error::Other("the recorder is failing")
+}
+
+#[cfg(test)]
+#[async_trait]
+impl Messenger for Recorder {
+    async fn send_text(&self, channel: ChannelId, content: &str) -> serenity::Result<MessageId> {
+        self.message(Recorded::Text(channel, content.to_owned()))
+            .ok_or_else(failure)
+    }
+
+    async fn send_reply(
+        &self,
+        channel: ChannelId,
+        message: MessageId,
+        content: &str,
+    ) -> serenity::Result<MessageId> {
+        if self.refusing_replies {
+            return Err(failure());
+        }
+
+        self.message(Recorded::Reply(channel, message, content.to_owned()))
+            .ok_or_else(failure)
+    }
+
+    async fn send_embed(
+        &self,
+        channel: ChannelId,
+        embed: CreateEmbed,
+    ) -> serenity::Result<MessageId> {
+        self.message(Recorded::Embed(channel, Box::new(embed)))
+            .ok_or_else(failure)
+    }
+
+    async fn send_components(
+        &self,
+        channel: ChannelId,
+        embed: CreateEmbed,
+        components: Vec<CreateActionRow>,
+    ) -> serenity::Result<MessageId> {
+        self.message(Recorded::Components(channel, Box::new(embed), components))
+            .ok_or_else(failure)
+    }
+
+    async fn remove_components(
+        &self,
+        channel: ChannelId,
+        message: MessageId,
+    ) -> serenity::Result<()> {
+        self.record(Recorded::ComponentsRemoved(channel, message))
+            .ok_or_else(failure)
+    }
+
+    async fn react(
+        &self,
+        channel: ChannelId,
+        message: MessageId,
+        reaction: ReactionType,
+    ) -> serenity::Result<()> {
+        self.record(Recorded::Reaction(channel, message, reaction))
+            .ok_or_else(failure)
+    }
+
+    async fn broadcast_typing(&self, channel: ChannelId) -> serenity::Result<()> {
+        self.record(Recorded::Typing(channel)).ok_or_else(failure)
+    }
+
+    async fn pin(&self, channel: ChannelId, message: MessageId) -> serenity::Result<()> {
+        self.record(Recorded::Pin(channel, message))
+            .ok_or_else(failure)
+    }
+
+    async fn direct_message(&self, user: UserId, content: &str) -> serenity::Result<MessageId> {
+        self.message(Recorded::Direct(user, content.to_owned()))
+            .ok_or_else(failure)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn recording() {
+        let recorder = Recorder::default();
+        let channel = ChannelId::new(7);
+
+        assert_eq!(
+            MessageId::new(1),
+            recorder.send_text(channel, "ciao").await.unwrap()
+        );
+        let embed = CreateEmbed::new().title("pino");
+        let second = recorder.send_embed(channel, embed.clone()).await.unwrap();
+        assert_eq!(MessageId::new(2), second);
+        recorder
+            .react(channel, second, ReactionType::Unicode("🦜".to_owned()))
+            .await
+            .unwrap();
+        recorder.broadcast_typing(channel).await.unwrap();
+        recorder.pin(channel, second).await.unwrap();
+        assert_eq!(
+            MessageId::new(3),
+            recorder
+                .direct_message(UserId::new(42), "psst")
+                .await
+                .unwrap()
+        );
+
+        assert_eq!(
+            vec![
+                Recorded::Text(channel, "ciao".to_owned()),
+                Recorded::Embed(channel, Box::new(embed)),
+                Recorded::Reaction(channel, second, ReactionType::Unicode("🦜".to_owned())),
+                Recorded::Typing(channel),
+                Recorded::Pin(channel, second),
+                Recorded::Direct(UserId::new(42), "psst".to_owned()),
+            ],
+            recorder.recorded()
+        );
+        assert_eq!(vec![(channel, "ciao".to_owned())], recorder.texts());
+    }
+
+    #[tokio::test]
+    async fn failing() {
+        let recorder = Recorder::failing();
+
+        assert!(recorder.send_text(ChannelId::new(7), "ciao").await.is_err());
+        assert!(recorder
+            .pin(ChannelId::new(7), MessageId::new(1))
+            .await
+            .is_err());
+        assert!(recorder.recorded().is_empty());
+
+        let recorder = Recorder::refusing_replies();
+        let channel = ChannelId::new(7);
+        assert!(recorder
+            .send_reply(channel, MessageId::new(1), "ciao")
+            .await
+            .is_err());
+        recorder.send_text(channel, "ciao").await.unwrap();
+        assert_eq!(vec![(channel, "ciao".to_owned())], recorder.texts());
+    }
+}
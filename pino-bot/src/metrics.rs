@@ -0,0 +1,384 @@
+use chrono::{DateTime, Utc};
+use hyper::{Body, Method, Request, Response, StatusCode};
+use pino_core::store::WordStore;
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    net::TcpListener,
+    sync::{
+        atomic::{AtomicI64, AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+};
+use tokio::sync::watch;
+
+use crate::{delivery::Failure, http_server};
+
+/// Content type of the Prometheus text exposition format.
+const CONTENT_TYPE_TEXT: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// The classes of [`Failure`], as labels of `pino_send_errors_total`.
+const FAILURES: [(Failure, &str); 3] = [
+    (Failure::Retryable, "retryable"),
+    (Failure::LostChannel, "lost_channel"),
+    (Failure::Other, "other"),
+];
+
+/// What happened in a single guild.
+#[derive(Default)]
+struct GuildMetrics {
+    words_counted: AtomicU64,
+    /// Unix timestamp of the last message learned from.
+    last_message: AtomicI64,
+}
+
+/// Counts what pino does, for `--metrics-addr`. Updating it takes no lock,
+/// except the first time a command or a guild is seen.
+#[derive(Default)]
+pub struct Metrics {
+    /// By shard.
+    messages_seen: RwLock<BTreeMap<u32, AtomicU64>>,
+    words_counted: AtomicU64,
+    words_expired: AtomicU64,
+    messages_sent: AtomicU64,
+    /// By [`FAILURES`].
+    send_errors: [AtomicU64; 3],
+    /// By shard.
+    gateway_reconnects: RwLock<BTreeMap<u32, AtomicU64>>,
+    commands: RwLock<BTreeMap<&'static str, AtomicU64>>,
+    guilds: RwLock<BTreeMap<u64, GuildMetrics>>,
+}
+
+/// Update the metrics of `key` in `map`, creating them if needed.
+fn update_entry<K: Ord + Copy, V: Default>(
+    map: &RwLock<BTreeMap<K, V>>,
+    key: K,
+    update: impl Fn(&V),
+) {
+    if let Some(value) = map.read().unwrap().get(&key) {
+        update(value);
+        return;
+    }
+
+    update(map.write().unwrap().entry(key).or_default());
+}
+
+impl Metrics {
+    /// A message of someone else, commands included, received by `shard`.
+    pub fn message_seen(&self, shard: u32) {
+        update_entry(&self.messages_seen, shard, |count: &AtomicU64| {
+            count.fetch_add(1, Ordering::Relaxed);
+        });
+    }
+
+    /// `words` words were learned from a message sent in `guild` at `time`.
+    pub fn words_counted(&self, guild: Option<u64>, words: usize, time: DateTime<Utc>) {
+        self.words_counted
+            .fetch_add(words as u64, Ordering::Relaxed);
+
+        if let Some(guild) = guild {
+            update_entry(&self.guilds, guild, |metrics: &GuildMetrics| {
+                metrics
+                    .words_counted
+                    .fetch_add(words as u64, Ordering::Relaxed);
+                metrics
+                    .last_message
+                    .fetch_max(time.timestamp(), Ordering::Relaxed);
+            });
+        }
+    }
+
+    /// `instances` word instances were forgotten because of their age.
+    pub fn words_expired(&self, instances: usize) {
+        self.words_expired
+            .fetch_add(instances as u64, Ordering::Relaxed);
+    }
+
+    pub fn message_sent(&self) {
+        self.messages_sent.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn send_error(&self, failure: Failure) {
+        let index = FAILURES
+            .iter()
+            .position(|(class, _label)| *class == failure)
+            .unwrap();
+
+        self.send_errors[index].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn gateway_reconnect(&self, shard: u32) {
+        update_entry(&self.gateway_reconnects, shard, |count: &AtomicU64| {
+            count.fetch_add(1, Ordering::Relaxed);
+        });
+    }
+
+    pub fn command(&self, name: &'static str) {
+        update_entry(&self.commands, name, |count: &AtomicU64| {
+            count.fetch_add(1, Ordering::Relaxed);
+        });
+    }
+
+    /// Every metric in the Prometheus text format, with the tracked words
+    /// counted from `words`, every server added up.
+    pub fn render(&self, words: &WordStore) -> String {
+        let mut out = String::new();
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+
+        let counters = [
+            (
+                "pino_words_counted_total",
+                "Word instances learned.",
+                &self.words_counted,
+            ),
+            (
+                "pino_words_expired_total",
+                "Word instances forgotten because of their age.",
+                &self.words_expired,
+            ),
+            (
+                "pino_messages_sent_total",
+                "Messages posted, in any way.",
+                &self.messages_sent,
+            ),
+        ];
+
+        for (name, help, counter) in counters {
+            header(&mut out, name, help, "counter");
+            writeln!(out, "{} {}", name, load(counter)).unwrap();
+        }
+
+        let by_shard = [
+            (
+                "pino_messages_seen_total",
+                "Messages of other users, commands included, by shard.",
+                &self.messages_seen,
+            ),
+            (
+                "pino_gateway_reconnects_total",
+                "Reconnections to the Discord gateway, by shard.",
+                &self.gateway_reconnects,
+            ),
+        ];
+
+        for (name, help, counters) in by_shard {
+            header(&mut out, name, help, "counter");
+            for (shard, count) in counters.read().unwrap().iter() {
+                writeln!(out, "{}{{shard=\"{}\"}} {}", name, shard, load(count)).unwrap();
+            }
+        }
+
+        header(
+            &mut out,
+            "pino_send_errors_total",
+            "Messages that couldn't be posted, by why.",
+            "counter",
+        );
+        for ((_failure, class), count) in FAILURES.iter().zip(&self.send_errors) {
+            writeln!(
+                out,
+                "pino_send_errors_total{{class=\"{}\"}} {}",
+                class,
+                load(count)
+            )
+            .unwrap();
+        }
+
+        header(
+            &mut out,
+            "pino_command_invocations_total",
+            "Commands run, by name.",
+            "counter",
+        );
+        for (name, count) in self.commands.read().unwrap().iter() {
+            writeln!(
+                out,
+                "pino_command_invocations_total{{command=\"{}\"}} {}",
+                name,
+                load(count)
+            )
+            .unwrap();
+        }
+
+        header(
+            &mut out,
+            "pino_tracked_words",
+            "Distinct words remembered, added up across servers.",
+            "gauge",
+        );
+        writeln!(out, "pino_tracked_words {}", words.word_count()).unwrap();
+
+        header(
+            &mut out,
+            "pino_word_instances",
+            "Times the remembered words have been said.",
+            "gauge",
+        );
+        writeln!(out, "pino_word_instances {}", words.instance_count()).unwrap();
+
+        let guilds = self.guilds.read().unwrap();
+
+        header(
+            &mut out,
+            "pino_guild_words_counted_total",
+            "Word instances learned, by server.",
+            "counter",
+        );
+        for (guild, metrics) in guilds.iter() {
+            writeln!(
+                out,
+                "pino_guild_words_counted_total{{guild=\"{}\"}} {}",
+                guild,
+                load(&metrics.words_counted)
+            )
+            .unwrap();
+        }
+
+        header(
+            &mut out,
+            "pino_guild_last_message_timestamp_seconds",
+            "When words were last learned, by server.",
+            "gauge",
+        );
+        for (guild, metrics) in guilds.iter() {
+            writeln!(
+                out,
+                "pino_guild_last_message_timestamp_seconds{{guild=\"{}\"}} {}",
+                guild,
+                metrics.last_message.load(Ordering::Relaxed)
+            )
+            .unwrap();
+        }
+
+        out
+    }
+}
+
+fn header(out: &mut String, name: &str, help: &str, kind: &str) {
+    writeln!(out, "# HELP {} {}", name, help).unwrap();
+    writeln!(out, "# TYPE {} {}", name, kind).unwrap();
+}
+
+/// Answer `GET /metrics`, and nothing else.
+async fn respond(
+    request: Request<Body>,
+    metrics: Arc<Metrics>,
+    words: Arc<tokio::sync::RwLock<WordStore>>,
+) -> Response<Body> {
+    if request.method() != Method::GET || request.uri().path() != "/metrics" {
+        return http_server::response(
+            StatusCode::NOT_FOUND,
+            "text/plain",
+            "Not found, try /metrics\n".to_owned(),
+        );
+    }
+
+    let body = metrics.render(&*words.read().await);
+    http_server::response(StatusCode::OK, CONTENT_TYPE_TEXT, body)
+}
+
+/// Serve the metrics on `listener` until shutdown.
+pub async fn serve(
+    listener: TcpListener,
+    metrics: Arc<Metrics>,
+    words: Arc<tokio::sync::RwLock<WordStore>>,
+    stop: watch::Receiver<bool>,
+) -> anyhow::Result<()> {
+    let handler = move |request| respond(request, metrics.clone(), words.clone());
+
+    http_server::serve(listener, handler, stop).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shutdown::Shutdown;
+    use std::time::Duration;
+
+    #[test]
+    fn rendering() {
+        let metrics = Metrics::default();
+        let mut words = WordStore::new();
+        let now = Utc::now();
+
+        words.learn(None, "pino".to_owned(), now);
+        words.learn(None, "pino".to_owned(), now);
+        words.learn(Some(42.into()), "pizza".to_owned(), now);
+
+        metrics.message_seen(0);
+        metrics.message_seen(1);
+        metrics.message_seen(1);
+        metrics.words_counted(Some(42), 3, now);
+        metrics.words_counted(None, 2, now);
+        metrics.send_error(Failure::LostChannel);
+        metrics.command("top");
+        metrics.command("top");
+
+        let text = metrics.render(&words);
+        let lines: Vec<_> = text.lines().collect();
+
+        assert!(lines.contains(&"pino_messages_seen_total{shard=\"0\"} 1"));
+        assert!(lines.contains(&"pino_messages_seen_total{shard=\"1\"} 2"));
+        assert!(lines.contains(&"pino_words_counted_total 5"));
+        assert!(lines.contains(&"pino_send_errors_total{class=\"lost_channel\"} 1"));
+        assert!(lines.contains(&"pino_send_errors_total{class=\"retryable\"} 0"));
+        assert!(lines.contains(&"pino_command_invocations_total{command=\"top\"} 2"));
+        assert!(lines.contains(&"pino_tracked_words 2"));
+        assert!(lines.contains(&"pino_word_instances 3"));
+        assert!(lines.contains(&"pino_guild_words_counted_total{guild=\"42\"} 3"));
+        assert!(lines.contains(&&*format!(
+            "pino_guild_last_message_timestamp_seconds{{guild=\"42\"}} {}",
+            now.timestamp()
+        )));
+    }
+
+    #[tokio::test]
+    async fn scraping() {
+        let metrics = Arc::new(Metrics::default());
+        let words = Arc::new(tokio::sync::RwLock::new(WordStore::new()));
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let mut shutdown = Shutdown::default();
+
+        metrics.message_sent();
+        metrics.gateway_reconnect(0);
+
+        {
+            let metrics = metrics.clone();
+            shutdown.spawn(move |stop| async move {
+                serve(listener, metrics, words, stop).await.unwrap();
+            });
+        }
+
+        let response = reqwest::get(format!("http://{}/metrics", addr))
+            .await
+            .unwrap();
+        assert_eq!(200, response.status().as_u16());
+        assert!(response.headers()["content-type"]
+            .to_str()
+            .unwrap()
+            .starts_with("text/plain"));
+
+        let text = response.text().await.unwrap();
+        for name in [
+            "pino_messages_seen_total",
+            "pino_words_counted_total",
+            "pino_words_expired_total",
+            "pino_messages_sent_total 1",
+            "pino_send_errors_total",
+            "pino_tracked_words 0",
+            "pino_word_instances 0",
+            "pino_guild_words_counted_total",
+            "pino_guild_last_message_timestamp_seconds",
+            "pino_command_invocations_total",
+            "pino_gateway_reconnects_total{shard=\"0\"} 1",
+        ] {
+            assert!(text.contains(name), "{} missing from:\n{}", name, text);
+        }
+
+        let missing = reqwest::get(format!("http://{}/", addr)).await.unwrap();
+        assert_eq!(404, missing.status().as_u16());
+
+        assert!(shutdown.stop(Duration::from_secs(5)).await);
+    }
+}
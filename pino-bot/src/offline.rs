@@ -0,0 +1,362 @@
+use anyhow::{anyhow, bail, Context};
+use chrono::{DateTime, Utc};
+use pino_core::{blocklist::Blocklist, ids::GuildId, store::WordStore};
+use std::{fs, io::Write, path::Path};
+use structopt::StructOpt;
+
+use crate::{
+    commands, config_file, emoji,
+    persistence::{self, FrequencySnapshot},
+    stop_words, Options,
+};
+
+/// What `pino-bot validate` found wrong in a config file.
+#[derive(Debug, Default, PartialEq)]
+pub struct Report {
+    /// Pino wouldn't start.
+    pub errors: Vec<String>,
+    /// Pino would start anyway.
+    pub warnings: Vec<String>,
+}
+
+/// Check `config` the way pino does when starting, with nothing from the
+/// command line, and also load the files it names.
+pub fn validate(config: &Path) -> Report {
+    let mut report = Report::default();
+
+    let (file, unknown) = match config_file::load(config) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            report.errors.push(format!("{:#}", e));
+            return report;
+        }
+    };
+
+    report.warnings.extend(
+        unknown
+            .iter()
+            .map(|key| format!("unknown key `{}`, it's ignored", key)),
+    );
+
+    if let Err(e) = file.guild_settings() {
+        report.errors.push(format!("{:#}", e));
+    }
+
+    let mut options = Options::from_iter(&["pino-bot"]);
+    file.merge(&mut options, |_name| false);
+
+    match options.validate() {
+        Ok(validated) => report
+            .warnings
+            .extend(validated.warnings.iter().map(ToString::to_string)),
+        Err(problems) => {
+            for problem in problems {
+                if problem.is_warning() {
+                    report.warnings.push(problem.to_string());
+                } else {
+                    report.errors.push(problem.to_string());
+                }
+            }
+        }
+    }
+
+    if let Some(path) = &options.stop_words_file {
+        if let Err(e) = stop_words::load(path) {
+            report.errors.push(format!("stop_words_file: {:#}", e));
+        }
+    }
+
+    if let Some(path) = &options.emoji_map_file {
+        if let Err(e) = emoji::load(path) {
+            report.errors.push(format!("emoji_map_file: {:#}", e));
+        }
+    }
+
+    report
+}
+
+/// `pino-bot validate`: write the warnings about `config` to `out`, and fail
+/// with every error if pino wouldn't start with it.
+pub fn check(config: &Path, out: &mut impl Write) -> anyhow::Result<()> {
+    let report = validate(config);
+
+    for warning in &report.warnings {
+        writeln!(out, "warning: {}", warning)?;
+    }
+
+    if !report.errors.is_empty() {
+        let errors: Vec<_> = report
+            .errors
+            .iter()
+            .map(|error| format!("\n  {}", error))
+            .collect();
+        bail!("invalid config {}:{}", config.display(), errors.concat());
+    }
+
+    writeln!(out, "{} is valid", config.display())?;
+
+    Ok(())
+}
+
+/// The words of every guild in the state file at `path`, all of them, however old.
+fn load_words(path: &Path) -> anyhow::Result<(FrequencySnapshot, WordStore)> {
+    let state = persistence::load(path)?
+        .ok_or_else(|| anyhow!("there's no state file at {}", path.display()))?;
+    let words = state.clone().into_store(DateTime::<Utc>::MIN_UTC);
+
+    Ok((state, words))
+}
+
+/// Write the words of `guild` in the state file as an export, the same as
+/// `!pino export`, telling `log` how many. Returns how many words were
+/// exported.
+pub fn export(
+    state_file: &Path,
+    out: &Path,
+    guild: Option<GuildId>,
+    log: &mut impl Write,
+) -> anyhow::Result<usize> {
+    let (_state, words) = load_words(state_file)?;
+    let export = FrequencySnapshot::from_word_map(words.guild(guild));
+    let json = serde_json::to_vec(&export).context("serializing the export")?;
+
+    fs::write(out, json).with_context(|| format!("writing {}", out.display()))?;
+    writeln!(
+        log,
+        "Exported {} words to {}",
+        export.words.len(),
+        out.display()
+    )?;
+
+    Ok(export.words.len())
+}
+
+/// Add the words of an export to `guild` in the state file, the same as
+/// `!pino import`, creating it if it's missing, telling `log` how many. Everything
+/// else in the state file is kept. Returns how many instances were added.
+pub fn import(
+    state_file: &Path,
+    input: &Path,
+    guild: Option<GuildId>,
+    now: DateTime<Utc>,
+    log: &mut impl Write,
+) -> anyhow::Result<usize> {
+    let content = fs::read(input).with_context(|| format!("reading {}", input.display()))?;
+    let import = persistence::parse_import(&content, now)
+        .with_context(|| format!("importing {}", input.display()))?;
+
+    let (mut state, mut words) = match persistence::load(state_file)? {
+        Some(state) => {
+            let words = state.clone().into_store(DateTime::<Utc>::MIN_UTC);
+            (state, words)
+        }
+        None => (
+            FrequencySnapshot::from_store(&WordStore::new()),
+            WordStore::new(),
+        ),
+    };
+
+    let added = import.merge_into(words.guild_mut(guild));
+    let merged = FrequencySnapshot::from_store(&words);
+    state.words = merged.words;
+    state.guilds = merged.guilds;
+    persistence::save(state_file, &state)?;
+    writeln!(
+        log,
+        "Added {} word instances to {}",
+        added,
+        state_file.display()
+    )?;
+
+    Ok(added)
+}
+
+/// Print the `top` most said words of `guild` in the state file, with how many
+/// times they were said.
+pub fn stats(
+    state_file: &Path,
+    top: usize,
+    guild: Option<GuildId>,
+    out: &mut impl Write,
+) -> anyhow::Result<()> {
+    let (state, words) = load_words(state_file)?;
+    let words = words.guild(guild);
+    let blocklist = Blocklist::load(&state.blocklist);
+    let instances: usize = words.values().map(|instances| instances.len()).sum();
+
+    writeln!(out, "{} words, said {} times", words.len(), instances)?;
+
+    for (rank, word) in commands::top_words(words, &blocklist, top)
+        .iter()
+        .enumerate()
+    {
+        writeln!(
+            out,
+            "{:>3}. {} ({}, last {})",
+            rank + 1,
+            word.word,
+            word.count,
+            word.last_heard.format("%Y-%m-%d %H:%M")
+        )?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use std::path::PathBuf;
+    use utils::SortedVec;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("pino-{}-offline-{}", std::process::id(), name))
+    }
+
+    fn save_words(path: &Path, words: &[(&str, usize)], now: DateTime<Utc>) {
+        let words: crate::WordMap = words
+            .iter()
+            .map(|&(word, count)| {
+                let instances = (0..count as i64).map(|i| now - Duration::seconds(i));
+                (word.to_owned(), SortedVec::from_vec(instances.collect()))
+            })
+            .collect();
+        let mut state = FrequencySnapshot::from_word_map(&words);
+        state.prefixes.insert(1, "?pino".to_owned());
+
+        persistence::save(path, &state).unwrap();
+    }
+
+    #[test]
+    fn validating() {
+        let path = temp_path("valid.toml");
+        fs::write(&path, "max_boost = 3\nunknown = 1\n").unwrap();
+        let report = validate(&path);
+        assert!(report.errors.is_empty(), "{:?}", report);
+        assert_eq!(1, report.warnings.len());
+        assert!(report.warnings[0].contains("unknown"));
+
+        let path = temp_path("invalid.toml");
+        fs::write(
+            &path,
+            "interval_low = 30\ninterval_high = 20\nprefix = \"\"\n\n[guilds.pino]\nlanguage = \"it\"\n",
+        )
+        .unwrap();
+        let report = validate(&path);
+        assert_eq!(3, report.errors.len(), "{:?}", report);
+        assert!(report.errors[0].contains("server id"));
+        assert!(report.errors[1].contains("--interval-low (30)"));
+        // pino would be fast, if it started
+        assert_eq!(1, report.warnings.len());
+
+        assert_eq!(1, validate(&temp_path("missing.toml")).errors.len());
+
+        let mut out = Vec::new();
+        check(&temp_path("valid.toml"), &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<_> = out.lines().collect();
+        assert_eq!(2, lines.len());
+        assert!(lines[0].starts_with("warning: unknown key `unknown`"));
+        assert!(lines[1].ends_with("valid.toml is valid"));
+
+        let error = check(&path, &mut Vec::new()).unwrap_err().to_string();
+        assert!(error.starts_with("invalid config "));
+        assert_eq!(4, error.lines().count());
+
+        fs::remove_file(temp_path("valid.toml")).unwrap();
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn exporting_and_importing() {
+        let now = Utc::now() - Duration::minutes(1);
+        let state_file = temp_path("state.json");
+        let dump = temp_path("dump.json");
+        save_words(&state_file, &[("ciao", 2), ("pizza", 1)], now);
+
+        let mut log = Vec::new();
+        assert_eq!(2, export(&state_file, &dump, None, &mut log).unwrap());
+        let log = String::from_utf8(log).unwrap();
+        assert!(log.starts_with("Exported 2 words to "));
+        let exported = persistence::load(&dump).unwrap().unwrap();
+        assert_eq!(2, exported.words["ciao"].len());
+        // only the words
+        assert!(exported.prefixes.is_empty());
+
+        let other = temp_path("other.json");
+        save_words(&other, &[("ciao", 1)], now - Duration::minutes(1));
+        let guild = Some(GuildId(7));
+        let mut log = Vec::new();
+        assert_eq!(
+            3,
+            import(&other, &dump, None, Utc::now(), &mut log).unwrap()
+        );
+        let log = String::from_utf8(log).unwrap();
+        assert!(log.starts_with("Added 3 word instances to "));
+        // again, nothing new
+        let again = import(&other, &dump, None, Utc::now(), &mut Vec::new());
+        assert_eq!(0, again.unwrap());
+        // but a server has its own words
+        let in_guild = import(&other, &dump, guild, Utc::now(), &mut Vec::new());
+        assert_eq!(3, in_guild.unwrap());
+
+        let imported = persistence::load(&other).unwrap().unwrap();
+        assert_eq!(3, imported.words["ciao"].len());
+        assert_eq!(1, imported.words["pizza"].len());
+        assert_eq!(2, imported.guilds[&7]["ciao"].len());
+        assert_eq!("?pino", imported.prefixes[&1]);
+
+        let guild_dump = temp_path("guild-dump.json");
+        assert_eq!(
+            2,
+            export(&other, &guild_dump, guild, &mut Vec::new()).unwrap()
+        );
+        let exported = persistence::load(&guild_dump).unwrap().unwrap();
+        assert_eq!(2, exported.words["ciao"].len());
+        assert!(exported.guilds.is_empty());
+
+        // a new state file
+        let new = temp_path("new.json");
+        let created = import(&new, &dump, None, Utc::now(), &mut Vec::new());
+        assert_eq!(3, created.unwrap());
+
+        let missing = temp_path("missing.json");
+        assert!(export(&missing, &dump, None, &mut Vec::new()).is_err());
+        assert!(import(&new, &missing, None, Utc::now(), &mut Vec::new()).is_err());
+
+        for path in [state_file, dump, other, guild_dump, new].iter() {
+            fs::remove_file(path).unwrap();
+        }
+    }
+
+    #[test]
+    fn printing_stats() {
+        let now = Utc::now();
+        let state_file = temp_path("stats.json");
+        save_words(
+            &state_file,
+            &[("ciao", 3), ("pizza", 1), ("gnocchi", 2)],
+            now,
+        );
+
+        let mut out = Vec::new();
+        stats(&state_file, 2, None, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<_> = out.lines().collect();
+
+        assert_eq!(3, lines.len());
+        assert_eq!("3 words, said 6 times", lines[0]);
+        assert!(lines[1].starts_with("  1. ciao (3, last "));
+        assert!(lines[2].starts_with("  2. gnocchi (2, "));
+
+        let mut out = Vec::new();
+        stats(&state_file, 2, Some(GuildId(7)), &mut out).unwrap();
+        assert_eq!("0 words, said 0 times\n", String::from_utf8(out).unwrap());
+
+        let missing = temp_path("missing.json");
+        assert!(stats(&missing, 2, None, &mut Vec::new()).is_err());
+
+        fs::remove_file(state_file).unwrap();
+    }
+}
@@ -0,0 +1,475 @@
+use anyhow::Context;
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use pino_core::{ids, store::WordStore};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt, fs,
+    io::ErrorKind,
+    path::Path,
+};
+use utils::SortedVec;
+
+use crate::{guild_settings::Settings, sent_log::SentLog, WordMap};
+
+/// Bumped whenever the on-disk format changes in an incompatible way.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Serializable representation of a [`WordMap`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FrequencySnapshot {
+    pub version: u32,
+    /// Every word with the time instants it has been said at, sorted. In the state
+    /// file, only the words said outside of any guild.
+    pub words: BTreeMap<String, Vec<DateTime<Utc>>>,
+    /// The words of each guild, by guild id, like `words`. Always empty in exports.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub guilds: BTreeMap<u64, BTreeMap<String, Vec<DateTime<Utc>>>>,
+    /// Settings changed at runtime, as key and value. Always empty in exports.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub settings: BTreeMap<String, String>,
+    /// Blocked words and regexes by id. Always empty in exports.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub blocklist: BTreeMap<u32, String>,
+    /// Command prefix of the guilds that changed it, by guild id. Always empty in exports.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub prefixes: BTreeMap<u64, String>,
+    /// Settings changed by the guilds, by guild id. Always empty in exports.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub guild_settings: BTreeMap<u64, Settings>,
+    /// The last messages pino sent. Always empty in exports.
+    #[serde(default, skip_serializing_if = "SentLog::is_empty")]
+    pub sent: SentLog,
+}
+
+impl FrequencySnapshot {
+    pub fn from_word_map(map: &WordMap) -> Self {
+        let words = map
+            .iter()
+            .map(|(word, instances)| (word.clone(), instances.as_ref().to_vec()))
+            .collect();
+
+        Self {
+            version: SNAPSHOT_VERSION,
+            words,
+            guilds: BTreeMap::new(),
+            settings: BTreeMap::new(),
+            blocklist: BTreeMap::new(),
+            prefixes: BTreeMap::new(),
+            guild_settings: BTreeMap::new(),
+            sent: SentLog::default(),
+        }
+    }
+
+    /// The words of every guild, for the state file.
+    pub fn from_store(store: &WordStore) -> Self {
+        let mut snapshot = Self::from_word_map(store.guild(None));
+        snapshot.guilds = store
+            .iter()
+            .filter_map(|(guild, words)| Some((guild?.get(), Self::from_word_map(words).words)))
+            .collect();
+
+        snapshot
+    }
+
+    /// Convert back into a [`WordStore`], like [`into_word_map`](Self::into_word_map)
+    /// for each guild.
+    pub fn into_store(mut self, older_than: DateTime<Utc>) -> WordStore {
+        let mut guilds: HashMap<_, _> = std::mem::take(&mut self.guilds)
+            .into_iter()
+            .map(|(guild, words)| (Some(ids::GuildId(guild)), into_words(words, older_than)))
+            .collect();
+        guilds.insert(None, self.into_word_map(older_than));
+
+        guilds.into()
+    }
+
+    /// Convert back into a [`WordMap`], dropping instances older than (or equal to)
+    /// `older_than` and words left without any instance.
+    pub fn into_word_map(self, older_than: DateTime<Utc>) -> WordMap {
+        into_words(self.words, older_than)
+    }
+
+    /// Add the instances of the snapshot to `map`. Instances are counted as a multiset:
+    /// a timestamp the snapshot has `n` times and the map `m` times is added `n - m`
+    /// times, so words said more than once in the same instant survive, and importing
+    /// the same snapshot twice has no effect. Returns the amount of added instances.
+    pub fn merge_into(&self, map: &mut WordMap) -> usize {
+        let mut added = 0;
+
+        for (word, instances) in &self.words {
+            let entry = map.entry(word.clone()).or_default();
+            let incoming = SortedVec::from_vec(instances.clone()).into_deduped_vec();
+
+            for (instance, count) in incoming {
+                let present = entry.upper_bound(&instance) - entry.lower_bound(&instance);
+
+                for _ in present..count {
+                    entry.insert(instance);
+                    added += 1;
+                }
+            }
+        }
+
+        // don't leave empty entries around if the snapshot contained words without instances
+        map.retain(|_word, instances| !instances.is_empty());
+
+        added
+    }
+}
+
+fn into_words(words: BTreeMap<String, Vec<DateTime<Utc>>>, older_than: DateTime<Utc>) -> WordMap {
+    words
+        .into_iter()
+        .filter_map(|(word, instances)| {
+            let mut instances = SortedVec::from_vec(instances);
+            instances.remove_le(&older_than);
+
+            if instances.is_empty() {
+                None
+            } else {
+                Some((word, instances))
+            }
+        })
+        .collect()
+}
+
+/// Largest file accepted by [`parse_import`], which is also Discord's attachment size limit.
+pub const MAX_IMPORT_SIZE: usize = 8 * 1024 * 1024;
+
+/// How far in the future an imported timestamp may be, to account for clock skew.
+const MAX_CLOCK_SKEW_SECONDS: i64 = 5 * 60;
+
+/// Unix timestamp of the discord epoch, the first second of 2015.
+const DISCORD_EPOCH: i64 = 1_420_070_400;
+
+/// Reasons why an imported snapshot gets rejected.
+#[derive(Debug)]
+pub enum ImportError {
+    TooLarge(usize),
+    Malformed(serde_json::Error),
+    UnsupportedVersion(u32),
+    /// The word has a timestamp before Discord even existed, or in the future.
+    BadTimestamp(String, DateTime<Utc>),
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportError::TooLarge(size) => write!(
+                f,
+                "the file is too large ({} bytes, at most {} are allowed)",
+                size, MAX_IMPORT_SIZE
+            ),
+            ImportError::Malformed(e) => write!(f, "the file is not a valid export: {}", e),
+            ImportError::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported export version {} (expected {})",
+                version, SNAPSHOT_VERSION
+            ),
+            ImportError::BadTimestamp(word, time) => {
+                write!(
+                    f,
+                    "the word '{}' has an impossible timestamp {}",
+                    word, time
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for ImportError {}
+
+/// Parse and validate an exported snapshot.
+pub fn parse_import(content: &[u8], now: DateTime<Utc>) -> Result<FrequencySnapshot, ImportError> {
+    if content.len() > MAX_IMPORT_SIZE {
+        return Err(ImportError::TooLarge(content.len()));
+    }
+
+    let snapshot: FrequencySnapshot =
+        serde_json::from_slice(content).map_err(ImportError::Malformed)?;
+
+    if snapshot.version != SNAPSHOT_VERSION {
+        return Err(ImportError::UnsupportedVersion(snapshot.version));
+    }
+
+    let min_time = Utc.timestamp_opt(DISCORD_EPOCH, 0).unwrap();
+    let max_time = now + Duration::seconds(MAX_CLOCK_SKEW_SECONDS);
+
+    for (word, instances) in &snapshot.words {
+        if let Some(time) = instances
+            .iter()
+            .find(|&&time| time < min_time || time > max_time)
+        {
+            return Err(ImportError::BadTimestamp(word.clone(), *time));
+        }
+    }
+
+    Ok(snapshot)
+}
+
+/// Write the snapshot to `path`. The data is written to a temporary file first,
+/// which then atomically replaces the old state.
+pub fn save(path: &Path, snapshot: &FrequencySnapshot) -> anyhow::Result<()> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+
+    let json = serde_json::to_vec(snapshot).context("serializing snapshot")?;
+
+    fs::write(&tmp_path, json).context("writing temporary state file")?;
+    fs::rename(&tmp_path, path).context("replacing state file")?;
+
+    Ok(())
+}
+
+/// Read a snapshot from `path`. Returns `Ok(None)` if the file doesn't exist.
+pub fn load(path: &Path) -> anyhow::Result<Option<FrequencySnapshot>> {
+    let content = match fs::read(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e).context("reading state file"),
+    };
+
+    let snapshot: FrequencySnapshot =
+        serde_json::from_slice(&content).context("parsing state file")?;
+
+    if snapshot.version != SNAPSHOT_VERSION {
+        anyhow::bail!(
+            "unsupported state file version {} (expected {})",
+            snapshot.version,
+            SNAPSHOT_VERSION
+        );
+    }
+
+    Ok(Some(snapshot))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sent_log::{SendKind, SentMessage};
+    use std::path::PathBuf;
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("pino-{}-{}.json", std::process::id(), name))
+    }
+
+    fn word_map(now: DateTime<Utc>) -> WordMap {
+        let mut map = WordMap::new();
+        map.insert(
+            "ciao".to_owned(),
+            SortedVec::from_vec(vec![now, now - Duration::seconds(10)]),
+        );
+        map.insert(
+            "gnocchi".to_owned(),
+            SortedVec::from_vec(vec![now - Duration::seconds(100)]),
+        );
+        map
+    }
+
+    #[test]
+    fn round_trip() {
+        let now = Utc::now();
+        let path = temp_path("round-trip");
+        let snapshot = FrequencySnapshot::from_word_map(&word_map(now));
+
+        save(&path, &snapshot).unwrap();
+        let loaded = load(&path).unwrap().unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(snapshot, loaded);
+
+        let map = loaded.into_word_map(now - Duration::seconds(1000));
+        assert_eq!(2, map["ciao"].len());
+        assert_eq!(1, map["gnocchi"].len());
+    }
+
+    #[test]
+    fn store_round_trip() {
+        let now = Utc::now();
+        let path = temp_path("store-round-trip");
+        let guild = Some(ids::GuildId(42));
+        let mut store = WordStore::new();
+        *store.guild_mut(guild) = word_map(now);
+        store.learn(None, "dm".to_owned(), now);
+
+        let snapshot = FrequencySnapshot::from_store(&store);
+        assert_eq!(vec!["dm"], snapshot.words.keys().collect::<Vec<_>>());
+        assert_eq!(2, snapshot.guilds[&42].len());
+
+        save(&path, &snapshot).unwrap();
+        let loaded = load(&path).unwrap().unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let store = loaded.into_store(now - Duration::seconds(50));
+        assert_eq!(1, store.guild(guild).len());
+        assert_eq!(2, store.guild(guild)["ciao"].len());
+        assert_eq!(1, store.guild(None).len());
+        assert!(store.guild(Some(ids::GuildId(1))).is_empty());
+    }
+
+    #[test]
+    fn drops_old_entries() {
+        let now = Utc::now();
+        let snapshot = FrequencySnapshot::from_word_map(&word_map(now));
+
+        let map = snapshot.into_word_map(now - Duration::seconds(50));
+
+        assert_eq!(1, map.len());
+        assert_eq!(2, map["ciao"].len());
+    }
+
+    #[test]
+    fn runtime_state_round_trip() {
+        let path = temp_path("runtime-state");
+        let mut snapshot = FrequencySnapshot::from_word_map(&WordMap::new());
+        snapshot
+            .settings
+            .insert("max_age".to_owned(), "60".to_owned());
+        snapshot.blocklist.insert(3, "/^gn/".to_owned());
+        snapshot.prefixes.insert(42, "?pino".to_owned());
+        snapshot.guild_settings.insert(
+            42,
+            Settings {
+                emoji_mode: Some(true),
+                ..Settings::default()
+            },
+        );
+        snapshot.sent.push(
+            Some(1),
+            SentMessage {
+                time: Utc.timestamp_opt(1_600_000_000, 0).unwrap(),
+                word: "ciao".to_owned(),
+                content: "ciao".to_owned(),
+                channel_id: Some(2),
+                message_id: Some(3),
+                kind: SendKind::Forced,
+            },
+        );
+
+        save(&path, &snapshot).unwrap();
+        let loaded = load(&path).unwrap().unwrap();
+        fs::write(&path, b"{\"version\": 1, \"words\": {}}").unwrap();
+        let old = load(&path).unwrap().unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(snapshot, loaded);
+        assert!(old.settings.is_empty());
+        assert!(old.blocklist.is_empty());
+        assert!(old.prefixes.is_empty());
+        assert!(old.guild_settings.is_empty());
+        assert!(old.sent.is_empty());
+    }
+
+    #[test]
+    fn missing_file() {
+        assert!(load(&temp_path("missing")).unwrap().is_none());
+    }
+
+    #[test]
+    fn corrupt_file() {
+        let path = temp_path("corrupt");
+
+        fs::write(&path, b"{\"version\": 1, \"words\": {\"ciao\": [").unwrap();
+        let truncated = load(&path);
+        fs::write(&path, b"{\"version\": 999, \"words\": {}}").unwrap();
+        let wrong_version = load(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert!(truncated.is_err());
+        assert!(wrong_version.is_err());
+    }
+
+    #[test]
+    fn merge() {
+        let now = Utc::now();
+        let snapshot = FrequencySnapshot::from_word_map(&word_map(now));
+
+        let mut map = WordMap::new();
+        map.insert("ciao".to_owned(), SortedVec::from_vec(vec![now]));
+
+        assert_eq!(2, snapshot.merge_into(&mut map));
+        assert_eq!(2, map["ciao"].len());
+        assert_eq!(1, map["gnocchi"].len());
+
+        // merging again doesn't duplicate anything
+        assert_eq!(0, snapshot.merge_into(&mut map));
+        assert_eq!(2, map["ciao"].len());
+    }
+
+    #[test]
+    fn merge_duplicated_timestamps() {
+        let now = Utc::now();
+        let mut words = WordMap::new();
+        words.insert("ciao".to_owned(), SortedVec::from_vec(vec![now, now, now]));
+        let snapshot = FrequencySnapshot::from_word_map(&words);
+
+        let path = temp_path("duplicated-timestamps");
+        save(&path, &snapshot).unwrap();
+        let loaded = load(&path).unwrap().unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let mut map = WordMap::new();
+        map.insert("ciao".to_owned(), SortedVec::from_vec(vec![now]));
+
+        // only the copies the map doesn't have yet are added
+        assert_eq!(2, loaded.merge_into(&mut map));
+        assert_eq!(&[now, now, now], map["ciao"].as_ref());
+        assert_eq!(0, loaded.merge_into(&mut map));
+        assert_eq!(3, map["ciao"].len());
+    }
+
+    #[test]
+    fn import_valid() {
+        let now = Utc::now();
+        let snapshot = FrequencySnapshot::from_word_map(&word_map(now));
+        let json = serde_json::to_vec(&snapshot).unwrap();
+
+        assert_eq!(snapshot, parse_import(&json, now).unwrap());
+    }
+
+    #[test]
+    fn import_bad_inputs() {
+        let now = Utc::now();
+
+        let too_large = vec![b' '; MAX_IMPORT_SIZE + 1];
+        assert!(matches!(
+            parse_import(&too_large, now),
+            Err(ImportError::TooLarge(_))
+        ));
+
+        assert!(matches!(
+            parse_import(b"not json", now),
+            Err(ImportError::Malformed(_))
+        ));
+        assert!(matches!(
+            parse_import(b"{\"words\": {}}", now),
+            Err(ImportError::Malformed(_))
+        ));
+        assert!(matches!(
+            parse_import(b"{\"version\": 1, \"words\": {\"ciao\": [42]}}", now),
+            Err(ImportError::Malformed(_))
+        ));
+        assert!(matches!(
+            parse_import(b"{\"version\": 2, \"words\": {}}", now),
+            Err(ImportError::UnsupportedVersion(2))
+        ));
+
+        let mut future = word_map(now);
+        future.insert(
+            "domani".to_owned(),
+            SortedVec::from_vec(vec![now + Duration::days(1)]),
+        );
+        let json = serde_json::to_vec(&FrequencySnapshot::from_word_map(&future)).unwrap();
+        assert!(matches!(
+            parse_import(&json, now),
+            Err(ImportError::BadTimestamp(word, _)) if word == "domani"
+        ));
+
+        let json = b"{\"version\": 1, \"words\": {\"ieri\": [\"1999-01-01T00:00:00Z\"]}}";
+        assert!(matches!(
+            parse_import(json, now),
+            Err(ImportError::BadTimestamp(word, _)) if word == "ieri"
+        ));
+    }
+}
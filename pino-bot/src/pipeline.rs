@@ -0,0 +1,145 @@
+use chrono::{DateTime, Utc};
+use pino_core::{blocklist::Blocklist, selection::Selection, tracking, WordMap};
+use rand::Rng;
+use serenity::async_trait;
+use std::collections::HashMap;
+
+use crate::{budget, emoji, guild_settings::Resolved, template};
+
+/// What a send cycle says: the word, how many times it was heard, and the
+/// message with it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Post {
+    pub word: String,
+    pub count: usize,
+    pub message: String,
+}
+
+/// Choose what to say: the best word by `selection`, e.g. the most said one
+/// after the random boosts, else the default word. The message follows `settings`, with the word as emoji in
+/// emoji mode. `None` when there's nothing to say.
+pub fn compose(
+    words: &WordMap,
+    blocklist: &Blocklist,
+    rng: &mut impl Rng,
+    selection: &Selection,
+    settings: &Resolved,
+    emoji_map: &HashMap<String, String>,
+) -> Option<Post> {
+    let word = selection
+        .pick(words, blocklist, rng)
+        .or_else(|| settings.default_word.clone())?;
+    let count = words.get(&word).map_or(0, |instances| instances.len());
+
+    let shown = if settings.emoji_mode {
+        let emoji = emoji::word_to_emoji(&word, emoji_map);
+
+        // Words made only of symbols have no emoji
+        if emoji.is_empty() {
+            word.clone()
+        } else {
+            emoji
+        }
+    } else {
+        word.clone()
+    };
+    let message = template::render(&settings.message_template, &shown, count);
+
+    Some(Post {
+        word,
+        count,
+        message,
+    })
+}
+
+/// Forget the instances said before `older_than`, and then the least recent
+/// words above `max_words`. Returns how many instances expired and how many
+/// words were evicted.
+pub fn forget_old(
+    words: &mut WordMap,
+    older_than: DateTime<Utc>,
+    max_words: Option<usize>,
+) -> (usize, usize) {
+    let expired = tracking::expire(words, older_than);
+    let evicted = max_words.map_or(0, |max_words| budget::evict_least_recent(words, max_words));
+
+    (expired, evicted)
+}
+
+/// Where the post of a send cycle goes: Discord, or the terminal with
+/// `--dry-run`. Each front-end builds one per cycle, knowing where pino
+/// talks at that moment.
+#[async_trait]
+pub trait Outlet: Send + Sync {
+    async fn deliver(&self, post: &Post);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+    use utils::SortedVec;
+
+    fn settings() -> Resolved {
+        Resolved {
+            message_template: "{word} ({count})".to_owned(),
+            emoji_mode: false,
+            default_word: None,
+            language: Default::default(),
+            scoring: Default::default(),
+            stop_words: None,
+        }
+    }
+
+    #[test]
+    fn composing() {
+        let now = Utc::now();
+        let mut rng = StdRng::seed_from_u64(1);
+        let blocklist = Blocklist::default();
+        let emoji_map = HashMap::new();
+        let by_count = Selection::by_count(0);
+        let mut settings = settings();
+        let mut words = WordMap::new();
+
+        assert_eq!(
+            None,
+            compose(&words, &blocklist, &mut rng, &by_count, &settings, &emoji_map)
+        );
+
+        settings.default_word = Some("pino".to_owned());
+        assert_eq!(
+            Some(Post {
+                word: "pino".to_owned(),
+                count: 0,
+                message: "pino (0)".to_owned(),
+            }),
+            compose(&words, &blocklist, &mut rng, &by_count, &settings, &emoji_map)
+        );
+
+        words.insert("ciao".to_owned(), SortedVec::from_vec(vec![now; 2]));
+        let post = compose(
+            &words, &blocklist, &mut rng, &by_count, &settings, &emoji_map,
+        )
+        .unwrap();
+        assert_eq!("ciao", post.word);
+        assert_eq!("ciao (2)", post.message);
+    }
+
+    #[test]
+    fn forgetting() {
+        let now = Utc::now();
+        let old = now - chrono::Duration::hours(2);
+        let mut words = WordMap::new();
+
+        tracking::learn(&mut words, "ciao".to_owned(), old);
+        tracking::learn(&mut words, "ciao".to_owned(), now);
+        tracking::learn(&mut words, "pizza".to_owned(), old);
+        tracking::learn(&mut words, "pasta".to_owned(), now);
+
+        assert_eq!(
+            (2, 1),
+            forget_old(&mut words, now - chrono::Duration::hours(1), Some(1))
+        );
+        assert_eq!(1, words.len());
+    }
+}
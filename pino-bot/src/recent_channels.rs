@@ -0,0 +1,140 @@
+use serenity::model::id::{ChannelId, GuildId};
+use std::collections::{HashMap, VecDeque};
+
+/// How many channels are remembered in each guild, to fall back on when pino
+/// can't post in the most recent one anymore.
+pub const CAPACITY: usize = 5;
+
+/// The channels people talked in last in each guild, the most recent first.
+/// Pino posts in the first one of the guild whose turn it is. Channels out of
+/// guilds, i.e. DMs, are under `None`.
+#[derive(Debug, Default)]
+pub struct RecentChannels {
+    guilds: HashMap<Option<GuildId>, VecDeque<ChannelId>>,
+    /// Where someone talked last, in any guild.
+    latest: Option<(ChannelId, Option<GuildId>)>,
+}
+
+impl RecentChannels {
+    /// Someone talked in `channel`, move it to the front.
+    pub fn touch(&mut self, channel: ChannelId, guild: Option<GuildId>) {
+        let recent = self.guilds.entry(guild).or_default();
+        recent.retain(|&recent| recent != channel);
+        recent.push_front(channel);
+        recent.truncate(CAPACITY);
+
+        self.latest = Some((channel, guild));
+    }
+
+    /// Where someone talked last, in any guild.
+    pub fn current(&self) -> Option<(ChannelId, Option<GuildId>)> {
+        self.latest
+    }
+
+    /// Where pino posts in `guild`, if anyone talked there yet.
+    pub fn current_in(&self, guild: Option<GuildId>) -> Option<ChannelId> {
+        self.guilds
+            .get(&guild)
+            .and_then(|recent| recent.front().copied())
+    }
+
+    /// Stop posting in a channel of `guild` pino can't post in anymore.
+    /// Returns the channel of the same guild to use instead, if any.
+    pub fn forget(&mut self, channel: ChannelId, guild: Option<GuildId>) -> Option<ChannelId> {
+        if let Some(recent) = self.guilds.get_mut(&guild) {
+            recent.retain(|&recent| recent != channel);
+
+            if recent.is_empty() {
+                self.guilds.remove(&guild);
+            }
+        }
+
+        let next = self.current_in(guild);
+
+        if self.latest == Some((channel, guild)) {
+            self.latest = next.map(|next| (next, guild));
+        }
+
+        next
+    }
+
+    /// Forget every channel of `guild`, e.g. because pino left it.
+    pub fn forget_guild(&mut self, guild: GuildId) {
+        self.guilds.remove(&Some(guild));
+
+        if self.latest.map(|(_channel, latest)| latest) == Some(Some(guild)) {
+            self.latest = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channel(id: u64) -> ChannelId {
+        ChannelId::new(id)
+    }
+
+    #[test]
+    fn recency() {
+        let mut recent = RecentChannels::default();
+        let guild = Some(GuildId::new(20));
+        assert_eq!(None, recent.current());
+        assert_eq!(None, recent.current_in(None));
+
+        recent.touch(channel(1), None);
+        recent.touch(channel(2), guild);
+        assert_eq!(Some((channel(2), guild)), recent.current());
+        recent.touch(channel(1), None);
+        assert_eq!(Some((channel(1), None)), recent.current());
+        // each guild has its own
+        assert_eq!(Some(channel(2)), recent.current_in(guild));
+        assert_eq!(Some(channel(1)), recent.current_in(None));
+
+        for id in 3..10 {
+            recent.touch(channel(id), None);
+        }
+        assert_eq!(CAPACITY, recent.guilds[&None].len());
+        assert_eq!(Some(channel(9)), recent.current_in(None));
+        assert_eq!(Some(channel(2)), recent.current_in(guild));
+    }
+
+    #[test]
+    fn fallback() {
+        let mut recent = RecentChannels::default();
+        let guild = Some(GuildId::new(20));
+        recent.touch(channel(1), None);
+        recent.touch(channel(2), guild);
+        recent.touch(channel(3), None);
+
+        assert_eq!(Some(channel(1)), recent.forget(channel(3), None));
+        assert_eq!(Some((channel(1), None)), recent.current());
+        // forgetting one that isn't the current one doesn't move pino
+        recent.touch(channel(4), None);
+        assert_eq!(Some(channel(4)), recent.forget(channel(1), None));
+        assert_eq!(None, recent.forget(channel(4), None));
+        assert_eq!(None, recent.forget(channel(4), None));
+        assert_eq!(None, recent.current());
+        // other guilds aren't touched
+        assert_eq!(Some(channel(2)), recent.current_in(guild));
+
+        // pino goes back once someone talks there again
+        recent.touch(channel(3), None);
+        assert_eq!(Some((channel(3), None)), recent.current());
+        assert_eq!(Some(channel(3)), recent.current_in(None));
+    }
+
+    #[test]
+    fn leaving() {
+        let mut recent = RecentChannels::default();
+        let guild = GuildId::new(20);
+        recent.touch(channel(1), None);
+        recent.touch(channel(2), Some(guild));
+
+        recent.forget_guild(guild);
+        assert_eq!(None, recent.current_in(Some(guild)));
+        assert_eq!(None, recent.current());
+        assert_eq!(Some(channel(1)), recent.current_in(None));
+    }
+}
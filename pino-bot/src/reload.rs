@@ -0,0 +1,286 @@
+use std::{collections::HashSet, fmt, path::PathBuf, sync::RwLock};
+
+use crate::{
+    config_file,
+    runtime_config::{ConfigError, RuntimeConfig},
+    stop_words,
+};
+
+/// Where the options reloaded on SIGHUP come from: the files pino was started
+/// with.
+#[derive(Debug, Clone, Default)]
+pub struct Sources {
+    pub config_file: Option<PathBuf>,
+    pub stop_words_file: Option<PathBuf>,
+    /// Keys of [`RuntimeConfig`] given on the command line, which still wins
+    /// over the config file.
+    pub given: Vec<&'static str>,
+}
+
+/// The reloadable options, read again but not applied yet.
+#[derive(Debug, Default, PartialEq)]
+pub struct Reload {
+    /// The settings of [`RuntimeConfig`] in the config file, written like
+    /// `!pino set` takes them.
+    pub settings: Vec<(&'static str, String)>,
+    pub stop_words: Option<HashSet<String>>,
+}
+
+impl Sources {
+    /// Read the files again. Nothing is applied yet, so a broken file changes
+    /// nothing. The token, the shards and the other options only read when
+    /// starting are left alone.
+    pub fn load(&self) -> anyhow::Result<Reload> {
+        let mut reload = Reload::default();
+
+        if let Some(path) = &self.config_file {
+            let (file, _unknown) = config_file::load(path)?;
+            let settings = [
+                ("interval_low", file.interval_low),
+                ("interval_high", file.interval_high),
+                ("max_age", file.max_age),
+                ("max_boost", file.max_boost.map(|boost| boost as u64)),
+            ];
+
+            reload.settings = settings
+                .iter()
+                .filter(|(key, _value)| !self.given.contains(key))
+                .filter_map(|&(key, value)| Some((key, value?.to_string())))
+                .collect();
+        }
+
+        if let Some(path) = &self.stop_words_file {
+            reload.stop_words = Some(stop_words::load(path)?);
+        }
+
+        Ok(reload)
+    }
+}
+
+/// A setting changed by a reload.
+#[derive(Debug, PartialEq)]
+pub struct Change {
+    pub key: &'static str,
+    pub old: String,
+    pub new: String,
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} -> {}", self.key, self.old, self.new)
+    }
+}
+
+/// The settings whose value differs between `old` and `new`.
+pub fn diff(old: &RuntimeConfig, new: &RuntimeConfig) -> Vec<Change> {
+    old.entries()
+        .into_iter()
+        .zip(new.entries())
+        .filter(|((_key, old), (_new_key, new))| old != new)
+        .map(|((key, old), (_new_key, new))| Change { key, old, new })
+        .collect()
+}
+
+/// What pino is running with, that a reload replaces.
+pub struct Live<'a> {
+    /// The config from the command line and the config file.
+    pub base: &'a RwLock<RuntimeConfig>,
+    /// The base with the changes of `!pino set`.
+    pub config: &'a RwLock<RuntimeConfig>,
+    pub stop_words: &'a RwLock<HashSet<String>>,
+}
+
+/// Swap in `reload` all at once. The settings of the file replace the base
+/// ones, the changes of `!pino set` stay on top of them. Nothing is swapped
+/// if the result isn't valid. Returns the changes of the running config.
+pub fn apply(reload: Reload, live: &Live<'_>) -> Result<Vec<Change>, ConfigError> {
+    let mut base = live.base.write().unwrap();
+    let mut config = live.config.write().unwrap();
+
+    let overrides = config.changes_from(&base);
+    let new_base = base.with_settings(
+        reload
+            .settings
+            .iter()
+            .map(|(key, value)| (*key, value.as_str())),
+    )?;
+    let new_config = new_base.with_settings(
+        overrides
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str())),
+    )?;
+
+    let changes = diff(&config, &new_config);
+    *base = new_base;
+    *config = new_config;
+
+    if let Some(words) = reload.stop_words {
+        *live.stop_words.write().unwrap() = words;
+    }
+
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs, path::Path};
+
+    fn config() -> RuntimeConfig {
+        RuntimeConfig {
+            interval_low: 600,
+            interval_high: 1200,
+            max_age: 1800,
+            max_boost: 10,
+        }
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("pino-{}-reload-{}", std::process::id(), name))
+    }
+
+    fn words(words: &[&str]) -> HashSet<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+
+    #[test]
+    fn diffing() {
+        let old = config();
+        assert!(diff(&old, &old).is_empty());
+
+        let new = RuntimeConfig {
+            interval_low: 300,
+            max_boost: 3,
+            ..config()
+        };
+        let changes = diff(&old, &new);
+        assert_eq!(
+            vec![
+                Change {
+                    key: "interval_low",
+                    old: "600".to_owned(),
+                    new: "300".to_owned(),
+                },
+                Change {
+                    key: "max_boost",
+                    old: "10".to_owned(),
+                    new: "3".to_owned(),
+                },
+            ],
+            changes
+        );
+        assert_eq!("interval_low: 600 -> 300", changes[0].to_string());
+    }
+
+    #[test]
+    fn applying() {
+        let base = RwLock::new(config());
+        let config = RwLock::new(RuntimeConfig {
+            max_boost: 3,
+            ..config()
+        });
+        let stop_words = RwLock::new(words(&["il"]));
+        let live = Live {
+            base: &base,
+            config: &config,
+            stop_words: &stop_words,
+        };
+
+        let reload = Reload {
+            settings: vec![
+                ("interval_low", "300".to_owned()),
+                ("max_boost", "5".to_owned()),
+            ],
+            stop_words: Some(words(&["il", "lo"])),
+        };
+        let changes = apply(reload, &live).unwrap();
+
+        // max_boost was set with `!pino set`, it stays
+        assert_eq!(1, changes.len());
+        assert_eq!("interval_low", changes[0].key);
+        assert_eq!(300, config.read().unwrap().interval_low);
+        assert_eq!(3, config.read().unwrap().max_boost);
+        assert_eq!(5, base.read().unwrap().max_boost);
+        assert_eq!(words(&["il", "lo"]), *stop_words.read().unwrap());
+
+        // without stop words file, they stay
+        assert!(apply(Reload::default(), &live).unwrap().is_empty());
+        assert_eq!(2, stop_words.read().unwrap().len());
+    }
+
+    #[test]
+    fn invalid_reloads_change_nothing() {
+        let base = RwLock::new(config());
+        let config = RwLock::new(RuntimeConfig {
+            interval_high: 1500,
+            ..config()
+        });
+        let stop_words = RwLock::new(words(&["il"]));
+        let live = Live {
+            base: &base,
+            config: &config,
+            stop_words: &stop_words,
+        };
+
+        for settings in [
+            vec![("interval_low", "2000".to_owned())],
+            vec![("max_age", "soon".to_owned())],
+            // only broken together with the change of `!pino set`
+            vec![("max_age", "1400".to_owned())],
+        ] {
+            let reload = Reload {
+                settings,
+                stop_words: Some(words(&["lo"])),
+            };
+            assert!(apply(reload, &live).is_err());
+        }
+
+        assert_eq!(self::config(), *base.read().unwrap());
+        assert_eq!(1500, config.read().unwrap().interval_high);
+        assert_eq!(words(&["il"]), *stop_words.read().unwrap());
+    }
+
+    #[test]
+    fn loading() {
+        let config_path = temp_path("config.toml");
+        let stop_words_path = temp_path("stop-words.txt");
+        fs::write(
+            &config_path,
+            "interval_low = \"5m\"\nmax_boost = 2\nmax_age = 3600\ntoken = \"secret\"\n",
+        )
+        .unwrap();
+        fs::write(&stop_words_path, "Il\nlo\n").unwrap();
+
+        let sources = Sources {
+            config_file: Some(config_path.clone()),
+            stop_words_file: Some(stop_words_path.clone()),
+            given: vec!["max_age"],
+        };
+        assert_eq!(
+            Reload {
+                settings: vec![
+                    ("interval_low", "300".to_owned()),
+                    ("max_boost", "2".to_owned())
+                ],
+                stop_words: Some(words(&["il", "lo"])),
+            },
+            sources.load().unwrap()
+        );
+
+        // nothing to read, nothing to change
+        assert_eq!(Reload::default(), Sources::default().load().unwrap());
+
+        fs::write(&config_path, "interval_low = \"soon\"\n").unwrap();
+        assert!(sources.load().is_err());
+
+        let missing = Sources {
+            stop_words_file: Some(temp_path("missing.txt")),
+            ..Sources::default()
+        };
+        assert!(missing.load().is_err());
+
+        for path in [config_path, stop_words_path].iter() {
+            fs::remove_file(Path::new(path)).unwrap();
+        }
+    }
+}
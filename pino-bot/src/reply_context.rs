@@ -0,0 +1,295 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serenity::model::id::{ChannelId, GuildId, MessageId};
+use std::{collections::HashMap, fmt, str::FromStr};
+use tracing::info;
+
+use crate::{
+    delivery::{self, Failure},
+    messenger::Messenger,
+};
+
+/// Words remembered in each guild. Past it, the one said least recently is
+/// forgotten.
+pub const CAPACITY: usize = 1000;
+
+/// How pino posts its words.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReplyStyle {
+    /// As a message of its own.
+    #[default]
+    Plain,
+    /// As a reply to the last message saying the word, for context.
+    Reference,
+}
+
+impl FromStr for ReplyStyle {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "plain" => Ok(ReplyStyle::Plain),
+            "reference" => Ok(ReplyStyle::Reference),
+            _ => Err(format!(
+                "`{}` is not a reply style, use plain or reference",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for ReplyStyle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplyStyle::Plain => write!(f, "plain"),
+            ReplyStyle::Reference => write!(f, "reference"),
+        }
+    }
+}
+
+/// Where a word was said last.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mention {
+    pub channel: ChannelId,
+    pub message: MessageId,
+    pub time: DateTime<Utc>,
+}
+
+/// The last message saying each word, in every guild. Messages out of guilds
+/// are under `None`.
+#[derive(Debug, Default)]
+pub struct LastMentions {
+    guilds: HashMap<Option<GuildId>, HashMap<String, Mention>>,
+}
+
+impl LastMentions {
+    /// `words` were said in `message`.
+    pub fn record(
+        &mut self,
+        guild: Option<GuildId>,
+        words: &[String],
+        channel: ChannelId,
+        message: MessageId,
+        time: DateTime<Utc>,
+    ) {
+        let mentions = self.guilds.entry(guild).or_default();
+        let mention = Mention {
+            channel,
+            message,
+            time,
+        };
+
+        for word in words {
+            if mentions.len() >= CAPACITY && !mentions.contains_key(word) {
+                let oldest = mentions
+                    .iter()
+                    .min_by_key(|(_word, mention)| mention.time)
+                    .map(|(word, _mention)| word.clone());
+
+                if let Some(oldest) = oldest {
+                    mentions.remove(&oldest);
+                }
+            }
+
+            mentions.insert(word.clone(), mention);
+        }
+    }
+
+    /// The message to reply to when posting `word` in `channel`: the last one
+    /// saying it in `guild`, if it's in the same channel and not older than
+    /// `since`. Discord replies only work within a channel.
+    pub fn reply_to(
+        &self,
+        guild: Option<GuildId>,
+        word: &str,
+        channel: ChannelId,
+        since: DateTime<Utc>,
+    ) -> Option<MessageId> {
+        self.guilds
+            .get(&guild)?
+            .get(word)
+            .filter(|mention| mention.channel == channel && mention.time >= since)
+            .map(|mention| mention.message)
+    }
+
+    /// Forget the mentions older than `older_than`, returning how many.
+    pub fn expire(&mut self, older_than: DateTime<Utc>) -> usize {
+        let mut expired = 0;
+
+        self.guilds.retain(|_guild, mentions| {
+            let before = mentions.len();
+            mentions.retain(|_word, mention| mention.time >= older_than);
+            expired += before - mentions.len();
+
+            !mentions.is_empty()
+        });
+
+        expired
+    }
+}
+
+/// Send `content` in `channel`, as a reply to `reply_to` if given. If Discord
+/// refuses the reference, e.g. because the message was deleted, it's sent
+/// again without. Other errors, like missing permissions, are returned.
+pub async fn send(
+    messenger: &dyn Messenger,
+    channel: ChannelId,
+    content: &str,
+    reply_to: Option<MessageId>,
+) -> serenity::Result<MessageId> {
+    let reply_to = match reply_to {
+        Some(reply_to) => reply_to,
+        None => return messenger.send_text(channel, content).await,
+    };
+
+    match messenger.send_reply(channel, reply_to, content).await {
+        Err(e) if delivery::classify_serenity(&e) == Failure::Other => {
+            info!(
+                "Can't reply to message {} anymore, sending without: {}",
+                reply_to, e
+            );
+            messenger.send_text(channel, content).await
+        }
+        sent => sent,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messenger::{Recorded, Recorder};
+    use chrono::Duration;
+
+    fn words(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+
+    #[test]
+    fn styles() {
+        for style in &[ReplyStyle::Plain, ReplyStyle::Reference] {
+            assert_eq!(Ok(*style), style.to_string().parse());
+        }
+        assert!("reply".parse::<ReplyStyle>().is_err());
+    }
+
+    #[test]
+    fn last_mentions() {
+        let now = Utc::now();
+        let since = now - Duration::minutes(30);
+        let guild = Some(GuildId::new(1));
+        let channel = ChannelId::new(7);
+        let mut mentions = LastMentions::default();
+
+        assert_eq!(None, mentions.reply_to(guild, "pizza", channel, since));
+
+        mentions.record(
+            guild,
+            &words(&["pizza", "pasta"]),
+            channel,
+            MessageId::new(10),
+            now - Duration::minutes(5),
+        );
+        mentions.record(guild, &words(&["pizza"]), channel, MessageId::new(11), now);
+
+        // the last one saying it
+        let reply_to = |word, channel, since| mentions.reply_to(guild, word, channel, since);
+        assert_eq!(Some(MessageId::new(11)), reply_to("pizza", channel, since));
+        assert_eq!(Some(MessageId::new(10)), reply_to("pasta", channel, since));
+        // only in the same channel, and not too old
+        assert_eq!(None, reply_to("pizza", ChannelId::new(8), since));
+        assert_eq!(None, reply_to("pasta", channel, now - Duration::minutes(1)));
+        // each guild on its own
+        assert_eq!(None, mentions.reply_to(None, "pizza", channel, since));
+    }
+
+    #[test]
+    fn expiry() {
+        let now = Utc::now();
+        let guild = Some(GuildId::new(1));
+        let channel = ChannelId::new(7);
+        let mut mentions = LastMentions::default();
+
+        let old = now - Duration::hours(2);
+        mentions.record(guild, &words(&["pizza"]), channel, MessageId::new(1), old);
+        mentions.record(None, &words(&["ciao"]), channel, MessageId::new(2), old);
+        mentions.record(guild, &words(&["pasta"]), channel, MessageId::new(3), now);
+
+        assert_eq!(2, mentions.expire(now - Duration::hours(1)));
+        assert_eq!(0, mentions.expire(now - Duration::hours(1)));
+        assert_eq!(
+            None,
+            mentions.reply_to(guild, "pizza", channel, old - Duration::hours(1))
+        );
+        assert!(!mentions.guilds.contains_key(&None));
+        assert_eq!(
+            Some(MessageId::new(3)),
+            mentions.reply_to(guild, "pasta", channel, old)
+        );
+    }
+
+    #[test]
+    fn capacity() {
+        let now = Utc::now();
+        let guild = Some(GuildId::new(1));
+        let channel = ChannelId::new(7);
+        let since = now - Duration::days(1);
+        let mut mentions = LastMentions::default();
+
+        for i in 0..CAPACITY + 2 {
+            let time = now - Duration::seconds((CAPACITY + 2 - i) as i64);
+            let message = MessageId::new(i as u64 + 1);
+            mentions.record(guild, &[format!("word{}", i)], channel, message, time);
+        }
+
+        // the least recent ones went first
+        assert_eq!(CAPACITY, mentions.guilds[&guild].len());
+        assert_eq!(None, mentions.reply_to(guild, "word0", channel, since));
+        assert_eq!(None, mentions.reply_to(guild, "word1", channel, since));
+        assert!(mentions.reply_to(guild, "word2", channel, since).is_some());
+
+        // words already there don't push others out
+        mentions.record(guild, &words(&["word2"]), channel, MessageId::new(99), now);
+        assert_eq!(CAPACITY, mentions.guilds[&guild].len());
+        assert!(mentions.reply_to(guild, "word3", channel, since).is_some());
+    }
+
+    #[tokio::test]
+    async fn replying() {
+        let recorder = Recorder::default();
+        let channel = ChannelId::new(7);
+
+        send(&recorder, channel, "pizza", Some(MessageId::new(42)))
+            .await
+            .unwrap();
+        send(&recorder, channel, "pasta", None).await.unwrap();
+
+        assert_eq!(
+            vec![
+                Recorded::Reply(channel, MessageId::new(42), "pizza".to_owned()),
+                Recorded::Text(channel, "pasta".to_owned()),
+            ],
+            recorder.recorded()
+        );
+    }
+
+    #[tokio::test]
+    async fn deleted_reference() {
+        // the message replied to is gone
+        let recorder = Recorder::refusing_replies();
+        let channel = ChannelId::new(7);
+
+        let sent = send(&recorder, channel, "pizza", Some(MessageId::new(42))).await;
+        assert!(sent.is_ok());
+        assert_eq!(
+            vec![Recorded::Text(channel, "pizza".to_owned())],
+            recorder.recorded()
+        );
+
+        // nothing to fall back on when Discord refuses everything
+        let recorder = Recorder::failing();
+        assert!(send(&recorder, channel, "pizza", Some(MessageId::new(42)))
+            .await
+            .is_err());
+    }
+}
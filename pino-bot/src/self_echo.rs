@@ -0,0 +1,239 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    str::FromStr,
+};
+
+use crate::sent_log::{SentLog, LOG_CAPACITY};
+
+/// What is learned from a message made only of words pino said itself, like
+/// another parrot bot or a bridge repeating it. Learning them all, two parrots
+/// in the same server end up repeating each other forever.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SelfEcho {
+    /// Nothing.
+    #[default]
+    Ignore,
+    /// Every other echo of a guild, so that they count half.
+    Halve,
+    /// Everything, like from any other message.
+    Full,
+}
+
+impl FromStr for SelfEcho {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "ignore" => Ok(SelfEcho::Ignore),
+            "halve" => Ok(SelfEcho::Halve),
+            "full" => Ok(SelfEcho::Full),
+            _ => Err(format!(
+                "`{}` is not a way to treat echoes, use ignore, halve or full",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for SelfEcho {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SelfEcho::Ignore => write!(f, "ignore"),
+            SelfEcho::Halve => write!(f, "halve"),
+            SelfEcho::Full => write!(f, "full"),
+        }
+    }
+}
+
+/// Every word of the messages sent in `guild` since `since`, as `tokenize`
+/// finds them. Only the last [`LOG_CAPACITY`] messages are looked at.
+pub fn sent_words<F, I>(
+    log: &SentLog,
+    guild: Option<u64>,
+    since: DateTime<Utc>,
+    mut tokenize: F,
+) -> HashSet<String>
+where
+    F: FnMut(&str) -> I,
+    I: IntoIterator<Item = String>,
+{
+    let mut words = HashSet::new();
+
+    for message in log
+        .last(guild, LOG_CAPACITY)
+        .take_while(|message| message.time >= since)
+    {
+        // The content might show the word differently, e.g. as emoji
+        words.extend(tokenize(&message.content));
+        words.extend(tokenize(&message.word));
+    }
+
+    words
+}
+
+/// Whether every one of `words` is in `sent`. Messages without words aren't
+/// echoes: there is nothing to learn from them anyway.
+pub fn is_echo(words: &[String], sent: &HashSet<String>) -> bool {
+    !words.is_empty() && words.iter().all(|word| sent.contains(word))
+}
+
+/// Decides which messages repeating pino are learned, following a
+/// [`SelfEcho`].
+#[derive(Debug, Default)]
+pub struct EchoFilter {
+    mode: SelfEcho,
+    /// Whether the next echo of the guild is skipped, when halving.
+    skip_next: HashMap<Option<u64>, bool>,
+}
+
+impl EchoFilter {
+    pub fn new(mode: SelfEcho) -> Self {
+        Self {
+            mode,
+            skip_next: HashMap::new(),
+        }
+    }
+
+    /// Whether to learn `words`, heard in `guild`. They are held back if they
+    /// only repeat what pino sent there since `since`, read with `tokenize`
+    /// like the messages heard.
+    pub fn learn<F, I>(
+        &mut self,
+        log: &SentLog,
+        guild: Option<u64>,
+        words: &[String],
+        since: DateTime<Utc>,
+        tokenize: F,
+    ) -> bool
+    where
+        F: FnMut(&str) -> I,
+        I: IntoIterator<Item = String>,
+    {
+        if self.mode == SelfEcho::Full {
+            return true;
+        }
+
+        if !is_echo(words, &sent_words(log, guild, since, tokenize)) {
+            return true;
+        }
+
+        match self.mode {
+            SelfEcho::Ignore => false,
+            SelfEcho::Halve => {
+                let skip = self.skip_next.entry(guild).or_default();
+                *skip = !*skip;
+
+                // The first one is learned
+                *skip
+            }
+            SelfEcho::Full => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sent_log::{SendKind, SentMessage};
+    use chrono::Duration;
+
+    fn tokenize(content: &str) -> Vec<String> {
+        content
+            .split(|c: char| !c.is_alphabetic())
+            .filter(|word| !word.is_empty())
+            .map(str::to_lowercase)
+            .collect()
+    }
+
+    fn sent(log: &mut SentLog, guild: Option<u64>, word: &str, time: DateTime<Utc>) {
+        log.push(
+            guild,
+            SentMessage {
+                time,
+                word: word.to_owned(),
+                content: format!("Oggi: **{}**", word),
+                channel_id: Some(42),
+                message_id: Some(1),
+                kind: SendKind::Scheduled,
+            },
+        );
+    }
+
+    #[test]
+    fn parsing() {
+        for mode in &[SelfEcho::Ignore, SelfEcho::Halve, SelfEcho::Full] {
+            assert_eq!(Ok(*mode), mode.to_string().parse());
+        }
+        assert_eq!(Ok(SelfEcho::Halve), "HALVE".parse());
+        assert!("half".parse::<SelfEcho>().is_err());
+    }
+
+    #[test]
+    fn words() {
+        let now = Utc::now();
+        let mut log = SentLog::default();
+        sent(&mut log, Some(1), "pizza", now - Duration::hours(2));
+        sent(&mut log, Some(1), "pasta", now - Duration::minutes(10));
+        sent(&mut log, Some(2), "gnocchi", now);
+
+        let words = sent_words(&log, Some(1), now - Duration::hours(1), tokenize);
+        let expected: HashSet<_> = ["oggi", "pasta"].iter().map(|w| w.to_string()).collect();
+        assert_eq!(expected, words);
+
+        assert!(is_echo(&tokenize("Oggi: pasta"), &words));
+        assert!(is_echo(&tokenize("pasta pasta"), &words));
+        assert!(!is_echo(&tokenize("oggi pizza"), &words));
+        assert!(!is_echo(&[], &words));
+    }
+
+    #[test]
+    fn bridge() {
+        let now = Utc::now();
+        let mut log = SentLog::default();
+        let since = now - Duration::minutes(30);
+        sent(&mut log, Some(1), "pizza", now - Duration::minutes(1));
+
+        // a bridge posts pino's message back, as someone else
+        let echo = tokenize("Oggi: **pizza**");
+        let mut filter = EchoFilter::new(SelfEcho::Ignore);
+        for _ in 0..3 {
+            assert!(!filter.learn(&log, Some(1), &echo, since, tokenize));
+        }
+
+        // people talking about it are still heard
+        let reply = tokenize("pizza again? I want pasta");
+        assert!(filter.learn(&log, Some(1), &reply, since, tokenize));
+        // and so is the echo elsewhere, or once forgotten
+        assert!(filter.learn(&log, Some(2), &echo, since, tokenize));
+        let later = now + Duration::hours(1);
+        assert!(filter.learn(&log, Some(1), &echo, later, tokenize));
+
+        let mut filter = EchoFilter::new(SelfEcho::Full);
+        assert!(filter.learn(&log, Some(1), &echo, since, tokenize));
+    }
+
+    #[test]
+    fn halving() {
+        let now = Utc::now();
+        let mut log = SentLog::default();
+        let since = now - Duration::minutes(30);
+        sent(&mut log, Some(1), "pizza", now);
+        sent(&mut log, Some(2), "pizza", now);
+
+        let echo = tokenize("oggi pizza");
+        let mut filter = EchoFilter::new(SelfEcho::Halve);
+        let learned: Vec<_> = (0..5)
+            .map(|_| filter.learn(&log, Some(1), &echo, since, tokenize))
+            .collect();
+        assert_eq!(vec![true, false, true, false, true], learned);
+
+        // every guild counts its own echoes, other messages don't count
+        assert!(filter.learn(&log, Some(2), &echo, since, tokenize));
+        assert!(filter.learn(&log, Some(1), &tokenize("pasta"), since, tokenize));
+        assert!(!filter.learn(&log, Some(1), &echo, since, tokenize));
+    }
+}
@@ -0,0 +1,204 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+
+use crate::i18n::{tr, Lang};
+
+/// Messages remembered for each guild.
+pub const LOG_CAPACITY: usize = 50;
+
+/// Guild id under which messages sent outside of guilds are logged. Real ids
+/// are never 0.
+const NO_GUILD: u64 = 0;
+
+/// Why pino sent a message.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SendKind {
+    /// The timer of the send loop went off.
+    Scheduled,
+    /// Someone used `!pino speak`.
+    Forced,
+    /// An admin used `!pino schedule`.
+    Planned,
+}
+
+/// A message sent by pino.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SentMessage {
+    pub time: DateTime<Utc>,
+    /// The word that was picked. The content might show it differently, e.g. as emoji.
+    #[serde(default)]
+    pub word: String,
+    pub content: String,
+    /// `None` if the message was posted through the webhook.
+    pub channel_id: Option<u64>,
+    /// `None` if the message was posted through the webhook.
+    #[serde(default)]
+    pub message_id: Option<u64>,
+    pub kind: SendKind,
+}
+
+impl SentMessage {
+    /// One line of `!pino history`.
+    pub fn describe(&self, lang: Lang) -> String {
+        let place = match self.channel_id {
+            Some(channel_id) => tr(
+                lang,
+                "history.channel",
+                &[("channel", &format!("<#{}>", channel_id))],
+            ),
+            None => tr(lang, "history.webhook", &[]),
+        };
+        let kind = match self.kind {
+            SendKind::Scheduled => "history.scheduled",
+            SendKind::Forced => "history.forced",
+            SendKind::Planned => "history.planned",
+        };
+
+        format!(
+            "<t:{}:R> **{}** {} ({})",
+            self.time.timestamp(),
+            self.content,
+            place,
+            tr(lang, kind, &[])
+        )
+    }
+}
+
+/// The last messages sent in every guild, oldest first.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct SentLog {
+    guilds: BTreeMap<u64, VecDeque<SentMessage>>,
+}
+
+impl SentLog {
+    /// Log a message, forgetting the oldest one of the guild if it already has
+    /// `LOG_CAPACITY` messages.
+    pub fn push(&mut self, guild_id: Option<u64>, message: SentMessage) {
+        let log = self.guilds.entry(guild_id.unwrap_or(NO_GUILD)).or_default();
+
+        if log.len() == LOG_CAPACITY {
+            log.pop_front();
+        }
+
+        log.push_back(message);
+    }
+
+    /// The last `n` messages sent in the guild, newest first.
+    pub fn last(&self, guild_id: Option<u64>, n: usize) -> impl Iterator<Item = &SentMessage> {
+        self.guilds
+            .get(&guild_id.unwrap_or(NO_GUILD))
+            .into_iter()
+            .flat_map(|log| log.iter().rev())
+            .take(n)
+    }
+
+    /// The message with the given id, if it was sent in the guild and is still in the log.
+    pub fn find(&self, guild_id: Option<u64>, message_id: u64) -> Option<&SentMessage> {
+        self.guilds
+            .get(&guild_id.unwrap_or(NO_GUILD))?
+            .iter()
+            .find(|message| message.message_id == Some(message_id))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.guilds.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn message(content: &str) -> SentMessage {
+        SentMessage {
+            time: Utc.timestamp_opt(1_600_000_000, 0).unwrap(),
+            word: content.to_owned(),
+            content: content.to_owned(),
+            channel_id: Some(42),
+            message_id: None,
+            kind: SendKind::Scheduled,
+        }
+    }
+
+    #[test]
+    fn eviction() {
+        let mut log = SentLog::default();
+        assert!(log.is_empty());
+
+        for i in 0..LOG_CAPACITY + 5 {
+            log.push(Some(1), message(&i.to_string()));
+        }
+        log.push(None, message("dm"));
+
+        let last: Vec<_> = log.last(Some(1), usize::MAX).collect();
+        assert_eq!(LOG_CAPACITY, last.len());
+        assert_eq!("54", last[0].content);
+        assert_eq!("5", last[LOG_CAPACITY - 1].content);
+
+        let last: Vec<_> = log.last(Some(1), 2).map(|m| m.content.as_str()).collect();
+        assert_eq!(vec!["54", "53"], last);
+
+        assert_eq!(1, log.last(None, 10).count());
+        assert_eq!(0, log.last(Some(2), 10).count());
+    }
+
+    #[test]
+    fn find() {
+        let mut log = SentLog::default();
+        log.push(
+            Some(1),
+            SentMessage {
+                message_id: Some(7),
+                ..message("ciao")
+            },
+        );
+        log.push(Some(1), message("webhook"));
+
+        assert_eq!("ciao", log.find(Some(1), 7).unwrap().content);
+        assert!(log.find(Some(2), 7).is_none());
+        assert!(log.find(Some(1), 8).is_none());
+    }
+
+    #[test]
+    fn describe() {
+        assert_eq!(
+            "<t:1600000000:R> **ciao** in <#42> (scheduled)",
+            message("ciao").describe(Lang::En)
+        );
+
+        let forced = SentMessage {
+            kind: SendKind::Forced,
+            ..message("gnocchi")
+        };
+        assert_eq!(
+            "<t:1600000000:R> **gnocchi** in <#42> (on demand)",
+            forced.describe(Lang::En)
+        );
+
+        let webhook = SentMessage {
+            channel_id: None,
+            ..message("pino")
+        };
+        assert_eq!(
+            "<t:1600000000:R> **pino** through the webhook (scheduled)",
+            webhook.describe(Lang::En)
+        );
+        assert_eq!(
+            "<t:1600000000:R> **pino** tramite il webhook (di routine)",
+            webhook.describe(Lang::It)
+        );
+    }
+
+    #[test]
+    fn serialization() {
+        let mut log = SentLog::default();
+        log.push(Some(1), message("ciao"));
+        log.push(None, message("dm"));
+
+        let json = serde_json::to_string(&log).unwrap();
+        assert_eq!(log, serde_json::from_str(&json).unwrap());
+    }
+}
@@ -0,0 +1,123 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{fmt, ops::Range, str::FromStr};
+
+/// `--shard-range`: the shards `start..end` out of `total`, run by this
+/// process while other processes run the rest.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShardRange {
+    pub start: u32,
+    /// Not included.
+    pub end: u32,
+    pub total: u32,
+}
+
+impl FromStr for ShardRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("`{}` is not a shard range like `0..4/8`", s);
+
+        let (range, total) = s.split_once('/').ok_or_else(invalid)?;
+        let (start, end) = range.split_once("..").ok_or_else(invalid)?;
+        let number = |n: &str| n.trim().parse::<u32>().map_err(|_| invalid());
+        let range = ShardRange {
+            start: number(start)?,
+            end: number(end)?,
+            total: number(total)?,
+        };
+
+        if range.start >= range.end || range.end > range.total {
+            return Err(format!(
+                "`{}` must have at least one shard, and end at most at the total",
+                s
+            ));
+        }
+
+        Ok(range)
+    }
+}
+
+impl fmt::Display for ShardRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}..{}/{}", self.start, self.end, self.total)
+    }
+}
+
+impl Serialize for ShardRange {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ShardRange {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// How the connection to Discord is split, from `--shards` and
+/// `--shard-range`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Sharding {
+    /// A single connection, the default.
+    Single,
+    /// Every shard of `total` in this process.
+    All(u32),
+    Range(ShardRange),
+}
+
+impl Sharding {
+    pub fn new(shards: Option<u32>, range: Option<ShardRange>) -> Self {
+        match (range, shards) {
+            (Some(range), _) => Sharding::Range(range),
+            (None, Some(total)) => Sharding::All(total),
+            (None, None) => Sharding::Single,
+        }
+    }
+
+    /// The ids of the shards run by this process.
+    pub fn shards(self) -> Range<u32> {
+        match self {
+            Sharding::Single => 0..1,
+            Sharding::All(total) => 0..total,
+            Sharding::Range(range) => range.start..range.end,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranges() {
+        let range: ShardRange = "2..4/8".parse().unwrap();
+        assert_eq!(
+            ShardRange {
+                start: 2,
+                end: 4,
+                total: 8
+            },
+            range
+        );
+        assert_eq!("2..4/8", range.to_string());
+
+        assert!("0..8/8".parse::<ShardRange>().is_ok());
+        assert!("4..4/8".parse::<ShardRange>().is_err());
+        assert!("4..2/8".parse::<ShardRange>().is_err());
+        assert!("0..9/8".parse::<ShardRange>().is_err());
+        assert!("0..4".parse::<ShardRange>().is_err());
+        assert!("0-4/8".parse::<ShardRange>().is_err());
+        assert!("a..4/8".parse::<ShardRange>().is_err());
+    }
+
+    #[test]
+    fn shards() {
+        assert_eq!(0..1, Sharding::new(None, None).shards());
+        assert_eq!(0..3, Sharding::new(Some(3), None).shards());
+
+        let range = "2..4/8".parse().unwrap();
+        assert_eq!(2..4, Sharding::new(None, Some(range)).shards());
+    }
+}
@@ -0,0 +1,160 @@
+use std::{future::Future, sync::Arc, time::Duration};
+use tokio::{sync::watch, task::JoinHandle};
+use tracing::error;
+
+/// How long the background tasks get to finish after a shutdown is requested.
+pub const TASKS_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The background tasks, which stop when a shutdown is requested.
+pub struct Shutdown {
+    sender: Arc<watch::Sender<bool>>,
+    receiver: watch::Receiver<bool>,
+    tasks: Vec<JoinHandle<()>>,
+}
+
+/// Requests a shutdown from anywhere, e.g. a signal handler.
+#[derive(Clone)]
+pub struct Trigger(Arc<watch::Sender<bool>>);
+
+impl Trigger {
+    pub fn fire(&self) {
+        self.0.send_replace(true);
+    }
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        let (sender, receiver) = watch::channel(false);
+
+        Self {
+            sender: Arc::new(sender),
+            receiver,
+            tasks: Vec::new(),
+        }
+    }
+}
+
+impl Shutdown {
+    /// A receiver to check with [`is_requested`] or wait on with [`requested`].
+    pub fn subscribe(&self) -> watch::Receiver<bool> {
+        self.receiver.clone()
+    }
+
+    pub fn trigger(&self) -> Trigger {
+        Trigger(self.sender.clone())
+    }
+
+    /// Spawn a task that is waited for when shutting down. It must return
+    /// soon after [`requested`] completes on the receiver it gets.
+    pub fn spawn<F>(&mut self, task: impl FnOnce(watch::Receiver<bool>) -> F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        let handle = tokio::spawn(task(self.subscribe()));
+        self.tasks.push(handle);
+    }
+
+    /// Request a shutdown, if nobody did yet, and wait for the tasks for at
+    /// most `timeout`. Returns whether they all finished.
+    pub async fn stop(self, timeout: Duration) -> bool {
+        self.trigger().fire();
+
+        let tasks = self.tasks;
+        let finished = tokio::time::timeout(timeout, async move {
+            for task in tasks {
+                if let Err(e) = task.await {
+                    error!("Background task failed: {}", e);
+                }
+            }
+        })
+        .await;
+
+        finished.is_ok()
+    }
+}
+
+/// Whether a shutdown has been requested.
+pub fn is_requested(receiver: &watch::Receiver<bool>) -> bool {
+    *receiver.borrow()
+}
+
+/// Completes once a shutdown is requested.
+pub async fn requested(receiver: &mut watch::Receiver<bool>) {
+    // An error means that the sender is gone, nobody can stop the task anymore
+    let _ = receiver.wait_for(|&requested| requested).await;
+}
+
+/// Completes on ctrl-c, or on SIGTERM on unix.
+pub async fn signal() -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut terminate = signal(SignalKind::terminate())?;
+
+        tokio::select! {
+            result = tokio::signal::ctrl_c() => result,
+            _ = terminate.recv() => Ok(()),
+        }
+    }
+
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const TICK: Duration = Duration::from_millis(10);
+
+    #[tokio::test]
+    async fn tasks_stop() {
+        let mut shutdown = Shutdown::default();
+        let flag = shutdown.subscribe();
+        let ticks = Arc::new(AtomicUsize::new(0));
+
+        // like the periodic saves
+        {
+            let ticks = ticks.clone();
+
+            shutdown.spawn(|mut stop| async move {
+                loop {
+                    tokio::select! {
+                        _ = tokio::time::sleep(TICK) => {}
+                        _ = requested(&mut stop) => break,
+                    }
+
+                    ticks.fetch_add(1, Ordering::SeqCst);
+                }
+            });
+        }
+        // a task that is already done
+        shutdown.spawn(|_stop| async {});
+
+        tokio::time::sleep(TICK * 5).await;
+        assert!(!is_requested(&flag));
+
+        shutdown.trigger().fire();
+        assert!(is_requested(&flag));
+        assert!(shutdown.stop(Duration::from_secs(5)).await);
+
+        let after_stop = ticks.load(Ordering::SeqCst);
+        assert!(after_stop > 0);
+
+        tokio::time::sleep(TICK * 5).await;
+        assert_eq!(after_stop, ticks.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn stuck_tasks_time_out() {
+        let mut shutdown = Shutdown::default();
+
+        shutdown.spawn(|_stop| async {
+            tokio::time::sleep(Duration::from_secs(60)).await;
+        });
+
+        assert!(!shutdown.stop(TICK).await);
+    }
+}
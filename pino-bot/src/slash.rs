@@ -0,0 +1,453 @@
+use serenity::{
+    async_trait,
+    builder::{
+        AutocompleteChoice, CreateActionRow, CreateAutocompleteResponse, CreateCommand,
+        CreateCommandOption, CreateEmbed, CreateInteractionResponse,
+        CreateInteractionResponseFollowup, CreateInteractionResponseMessage,
+    },
+    model::{
+        application::{CommandData, CommandDataOptionValue, CommandInteraction, CommandOptionType},
+        id::{ChannelId, GuildId, MessageId, UserId},
+        permissions::Permissions,
+    },
+};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::command_io::CommandIo;
+
+/// The slash command every command of pino is a subcommand of, e.g. `/pino top`.
+pub const NAME: &str = "pino";
+/// Shown in the hints of the slash commands instead of the prefix.
+pub const PREFIX: &str = "/pino";
+
+/// What kind of value an option of a slash command takes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OptionKind {
+    /// A word, suggested while typing among the words of the guild.
+    Word,
+    /// A number of words, up to the max of an embed.
+    Amount,
+    /// A switch, passed to the handler as `--<name>` when on.
+    Flag,
+    /// One of these values, picked from a list.
+    Choice(&'static [&'static str]),
+    /// Any text, spaces included.
+    Text,
+}
+
+/// An option of a slash command. The handler gets them as arguments, in the
+/// order they are declared, as if they were typed after a prefix command.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SlashOption {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub kind: OptionKind,
+    pub required: bool,
+}
+
+/// Max value of an [`OptionKind::Amount`].
+const AMOUNT_MAX: u64 = 25;
+
+impl SlashOption {
+    fn build(&self) -> CreateCommandOption {
+        let option = match self.kind {
+            OptionKind::Word => {
+                CreateCommandOption::new(CommandOptionType::String, self.name, self.description)
+                    .set_autocomplete(true)
+            }
+            OptionKind::Amount => {
+                CreateCommandOption::new(CommandOptionType::Integer, self.name, self.description)
+                    .min_int_value(1)
+                    .max_int_value(AMOUNT_MAX)
+            }
+            OptionKind::Flag => {
+                CreateCommandOption::new(CommandOptionType::Boolean, self.name, self.description)
+            }
+            OptionKind::Choice(values) => values.iter().fold(
+                CreateCommandOption::new(CommandOptionType::String, self.name, self.description),
+                |option, &value| option.add_string_choice(value, value),
+            ),
+            OptionKind::Text => {
+                CreateCommandOption::new(CommandOptionType::String, self.name, self.description)
+            }
+        };
+
+        option.required(self.required)
+    }
+}
+
+/// `/pino` with a subcommand for each `(name, description, options)`.
+pub fn command<'a>(
+    subcommands: impl IntoIterator<Item = (&'a str, String, &'a [SlashOption])>,
+) -> CreateCommand {
+    subcommands.into_iter().fold(
+        CreateCommand::new(NAME).description("Pino repeats the most said words"),
+        |command, (name, description, options)| {
+            let subcommand = options.iter().fold(
+                CreateCommandOption::new(CommandOptionType::SubCommand, name, description),
+                |subcommand, option| subcommand.add_sub_option(option.build()),
+            );
+
+            command.add_option(subcommand)
+        },
+    )
+}
+
+/// The subcommand of `/pino` that was used, and its options as the arguments
+/// of the prefix command: values as they are, flags as `--<name>` when on.
+/// `None` if it's not `/pino <subcommand>`.
+pub fn arguments(data: &CommandData) -> Option<(&str, Vec<String>)> {
+    if data.name != NAME {
+        return None;
+    }
+
+    let subcommand = data.options.first()?;
+    let options = match &subcommand.value {
+        CommandDataOptionValue::SubCommand(options) => options,
+        _ => return None,
+    };
+
+    let args = options
+        .iter()
+        .filter_map(|option| match &option.value {
+            CommandDataOptionValue::String(value) => Some(value.clone()),
+            CommandDataOptionValue::Integer(value) => Some(value.to_string()),
+            CommandDataOptionValue::Boolean(true) => Some(format!("--{}", option.name)),
+            _ => None,
+        })
+        .collect();
+
+    Some((&subcommand.name, args))
+}
+
+/// How many words Discord shows at most while an option is typed.
+pub const CHOICES_MAX: usize = 25;
+/// Discord refuses longer choices.
+const CHOICE_MAX_LENGTH: usize = 100;
+
+/// Suggest `words` for the option being typed, in their order.
+pub fn choices<'a>(words: impl IntoIterator<Item = &'a str>) -> CreateInteractionResponse {
+    let choices = words
+        .into_iter()
+        .filter(|word| word.chars().count() <= CHOICE_MAX_LENGTH)
+        .take(CHOICES_MAX)
+        .map(|word| AutocompleteChoice::new(word, word))
+        .collect();
+
+    CreateInteractionResponse::Autocomplete(CreateAutocompleteResponse::new().set_choices(choices))
+}
+
+/// Permissions of whoever used the command in its channel, as Discord sent
+/// them with the interaction. Outside of guilds nobody has any.
+pub fn permissions(interaction: &CommandInteraction) -> Permissions {
+    interaction
+        .member
+        .as_ref()
+        .and_then(|member| member.permissions)
+        .unwrap_or_else(Permissions::empty)
+}
+
+/// A slash command being answered. The first reply answers the interaction,
+/// the other ones follow it up.
+pub struct SlashCommand<'a> {
+    interaction: &'a CommandInteraction,
+    /// Whether every reply is only for whoever used the command.
+    ephemeral: bool,
+    answered: AtomicBool,
+}
+
+impl<'a> SlashCommand<'a> {
+    pub fn new(interaction: &'a CommandInteraction, ephemeral: bool) -> Self {
+        Self {
+            interaction,
+            ephemeral,
+            answered: AtomicBool::new(false),
+        }
+    }
+
+    /// Whether Discord got an answer, else it shows the command as failed.
+    pub fn answered(&self) -> bool {
+        self.answered.load(Ordering::SeqCst)
+    }
+
+    async fn send(
+        &self,
+        context: &serenity::client::Context,
+        content: Option<String>,
+        embed: Option<CreateEmbed>,
+        ephemeral: bool,
+    ) -> serenity::Result<()> {
+        if self.answered.swap(true, Ordering::SeqCst) {
+            let mut followup = CreateInteractionResponseFollowup::new().ephemeral(ephemeral);
+            if let Some(content) = content {
+                followup = followup.content(content);
+            }
+            if let Some(embed) = embed {
+                followup = followup.embed(embed);
+            }
+
+            self.interaction
+                .create_followup(&context.http, followup)
+                .await
+                .map(drop)
+        } else {
+            let mut message = CreateInteractionResponseMessage::new().ephemeral(ephemeral);
+            if let Some(content) = content {
+                message = message.content(content);
+            }
+            if let Some(embed) = embed {
+                message = message.embed(embed);
+            }
+
+            self.interaction
+                .create_response(&context.http, CreateInteractionResponse::Message(message))
+                .await
+        }
+    }
+}
+
+#[async_trait]
+impl CommandIo for SlashCommand<'_> {
+    fn guild_id(&self) -> Option<GuildId> {
+        self.interaction.guild_id
+    }
+
+    fn channel_id(&self) -> ChannelId {
+        self.interaction.channel_id
+    }
+
+    fn author_id(&self) -> UserId {
+        self.interaction.user.id
+    }
+
+    async fn permissions(&self, _context: &serenity::client::Context) -> Permissions {
+        permissions(self.interaction)
+    }
+
+    async fn reply_text(
+        &self,
+        context: &serenity::client::Context,
+        content: String,
+    ) -> serenity::Result<()> {
+        self.send(context, Some(content), None, self.ephemeral)
+            .await
+    }
+
+    async fn reply_embed(
+        &self,
+        context: &serenity::client::Context,
+        content: Option<String>,
+        embed: CreateEmbed,
+    ) -> serenity::Result<()> {
+        self.send(context, content, Some(embed), self.ephemeral)
+            .await
+    }
+
+    async fn reply_components(
+        &self,
+        context: &serenity::client::Context,
+        embed: CreateEmbed,
+        components: Vec<CreateActionRow>,
+    ) -> serenity::Result<MessageId> {
+        if self.answered.swap(true, Ordering::SeqCst) {
+            let followup = CreateInteractionResponseFollowup::new()
+                .ephemeral(self.ephemeral)
+                .embed(embed)
+                .components(components);
+
+            return Ok(self
+                .interaction
+                .create_followup(&context.http, followup)
+                .await?
+                .id);
+        }
+
+        let message = CreateInteractionResponseMessage::new()
+            .ephemeral(self.ephemeral)
+            .embed(embed)
+            .components(components);
+        self.interaction
+            .create_response(&context.http, CreateInteractionResponse::Message(message))
+            .await?;
+
+        Ok(self.interaction.get_response(&context.http).await?.id)
+    }
+
+    async fn reply_ephemeral(
+        &self,
+        context: &serenity::client::Context,
+        content: String,
+    ) -> serenity::Result<()> {
+        self.send(context, Some(content), None, true).await
+    }
+
+    /// Interactions can't get reactions, the emoji is an ephemeral reply.
+    async fn react(
+        &self,
+        context: &serenity::client::Context,
+        emoji: &str,
+    ) -> serenity::Result<()> {
+        self.send(context, Some(emoji.to_owned()), None, true).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn data(options: serde_json::Value) -> CommandData {
+        serde_json::from_value(json!({
+            "id": "1",
+            "name": "pino",
+            "type": 1,
+            "options": options,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn extracting_arguments() {
+        let top = data(json!([{
+            "name": "top",
+            "type": 1,
+            "options": [
+                { "name": "amount", "type": 4, "value": 10 },
+                { "name": "trending", "type": 5, "value": true },
+            ],
+        }]));
+        assert_eq!(
+            Some(("top", vec!["10".to_owned(), "--trending".to_owned()])),
+            arguments(&top)
+        );
+
+        let stats = data(json!([{
+            "name": "stats",
+            "type": 1,
+            "options": [{ "name": "word", "type": 3, "value": "pizza" }],
+        }]));
+        assert_eq!(Some(("stats", vec!["pizza".to_owned()])), arguments(&stats));
+
+        // switches that are off aren't there
+        let top = data(json!([{
+            "name": "top",
+            "type": 1,
+            "options": [{ "name": "trending", "type": 5, "value": false }],
+        }]));
+        assert_eq!(Some(("top", vec![])), arguments(&top));
+
+        let speak = data(json!([{ "name": "speak", "type": 1, "options": [] }]));
+        assert_eq!(Some(("speak", vec![])), arguments(&speak));
+
+        // not a subcommand of pino
+        assert_eq!(None, arguments(&data(json!([]))));
+        let mut other = data(json!([{ "name": "speak", "type": 1, "options": [] }]));
+        other.name = "other".to_owned();
+        assert_eq!(None, arguments(&other));
+    }
+
+    #[test]
+    fn member_permissions() {
+        let interaction = |member: serde_json::Value| -> CommandInteraction {
+            let mut interaction = json!({
+                "id": "1",
+                "application_id": "2",
+                "type": 2,
+                "data": { "id": "1", "name": "pino", "type": 1 },
+                "channel_id": "3",
+                "user": { "id": "4", "username": "walter", "discriminator": "0000" },
+                "token": "token",
+                "version": 1,
+                "locale": "en-US",
+                "entitlements": [],
+                "attachment_size_limit": 10485760,
+            });
+            if !member.is_null() {
+                interaction["guild_id"] = json!("5");
+                interaction["member"] = member;
+            }
+
+            serde_json::from_value(interaction).unwrap()
+        };
+        let member = |permissions: &str| {
+            json!({
+                "user": { "id": "4", "username": "walter", "discriminator": "0000" },
+                "roles": [],
+                "joined_at": "2021-03-14T12:00:00Z",
+                "deaf": false,
+                "mute": false,
+                "flags": 0,
+                "permissions": permissions,
+            })
+        };
+
+        let moderator = Permissions::MANAGE_MESSAGES.bits().to_string();
+        assert_eq!(
+            Permissions::MANAGE_MESSAGES,
+            permissions(&interaction(member(&moderator)))
+        );
+        // in direct messages there's no member
+        assert_eq!(
+            Permissions::empty(),
+            permissions(&interaction(serde_json::Value::Null))
+        );
+    }
+
+    #[test]
+    fn registering() {
+        let options = [
+            SlashOption {
+                name: "amount",
+                description: "How many words",
+                kind: OptionKind::Amount,
+                required: false,
+            },
+            SlashOption {
+                name: "word",
+                description: "The word",
+                kind: OptionKind::Word,
+                required: true,
+            },
+            SlashOption {
+                name: "action",
+                description: "What to do",
+                kind: OptionKind::Choice(&["set", "unset"]),
+                required: false,
+            },
+        ];
+        let command = serde_json::to_value(command(vec![
+            ("top", "the most said words".to_owned(), &options[..1]),
+            ("forget", "forget a word".to_owned(), &options[1..2]),
+            ("speak", "say it now".to_owned(), &[][..]),
+            ("settings", "change a setting".to_owned(), &options[2..]),
+        ]))
+        .unwrap();
+
+        assert_eq!("pino", command["name"]);
+        let subcommands = command["options"].as_array().unwrap();
+        assert_eq!(4, subcommands.len());
+        assert_eq!("top", subcommands[0]["name"]);
+        assert_eq!(25, subcommands[0]["options"][0]["max_value"]);
+        assert_eq!(false, subcommands[0]["options"][0]["required"]);
+        assert_eq!(true, subcommands[1]["options"][0]["autocomplete"]);
+        assert_eq!(true, subcommands[1]["options"][0]["required"]);
+        assert!(subcommands[2]["options"]
+            .as_array()
+            .is_none_or(|options| options.is_empty()));
+        let choices = subcommands[3]["options"][0]["choices"].as_array().unwrap();
+        assert_eq!(2, choices.len());
+        assert_eq!("unset", choices[1]["value"]);
+    }
+
+    #[test]
+    fn suggesting() {
+        let long = "a".repeat(101);
+        let response = serde_json::to_value(choices(vec!["pino", long.as_str(), "pizza"])).unwrap();
+
+        let choices = response["data"]["choices"].as_array().unwrap();
+        assert_eq!(2, choices.len());
+        assert_eq!("pino", choices[0]["name"]);
+        assert_eq!("pino", choices[0]["value"]);
+        assert_eq!("pizza", choices[1]["value"]);
+    }
+}
@@ -0,0 +1,100 @@
+use chrono::{DateTime, Utc};
+use pino_core::store::WordStore;
+use std::collections::{HashMap, VecDeque};
+
+/// Amount of instances of every word at a given time.
+pub type WordCounts = HashMap<String, usize>;
+
+/// Circular buffer of the word counts taken at regular intervals, oldest first.
+pub struct History {
+    capacity: usize,
+    snapshots: VecDeque<(DateTime<Utc>, WordCounts)>,
+}
+
+impl History {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            snapshots: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Add a snapshot, dropping the oldest one if the buffer is full.
+    pub fn push(&mut self, time: DateTime<Utc>, counts: WordCounts) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.snapshots.len() == self.capacity {
+            self.snapshots.pop_front();
+        }
+
+        self.snapshots.push_back((time, counts));
+    }
+
+    #[cfg(test)]
+    pub fn iter(&self) -> impl Iterator<Item = &(DateTime<Utc>, WordCounts)> {
+        self.snapshots.iter()
+    }
+
+    /// When the latest snapshot was taken.
+    pub fn latest(&self) -> Option<DateTime<Utc>> {
+        self.snapshots.back().map(|(time, _counts)| *time)
+    }
+
+    /// Whether snapshots are kept at all.
+    pub fn is_enabled(&self) -> bool {
+        self.capacity > 0
+    }
+}
+
+/// Take the current count of every word, added up across the guilds.
+pub fn counts(words: &WordStore) -> WordCounts {
+    let mut counts = WordCounts::new();
+
+    for (_guild, words) in words.iter() {
+        for (word, instances) in words {
+            *counts.entry(word.clone()).or_insert(0) += instances.len();
+        }
+    }
+
+    counts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn eviction() {
+        let now = Utc::now();
+        let mut history = History::new(3);
+
+        for i in 0..5 {
+            history.push(now + Duration::minutes(i), WordCounts::new());
+        }
+
+        assert_eq!(3, history.iter().count());
+        let times: Vec<_> = history.iter().map(|(time, _)| *time).collect();
+        assert_eq!(
+            vec![
+                now + Duration::minutes(2),
+                now + Duration::minutes(3),
+                now + Duration::minutes(4)
+            ],
+            times
+        );
+        assert_eq!(Some(now + Duration::minutes(4)), history.latest());
+    }
+
+    #[test]
+    fn zero_capacity() {
+        let mut history = History::new(0);
+        history.push(Utc::now(), WordCounts::new());
+
+        assert_eq!(0, history.iter().count());
+        assert_eq!(None, history.latest());
+        assert!(!history.is_enabled());
+    }
+}
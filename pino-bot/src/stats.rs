@@ -0,0 +1,145 @@
+use chrono::{DateTime, Duration, Utc};
+
+/// Bars of the sparkline, from the lowest to the highest.
+const BARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Count the instances in each of `count` consecutive buckets of length `bucket`,
+/// oldest first. The last bucket ends at `now`, older and newer instances are ignored.
+pub fn buckets(
+    instances: &[DateTime<Utc>],
+    now: DateTime<Utc>,
+    bucket: Duration,
+    count: usize,
+) -> Vec<usize> {
+    let mut buckets = vec![0; count];
+    let bucket_millis = bucket.num_milliseconds().max(1);
+
+    for instance in instances {
+        let age = (now - *instance).num_milliseconds();
+
+        if age < 0 {
+            continue;
+        }
+
+        // 0 is the newest bucket
+        let index = (age / bucket_millis) as usize;
+
+        if index < count {
+            buckets[count - 1 - index] += 1;
+        }
+    }
+
+    buckets
+}
+
+/// One character per bucket, scaled to the largest one. Empty buckets are blank.
+pub fn sparkline(buckets: &[usize]) -> String {
+    let max = buckets.iter().copied().max().unwrap_or(0);
+
+    sparkline_scaled(buckets, max)
+}
+
+/// Like [`sparkline`], but scaled to `max`, so that sparklines of different
+/// buckets can be compared. `max` must not be smaller than any bucket.
+pub fn sparkline_scaled(buckets: &[usize], max: usize) -> String {
+    buckets
+        .iter()
+        .map(|&count| {
+            if count == 0 {
+                ' '
+            } else {
+                // 1..=8, the largest bucket gets the full bar
+                let level = (count * BARS.len()).div_ceil(max);
+                BARS[level - 1]
+            }
+        })
+        .collect()
+}
+
+/// Edit distance between two words, counting insertions, deletions and substitutions.
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + if a == *b { 0 } else { 1 };
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+/// The known word closest to `word`, if any is close enough to be a typo:
+/// at most one edit every three characters. Ties go to the alphabetically first.
+pub fn did_you_mean<'a>(word: &str, known: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    let max_distance = (word.chars().count() / 3).max(1);
+
+    known
+        .into_iter()
+        .map(|candidate| (edit_distance(word, candidate), candidate))
+        .filter(|&(distance, _)| distance <= max_distance)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bucketing() {
+        let now = Utc::now();
+        let bucket = Duration::minutes(10);
+        let instances = vec![
+            now - Duration::minutes(45), // too old
+            now - Duration::minutes(25),
+            now - Duration::minutes(21),
+            now - Duration::minutes(10), // exactly on the edge goes to the older bucket
+            now - Duration::minutes(1),
+            now,
+            now + Duration::minutes(1), // in the future
+        ];
+
+        assert_eq!(vec![0, 2, 1, 2], buckets(&instances, now, bucket, 4));
+        assert_eq!(vec![0; 3], buckets(&[], now, bucket, 3));
+        assert!(buckets(&instances, now, bucket, 0).is_empty());
+    }
+
+    #[test]
+    fn sparklines() {
+        assert_eq!("", sparkline(&[]));
+        assert_eq!("   ", sparkline(&[0, 0, 0]));
+        assert_eq!(" ▁█▄", sparkline(&[0, 1, 8, 4]));
+        assert_eq!("▁█", sparkline(&[1, 100]));
+        assert_eq!("███", sparkline(&[2, 2, 2]));
+    }
+
+    #[test]
+    fn distances() {
+        assert_eq!(0, edit_distance("ciao", "ciao"));
+        assert_eq!(1, edit_distance("ciao", "cia"));
+        assert_eq!(1, edit_distance("ciao", "ciai"));
+        assert_eq!(2, edit_distance("ciao", "icao"));
+        assert_eq!(4, edit_distance("", "ciao"));
+        assert_eq!(1, edit_distance("perché", "perche"));
+    }
+
+    #[test]
+    fn suggestions() {
+        let known = vec!["gnocchi", "gnocco", "ciao", "pino"];
+
+        assert_eq!(Some("gnocchi"), did_you_mean("gnochi", known.clone()));
+        assert_eq!(Some("ciao"), did_you_mean("cia", known.clone()));
+        assert_eq!(None, did_you_mean("pizza", known.clone()));
+        assert_eq!(None, did_you_mean("ciao", vec![]));
+
+        // same distance, alphabetical order wins
+        assert_eq!(Some("pina"), did_you_mean("pinu", vec!["pino", "pina"]));
+    }
+}
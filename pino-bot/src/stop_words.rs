@@ -0,0 +1,35 @@
+use anyhow::Context;
+use std::{collections::HashSet, fs, path::Path};
+
+/// Read the stop words from a file containing one word per line.
+pub fn load(path: &Path) -> anyhow::Result<HashSet<String>> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("reading stop words file {}", path.display()))?;
+
+    Ok(parse(&content))
+}
+
+/// Words are lowercased, like the ones in the word map. Blank lines are skipped.
+pub fn parse(content: &str) -> HashSet<String> {
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_lines() {
+        let words = parse("il\n  LA \n\nche\r\n");
+
+        assert_eq!(3, words.len());
+        assert!(words.contains("il"));
+        assert!(words.contains("la"));
+        assert!(words.contains("che"));
+    }
+}
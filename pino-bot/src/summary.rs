@@ -0,0 +1,63 @@
+use pino_core::summary::Summary;
+use serenity::{builder::CreateEmbed, model::id::UserId, prelude::Mentionable};
+
+use crate::i18n::{tr, Lang};
+
+/// An embed with a field for each highlight of `summary`.
+pub fn embed(summary: &Summary, lang: Lang) -> CreateEmbed {
+    let none = || tr(lang, "summary.none", &[]);
+
+    let words = if summary.top_words.is_empty() {
+        none()
+    } else {
+        summary
+            .top_words
+            .iter()
+            .enumerate()
+            .map(|(i, (word, count))| {
+                tr(
+                    lang,
+                    "summary.word_line",
+                    &[("rank", &(i + 1)), ("word", word), ("count", count)],
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    let user = match summary.top_user {
+        Some((user, count)) => tr(
+            lang,
+            "summary.user_line",
+            &[
+                ("user", &UserId::new(user.get()).mention()),
+                ("count", &count),
+            ],
+        ),
+        None => none(),
+    };
+
+    let hour = match summary.busiest_hour {
+        Some((hour, count)) => tr(
+            lang,
+            "summary.hour_line",
+            &[("hour", &format!("{:02}", hour)), ("count", &count)],
+        ),
+        None => none(),
+    };
+
+    CreateEmbed::new()
+        .title(tr(
+            lang,
+            "summary.title",
+            &[("date", &summary.start.format("%Y-%m-%d"))],
+        ))
+        .field(tr(lang, "summary.words", &[]), words, false)
+        .field(tr(lang, "summary.user", &[]), user, true)
+        .field(tr(lang, "summary.hour", &[]), hour, true)
+        .field(
+            tr(lang, "summary.messages", &[]),
+            summary.messages.to_string(),
+            true,
+        )
+}
@@ -0,0 +1,247 @@
+use std::{
+    any::Any,
+    collections::{BTreeMap, BTreeSet},
+    future::Future,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+use tokio::sync::watch;
+use tracing::{error, warn};
+
+use crate::shutdown::{self, Shutdown};
+
+/// Wait before restarting a task that panicked, doubled at each restart.
+pub const FIRST_BACKOFF: Duration = Duration::from_secs(1);
+/// Longest wait before a restart.
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Runs the background tasks, restarting the ones that panic so that pino
+/// doesn't silently stop speaking.
+#[derive(Clone)]
+pub struct Supervisor {
+    first_backoff: Duration,
+    /// How many times each task was restarted, by name.
+    restarts: Arc<RwLock<BTreeMap<&'static str, usize>>>,
+    /// The tasks waiting to be restarted.
+    down: Arc<RwLock<BTreeSet<&'static str>>>,
+    /// Every task ever spawned, so that none runs twice.
+    spawned: Arc<RwLock<BTreeSet<&'static str>>>,
+}
+
+impl Default for Supervisor {
+    fn default() -> Self {
+        Self::new(FIRST_BACKOFF)
+    }
+}
+
+impl Supervisor {
+    pub fn new(first_backoff: Duration) -> Self {
+        Self {
+            first_backoff,
+            restarts: Arc::new(RwLock::new(BTreeMap::new())),
+            down: Arc::new(RwLock::new(BTreeSet::new())),
+            spawned: Arc::new(RwLock::new(BTreeSet::new())),
+        }
+    }
+
+    /// How many times each task was restarted. Tasks that never panicked
+    /// aren't there.
+    pub fn restarts(&self) -> BTreeMap<&'static str, usize> {
+        self.restarts.read().unwrap().clone()
+    }
+
+    /// The tasks that panicked and haven't been restarted yet, by name.
+    pub fn down(&self) -> Vec<&'static str> {
+        self.down.read().unwrap().iter().copied().collect()
+    }
+
+    /// Wait before the restart number `restart`, counting from 0.
+    fn backoff(&self, restart: usize) -> Duration {
+        let doublings = restart.min(16) as u32;
+        (self.first_backoff * 2u32.pow(doublings)).min(MAX_BACKOFF)
+    }
+
+    /// Spawn a task that is waited for when shutting down, like
+    /// [`Shutdown::spawn`]. `make_task` is called again to restart it after
+    /// a panic, so it must clone what the task needs. The task is over when
+    /// it returns.
+    ///
+    /// Each name is spawned once: a second task with the same name, e.g. a
+    /// second send loop that would post twice as often, is refused. Returns
+    /// whether the task was spawned.
+    pub fn spawn<F, M>(&self, shutdown: &mut Shutdown, name: &'static str, make_task: M) -> bool
+    where
+        M: Fn(watch::Receiver<bool>) -> F + Send + 'static,
+        F: Future<Output = ()> + Send + 'static,
+    {
+        if !self.spawned.write().unwrap().insert(name) {
+            warn!("Task '{}' is already running, not starting it again", name);
+            return false;
+        }
+
+        let supervisor = self.clone();
+
+        shutdown.spawn(move |stop| supervisor.run(name, make_task, stop));
+        true
+    }
+
+    async fn run<F, M>(self, name: &'static str, make_task: M, mut stop: watch::Receiver<bool>)
+    where
+        M: Fn(watch::Receiver<bool>) -> F,
+        F: Future<Output = ()> + Send + 'static,
+    {
+        loop {
+            let error = match tokio::spawn(make_task(stop.clone())).await {
+                Ok(()) => return,
+                Err(e) if e.is_panic() => e.into_panic(),
+                Err(e) => {
+                    warn!("Task '{}' was cancelled: {}", name, e);
+                    return;
+                }
+            };
+
+            if shutdown::is_requested(&stop) {
+                warn!("Task '{}' panicked while shutting down", name);
+                return;
+            }
+
+            let restart = {
+                let mut restarts = self.restarts.write().unwrap();
+                let count = restarts.entry(name).or_insert(0);
+                *count += 1;
+                *count - 1
+            };
+            let backoff = self.backoff(restart);
+            self.down.write().unwrap().insert(name);
+
+            error!(
+                "Task '{}' panicked ({}), restarting it in {} seconds",
+                name,
+                panic_message(&*error),
+                backoff.as_secs_f32()
+            );
+
+            tokio::select! {
+                _ = tokio::time::sleep(backoff) => {}
+                _ = shutdown::requested(&mut stop) => return,
+            }
+
+            self.down.write().unwrap().remove(name);
+        }
+    }
+}
+
+/// The message of a panic, if it has one.
+fn panic_message(error: &(dyn Any + Send)) -> &str {
+    if let Some(message) = error.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = error.downcast_ref::<String>() {
+        message
+    } else {
+        "no message"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const TICK: Duration = Duration::from_millis(10);
+
+    #[tokio::test]
+    async fn panics_restart() {
+        let supervisor = Supervisor::new(TICK);
+        let mut shutdown = Shutdown::default();
+        let runs = Arc::new(AtomicUsize::new(0));
+
+        {
+            let runs = runs.clone();
+
+            // panics the first 3 times, then runs until shutdown
+            supervisor.spawn(&mut shutdown, "flaky", move |mut stop| {
+                let runs = runs.clone();
+
+                async move {
+                    if runs.fetch_add(1, Ordering::SeqCst) < 3 {
+                        panic!("flaky task");
+                    }
+
+                    shutdown::requested(&mut stop).await;
+                }
+            });
+        }
+        supervisor.spawn(&mut shutdown, "done", |_stop| async {});
+
+        // 10 + 20 + 40 ms of backoff
+        tokio::time::sleep(TICK * 20).await;
+        assert_eq!(4, runs.load(Ordering::SeqCst));
+        assert!(supervisor.down().is_empty());
+
+        assert!(shutdown.stop(Duration::from_secs(5)).await);
+        assert_eq!(
+            vec![("flaky", 3)],
+            supervisor.restarts().into_iter().collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn shutdown_during_backoff() {
+        let supervisor = Supervisor::new(Duration::from_secs(60));
+        let mut shutdown = Shutdown::default();
+
+        supervisor.spawn(&mut shutdown, "broken", |_stop| async {
+            panic!("always");
+        });
+
+        tokio::time::sleep(TICK * 5).await;
+        assert_eq!(Some(&1), supervisor.restarts().get("broken"));
+        assert_eq!(vec!["broken"], supervisor.down());
+
+        // doesn't wait for the restart
+        assert!(shutdown.stop(Duration::from_secs(5)).await);
+    }
+
+    #[tokio::test]
+    async fn spawned_once() {
+        let supervisor = Supervisor::default();
+        let mut shutdown = Shutdown::default();
+        let runs = Arc::new(AtomicUsize::new(0));
+
+        // e.g. once per shard
+        let spawned: Vec<_> = (0..3)
+            .map(|_shard| {
+                let runs = runs.clone();
+
+                supervisor.spawn(&mut shutdown, "send loop", move |_stop| {
+                    let runs = runs.clone();
+
+                    async move {
+                        runs.fetch_add(1, Ordering::SeqCst);
+                    }
+                })
+            })
+            .collect();
+
+        assert_eq!(vec![true, false, false], spawned);
+        assert!(shutdown.stop(Duration::from_secs(5)).await);
+        assert_eq!(1, runs.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn backoff() {
+        let supervisor = Supervisor::default();
+
+        assert_eq!(FIRST_BACKOFF, supervisor.backoff(0));
+        assert_eq!(FIRST_BACKOFF * 4, supervisor.backoff(2));
+        assert_eq!(MAX_BACKOFF, supervisor.backoff(10));
+        assert_eq!(MAX_BACKOFF, supervisor.backoff(usize::MAX));
+    }
+
+    #[test]
+    fn messages() {
+        assert_eq!("static", panic_message(&"static"));
+        assert_eq!("formatted 3", panic_message(&format!("formatted {}", 3)));
+        assert_eq!("no message", panic_message(&3));
+    }
+}
@@ -0,0 +1,96 @@
+use anyhow::bail;
+
+/// Fill in the placeholders of a message template: `{word}` with the word and
+/// `{count}` with how many times it has been said. Anything else is kept as is,
+/// and the word itself is never searched for placeholders.
+pub fn render(template: &str, word: &str, count: usize) -> String {
+    fill(template, &[("word", word), ("count", &count.to_string())])
+}
+
+/// Replace each `{name}` in `template` with its value in `values`, like
+/// [`render`] does for `{word}` and `{count}`. The values are never searched
+/// for placeholders.
+pub fn fill(template: &str, values: &[(&str, &str)]) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+
+    'scan: while let Some(start) = rest.find('{') {
+        rendered.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        for (name, value) in values {
+            let placeholder = rest
+                .strip_prefix('{')
+                .and_then(|after| after.strip_prefix(name))
+                .and_then(|after| after.strip_prefix('}'));
+
+            if let Some(after) = placeholder {
+                rendered.push_str(value);
+                rest = after;
+                continue 'scan;
+            }
+        }
+
+        rendered.push('{');
+        rest = &rest[1..];
+    }
+
+    rendered.push_str(rest);
+    rendered
+}
+
+/// Check that the template contains the word, or every message would look the same.
+pub fn validate(template: &str) -> anyhow::Result<()> {
+    if !template.contains("{word}") {
+        bail!("the message template must contain {{word}}");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rendering() {
+        assert_eq!("ciao", render("{word}", "ciao", 3));
+        assert_eq!(
+            "Today's buzzword: **ciao** (said 3 times)",
+            render(
+                "Today's buzzword: **{word}** (said {count} times)",
+                "ciao",
+                3
+            )
+        );
+        assert_eq!("ciao ciao", render("{word} {word}", "ciao", 1));
+        // unknown placeholders and stray braces are kept
+        assert_eq!("{ciao} {} {wor", render("{{word}} {} {wor", "ciao", 1));
+        // placeholders in the word aren't replaced
+        assert_eq!("{count} 2", render("{word} {count}", "{count}", 2));
+    }
+
+    #[test]
+    fn filling() {
+        assert_eq!(
+            "Welcome <@42>, we're talking about pizza",
+            fill(
+                "Welcome {user}, we're talking about {word}",
+                &[("user", "<@42>"), ("word", "pizza")]
+            )
+        );
+        assert_eq!("{word} {user}", fill("{word} {user}", &[]));
+        assert_eq!(
+            "{word}",
+            fill("{user}", &[("user", "{word}"), ("word", "no")])
+        );
+    }
+
+    #[test]
+    fn validation() {
+        assert!(validate("{word}").is_ok());
+        assert!(validate("said {count} times: {word}").is_ok());
+        assert!(validate("said {count} times").is_err());
+        assert!(validate("").is_err());
+    }
+}
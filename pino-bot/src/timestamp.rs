@@ -0,0 +1,32 @@
+use chrono::{DateTime, Utc};
+use serenity::model::Timestamp;
+
+/// The time of a Discord timestamp, e.g. of a message, in the type used everywhere else.
+pub fn to_utc(timestamp: Timestamp) -> DateTime<Utc> {
+    *timestamp
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, TimeZone};
+
+    #[test]
+    fn conversion() {
+        let timestamp = Timestamp::parse("2016-04-30T11:18:25.796Z").unwrap();
+        let expected = NaiveDate::from_ymd_opt(2016, 4, 30)
+            .unwrap()
+            .and_hms_milli_opt(11, 18, 25, 796)
+            .unwrap();
+
+        assert_eq!(Utc.from_utc_datetime(&expected), to_utc(timestamp));
+        assert_eq!(1462015105, to_utc(timestamp).timestamp());
+
+        // other offsets end up in UTC
+        let timestamp = Timestamp::parse("2016-04-30T13:18:25.796+02:00").unwrap();
+        assert_eq!(Utc.from_utc_datetime(&expected), to_utc(timestamp));
+
+        let now = Utc::now();
+        assert_eq!(now, to_utc(Timestamp::from(now)));
+    }
+}
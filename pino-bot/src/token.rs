@@ -0,0 +1,93 @@
+use anyhow::{bail, Context};
+use std::{env, fs, path::Path};
+
+/// Environment variable with the token, used when there's no `--token-file`.
+pub const TOKEN_VAR: &str = "DISCORD_TOKEN";
+
+/// The discord token from `--token-file`, or else from `DISCORD_TOKEN`, or
+/// else from `--token` (which also reads `PINO_TOKEN`).
+pub fn resolve(token_file: Option<&Path>, flag: Option<&str>) -> anyhow::Result<String> {
+    let token = match (token_file, env::var(TOKEN_VAR)) {
+        (Some(path), _) => fs::read_to_string(path)
+            .with_context(|| format!("reading token file {}", path.display()))?
+            .trim()
+            .to_owned(),
+        (None, Ok(token)) => token,
+        (None, Err(_)) => match flag {
+            Some(token) => token.to_owned(),
+            None => bail!(
+                "missing token, pass --token-file <path>, set the {} environment variable, or pass --token (or set PINO_TOKEN)",
+                TOKEN_VAR
+            ),
+        },
+    };
+
+    validate(&token)
+        .map_err(anyhow::Error::msg)
+        .context("invalid token")?;
+    Ok(token)
+}
+
+/// Catch the tokens that would only fail when connecting, with a less clear error.
+pub fn validate(token: &str) -> Result<(), &'static str> {
+    if token.is_empty() {
+        return Err("the token is empty");
+    }
+
+    if token.starts_with(['"', '\''].as_ref()) || token.ends_with(['"', '\''].as_ref()) {
+        return Err("the token is wrapped in quotes, remove them");
+    }
+
+    if token.chars().any(char::is_whitespace) {
+        return Err("the token contains whitespace, copy it again without spaces or newlines");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validation() {
+        assert!(validate("abc.def.ghi").is_ok());
+        assert!(validate("").is_err());
+        assert!(validate("\"abc.def.ghi\"").is_err());
+        assert!(validate("'abc.def.ghi").is_err());
+        assert!(validate(" abc.def.ghi").is_err());
+        assert!(validate("Bot abc.def.ghi").is_err());
+    }
+
+    #[test]
+    fn resolution_order() {
+        let path = env::temp_dir().join(format!("pino-{}-token", std::process::id()));
+        fs::write(&path, "from-file\n").unwrap();
+        let previous = env::var(TOKEN_VAR);
+
+        env::set_var(TOKEN_VAR, "from-env");
+        assert_eq!(
+            "from-file",
+            resolve(Some(&path), Some("from-flag")).unwrap()
+        );
+        assert_eq!("from-env", resolve(None, Some("from-flag")).unwrap());
+
+        env::remove_var(TOKEN_VAR);
+        assert_eq!("from-flag", resolve(None, Some("from-flag")).unwrap());
+        let missing = resolve(None, None).unwrap_err().to_string();
+        assert!(missing.contains("--token-file"));
+        assert!(missing.contains(TOKEN_VAR));
+        assert!(missing.contains("--token"));
+
+        // the file is trimmed, but the other sources are not
+        env::set_var(TOKEN_VAR, "from-env\n");
+        assert!(resolve(None, None).is_err());
+        assert!(resolve(Some(&path.with_extension("missing")), None).is_err());
+
+        match previous {
+            Ok(token) => env::set_var(TOKEN_VAR, token),
+            Err(_) => env::remove_var(TOKEN_VAR),
+        }
+        fs::remove_file(&path).unwrap();
+    }
+}
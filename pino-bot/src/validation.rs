@@ -0,0 +1,358 @@
+use regex::Regex;
+use std::fmt;
+use tracing_subscriber::EnvFilter;
+
+use crate::{commands, i18n::Lang, logging, selection::Scoring, template, Options};
+
+/// Largest `--max-boost`. The boost is added to the counts, bigger ones
+/// would only make the picks random.
+pub const MAX_BOOST_LIMIT: usize = 1_000_000;
+/// Below this `--interval-low` (in seconds), pino risks being rate limited.
+pub const MIN_SAFE_INTERVAL: u64 = 60;
+
+/// Something wrong with the options, found before connecting.
+#[derive(Debug, PartialEq)]
+pub enum OptionsError {
+    IntervalOrder {
+        low: u64,
+        high: u64,
+    },
+    /// Words would be forgotten before pino speaks.
+    MaxAgeTooShort {
+        max_age: u64,
+        interval_high: u64,
+    },
+    MaxBoostTooLarge(usize),
+    /// With `--scoring trending`, the previous window would be forgotten
+    /// before it's compared.
+    TrendingWindowTooLong {
+        trending_window: u64,
+        max_age: u64,
+    },
+    InvalidRegex(String),
+    InvalidPrefix(String),
+    InvalidTemplate(String),
+    /// Also when it comes from `RUST_LOG`.
+    InvalidLogLevel(String),
+    NoShards,
+    /// Not a positive number.
+    TimeScale(f64),
+    /// Two options that can't be used together.
+    Conflict(&'static str, &'static str),
+    /// Only a warning: pino can post faster than Discord likes.
+    FastPosting {
+        interval_low: u64,
+    },
+}
+
+impl OptionsError {
+    /// Whether pino can start anyway.
+    pub fn is_warning(&self) -> bool {
+        matches!(self, OptionsError::FastPosting { .. })
+    }
+}
+
+impl fmt::Display for OptionsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OptionsError::IntervalOrder { low, high } => write!(
+                f,
+                "--interval-low ({}) can't be greater than --interval-high ({})",
+                low, high
+            ),
+            OptionsError::MaxAgeTooShort {
+                max_age,
+                interval_high,
+            } => write!(
+                f,
+                "--max-age ({}) can't be shorter than --interval-high ({}), or words would be forgotten before pino speaks",
+                max_age, interval_high
+            ),
+            OptionsError::MaxBoostTooLarge(max_boost) => write!(
+                f,
+                "--max-boost ({}) can't be greater than {}",
+                max_boost, MAX_BOOST_LIMIT
+            ),
+            OptionsError::TrendingWindowTooLong {
+                trending_window,
+                max_age,
+            } => write!(
+                f,
+                "--trending-window ({}) can't be longer than half of --max-age ({}), or the window before would already be forgotten",
+                trending_window, max_age
+            ),
+            OptionsError::InvalidRegex(e) => write!(f, "--word-regex is not valid: {}", e),
+            OptionsError::InvalidPrefix(e) => write!(f, "--prefix is not valid: {}", e),
+            OptionsError::InvalidTemplate(e) => write!(f, "--message-template is not valid: {}", e),
+            OptionsError::InvalidLogLevel(e) => {
+                write!(f, "--log-level (or RUST_LOG) is not valid: {}", e)
+            }
+            OptionsError::NoShards => write!(f, "--shards must be at least 1"),
+            OptionsError::TimeScale(scale) => {
+                write!(f, "--time-scale ({}) must be greater than 0", scale)
+            }
+            OptionsError::Conflict(a, b) => {
+                write!(f, "--{} and --{} can't be used together", a, b)
+            }
+            OptionsError::FastPosting { interval_low } => write!(
+                f,
+                "--interval-low ({}) is below {} seconds, pino might get rate limited",
+                interval_low, MIN_SAFE_INTERVAL
+            ),
+        }
+    }
+}
+
+impl std::error::Error for OptionsError {}
+
+/// The options that passed validation, with what was built along the way.
+pub struct Validated {
+    pub word_regex: Regex,
+    pub log_filter: EnvFilter,
+    pub warnings: Vec<OptionsError>,
+}
+
+impl Options {
+    /// Check every option, after the config file has been merged in. Returns
+    /// all the problems, warnings included, if any of them is an error.
+    pub fn validate(&self) -> Result<Validated, Vec<OptionsError>> {
+        let mut problems = Vec::new();
+
+        if self.interval_low > self.interval_high {
+            problems.push(OptionsError::IntervalOrder {
+                low: self.interval_low,
+                high: self.interval_high,
+            });
+        }
+
+        if self.max_age < self.interval_high {
+            problems.push(OptionsError::MaxAgeTooShort {
+                max_age: self.max_age,
+                interval_high: self.interval_high,
+            });
+        }
+
+        if self.max_boost > MAX_BOOST_LIMIT {
+            problems.push(OptionsError::MaxBoostTooLarge(self.max_boost));
+        }
+
+        if self.scoring == Scoring::Trending && self.trending_window * 2 > self.max_age {
+            problems.push(OptionsError::TrendingWindowTooLong {
+                trending_window: self.trending_window,
+                max_age: self.max_age,
+            });
+        }
+
+        let word_regex = Regex::new(&self.word_regex)
+            .map_err(|e| problems.push(OptionsError::InvalidRegex(e.to_string())))
+            .ok();
+
+        if let Err(e) = commands::validate_prefix(&self.prefix, Lang::En) {
+            problems.push(OptionsError::InvalidPrefix(e));
+        }
+
+        if let Some(template) = &self.message_template {
+            if let Err(e) = template::validate(template) {
+                problems.push(OptionsError::InvalidTemplate(e.to_string()));
+            }
+        }
+
+        let log_filter = logging::filter(self.log_level.as_deref())
+            .map_err(|e| problems.push(OptionsError::InvalidLogLevel(e)))
+            .ok();
+
+        // The command line already refuses both, but the config file can add one
+        #[cfg(feature = "sqlite")]
+        if self.db.is_some() && self.state_file.is_some() {
+            problems.push(OptionsError::Conflict("db", "state-file"));
+        }
+
+        if self.shards == Some(0) {
+            problems.push(OptionsError::NoShards);
+        }
+
+        if !(self.time_scale > 0.0 && self.time_scale.is_finite()) {
+            problems.push(OptionsError::TimeScale(self.time_scale));
+        }
+
+        if self.shards.is_some() && self.shard_range.is_some() {
+            problems.push(OptionsError::Conflict("shards", "shard-range"));
+        }
+
+        if self.interval_low < MIN_SAFE_INTERVAL {
+            problems.push(OptionsError::FastPosting {
+                interval_low: self.interval_low,
+            });
+        }
+
+        match (word_regex, log_filter) {
+            (Some(word_regex), Some(log_filter))
+                if problems.iter().all(OptionsError::is_warning) =>
+            {
+                Ok(Validated {
+                    word_regex,
+                    log_filter,
+                    warnings: problems,
+                })
+            }
+            _ => Err(problems),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use structopt::StructOpt;
+
+    fn options(args: &[&str]) -> Options {
+        Options::from_iter(std::iter::once("pino-bot").chain(args.iter().copied()))
+    }
+
+    #[test]
+    fn defaults_are_valid() {
+        let validated = options(&[]).validate().unwrap();
+
+        assert!(validated.warnings.is_empty());
+        assert!(validated.word_regex.is_match("perché"));
+    }
+
+    #[test]
+    fn every_problem_is_reported() {
+        let problems = options(&[
+            "--interval-low",
+            "900",
+            "--interval-high",
+            "300",
+            "--max-age",
+            "60",
+            "--word-regex",
+            "[a-z",
+            "--prefix",
+            "! pino",
+            "--message-template",
+            "no word",
+            "--max-boost",
+            "2000000",
+            "--log-level",
+            "info,serenity=loud",
+        ])
+        .validate()
+        .err()
+        .unwrap();
+
+        assert_eq!(
+            OptionsError::IntervalOrder {
+                low: 900,
+                high: 300
+            },
+            problems[0]
+        );
+        assert_eq!(
+            OptionsError::MaxAgeTooShort {
+                max_age: 60,
+                interval_high: 300
+            },
+            problems[1]
+        );
+        assert_eq!(OptionsError::MaxBoostTooLarge(2_000_000), problems[2]);
+        assert!(matches!(problems[3], OptionsError::InvalidRegex(_)));
+        assert!(matches!(problems[4], OptionsError::InvalidPrefix(_)));
+        assert!(matches!(problems[5], OptionsError::InvalidTemplate(_)));
+        assert!(matches!(problems[6], OptionsError::InvalidLogLevel(_)));
+        assert_eq!(7, problems.len());
+        assert!(problems.iter().all(|problem| !problem.is_warning()));
+    }
+
+    #[test]
+    fn warnings() {
+        let validated = options(&["--interval-low", "10", "--interval-high", "20"])
+            .validate()
+            .unwrap();
+
+        assert_eq!(
+            vec![OptionsError::FastPosting { interval_low: 10 }],
+            validated.warnings
+        );
+
+        // reported along with the errors
+        let problems = options(&["--interval-low", "10", "--prefix", ""])
+            .validate()
+            .err()
+            .unwrap();
+        assert_eq!(2, problems.len());
+        assert!(problems[1].is_warning());
+    }
+
+    #[test]
+    fn shards() {
+        assert!(options(&["--shards", "4"]).validate().is_ok());
+        assert!(options(&["--shard-range", "0..2/4"]).validate().is_ok());
+        assert_eq!(
+            vec![OptionsError::NoShards],
+            options(&["--shards", "0"]).validate().err().unwrap()
+        );
+
+        // e.g. from the config file
+        let mut options = options(&["--shards", "4"]);
+        options.shard_range = Some("0..2/4".parse().unwrap());
+        assert_eq!(
+            vec![OptionsError::Conflict("shards", "shard-range")],
+            options.validate().err().unwrap()
+        );
+    }
+
+    #[test]
+    fn trending_window() {
+        assert!(options(&["--scoring", "trending"]).validate().is_ok());
+        assert!(
+            options(&["--scoring", "trending", "--trending-window", "15m"])
+                .validate()
+                .is_ok()
+        );
+        assert_eq!(
+            vec![OptionsError::TrendingWindowTooLong {
+                trending_window: 960,
+                max_age: 1800,
+            }],
+            options(&["--scoring", "trending", "--trending-window", "16m"])
+                .validate()
+                .err()
+                .unwrap()
+        );
+        // only counted words don't care
+        assert!(options(&["--trending-window", "1h"]).validate().is_ok());
+    }
+
+    #[test]
+    fn time_scale() {
+        assert!(options(&["--dry-run", "--time-scale", "60"])
+            .validate()
+            .is_ok());
+
+        for scale in ["0", "-1", "NaN", "inf"] {
+            let scale: f64 = scale.parse().unwrap();
+            let mut options = options(&[]);
+            options.time_scale = scale;
+
+            assert!(matches!(
+                options.validate().err().unwrap()[..],
+                [OptionsError::TimeScale(_)]
+            ));
+        }
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn conflicts() {
+        let mut options = options(&["--db", "sqlite://pino.db"]);
+        assert!(options.validate().is_ok());
+
+        // e.g. from the config file
+        options.state_file = Some("pino.json".into());
+        assert_eq!(
+            vec![OptionsError::Conflict("db", "state-file")],
+            options.validate().err().unwrap()
+        );
+    }
+}
@@ -0,0 +1,129 @@
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{HashMap, HashSet};
+
+/// Reaction that votes against a message of pino. Skin tones count too.
+pub const VETO_EMOJI: char = '👎';
+/// Messages can be vetoed for this many minutes after being sent.
+const VETO_WINDOW_MINUTES: i64 = 10;
+/// Vetoed words are blocked for this many minutes.
+pub const VETO_BLOCK_MINUTES: i64 = 60;
+
+/// Votes on a single message.
+#[derive(Debug)]
+struct Ballot {
+    sent: DateTime<Utc>,
+    voters: HashSet<u64>,
+    /// Vetoed messages don't count votes anymore.
+    vetoed: bool,
+}
+
+/// Votes against the recent messages of pino.
+#[derive(Debug)]
+pub struct Vetoes {
+    /// Votes needed to veto a message.
+    threshold: usize,
+    /// By message id.
+    ballots: HashMap<u64, Ballot>,
+}
+
+impl Vetoes {
+    pub fn new(threshold: usize) -> Self {
+        Self {
+            threshold: threshold.max(1),
+            ballots: HashMap::new(),
+        }
+    }
+
+    /// Count the vote of a user against a message sent at `sent`. Returns true
+    /// if the vote vetoed the message. Every user votes once, and messages
+    /// older than the window can't be vetoed anymore.
+    pub fn vote(
+        &mut self,
+        message_id: u64,
+        sent: DateTime<Utc>,
+        user_id: u64,
+        now: DateTime<Utc>,
+    ) -> bool {
+        self.purge(now);
+
+        if now - sent > Duration::minutes(VETO_WINDOW_MINUTES) {
+            return false;
+        }
+
+        let ballot = self.ballots.entry(message_id).or_insert_with(|| Ballot {
+            sent,
+            voters: HashSet::new(),
+            vetoed: false,
+        });
+
+        if ballot.vetoed {
+            return false;
+        }
+
+        ballot.voters.insert(user_id);
+        ballot.vetoed = ballot.voters.len() >= self.threshold;
+
+        ballot.vetoed
+    }
+
+    /// Take back the vote of a user, if the message wasn't vetoed yet.
+    pub fn unvote(&mut self, message_id: u64, user_id: u64) {
+        if let Some(ballot) = self.ballots.get_mut(&message_id) {
+            if !ballot.vetoed {
+                ballot.voters.remove(&user_id);
+            }
+        }
+    }
+
+    /// Forget the messages that can't be vetoed anymore.
+    fn purge(&mut self, now: DateTime<Utc>) {
+        self.ballots
+            .retain(|_id, ballot| now - ballot.sent <= Duration::minutes(VETO_WINDOW_MINUTES));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counting() {
+        let now = Utc::now();
+        let mut vetoes = Vetoes::new(3);
+
+        assert!(!vetoes.vote(1, now, 10, now));
+        // voting twice doesn't count
+        assert!(!vetoes.vote(1, now, 10, now));
+        assert!(!vetoes.vote(1, now, 11, now));
+        vetoes.unvote(1, 11);
+        assert!(!vetoes.vote(1, now, 12, now));
+        // votes on other messages are separate
+        assert!(!vetoes.vote(2, now, 13, now));
+
+        assert!(vetoes.vote(1, now, 14, now));
+        // already vetoed
+        assert!(!vetoes.vote(1, now, 15, now));
+        vetoes.unvote(1, 14);
+        assert!(!vetoes.vote(1, now, 14, now));
+    }
+
+    #[test]
+    fn expiry() {
+        let now = Utc::now();
+        let mut vetoes = Vetoes::new(2);
+
+        let old = now - Duration::minutes(11);
+        assert!(!vetoes.vote(1, old, 10, now));
+        assert!(!vetoes.vote(1, old, 11, now));
+
+        let recent = now - Duration::minutes(9);
+        assert!(!vetoes.vote(2, recent, 10, now));
+        // the first vote is forgotten along with the message
+        assert!(!vetoes.vote(2, recent, 11, now + Duration::minutes(2)));
+        assert!(vetoes.ballots.is_empty());
+
+        // a threshold of 0 would veto everything
+        assert!(!Vetoes::new(0).vote(1, now, 10, now + Duration::minutes(11)));
+        assert!(Vetoes::new(0).vote(1, now, 10, now));
+    }
+}
@@ -0,0 +1,83 @@
+use anyhow::Context;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+
+/// Shared client, so that connections to Discord are reused between posts.
+static CLIENT: Lazy<reqwest::Client> = Lazy::new(reqwest::Client::new);
+
+/// Longest username Discord accepts for a webhook message.
+const MAX_USERNAME_LENGTH: usize = 80;
+
+/// Body of a webhook execution, see
+/// https://discord.com/developers/docs/resources/webhook#execute-webhook
+#[derive(Serialize, Debug)]
+struct Payload<'a> {
+    content: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    username: Option<&'a str>,
+}
+
+/// Post a message through a webhook, optionally overriding the webhook's username.
+pub async fn post_via_webhook(
+    url: &str,
+    content: &str,
+    username: Option<&str>,
+) -> anyhow::Result<()> {
+    CLIENT
+        .post(url)
+        .json(&Payload { content, username })
+        .send()
+        .await
+        .context("sending webhook request")?
+        .error_for_status()
+        .context("executing webhook")?;
+
+    Ok(())
+}
+
+/// The word as a webhook username, if Discord would accept it. Usernames can't
+/// mention Discord or Clyde, and can't be too long.
+pub fn username_for(word: &str) -> Option<&str> {
+    let lowercase = word.to_lowercase();
+
+    if word.is_empty()
+        || word.chars().count() > MAX_USERNAME_LENGTH
+        || lowercase.contains("discord")
+        || lowercase.contains("clyde")
+    {
+        None
+    } else {
+        Some(word)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payload() {
+        let json = serde_json::to_string(&Payload {
+            content: "ciao",
+            username: Some("ciao"),
+        })
+        .unwrap();
+        assert_eq!(r#"{"content":"ciao","username":"ciao"}"#, json);
+
+        let json = serde_json::to_string(&Payload {
+            content: "ciao",
+            username: None,
+        })
+        .unwrap();
+        assert_eq!(r#"{"content":"ciao"}"#, json);
+    }
+
+    #[test]
+    fn usernames() {
+        assert_eq!(Some("gnocchi"), username_for("gnocchi"));
+        assert_eq!(None, username_for(""));
+        assert_eq!(None, username_for("discordiano"));
+        assert_eq!(None, username_for("CLYDE"));
+        assert_eq!(None, username_for(&"a".repeat(81)));
+    }
+}
@@ -0,0 +1,63 @@
+use serenity::model::id::ChannelId;
+
+use crate::{
+    i18n::{tr, Lang},
+    template,
+};
+
+/// `--welcome-channel` and `--welcome-template`.
+#[derive(Debug, Clone)]
+pub struct Welcome {
+    /// New members of the server of this channel are greeted there.
+    pub channel: ChannelId,
+    /// With `{user}` and `{word}`. The text of the language of the server
+    /// if missing.
+    pub template: Option<String>,
+}
+
+impl Welcome {
+    /// The greeting of `user`, a mention, when people are talking about
+    /// `word`. Without a word, there's nothing to fill the template with and
+    /// the greeting is a plain one.
+    pub fn message(&self, lang: Lang, user: &str, word: Option<&str>) -> String {
+        match (&self.template, word) {
+            (Some(template), Some(word)) => {
+                template::fill(template, &[("user", user), ("word", word)])
+            }
+            (None, Some(word)) => tr(lang, "welcome.word", &[("user", &user), ("word", &word)]),
+            (_, None) => tr(lang, "welcome.quiet", &[("user", &user)]),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages() {
+        let mut welcome = Welcome {
+            channel: ChannelId::new(1),
+            template: None,
+        };
+
+        assert_eq!(
+            "Welcome <@42>! You joined while everyone is talking about **pizza** 🦜",
+            welcome.message(Lang::En, "<@42>", Some("pizza"))
+        );
+        assert_eq!(
+            "Benvenuto <@42>! 🦜",
+            welcome.message(Lang::It, "<@42>", None)
+        );
+
+        welcome.template = Some("Ciao {user}, qui si parla di {word}".to_owned());
+        assert_eq!(
+            "Ciao <@42>, qui si parla di pizza",
+            welcome.message(Lang::En, "<@42>", Some("pizza"))
+        );
+        assert_eq!(
+            "Welcome <@42>! 🦜",
+            welcome.message(Lang::En, "<@42>", None)
+        );
+    }
+}
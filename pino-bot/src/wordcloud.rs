@@ -0,0 +1,268 @@
+use ab_glyph::{FontArc, PxScale};
+use anyhow::Context;
+use image::{ImageFormat, Rgb, RgbImage};
+use imageproc::drawing::{draw_text_mut, text_size};
+use std::{fs, io::Cursor, path::Path};
+
+/// Font size of the least said words, in pixels.
+const MIN_FONT_SIZE: f32 = 16.0;
+/// Font size of the most said word, in pixels.
+const MAX_FONT_SIZE: f32 = 80.0;
+/// Width of the image. The height depends on how many rows of words there are.
+const WIDTH: u32 = 800;
+/// Space between the words and the border of the image.
+const MARGIN: u32 = 24;
+/// Space between two words, and between two rows.
+const GAP: u32 = 12;
+/// Discord's dark theme, so that the image blends in.
+const BACKGROUND: Rgb<u8> = Rgb([0x36, 0x39, 0x3f]);
+/// Colors of the words, in turn.
+const PALETTE: [Rgb<u8>; 5] = [
+    Rgb([0x57, 0xf2, 0x87]),
+    Rgb([0xfe, 0xe7, 0x5c]),
+    Rgb([0xeb, 0x45, 0x9e]),
+    Rgb([0x58, 0x65, 0xf2]),
+    Rgb([0xff, 0xff, 0xff]),
+];
+
+/// Longest text cloud, well below Discord's 2000 characters limit.
+const MAX_TEXT_LENGTH: usize = 1900;
+const TEXT_SEPARATOR: &str = " · ";
+
+/// How `!pino wordcloud` is drawn, from the command line.
+#[derive(Clone)]
+pub struct Settings {
+    pub max_words: usize,
+    /// Without a font, the cloud is sent as text.
+    pub font: Option<FontArc>,
+}
+
+/// Read the font of `--wordcloud-font-file`.
+pub fn load_font(path: &Path) -> anyhow::Result<FontArc> {
+    let data = fs::read(path).with_context(|| format!("reading font {}", path.display()))?;
+
+    FontArc::try_from_vec(data)
+        .with_context(|| format!("{} is not a TrueType or OpenType font", path.display()))
+}
+
+/// How big a word said `count` times is, from 0 to 1, when the most said one
+/// was said `max_count` times. It grows with the logarithm of the count, so
+/// that a few very common words don't make the rest unreadable.
+pub fn weight(count: usize, max_count: usize) -> f32 {
+    if count == 0 || max_count == 0 {
+        return 0.0;
+    }
+
+    let weight = (1.0 + count as f32).ln() / (1.0 + max_count as f32).ln();
+    weight.min(1.0)
+}
+
+/// Font size of a word, in pixels.
+pub fn font_size(count: usize, max_count: usize) -> f32 {
+    MIN_FONT_SIZE + (MAX_FONT_SIZE - MIN_FONT_SIZE) * weight(count, max_count)
+}
+
+/// Place boxes of the given sizes in rows, left to right, starting a new row
+/// when one would get wider than `width`. Rows are centered, and the boxes in
+/// a row are aligned at the bottom. Returns the top left corner of each box
+/// and the height of the image.
+pub fn layout(sizes: &[(u32, u32)], width: u32) -> (Vec<(u32, u32)>, u32) {
+    let mut positions = Vec::with_capacity(sizes.len());
+    let mut row = Vec::new();
+    let mut row_width = 0;
+    let mut y = MARGIN;
+
+    for &(w, h) in sizes {
+        if !row.is_empty() && row_width + GAP + w + 2 * MARGIN > width {
+            y += place_row(&row, row_width, y, width, &mut positions) + GAP;
+            row.clear();
+        }
+
+        row_width = if row.is_empty() {
+            w
+        } else {
+            row_width + GAP + w
+        };
+        row.push((w, h));
+    }
+
+    if !row.is_empty() {
+        y += place_row(&row, row_width, y, width, &mut positions);
+    }
+
+    (positions, y + MARGIN)
+}
+
+/// Add the positions of a row starting at `y`. Returns its height.
+fn place_row(
+    row: &[(u32, u32)],
+    row_width: u32,
+    y: u32,
+    width: u32,
+    positions: &mut Vec<(u32, u32)>,
+) -> u32 {
+    let height = row.iter().map(|&(_w, h)| h).max().unwrap_or(0);
+    // a word wider than the image starts at the border, and is cut
+    let mut x = width.saturating_sub(row_width) / 2;
+
+    for &(w, h) in row {
+        positions.push((x, y + height - h));
+        x += w + GAP;
+    }
+
+    height
+}
+
+/// Draw the words, most said first, as a PNG image.
+pub fn render(words: &[(&str, usize)], font: &FontArc) -> anyhow::Result<Vec<u8>> {
+    let max_count = words.iter().map(|&(_word, count)| count).max().unwrap_or(0);
+    let scales: Vec<_> = words
+        .iter()
+        .map(|&(_word, count)| PxScale::from(font_size(count, max_count)))
+        .collect();
+    let sizes: Vec<_> = words
+        .iter()
+        .zip(&scales)
+        .map(|(&(word, _count), &scale)| text_size(scale, font, word))
+        .collect();
+
+    let (positions, height) = layout(&sizes, WIDTH);
+    let mut image = RgbImage::from_pixel(WIDTH, height, BACKGROUND);
+
+    for (i, ((&(word, _count), &scale), &(x, y))) in
+        words.iter().zip(&scales).zip(&positions).enumerate()
+    {
+        let color = PALETTE[i % PALETTE.len()];
+        draw_text_mut(&mut image, color, x as i32, y as i32, scale, font, word);
+    }
+
+    let mut png = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+        .context("encoding the word cloud")?;
+
+    Ok(png)
+}
+
+/// The cloud as text, for when it can't be drawn: in alphabetical order, with
+/// the most said words in bold. `words` are the most said first, the least
+/// said ones are left out if they don't fit in a message.
+pub fn text_cloud(words: &[(&str, usize)]) -> String {
+    let max_count = words.iter().map(|&(_word, count)| count).max().unwrap_or(0);
+    let mut shown = Vec::new();
+    let mut length = 0;
+
+    for &(word, count) in words {
+        let word = escape(word);
+        let weight = weight(count, max_count);
+        let text = if weight > 2.0 / 3.0 {
+            format!("**__{}__**", word)
+        } else if weight > 1.0 / 3.0 {
+            format!("**{}**", word)
+        } else {
+            word.clone()
+        };
+
+        length += text.chars().count() + TEXT_SEPARATOR.chars().count();
+        if length > MAX_TEXT_LENGTH {
+            break;
+        }
+
+        shown.push((word, text));
+    }
+
+    shown.sort_unstable();
+    shown
+        .into_iter()
+        .map(|(_word, text)| text)
+        .collect::<Vec<_>>()
+        .join(TEXT_SEPARATOR)
+}
+
+/// Keep the characters of a word from being read as markdown.
+fn escape(word: &str) -> String {
+    let mut escaped = String::with_capacity(word.len());
+
+    for c in word.chars() {
+        if matches!(c, '\\' | '*' | '_' | '~' | '`' | '|') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sizes() {
+        assert_eq!(MAX_FONT_SIZE, font_size(100, 100));
+        assert_eq!(MAX_FONT_SIZE, font_size(1, 1));
+        assert_eq!(MIN_FONT_SIZE, font_size(0, 100));
+
+        let sizes: Vec<_> = [1, 2, 10, 50, 100]
+            .iter()
+            .map(|&count| font_size(count, 100))
+            .collect();
+        assert!(sizes.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(sizes[0] > MIN_FONT_SIZE);
+        // logarithmic: a tenth of the count is still more than half the size
+        assert!(weight(10, 100) > 0.5);
+    }
+
+    #[test]
+    fn rows() {
+        let (positions, height) = layout(&[], WIDTH);
+        assert!(positions.is_empty());
+        assert_eq!(2 * MARGIN, height);
+
+        // two boxes fill the first row exactly
+        let width = 2 * MARGIN + 30 + GAP + 30;
+        let (positions, height) = layout(&[(30, 10), (30, 20), (20, 10)], width);
+
+        assert_eq!(
+            vec![
+                (MARGIN, MARGIN + 10),
+                (MARGIN + 30 + GAP, MARGIN),
+                ((width - 20) / 2, MARGIN + 20 + GAP),
+            ],
+            positions
+        );
+        assert_eq!(MARGIN + 20 + GAP + 10 + MARGIN, height);
+
+        // too wide for any row
+        let (positions, _height) = layout(&[(10, 10), (width + 10, 10)], width);
+        assert_eq!((0, MARGIN + 10 + GAP), positions[1]);
+    }
+
+    #[test]
+    fn text() {
+        assert_eq!("", text_cloud(&[]));
+        assert_eq!(
+            "ciao · **__pino__** · **pizza**",
+            text_cloud(&[("pino", 100), ("pizza", 10), ("ciao", 1)])
+        );
+        assert_eq!("**__a\\_b__**", text_cloud(&[("a_b", 3)]));
+
+        let many: Vec<_> = (0..1000).map(|i| format!("word{:03}", i)).collect();
+        let words: Vec<_> = many.iter().map(|word| (word.as_str(), 1)).collect();
+        let cloud = text_cloud(&words);
+
+        assert!(cloud.chars().count() <= MAX_TEXT_LENGTH);
+        assert!(cloud.starts_with("**__word000__** · **__word001__**"));
+    }
+
+    #[test]
+    fn invalid_font() {
+        let path = std::env::temp_dir().join(format!("pino-{}-font.ttf", std::process::id()));
+        fs::write(&path, "not a font").unwrap();
+
+        assert!(load_font(&path).is_err());
+        assert!(load_font(&path.with_extension("missing")).is_err());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,219 @@
+use chrono::{DateTime, Utc};
+use regex::{Regex, RegexBuilder};
+use std::{collections::BTreeMap, fmt};
+use tracing::warn;
+
+use crate::ttl::TtlMap;
+
+/// Max size of a compiled regex, so that nobody can block `a{1000}{1000}` and
+/// eat all the memory.
+const REGEX_SIZE_LIMIT: usize = 1 << 16;
+
+/// Something that blocks words: either a single word or a regex.
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    Word(String),
+    Regex(Regex),
+}
+
+impl Pattern {
+    /// Parse `/regex/` as a regex and anything else as a word.
+    pub fn parse(input: &str) -> Result<Pattern, regex::Error> {
+        match input
+            .strip_prefix('/')
+            .and_then(|input| input.strip_suffix('/'))
+        {
+            Some(regex) => {
+                let regex = RegexBuilder::new(regex)
+                    .size_limit(REGEX_SIZE_LIMIT)
+                    .build()?;
+
+                Ok(Pattern::Regex(regex))
+            }
+            None => Ok(Pattern::Word(input.to_lowercase())),
+        }
+    }
+
+    pub fn matches(&self, word: &str) -> bool {
+        match self {
+            Pattern::Word(blocked) => blocked == word,
+            Pattern::Regex(regex) => regex.is_match(word),
+        }
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Pattern::Word(word) => write!(f, "{}", word),
+            Pattern::Regex(regex) => write!(f, "/{}/", regex),
+        }
+    }
+}
+
+/// Words that are never learned, managed at runtime. Every entry has an id,
+/// so that regexes can be removed without typing them again.
+#[derive(Debug, Default)]
+pub struct Blocklist {
+    entries: BTreeMap<u32, Pattern>,
+    /// Words blocked for a while, e.g. after a veto. They aren't persisted.
+    temporary: TtlMap<String>,
+}
+
+impl Blocklist {
+    /// Rebuild the blocklist from the persisted entries. Entries that don't
+    /// compile anymore are skipped.
+    pub fn load(entries: &BTreeMap<u32, String>) -> Self {
+        let entries = entries
+            .iter()
+            .filter_map(|(&id, pattern)| match Pattern::parse(pattern) {
+                Ok(pattern) => Some((id, pattern)),
+                Err(e) => {
+                    warn!("Skipping blocklist entry {} '{}': {}", id, pattern, e);
+                    None
+                }
+            })
+            .collect();
+
+        Self {
+            entries,
+            temporary: TtlMap::default(),
+        }
+    }
+
+    /// The entries as text, to be persisted.
+    pub fn to_map(&self) -> BTreeMap<u32, String> {
+        self.entries
+            .iter()
+            .map(|(&id, pattern)| (id, pattern.to_string()))
+            .collect()
+    }
+
+    /// Add a pattern, returning its id. Returns `None` if it was already there.
+    pub fn add(&mut self, pattern: Pattern) -> Option<u32> {
+        let text = pattern.to_string();
+
+        if self.entries.values().any(|other| other.to_string() == text) {
+            return None;
+        }
+
+        let id = self.entries.keys().next_back().map_or(1, |last| last + 1);
+        self.entries.insert(id, pattern);
+
+        Some(id)
+    }
+
+    /// Remove the entry with the given id, or the given word or `/regex/`.
+    pub fn remove(&mut self, id_or_pattern: &str) -> Option<(u32, Pattern)> {
+        let id = match id_or_pattern.parse::<u32>() {
+            Ok(id) if self.entries.contains_key(&id) => id,
+            _ => {
+                let text = match Pattern::parse(id_or_pattern) {
+                    Ok(pattern) => pattern.to_string(),
+                    Err(_) => id_or_pattern.to_owned(),
+                };
+
+                *self
+                    .entries
+                    .iter()
+                    .find(|(_id, pattern)| pattern.to_string() == text)?
+                    .0
+            }
+        };
+
+        self.entries.remove_entry(&id)
+    }
+
+    /// Block a word until the given time.
+    pub fn block_until(&mut self, word: &str, until: DateTime<Utc>) {
+        let now = Utc::now();
+
+        self.temporary.purge(now);
+        self.temporary.insert(word.to_lowercase(), until);
+    }
+
+    pub fn is_blocked(&self, word: &str) -> bool {
+        self.temporary.contains(word, Utc::now())
+            || self.entries.values().any(|pattern| pattern.matches(word))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (u32, &Pattern)> {
+        self.entries.iter().map(|(&id, pattern)| (id, pattern))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patterns() {
+        let word = Pattern::parse("Gnocchi").unwrap();
+        assert!(word.matches("gnocchi"));
+        assert!(!word.matches("gnocchis"));
+        assert_eq!("gnocchi", word.to_string());
+
+        let regex = Pattern::parse("/^gn/").unwrap();
+        assert!(regex.matches("gnocchi"));
+        assert!(!regex.matches("agnocchi"));
+        assert_eq!("/^gn/", regex.to_string());
+
+        // a lone slash is a word
+        assert!(Pattern::parse("/").unwrap().matches("/"));
+
+        assert!(Pattern::parse("/(/").is_err());
+        assert!(Pattern::parse("/a{1000}{1000}/").is_err());
+    }
+
+    #[test]
+    fn add_and_remove() {
+        let mut blocklist = Blocklist::default();
+
+        assert_eq!(Some(1), blocklist.add(Pattern::parse("ciao").unwrap()));
+        assert_eq!(Some(2), blocklist.add(Pattern::parse("/^gn/").unwrap()));
+        assert_eq!(None, blocklist.add(Pattern::parse("CIAO").unwrap()));
+
+        assert!(blocklist.is_blocked("ciao"));
+        assert!(blocklist.is_blocked("gnocchi"));
+        assert!(!blocklist.is_blocked("pino"));
+
+        assert_eq!(1, blocklist.remove("Ciao").unwrap().0);
+        assert!(blocklist.remove("ciao").is_none());
+        assert!(blocklist.remove("/(/").is_none());
+        assert_eq!(2, blocklist.remove("2").unwrap().0);
+        assert_eq!(0, blocklist.iter().count());
+
+        // ids aren't reused while there are later entries
+        blocklist.add(Pattern::parse("a").unwrap());
+        blocklist.add(Pattern::parse("b").unwrap());
+        blocklist.remove("a");
+        assert_eq!(Some(3), blocklist.add(Pattern::parse("c").unwrap()));
+    }
+
+    #[test]
+    fn temporary() {
+        let mut blocklist = Blocklist::default();
+
+        blocklist.block_until("Ciao", Utc::now() + chrono::Duration::hours(1));
+        blocklist.block_until("pino", Utc::now() - chrono::Duration::hours(1));
+
+        assert!(blocklist.is_blocked("ciao"));
+        assert!(!blocklist.is_blocked("pino"));
+        // temporary entries aren't listed nor saved
+        assert_eq!(0, blocklist.iter().count());
+        assert!(blocklist.to_map().is_empty());
+    }
+
+    #[test]
+    fn load() {
+        let mut entries = BTreeMap::new();
+        entries.insert(4, "ciao".to_owned());
+        entries.insert(7, "/^gn/".to_owned());
+        entries.insert(9, "/(/".to_owned());
+
+        let blocklist = Blocklist::load(&entries);
+        entries.remove(&9);
+
+        assert_eq!(entries, blocklist.to_map());
+    }
+}
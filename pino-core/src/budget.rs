@@ -0,0 +1,61 @@
+use crate::WordMap;
+
+/// Forget the words that haven't been said for the longest time, until at most
+/// `max_words` are left. Returns the amount of forgotten words.
+///
+/// The instances are sorted, so the last one of each word is when it was last said.
+pub fn evict_least_recent(words: &mut WordMap, max_words: usize) -> usize {
+    if words.len() <= max_words {
+        return 0;
+    }
+
+    let mut last_seen: Vec<_> = words
+        .iter()
+        .map(|(word, instances)| (instances.as_ref().last().copied(), word.clone()))
+        .collect();
+    // oldest first, words without instances before everything else
+    last_seen.sort_unstable();
+
+    let excess = words.len() - max_words;
+
+    for (_last_seen, word) in last_seen.into_iter().take(excess) {
+        words.remove(&word);
+    }
+
+    excess
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, Utc};
+    use utils::SortedVec;
+
+    #[test]
+    fn eviction() {
+        let now = Utc::now();
+        let mut words = WordMap::new();
+        // said a lot, but long ago
+        words.insert(
+            "ciao".to_owned(),
+            SortedVec::from_vec(vec![now - Duration::minutes(20); 10]),
+        );
+        words.insert(
+            "pino".to_owned(),
+            SortedVec::from_vec(vec![now - Duration::minutes(30), now]),
+        );
+        words.insert(
+            "gnocchi".to_owned(),
+            SortedVec::from_vec(vec![now - Duration::minutes(5)]),
+        );
+
+        assert_eq!(0, evict_least_recent(&mut words, 3));
+        assert_eq!(3, words.len());
+
+        assert_eq!(1, evict_least_recent(&mut words, 2));
+        assert!(!words.contains_key("ciao"));
+
+        assert_eq!(2, evict_least_recent(&mut words, 0));
+        assert!(words.is_empty());
+    }
+}
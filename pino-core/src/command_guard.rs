@@ -0,0 +1,186 @@
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{HashMap, VecDeque};
+
+use crate::{ids::UserId, ttl::TtlMap};
+
+/// Using commands during their cooldown this many times within a minute...
+pub const MAX_VIOLATIONS: usize = 3;
+const VIOLATION_WINDOW_MINUTES: i64 = 1;
+/// ...gets the commands of the user ignored for this many minutes.
+pub const IGNORE_MINUTES: i64 = 5;
+
+/// What to do with a command.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Verdict {
+    Allowed,
+    /// The user used the command too recently.
+    CoolingDown,
+    /// The user's commands are being ignored for a while.
+    Ignored,
+}
+
+/// Per user cooldowns of the commands, ignoring whoever keeps hitting them.
+#[derive(Debug, Default)]
+pub struct CommandGuard {
+    /// By user and command.
+    cooldowns: TtlMap<(UserId, &'static str)>,
+    /// Recent violations of each user, oldest first.
+    violations: HashMap<UserId, VecDeque<DateTime<Utc>>>,
+    ignored: TtlMap<UserId>,
+}
+
+impl CommandGuard {
+    /// Check whether a user can use a command with the given cooldown now,
+    /// and record the use if so.
+    pub fn check(
+        &mut self,
+        user_id: UserId,
+        command: &'static str,
+        cooldown: Duration,
+        now: DateTime<Utc>,
+    ) -> Verdict {
+        self.purge(now);
+
+        if self.ignored.contains(&user_id, now) {
+            return Verdict::Ignored;
+        }
+
+        if !self.cooldowns.contains(&(user_id, command), now) {
+            if cooldown > Duration::zero() {
+                self.cooldowns.insert((user_id, command), now + cooldown);
+            }
+
+            return Verdict::Allowed;
+        }
+
+        let violations = self.violations.entry(user_id).or_default();
+        violations.push_back(now);
+
+        if violations.len() < MAX_VIOLATIONS {
+            return Verdict::CoolingDown;
+        }
+
+        self.violations.remove(&user_id);
+        self.ignored
+            .insert(user_id, now + Duration::minutes(IGNORE_MINUTES));
+
+        Verdict::Ignored
+    }
+
+    /// Forget what expired at `now`, so that nothing grows forever.
+    fn purge(&mut self, now: DateTime<Utc>) {
+        let window = Duration::minutes(VIOLATION_WINDOW_MINUTES);
+
+        self.cooldowns.purge(now);
+        self.ignored.purge(now);
+        self.violations.retain(|_user_id, violations| {
+            while matches!(violations.front(), Some(&time) if now - time >= window) {
+                violations.pop_front();
+            }
+
+            !violations.is_empty()
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cooldowns() {
+        let now = Utc::now();
+        let cooldown = Duration::seconds(10);
+        let mut guard = CommandGuard::default();
+
+        assert_eq!(
+            Verdict::Allowed,
+            guard.check(UserId(1), "top", cooldown, now)
+        );
+        assert_eq!(
+            Verdict::CoolingDown,
+            guard.check(UserId(1), "top", cooldown, now)
+        );
+        // other users and commands have their own cooldown
+        assert_eq!(
+            Verdict::Allowed,
+            guard.check(UserId(2), "top", cooldown, now)
+        );
+        assert_eq!(
+            Verdict::Allowed,
+            guard.check(UserId(1), "speak", cooldown, now)
+        );
+
+        let later = now + cooldown;
+        assert_eq!(
+            Verdict::Allowed,
+            guard.check(UserId(1), "top", cooldown, later)
+        );
+
+        // no cooldown at all
+        for _ in 0..10 {
+            assert_eq!(
+                Verdict::Allowed,
+                guard.check(UserId(1), "help", Duration::zero(), now)
+            );
+        }
+    }
+
+    #[test]
+    fn escalation() {
+        let now = Utc::now();
+        let cooldown = Duration::minutes(10);
+        let mut guard = CommandGuard::default();
+
+        assert_eq!(
+            Verdict::Allowed,
+            guard.check(UserId(1), "top", cooldown, now)
+        );
+        assert_eq!(
+            Verdict::CoolingDown,
+            guard.check(UserId(1), "top", cooldown, now)
+        );
+
+        // violations older than a minute don't count
+        let now = now + Duration::minutes(1);
+        assert_eq!(
+            Verdict::CoolingDown,
+            guard.check(UserId(1), "top", cooldown, now)
+        );
+        assert_eq!(
+            Verdict::CoolingDown,
+            guard.check(UserId(1), "top", cooldown, now)
+        );
+        assert_eq!(
+            Verdict::Ignored,
+            guard.check(UserId(1), "top", cooldown, now)
+        );
+
+        // every command is ignored, even without cooldown
+        assert_eq!(
+            Verdict::Ignored,
+            guard.check(
+                UserId(1),
+                "help",
+                Duration::zero(),
+                now + Duration::minutes(4)
+            )
+        );
+        assert_eq!(
+            Verdict::Allowed,
+            guard.check(UserId(2), "top", cooldown, now)
+        );
+
+        // the violations start over after being ignored
+        let now = now + Duration::minutes(IGNORE_MINUTES);
+        assert_eq!(
+            Verdict::Allowed,
+            guard.check(UserId(1), "help", Duration::zero(), now)
+        );
+        assert_eq!(
+            Verdict::CoolingDown,
+            guard.check(UserId(1), "top", cooldown, now)
+        );
+        assert_eq!(1, guard.violations[&UserId(1)].len());
+    }
+}
@@ -0,0 +1,229 @@
+use chrono::Duration;
+use std::fmt;
+
+/// Longest duration representable by chrono, which uses milliseconds internally.
+const MAX_SECONDS: i64 = i64::MAX / 1000;
+
+/// Why a duration couldn't be parsed. Every variant has the input with it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DurationError {
+    /// Neither a number nor numbers with units, e.g. `1.5h` or `soon`.
+    Invalid(String),
+    Negative(String),
+    /// Zero where it makes no sense, e.g. between two messages.
+    Zero(String),
+    TooLong(String),
+}
+
+impl fmt::Display for DurationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DurationError::Invalid(input) => write!(
+                f,
+                "`{}` is not a valid duration, try `45s`, `10m`, `1h30m` or a number of seconds",
+                input
+            ),
+            DurationError::Negative(input) => {
+                write!(f, "`{}` is negative, durations can't be", input)
+            }
+            DurationError::Zero(input) => write!(f, "`{}` is zero, it must be longer", input),
+            DurationError::TooLong(input) => write!(f, "`{}` is too long", input),
+        }
+    }
+}
+
+impl std::error::Error for DurationError {}
+
+/// Parse a human duration like `45s`, `10m`, `2h`, `1d` or `1h30m`, with the
+/// units in any order and optionally spaced like `1h 30m`. A bare number is
+/// seconds, which is what the flags used to take.
+pub fn parse_duration(input: &str) -> Result<Duration, DurationError> {
+    let invalid = || DurationError::Invalid(input.to_owned());
+    let too_long = || DurationError::TooLong(input.to_owned());
+    let trimmed = input.trim();
+
+    if trimmed.starts_with('-') {
+        return Err(DurationError::Negative(input.to_owned()));
+    }
+
+    if !trimmed.is_empty() && trimmed.chars().all(|c| c.is_ascii_digit()) {
+        let seconds: i64 = trimmed.parse().map_err(|_| too_long())?;
+
+        return if seconds <= MAX_SECONDS {
+            Ok(Duration::seconds(seconds))
+        } else {
+            Err(too_long())
+        };
+    }
+
+    let mut total = Duration::zero();
+    let mut number = String::new();
+    let mut parts = 0;
+
+    for c in trimmed.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+
+        if c.is_whitespace() && number.is_empty() {
+            continue;
+        }
+
+        if number.is_empty() {
+            return Err(invalid());
+        }
+
+        let value: i64 = number.parse().map_err(|_| too_long())?;
+        number.clear();
+
+        let seconds = match c {
+            's' => Some(value),
+            'm' => value.checked_mul(60),
+            'h' => value.checked_mul(60 * 60),
+            'd' => value.checked_mul(24 * 60 * 60),
+            _ => return Err(invalid()),
+        }
+        .filter(|&seconds| seconds <= MAX_SECONDS)
+        .ok_or_else(too_long)?;
+
+        total = total
+            .checked_add(&Duration::seconds(seconds))
+            .filter(|total| total.num_seconds() <= MAX_SECONDS)
+            .ok_or_else(too_long)?;
+        parts += 1;
+    }
+
+    // A number without unit after the others, or nothing at all
+    if !number.is_empty() || parts == 0 {
+        return Err(invalid());
+    }
+
+    Ok(total)
+}
+
+/// Parse a duration with [`parse_duration`] into whole seconds, as taken by
+/// the options. Zero is rejected: no option makes sense without time.
+pub fn parse_seconds(input: &str) -> Result<u64, DurationError> {
+    match parse_seconds_or_zero(input)? {
+        0 => Err(DurationError::Zero(input.to_owned())),
+        seconds => Ok(seconds),
+    }
+}
+
+/// Like [`parse_seconds`], for the options that zero turns off.
+pub fn parse_seconds_or_zero(input: &str) -> Result<u64, DurationError> {
+    Ok(parse_duration(input)?.num_seconds() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations() {
+        assert_eq!(Ok(Duration::minutes(10)), parse_duration("10m"));
+        assert_eq!(Ok(Duration::hours(2)), parse_duration("2h"));
+        assert_eq!(Ok(Duration::minutes(90)), parse_duration("1h30m"));
+        assert_eq!(Ok(Duration::minutes(90)), parse_duration("30m1h"));
+        assert_eq!(Ok(Duration::minutes(90)), parse_duration("1h 30m"));
+        assert_eq!(Ok(Duration::seconds(45)), parse_duration(" 45s "));
+        assert_eq!(Ok(Duration::days(1)), parse_duration("1d"));
+        assert_eq!(
+            Ok(Duration::seconds(86400 + 3600 + 60 + 1)),
+            parse_duration("1d1h1m1s")
+        );
+        assert_eq!(Ok(Duration::zero()), parse_duration("0m"));
+    }
+
+    #[test]
+    fn bare_numbers_are_seconds() {
+        assert_eq!(Ok(Duration::seconds(600)), parse_duration("600"));
+        assert_eq!(Ok(Duration::zero()), parse_duration("0"));
+        assert_eq!(Ok(1800), parse_seconds("1800"));
+        assert_eq!(Ok(1800), parse_seconds("30m"));
+        assert_eq!(Ok(0), parse_seconds_or_zero("0s"));
+        assert_eq!(Ok(300), parse_seconds_or_zero("300"));
+    }
+
+    #[test]
+    fn garbage() {
+        for input in &[
+            "",
+            " ",
+            "m",
+            "30x",
+            "1.5h",
+            "10 m",
+            "1h30",
+            "ten minutes",
+            "h1",
+            "1m+1s",
+            "١٠m",
+        ] {
+            assert_eq!(
+                Err(DurationError::Invalid(input.to_string())),
+                parse_duration(input),
+                "{:?} should be invalid",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn negative() {
+        for input in &["-5m", "-1", " -0"] {
+            assert_eq!(
+                Err(DurationError::Negative(input.to_string())),
+                parse_duration(input)
+            );
+        }
+    }
+
+    #[test]
+    fn zero() {
+        for input in &["0", "0s", "0h0m"] {
+            assert_eq!(
+                Err(DurationError::Zero(input.to_string())),
+                parse_seconds(input)
+            );
+        }
+    }
+
+    #[test]
+    fn overflow() {
+        for input in &[
+            "99999999999999999999",
+            "99999999999999999999d",
+            "9300000000000000",
+            "9300000000000000s",
+            "106751991167301d",
+            "9223372036854775s9223372036854775s",
+        ] {
+            assert_eq!(
+                Err(DurationError::TooLong(input.to_string())),
+                parse_duration(input),
+                "{:?} should be too long",
+                input
+            );
+        }
+
+        // the longest there is
+        assert_eq!(
+            Ok(Duration::seconds(MAX_SECONDS)),
+            parse_duration(&MAX_SECONDS.to_string())
+        );
+    }
+
+    #[test]
+    fn messages() {
+        assert_eq!(
+            "`soon` is not a valid duration, try `45s`, `10m`, `1h30m` or a number of seconds",
+            parse_duration("soon").unwrap_err().to_string()
+        );
+        assert_eq!(
+            "`0m` is zero, it must be longer",
+            parse_seconds("0m").unwrap_err().to_string()
+        );
+    }
+}
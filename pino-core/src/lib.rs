@@ -0,0 +1,28 @@
+//! The logic of pino that doesn't talk to Discord: turning messages into
+//! words, remembering them, choosing what to say and when. Ids are plain
+//! numbers, see [`ids`], so nothing here needs serenity nor a runtime.
+
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use utils::SortedVec;
+
+pub mod blocklist;
+pub mod budget;
+pub mod command_guard;
+pub mod duration;
+pub mod ids;
+pub mod pause;
+pub mod rate_limit;
+pub mod runtime_config;
+pub mod schedule;
+pub mod scheduled_posts;
+pub mod selection;
+pub mod store;
+pub mod summary;
+pub mod tracking;
+pub mod trending;
+pub mod ttl;
+pub mod voting;
+
+/// Every word learned, with the time instants it has been said at.
+pub type WordMap = HashMap<String, SortedVec<DateTime<Utc>>>;
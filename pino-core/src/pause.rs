@@ -0,0 +1,174 @@
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+
+use crate::ids::GuildId;
+
+/// Whether pino is allowed to speak. While paused it keeps learning words.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct PauseState {
+    paused: bool,
+    /// When the pause expires by itself. `None` means until resumed.
+    until: Option<DateTime<Utc>>,
+}
+
+impl PauseState {
+    /// Pause for the given duration, or until resumed if `None`.
+    pub fn pause(&mut self, now: DateTime<Utc>, duration: Option<Duration>) {
+        self.paused = true;
+        // durations too long to be represented mean forever
+        self.until = duration.and_then(|duration| now.checked_add_signed(duration));
+    }
+
+    pub fn resume(&mut self) {
+        self.paused = false;
+        self.until = None;
+    }
+
+    /// When the pause expires by itself, `None` if paused until resumed.
+    pub fn until(&self) -> Option<DateTime<Utc>> {
+        self.until
+    }
+
+    pub fn is_paused(&self, now: DateTime<Utc>) -> bool {
+        match self.until {
+            Some(until) => self.paused && now < until,
+            None => self.paused,
+        }
+    }
+
+    /// Time left before the pause expires by itself, `None` if not paused or
+    /// paused until resumed.
+    pub fn remaining(&self, now: DateTime<Utc>) -> Option<Duration> {
+        if self.is_paused(now) {
+            self.until.map(|until| until - now)
+        } else {
+            None
+        }
+    }
+}
+
+/// The pause of each guild, pausing one doesn't silence the others. Direct
+/// messages are under `None`.
+#[derive(Debug, Default, Clone)]
+pub struct Pauses {
+    guilds: HashMap<Option<GuildId>, PauseState>,
+}
+
+impl Pauses {
+    /// The pause of `guild`, not paused if it never was.
+    pub fn get(&self, guild: Option<GuildId>) -> PauseState {
+        self.guilds.get(&guild).copied().unwrap_or_default()
+    }
+
+    /// Pause `guild` for the given duration, or until resumed if `None`.
+    pub fn pause(
+        &mut self,
+        guild: Option<GuildId>,
+        now: DateTime<Utc>,
+        duration: Option<Duration>,
+    ) {
+        self.guilds.entry(guild).or_default().pause(now, duration);
+    }
+
+    pub fn resume(&mut self, guild: Option<GuildId>) {
+        if let Some(state) = self.guilds.get_mut(&guild) {
+            state.resume();
+        }
+    }
+
+    pub fn is_paused(&self, guild: Option<GuildId>, now: DateTime<Utc>) -> bool {
+        self.get(guild).is_paused(now)
+    }
+
+    /// The guilds paused at `now`, with when their pause expires by itself.
+    pub fn paused(
+        &self,
+        now: DateTime<Utc>,
+    ) -> impl Iterator<Item = (Option<GuildId>, Option<DateTime<Utc>>)> + '_ {
+        self.guilds
+            .iter()
+            .filter(move |(_guild, state)| state.is_paused(now))
+            .map(|(&guild, state)| (guild, state.until()))
+    }
+}
+
+/// Format a duration like `1h 5m`, skipping zero components. Precision is
+/// limited to seconds.
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.num_seconds().max(0);
+
+    let parts: Vec<_> = [
+        (seconds / 86400, "d"),
+        (seconds / 3600 % 24, "h"),
+        (seconds / 60 % 60, "m"),
+        (seconds % 60, "s"),
+    ]
+    .iter()
+    .filter(|(value, _)| *value > 0)
+    .map(|(value, unit)| format!("{}{}", value, unit))
+    .collect();
+
+    if parts.is_empty() {
+        "0s".to_owned()
+    } else {
+        parts.join(" ")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format() {
+        assert_eq!("0s", format_duration(Duration::zero()));
+        assert_eq!("1h 5m", format_duration(Duration::minutes(65)));
+        assert_eq!("2d 3s", format_duration(Duration::seconds(2 * 86400 + 3)));
+    }
+
+    #[test]
+    fn expiry() {
+        let now = Utc::now();
+        let mut state = PauseState::default();
+        assert!(!state.is_paused(now));
+
+        state.pause(now, Some(Duration::minutes(30)));
+        assert!(state.is_paused(now));
+        assert_eq!(
+            Some(Duration::minutes(10)),
+            state.remaining(now + Duration::minutes(20))
+        );
+        assert!(!state.is_paused(now + Duration::minutes(30)));
+        assert_eq!(None, state.remaining(now + Duration::minutes(30)));
+
+        state.pause(now, None);
+        assert!(state.is_paused(now + Duration::days(365)));
+        assert_eq!(None, state.remaining(now));
+
+        state.resume();
+        assert!(!state.is_paused(now));
+    }
+
+    #[test]
+    fn guilds() {
+        let now = Utc::now();
+        let a = Some(GuildId(1));
+        let b = Some(GuildId(2));
+        let mut pauses = Pauses::default();
+
+        pauses.pause(a, now, Some(Duration::minutes(30)));
+        pauses.pause(None, now, None);
+        assert!(pauses.is_paused(a, now));
+        // pausing one guild doesn't silence another
+        assert!(!pauses.is_paused(b, now));
+        assert_eq!(2, pauses.paused(now).count());
+        assert_eq!(
+            vec![(None, None)],
+            pauses.paused(now + Duration::hours(1)).collect::<Vec<_>>()
+        );
+
+        pauses.resume(a);
+        assert_eq!(PauseState::default(), pauses.get(a));
+        assert!(pauses.is_paused(None, now));
+    }
+}
@@ -0,0 +1,76 @@
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+use utils::SortedVec;
+
+use crate::ids::UserId;
+
+/// `--rate-limit-words` counts the words learned from a user in this many
+/// seconds.
+pub const WINDOW_SECONDS: i64 = 10;
+
+/// When the words learned from each user in the last window were said, one
+/// instant per word.
+pub type RateLimits = HashMap<UserId, SortedVec<DateTime<Utc>>>;
+
+/// How many of the `wanted` words said by `user` at `now` can be learned,
+/// when at most `limit` are learned per window. The allowed ones are counted.
+pub fn take(
+    limits: &mut RateLimits,
+    user: UserId,
+    wanted: usize,
+    limit: usize,
+    now: DateTime<Utc>,
+) -> usize {
+    let learned = limits.entry(user).or_default();
+    learned.remove_le(&(now - Duration::seconds(WINDOW_SECONDS)));
+
+    let allowed = limit.saturating_sub(learned.len()).min(wanted);
+    learned.extend_sorted(std::iter::repeat_n(now, allowed));
+
+    allowed
+}
+
+/// Drop the users that didn't say anything in the last window.
+pub fn prune(limits: &mut RateLimits, now: DateTime<Utc>) {
+    let start = now - Duration::seconds(WINDOW_SECONDS);
+
+    limits.retain(|_user, learned| learned.as_ref().last().is_some_and(|&last| last > start));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windows() {
+        let mut limits = RateLimits::new();
+        let spammer = UserId(1);
+        let now = Utc::now();
+
+        assert_eq!(4, take(&mut limits, spammer, 4, 10, now));
+        assert_eq!(6, take(&mut limits, spammer, 8, 10, now));
+        assert_eq!(
+            0,
+            take(&mut limits, spammer, 1, 10, now + Duration::seconds(5))
+        );
+        // someone else isn't slowed down
+        assert_eq!(3, take(&mut limits, UserId(2), 3, 10, now));
+
+        // the first words are out of the window
+        let later = now + Duration::seconds(WINDOW_SECONDS);
+        assert_eq!(10, take(&mut limits, spammer, 20, 10, later));
+        assert_eq!(0, take(&mut limits, spammer, 0, 10, later));
+    }
+
+    #[test]
+    fn pruning() {
+        let mut limits = RateLimits::new();
+        let now = Utc::now();
+
+        take(&mut limits, UserId(1), 2, 10, now);
+        take(&mut limits, UserId(2), 2, 10, now + Duration::seconds(5));
+
+        prune(&mut limits, now + Duration::seconds(WINDOW_SECONDS + 1));
+        assert_eq!(vec![&UserId(2)], limits.keys().collect::<Vec<_>>());
+    }
+}
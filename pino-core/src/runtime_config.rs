@@ -0,0 +1,289 @@
+use std::{collections::BTreeMap, fmt};
+
+use crate::duration;
+
+/// Options that can be tuned while pino is running. The send loop reads them
+/// again before every message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RuntimeConfig {
+    /// Min interval between messages (in seconds)
+    pub interval_low: u64,
+    /// Max interval between messages (in seconds)
+    pub interval_high: u64,
+    /// Instances of words older than this (in seconds) are forgotten
+    pub max_age: u64,
+    /// Max random boost to word count
+    pub max_boost: usize,
+}
+
+/// Every key accepted by [`RuntimeConfig::get`] and [`RuntimeConfig::set`].
+pub const KEYS: &[&str] = &["interval_low", "interval_high", "max_age", "max_boost"];
+
+#[derive(Debug, PartialEq)]
+pub enum ConfigError {
+    UnknownKey(String),
+    NotANumber { key: String, value: String },
+    NotADuration { key: String, value: String },
+    IntervalOrder { low: u64, high: u64 },
+    MaxAgeTooShort { max_age: u64, interval_high: u64 },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::UnknownKey(key) => write!(
+                f,
+                "unknown setting `{}`, valid ones are {}",
+                key,
+                KEYS.join(", ")
+            ),
+            ConfigError::NotANumber { key, value } => write!(
+                f,
+                "`{}` is not a valid value for {}, it must be a positive whole number",
+                value, key
+            ),
+            ConfigError::NotADuration { key, value } => write!(
+                f,
+                "`{}` is not a valid value for {}, it must be a duration like `10m`, `1h30m` or a number of seconds",
+                value, key
+            ),
+            ConfigError::IntervalOrder { low, high } => write!(
+                f,
+                "interval_low ({}) can't be greater than interval_high ({})",
+                low, high
+            ),
+            ConfigError::MaxAgeTooShort {
+                max_age,
+                interval_high,
+            } => write!(
+                f,
+                "max_age ({}) can't be shorter than interval_high ({}), or words would be forgotten before pino speaks",
+                max_age, interval_high
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl RuntimeConfig {
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        if self.interval_low > self.interval_high {
+            return Err(ConfigError::IntervalOrder {
+                low: self.interval_low,
+                high: self.interval_high,
+            });
+        }
+
+        if self.max_age < self.interval_high {
+            return Err(ConfigError::MaxAgeTooShort {
+                max_age: self.max_age,
+                interval_high: self.interval_high,
+            });
+        }
+
+        Ok(())
+    }
+
+    pub fn get(&self, key: &str) -> Result<String, ConfigError> {
+        let value = match key {
+            "interval_low" => self.interval_low.to_string(),
+            "interval_high" => self.interval_high.to_string(),
+            "max_age" => self.max_age.to_string(),
+            "max_boost" => self.max_boost.to_string(),
+            _ => return Err(ConfigError::UnknownKey(key.to_owned())),
+        };
+
+        Ok(value)
+    }
+
+    /// Change a setting. Nothing changes if the value is invalid or would break
+    /// the constraints between the settings.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), ConfigError> {
+        let mut changed = self.clone();
+        changed.assign(key, value)?;
+        changed.validate()?;
+
+        *self = changed;
+
+        Ok(())
+    }
+
+    /// A copy with every setting changed, validated only at the end so that
+    /// the order of the settings doesn't matter.
+    pub fn with_settings<'a>(
+        &self,
+        settings: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Result<RuntimeConfig, ConfigError> {
+        let mut changed = self.clone();
+
+        for (key, value) in settings {
+            changed.assign(key, value)?;
+        }
+
+        changed.validate()?;
+
+        Ok(changed)
+    }
+
+    /// The settings whose value differs from the ones in `base`.
+    pub fn changes_from(&self, base: &RuntimeConfig) -> BTreeMap<String, String> {
+        self.entries()
+            .into_iter()
+            .filter(|(key, value)| base.get(key).as_ref() != Ok(value))
+            .map(|(key, value)| (key.to_owned(), value))
+            .collect()
+    }
+
+    fn assign(&mut self, key: &str, value: &str) -> Result<(), ConfigError> {
+        fn parse<T: std::str::FromStr>(key: &str, value: &str) -> Result<T, ConfigError> {
+            value.trim().parse().map_err(|_| ConfigError::NotANumber {
+                key: key.to_owned(),
+                value: value.to_owned(),
+            })
+        }
+
+        fn seconds(key: &str, value: &str) -> Result<u64, ConfigError> {
+            duration::parse_seconds(value).map_err(|_| ConfigError::NotADuration {
+                key: key.to_owned(),
+                value: value.to_owned(),
+            })
+        }
+
+        match key {
+            "interval_low" => self.interval_low = seconds(key, value)?,
+            "interval_high" => self.interval_high = seconds(key, value)?,
+            "max_age" => self.max_age = seconds(key, value)?,
+            "max_boost" => self.max_boost = parse(key, value)?,
+            _ => return Err(ConfigError::UnknownKey(key.to_owned())),
+        }
+
+        Ok(())
+    }
+
+    /// Every setting with its current value.
+    pub fn entries(&self) -> Vec<(&'static str, String)> {
+        KEYS.iter()
+            .map(|&key| (key, self.get(key).expect("every key to be gettable")))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> RuntimeConfig {
+        RuntimeConfig {
+            interval_low: 600,
+            interval_high: 1200,
+            max_age: 1800,
+            max_boost: 10,
+        }
+    }
+
+    #[test]
+    fn get_and_set() {
+        let mut config = config();
+
+        config.set("max_boost", "3").unwrap();
+        config.set("interval_low", " 60 ").unwrap();
+        assert_eq!(Ok("3".to_owned()), config.get("max_boost"));
+        assert_eq!(60, config.interval_low);
+        config.set("interval_high", "15m").unwrap();
+        assert_eq!(Ok("900".to_owned()), config.get("interval_high"));
+
+        assert_eq!(
+            Err(ConfigError::UnknownKey("temperature".to_owned())),
+            config.set("temperature", "1")
+        );
+        assert_eq!(
+            Err(ConfigError::UnknownKey("token".to_owned())),
+            config.get("token")
+        );
+        for value in &["-1", "tanti", "1.5", "", "0m"] {
+            assert!(matches!(
+                config.set("max_age", value),
+                Err(ConfigError::NotADuration { .. })
+            ));
+        }
+        for value in &["-1", "3m"] {
+            assert!(matches!(
+                config.set("max_boost", value),
+                Err(ConfigError::NotANumber { .. })
+            ));
+        }
+
+        assert_eq!(
+            KEYS.to_vec(),
+            config
+                .entries()
+                .iter()
+                .map(|(key, _)| *key)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn validation() {
+        assert_eq!(Ok(()), config().validate());
+
+        let mut config = config();
+
+        assert_eq!(
+            Err(ConfigError::IntervalOrder {
+                low: 1300,
+                high: 1200
+            }),
+            config.set("interval_low", "1300")
+        );
+        assert_eq!(
+            Err(ConfigError::MaxAgeTooShort {
+                max_age: 1800,
+                interval_high: 2000
+            }),
+            config.set("interval_high", "2000")
+        );
+        assert_eq!(
+            Err(ConfigError::MaxAgeTooShort {
+                max_age: 1000,
+                interval_high: 1200
+            }),
+            config.set("max_age", "1000")
+        );
+        // failed changes leave everything as it was
+        assert_eq!(self::config(), config);
+
+        // equal bounds are fine
+        config.set("interval_low", "1200").unwrap();
+        config.set("max_age", "1200").unwrap();
+
+        assert_eq!(
+            vec![
+                ("interval_low".to_owned(), "1200".to_owned()),
+                ("max_age".to_owned(), "1200".to_owned())
+            ],
+            config
+                .changes_from(&self::config())
+                .into_iter()
+                .collect::<Vec<_>>()
+        );
+        assert!(config.changes_from(&config).is_empty());
+    }
+
+    #[test]
+    fn with_settings() {
+        let base = config();
+
+        // raising interval_high first would be invalid on its own
+        let changed = base
+            .with_settings(vec![("interval_high", "5000"), ("max_age", "6000")])
+            .unwrap();
+        assert_eq!(5000, changed.interval_high);
+        assert_eq!(6000, changed.max_age);
+
+        assert!(base.with_settings(vec![("interval_high", "5000")]).is_err());
+        assert!(base.with_settings(vec![("max_boost", "tanti")]).is_err());
+        assert_eq!(Ok(base.clone()), base.with_settings(vec![]));
+    }
+}
@@ -0,0 +1,718 @@
+use chrono::{DateTime, Duration, Utc};
+use rand::Rng;
+use std::{collections::HashMap, hash::Hash};
+use utils::SortedVec;
+
+use crate::runtime_config::RuntimeConfig;
+
+/// Why the send loop woke up.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Wakeup {
+    /// The sleep until the next message is over.
+    Timer,
+    /// Someone made pino speak on demand, so the timer starts over.
+    Forced,
+}
+
+/// Random time to wait before the next message.
+pub fn next_delay(rng: &mut impl Rng, config: &RuntimeConfig) -> Duration {
+    let time: u64 = rng.gen_range(config.interval_low..=config.interval_high);
+    Duration::seconds(time as i64)
+}
+
+/// Random time to wait for the first message of a target on top of
+/// [`next_delay`], up to a quarter of `interval_low`, so that targets heard at
+/// the same time don't keep getting their messages together.
+pub fn initial_offset(rng: &mut impl Rng, config: &RuntimeConfig) -> Duration {
+    let time: u64 = rng.gen_range(0..=config.interval_low / 4);
+    Duration::seconds(time as i64)
+}
+
+/// Keeps track of when the send loop posts the next message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Scheduler {
+    next: DateTime<Utc>,
+}
+
+impl Scheduler {
+    pub fn new(now: DateTime<Utc>, delay: Duration) -> Self {
+        Self { next: now + delay }
+    }
+
+    pub fn next(&self) -> DateTime<Utc> {
+        self.next
+    }
+
+    /// How long to sleep before the next message, zero if it's overdue.
+    pub fn remaining(&self, now: DateTime<Utc>) -> std::time::Duration {
+        (self.next - now).to_std().unwrap_or_default()
+    }
+
+    /// Handle a wakeup of the send loop, scheduling the next message `delay`
+    /// from now if needed. Returns whether the loop should post now.
+    pub fn on_wakeup(&mut self, wakeup: Wakeup, now: DateTime<Utc>, delay: Duration) -> bool {
+        match wakeup {
+            // Timers can fire a bit early, keep waiting for the same message
+            Wakeup::Timer if now < self.next => false,
+            Wakeup::Timer => {
+                self.next = now + delay;
+                true
+            }
+            // The message has just been posted by whoever forced it
+            Wakeup::Forced => {
+                self.next = now + delay;
+                false
+            }
+        }
+    }
+}
+
+/// What to do with the target whose time has come.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Due<K> {
+    /// Post there, the next message is already scheduled.
+    Send(K),
+    /// Nobody talked there for too long, it was removed instead.
+    Idle(K),
+}
+
+/// When a target was last heard and when it gets its next message.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Timer {
+    next: DateTime<Utc>,
+    last_heard: DateTime<Utc>,
+}
+
+/// The next message of each target, e.g. each guild, so that they don't all
+/// get one at the same instant. Targets are added when someone talks there
+/// and removed once nobody did for a while. A single task sleeps until the
+/// earliest one and then calls [`pop_due`](Self::pop_due).
+///
+/// Nothing is due during the warmup, which starts when pino is
+/// [`ready`](Self::ready): it has barely heard anything yet.
+#[derive(Clone)]
+pub struct Deadlines<K: Ord> {
+    /// Sorted by time, the earliest first.
+    queue: SortedVec<(DateTime<Utc>, K)>,
+    timers: HashMap<K, Timer>,
+    warmup: Duration,
+    /// When the warmup is over, once pino is ready.
+    warm_at: Option<DateTime<Utc>>,
+}
+
+impl<K: Ord> Default for Deadlines<K> {
+    fn default() -> Self {
+        Self::with_warmup(Duration::zero())
+    }
+}
+
+impl<K: Ord> Deadlines<K> {
+    pub fn with_warmup(warmup: Duration) -> Self {
+        Self {
+            queue: SortedVec::new(),
+            timers: HashMap::new(),
+            warmup,
+            warm_at: None,
+        }
+    }
+}
+
+impl<K: Ord + Hash + Copy> Deadlines<K> {
+    /// Pino is ready at `now`, the warmup starts. Only the first time counts,
+    /// reconnecting doesn't make pino forget what it heard.
+    pub fn ready(&mut self, now: DateTime<Utc>) {
+        self.warm_at.get_or_insert(now + self.warmup);
+    }
+
+    /// Whether scheduled messages are held back at `now`. Messages on demand
+    /// aren't scheduled, so they don't care.
+    pub fn warming_up(&self, now: DateTime<Utc>) -> bool {
+        self.warm_at.is_some_and(|warm_at| now < warm_at)
+    }
+
+    /// Someone talked in `target`. A new target gets its first message
+    /// `delay` from now, or from the end of the warmup, which is only computed
+    /// then: returns true, since the task sleeping until the earliest message
+    /// might have to wake up sooner.
+    pub fn heard(
+        &mut self,
+        target: K,
+        now: DateTime<Utc>,
+        delay: impl FnOnce() -> Duration,
+    ) -> bool {
+        if let Some(timer) = self.timers.get_mut(&target) {
+            timer.last_heard = timer.last_heard.max(now);
+            return false;
+        }
+
+        let start = match self.warm_at {
+            Some(warm_at) => warm_at.max(now),
+            None => now,
+        };
+        let next = start + delay();
+        self.timers.insert(
+            target,
+            Timer {
+                next,
+                last_heard: now,
+            },
+        );
+        self.queue.insert((next, target));
+
+        true
+    }
+
+    /// Someone talked in `target`, see [`heard`](Self::heard). A new target
+    /// gets its first message after a [`next_delay`] and an
+    /// [`initial_offset`], so that targets heard together don't keep getting
+    /// their messages together.
+    pub fn hear(
+        &mut self,
+        target: K,
+        now: DateTime<Utc>,
+        rng: &mut impl Rng,
+        config: &RuntimeConfig,
+    ) -> bool {
+        self.heard(target, now, || {
+            next_delay(rng, config) + initial_offset(rng, config)
+        })
+    }
+
+    /// Pino just spoke in `target` on demand, so its timer starts over.
+    pub fn spoke(
+        &mut self,
+        target: K,
+        now: DateTime<Utc>,
+        rng: &mut impl Rng,
+        config: &RuntimeConfig,
+    ) -> bool {
+        self.reschedule(target, now, next_delay(rng, config))
+    }
+
+    /// Stop sending messages to `target`, e.g. because pino left the guild.
+    /// Returns whether it was there.
+    pub fn remove(&mut self, target: K) -> bool {
+        match self.timers.remove(&target) {
+            Some(timer) => {
+                let index = self
+                    .queue
+                    .position(&(timer.next, target))
+                    .expect("every timer to be in the queue");
+                self.queue.drain(index..=index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Start waiting for the next message of `target` from scratch, e.g.
+    /// after someone made pino speak there. Returns whether it was there.
+    pub fn reschedule(&mut self, target: K, now: DateTime<Utc>, delay: Duration) -> bool {
+        let timer = match self.timers.get_mut(&target) {
+            Some(timer) => timer,
+            None => return false,
+        };
+
+        let next = now + delay;
+        self.queue.replace(&(timer.next, target), (next, target));
+        timer.next = next;
+
+        true
+    }
+
+    /// The earliest message, with its target.
+    pub fn next(&self) -> Option<(DateTime<Utc>, K)> {
+        self.queue.as_ref().first().copied()
+    }
+
+    /// When `target` gets its next message, if it's there.
+    pub fn next_for(&self, target: K) -> Option<DateTime<Utc>> {
+        self.timers.get(&target).map(|timer| timer.next)
+    }
+
+    /// How long to sleep before the earliest message, zero if it's overdue.
+    /// `None` without targets: there's nothing to wait for until someone talks.
+    pub fn remaining(&self, now: DateTime<Utc>) -> Option<std::time::Duration> {
+        self.next()
+            .map(|(next, _target)| (next - now).to_std().unwrap_or_default())
+    }
+
+    /// The earliest target, if its time has come. It gets its next message
+    /// `delay` from now, unless nobody talked there for `idle`: then it's
+    /// removed until someone does. `None` if it's not time yet, since timers
+    /// can fire a bit early, and during the warmup, which postpones the target
+    /// to `delay` after it.
+    pub fn pop_due(
+        &mut self,
+        now: DateTime<Utc>,
+        delay: Duration,
+        idle: Duration,
+    ) -> Option<Due<K>> {
+        let (next, target) = self.next()?;
+
+        if now < next {
+            return None;
+        }
+
+        if now - self.timers[&target].last_heard >= idle {
+            self.remove(target);
+            return Some(Due::Idle(target));
+        }
+
+        if let Some(warm_at) = self.warm_at.filter(|&warm_at| now < warm_at) {
+            self.reschedule(target, warm_at, delay);
+            return None;
+        }
+
+        self.reschedule(target, now, delay);
+        Some(Due::Send(target))
+    }
+
+    /// The turn of the send loop at `now`, see [`pop_due`](Self::pop_due). The
+    /// target gets its next message after a [`next_delay`], unless nobody
+    /// talked there since its words expired, after `max_age`.
+    pub fn turn(
+        &mut self,
+        now: DateTime<Utc>,
+        rng: &mut impl Rng,
+        config: &RuntimeConfig,
+    ) -> Option<Due<K>> {
+        let idle = Duration::seconds(config.max_age as i64);
+
+        self.pop_due(now, next_delay(rng, config), idle)
+    }
+
+    /// How many targets get messages.
+    pub fn len(&self) -> usize {
+        self.timers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.timers.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn delays() {
+        let config = RuntimeConfig {
+            interval_low: 600,
+            interval_high: 1200,
+            max_age: 1800,
+            max_boost: 10,
+        };
+        let delays = |seed| -> Vec<_> {
+            let mut rng = StdRng::seed_from_u64(seed);
+            (0..20).map(|_| next_delay(&mut rng, &config)).collect()
+        };
+
+        let first = delays(1);
+        assert!(first
+            .iter()
+            .all(|&delay| Duration::seconds(600) <= delay && delay <= Duration::seconds(1200)));
+        // the same seed waits the same times
+        assert_eq!(first, delays(1));
+        assert_ne!(first, delays(2));
+
+        let fixed = RuntimeConfig {
+            interval_low: 60,
+            interval_high: 60,
+            ..config
+        };
+        assert_eq!(
+            Duration::seconds(60),
+            next_delay(&mut StdRng::seed_from_u64(1), &fixed)
+        );
+    }
+
+    #[test]
+    fn offsets() {
+        let config = RuntimeConfig {
+            interval_low: 600,
+            interval_high: 1200,
+            max_age: 1800,
+            max_boost: 10,
+        };
+        let mut rng = StdRng::seed_from_u64(1);
+        let offsets: Vec<_> = (0..20).map(|_| initial_offset(&mut rng, &config)).collect();
+
+        assert!(offsets
+            .iter()
+            .all(|&offset| Duration::zero() <= offset && offset <= Duration::seconds(150)));
+        assert!(offsets.iter().any(|&offset| offset != offsets[0]));
+    }
+
+    #[test]
+    fn timer() {
+        let now = Utc::now();
+        let delay = Duration::seconds(600);
+        let mut scheduler = Scheduler::new(now, delay);
+
+        assert_eq!(now + delay, scheduler.next());
+        assert_eq!(
+            std::time::Duration::from_secs(100),
+            scheduler.remaining(now + Duration::seconds(500))
+        );
+
+        // early wakeups don't post nor reschedule
+        assert!(!scheduler.on_wakeup(Wakeup::Timer, now + Duration::seconds(599), delay));
+        assert_eq!(now + delay, scheduler.next());
+
+        let later = now + Duration::seconds(601);
+        assert!(scheduler.on_wakeup(Wakeup::Timer, later, Duration::seconds(60)));
+        assert_eq!(later + Duration::seconds(60), scheduler.next());
+    }
+
+    #[test]
+    fn forced() {
+        let now = Utc::now();
+        let delay = Duration::seconds(600);
+        let mut scheduler = Scheduler::new(now, delay);
+
+        let forced_at = now + Duration::seconds(590);
+        assert!(!scheduler.on_wakeup(Wakeup::Forced, forced_at, delay));
+
+        // the message that was due 10 seconds after is pushed back
+        assert_eq!(forced_at + delay, scheduler.next());
+        assert!(!scheduler.on_wakeup(Wakeup::Timer, now + delay, delay));
+        assert!(scheduler.on_wakeup(Wakeup::Timer, forced_at + delay, delay));
+    }
+
+    /// The queue and the timers agree, and the queue is in order.
+    fn check(deadlines: &Deadlines<u64>) {
+        assert!(deadlines.queue.is_sorted());
+        assert_eq!(deadlines.timers.len(), deadlines.queue.len());
+
+        for (target, timer) in &deadlines.timers {
+            assert!(deadlines.queue.position(&(timer.next, *target)).is_some());
+        }
+    }
+
+    #[test]
+    fn targets() {
+        let now = Utc::now();
+        let minutes = Duration::minutes;
+        let mut deadlines = Deadlines::default();
+
+        assert!(deadlines.heard(1, now, || minutes(10)));
+        assert!(deadlines.heard(2, now, || minutes(5)));
+        assert!(deadlines.heard(3, now + minutes(1), || minutes(20)));
+        // talking again doesn't move the message, no delay is needed
+        assert!(!deadlines.heard(2, now + minutes(2), || unreachable!()));
+        check(&deadlines);
+
+        assert_eq!(3, deadlines.len());
+        assert_eq!(Some((now + minutes(5), 2)), deadlines.next());
+        assert_eq!(Some(now + minutes(10)), deadlines.next_for(1));
+        assert_eq!(Some(now + minutes(21)), deadlines.next_for(3));
+        assert_eq!(None, deadlines.next_for(4));
+        assert_eq!(
+            Some(std::time::Duration::from_secs(4 * 60)),
+            deadlines.remaining(now + minutes(1))
+        );
+    }
+
+    #[test]
+    fn popping() {
+        let now = Utc::now();
+        let minutes = Duration::minutes;
+        let idle = Duration::hours(1);
+        let mut deadlines = Deadlines::default();
+
+        deadlines.heard(1, now, || minutes(10));
+        deadlines.heard(2, now, || minutes(5));
+
+        // not yet
+        assert_eq!(None, deadlines.pop_due(now + minutes(4), minutes(7), idle));
+        assert_eq!(Some((now + minutes(5), 2)), deadlines.next());
+
+        // each one with its own delay
+        let at = now + minutes(5);
+        assert_eq!(Some(Due::Send(2)), deadlines.pop_due(at, minutes(7), idle));
+        assert_eq!(Some(at + minutes(7)), deadlines.next_for(2));
+        assert_eq!(Some((now + minutes(10), 1)), deadlines.next());
+        check(&deadlines);
+
+        // late wakeups catch up one target at a time, the earliest first
+        let late = now + minutes(15);
+        assert_eq!(
+            Some(Due::Send(1)),
+            deadlines.pop_due(late, minutes(3), idle)
+        );
+        assert_eq!(
+            Some(Due::Send(2)),
+            deadlines.pop_due(late, minutes(4), idle)
+        );
+        assert_eq!(None, deadlines.pop_due(late, minutes(4), idle));
+        assert_eq!(Some((late + minutes(3), 1)), deadlines.next());
+        assert_eq!(
+            Some(std::time::Duration::from_secs(0)),
+            deadlines.remaining(late + minutes(10))
+        );
+        check(&deadlines);
+    }
+
+    #[test]
+    fn same_instant() {
+        let now = Utc::now();
+        let delay = Duration::minutes(10);
+        let mut deadlines = Deadlines::default();
+
+        for target in &[3, 1, 2] {
+            deadlines.heard(*target, now, || delay);
+        }
+
+        let due: Vec<_> = (0..4)
+            .map(|_| deadlines.pop_due(now + delay, delay, Duration::hours(1)))
+            .collect();
+        assert_eq!(
+            vec![
+                Some(Due::Send(1)),
+                Some(Due::Send(2)),
+                Some(Due::Send(3)),
+                None
+            ],
+            due
+        );
+        check(&deadlines);
+    }
+
+    #[test]
+    fn idle() {
+        let now = Utc::now();
+        let minutes = Duration::minutes;
+        let idle = minutes(30);
+        let mut deadlines = Deadlines::default();
+
+        deadlines.heard(1, now, || minutes(10));
+        deadlines.heard(2, now, || minutes(20));
+        deadlines.heard(2, now + minutes(15), || minutes(20));
+
+        assert_eq!(
+            Some(Due::Send(1)),
+            deadlines.pop_due(now + minutes(10), minutes(25), idle)
+        );
+        assert_eq!(
+            Some(Due::Send(2)),
+            deadlines.pop_due(now + minutes(20), minutes(25), idle)
+        );
+        // 1 was last heard 35 minutes ago, 2 only 30 minutes ago
+        assert_eq!(
+            Some(Due::Idle(1)),
+            deadlines.pop_due(now + minutes(35), minutes(25), idle)
+        );
+        assert_eq!(None, deadlines.next_for(1));
+        assert_eq!(1, deadlines.len());
+        check(&deadlines);
+
+        // until someone talks there again
+        assert!(deadlines.heard(1, now + minutes(40), || minutes(10)));
+        assert_eq!(Some(now + minutes(50)), deadlines.next_for(1));
+        check(&deadlines);
+
+        assert_eq!(
+            Some(Due::Idle(2)),
+            deadlines.pop_due(now + minutes(45), minutes(25), idle)
+        );
+        assert_eq!(
+            Some(Due::Send(1)),
+            deadlines.pop_due(now + minutes(50), minutes(25), idle)
+        );
+    }
+
+    #[test]
+    fn turns() {
+        let now = Utc::now();
+        let config = RuntimeConfig {
+            interval_low: 600,
+            interval_high: 600,
+            max_age: 1800,
+            max_boost: 10,
+        };
+        let mut rng = StdRng::seed_from_u64(1);
+        let mut deadlines = Deadlines::default();
+
+        assert!(deadlines.hear(1, now, &mut rng, &config));
+        assert!(!deadlines.hear(1, now, &mut rng, &config));
+        let first = deadlines.next_for(1).unwrap();
+        // after the delay and at most a quarter of it
+        assert!(now + Duration::seconds(600) <= first);
+        assert!(first <= now + Duration::seconds(750));
+
+        assert_eq!(None, deadlines.turn(now, &mut rng, &config));
+        assert_eq!(Some(Due::Send(1)), deadlines.turn(first, &mut rng, &config));
+        assert_eq!(Some(first + Duration::seconds(600)), deadlines.next_for(1));
+
+        // speaking on demand starts over
+        let later = first + Duration::seconds(60);
+        assert!(deadlines.spoke(1, later, &mut rng, &config));
+        assert_eq!(Some(later + Duration::seconds(600)), deadlines.next_for(1));
+        assert!(!deadlines.spoke(2, later, &mut rng, &config));
+
+        // nobody talked since the words expired
+        assert_eq!(
+            Some(Due::Idle(1)),
+            deadlines.turn(now + Duration::seconds(1800), &mut rng, &config)
+        );
+        assert!(deadlines.is_empty());
+    }
+
+    #[test]
+    fn warmup() {
+        let now = Utc::now();
+        let minutes = Duration::minutes;
+        let idle = Duration::hours(1);
+        let mut deadlines = Deadlines::with_warmup(minutes(5));
+
+        deadlines.heard(1, now - minutes(10), || minutes(1));
+        assert!(!deadlines.warming_up(now));
+
+        deadlines.ready(now);
+        assert!(deadlines.warming_up(now));
+        assert!(deadlines.warming_up(now + minutes(5) - Duration::seconds(1)));
+        assert!(!deadlines.warming_up(now + minutes(5)));
+        // reconnecting doesn't start over
+        deadlines.ready(now + Duration::hours(1));
+        assert!(!deadlines.warming_up(now + minutes(5)));
+
+        // overdue, but held back until a delay after the warmup
+        assert_eq!(None, deadlines.pop_due(now + minutes(1), minutes(2), idle));
+        assert_eq!(Some(now + minutes(7)), deadlines.next_for(1));
+
+        // new targets count from the end of the warmup
+        deadlines.heard(2, now + minutes(2), Duration::zero);
+        assert_eq!(Some(now + minutes(5)), deadlines.next_for(2));
+        check(&deadlines);
+
+        assert_eq!(
+            None,
+            deadlines.pop_due(now + minutes(5) - Duration::seconds(1), minutes(10), idle)
+        );
+        assert_eq!(
+            Some(Due::Send(2)),
+            deadlines.pop_due(now + minutes(5), minutes(10), idle)
+        );
+        assert_eq!(
+            Some(Due::Send(1)),
+            deadlines.pop_due(now + minutes(7), minutes(10), idle)
+        );
+        check(&deadlines);
+    }
+
+    #[test]
+    fn forced_during_warmup() {
+        let now = Utc::now();
+        let minutes = Duration::minutes;
+        let idle = Duration::hours(1);
+        let mut deadlines = Deadlines::with_warmup(minutes(5));
+
+        deadlines.ready(now);
+        deadlines.heard(1, now, || minutes(1));
+        assert_eq!(Some(now + minutes(6)), deadlines.next_for(1));
+
+        // speaking on demand isn't held back, it only moves the timer
+        assert!(deadlines.reschedule(1, now + minutes(1), minutes(1)));
+        assert_eq!(Some(now + minutes(2)), deadlines.next_for(1));
+
+        // the scheduled message after it still waits for the warmup
+        assert_eq!(None, deadlines.pop_due(now + minutes(2), minutes(3), idle));
+        assert_eq!(Some(now + minutes(8)), deadlines.next_for(1));
+        assert_eq!(
+            Some(Due::Send(1)),
+            deadlines.pop_due(now + minutes(8), minutes(3), idle)
+        );
+        check(&deadlines);
+    }
+
+    #[test]
+    fn removing_and_rescheduling() {
+        let now = Utc::now();
+        let delay = Duration::minutes(10);
+        let mut deadlines = Deadlines::default();
+
+        assert_eq!(None, deadlines.remaining(now));
+        assert_eq!(None, deadlines.pop_due(now, delay, delay));
+        assert!(!deadlines.remove(1));
+        assert!(!deadlines.reschedule(1, now, delay));
+
+        deadlines.heard(1, now, || delay);
+        deadlines.heard(2, now, || delay);
+        deadlines.heard(3, now, || Duration::minutes(1));
+
+        // someone made pino speak in 3
+        assert!(deadlines.reschedule(3, now + Duration::minutes(1), delay));
+        assert_eq!(Some(now + Duration::minutes(11)), deadlines.next_for(3));
+        assert_eq!(Some((now + delay, 1)), deadlines.next());
+        check(&deadlines);
+
+        assert!(deadlines.remove(1));
+        assert!(!deadlines.remove(1));
+        assert_eq!(Some((now + delay, 2)), deadlines.next());
+        assert_eq!(2, deadlines.len());
+        check(&deadlines);
+
+        assert!(deadlines.remove(2));
+        assert!(deadlines.remove(3));
+        assert!(deadlines.is_empty());
+        assert_eq!(None, deadlines.next());
+        check(&deadlines);
+    }
+
+    #[test]
+    fn random_operations() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut now = Utc::now();
+        let idle = Duration::minutes(30);
+        let mut deadlines = Deadlines::default();
+
+        for _ in 0..2000 {
+            let target = rng.gen_range(0..8u64);
+            let delay = Duration::seconds(rng.gen_range(60..=600));
+
+            match rng.gen_range(0..10) {
+                0..=4 => {
+                    deadlines.heard(target, now, || delay);
+                }
+                5 => {
+                    deadlines.remove(target);
+                }
+                6 => {
+                    deadlines.reschedule(target, now, delay);
+                }
+                _ => {
+                    let earliest = deadlines.next();
+
+                    match deadlines.pop_due(now, delay, idle) {
+                        Some(Due::Send(popped)) => {
+                            assert_eq!(Some(popped), earliest.map(|(_next, target)| target));
+                            assert_eq!(Some(now + delay), deadlines.next_for(popped));
+                        }
+                        Some(Due::Idle(popped)) => {
+                            assert_eq!(Some(popped), earliest.map(|(_next, target)| target));
+                            assert_eq!(None, deadlines.next_for(popped));
+                        }
+                        None => assert!(earliest.is_none_or(|(next, _target)| now < next)),
+                    }
+                }
+            }
+
+            check(&deadlines);
+            now += Duration::seconds(rng.gen_range(0..120));
+        }
+    }
+
+    #[test]
+    fn overdue() {
+        let now = Utc::now();
+        let scheduler = Scheduler::new(now, Duration::seconds(10));
+
+        assert_eq!(
+            std::time::Duration::from_secs(0),
+            scheduler.remaining(now + Duration::seconds(20))
+        );
+    }
+}
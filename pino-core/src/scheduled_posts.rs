@@ -0,0 +1,216 @@
+use chrono::{DateTime, Duration, NaiveTime, TimeZone, Utc};
+
+use crate::{
+    ids::{ChannelId, GuildId},
+    pause::Pauses,
+};
+
+/// Posts each guild can have waiting at the same time.
+pub const MAX_PENDING_PER_GUILD: usize = 10;
+
+/// A word an admin asked pino to post at a given time, with `!pino schedule`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScheduledPost {
+    pub word: String,
+    /// UTC time of the day, as typed by the admin.
+    pub at: NaiveTime,
+    /// The first time after scheduling when it's `at`.
+    pub due: DateTime<Utc>,
+    pub channel_id: ChannelId,
+    pub guild_id: Option<GuildId>,
+}
+
+impl ScheduledPost {
+    /// A post for the next time it's `at`, which might be tomorrow.
+    pub fn new(
+        word: String,
+        at: NaiveTime,
+        now: DateTime<Utc>,
+        channel_id: ChannelId,
+        guild_id: Option<GuildId>,
+    ) -> Self {
+        let today = Utc.from_utc_datetime(&now.naive_utc().date().and_time(at));
+        let due = if today > now {
+            today
+        } else {
+            today + Duration::days(1)
+        };
+
+        Self {
+            word,
+            at,
+            due,
+            channel_id,
+            guild_id,
+        }
+    }
+}
+
+/// The posts that haven't been sent yet.
+#[derive(Debug, Default)]
+pub struct ScheduledPosts {
+    posts: Vec<ScheduledPost>,
+}
+
+impl ScheduledPosts {
+    /// Add a post, unless its guild already has too many waiting.
+    pub fn add(&mut self, post: ScheduledPost) -> Result<(), String> {
+        let pending = self
+            .posts
+            .iter()
+            .filter(|pending| pending.guild_id == post.guild_id)
+            .count();
+
+        if pending >= MAX_PENDING_PER_GUILD {
+            return Err(format!(
+                "There are already {} posts waiting in this server",
+                MAX_PENDING_PER_GUILD
+            ));
+        }
+
+        self.posts.push(post);
+        Ok(())
+    }
+
+    /// Remove and return the posts that are due at `now`, oldest first. The
+    /// posts of the guilds paused at `now` keep waiting until they resume.
+    pub fn take_due(&mut self, now: DateTime<Utc>, pauses: &Pauses) -> Vec<ScheduledPost> {
+        let (mut due, pending) = self
+            .posts
+            .drain(..)
+            .partition(|post| post.due <= now && !pauses.is_paused(post.guild_id, now));
+        self.posts = pending;
+
+        due.sort_by_key(|post: &ScheduledPost| post.due);
+        due
+    }
+}
+
+/// Parse a UTC time of the day like `18:30`.
+pub fn parse_time(input: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(input, "%H:%M").map_err(|_| {
+        format!(
+            "`{}` is not a valid time, use the 24 hour UTC format like `18:30`",
+            input
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    /// A time on a day of March 2021.
+    fn march(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        let date = NaiveDate::from_ymd_opt(2021, 3, day).unwrap();
+        Utc.from_utc_datetime(&date.and_hms_opt(hour, minute, 0).unwrap())
+    }
+
+    #[test]
+    fn due_times() {
+        let now = march(14, 12, 0);
+
+        let later = ScheduledPost::new(
+            "ciao".to_owned(),
+            parse_time("18:30").unwrap(),
+            now,
+            ChannelId(1),
+            None,
+        );
+        assert_eq!(march(14, 18, 30), later.due);
+
+        // the time already passed today
+        let tomorrow = ScheduledPost::new(
+            "ciao".to_owned(),
+            parse_time("12:00").unwrap(),
+            now,
+            ChannelId(1),
+            None,
+        );
+        assert_eq!(march(15, 12, 0), tomorrow.due);
+
+        assert!(parse_time("24:00").is_err());
+        assert!(parse_time("noon").is_err());
+        assert!(parse_time("9:05").is_ok());
+    }
+
+    #[test]
+    fn pending_posts() {
+        let now = march(14, 12, 0);
+        let post = |word: &str, at: &str, guild_id| {
+            ScheduledPost::new(
+                word.to_owned(),
+                parse_time(at).unwrap(),
+                now,
+                ChannelId(1),
+                guild_id,
+            )
+        };
+        let mut posts = ScheduledPosts::default();
+        let mut pauses = Pauses::default();
+
+        posts
+            .add(post("gnocchi", "13:00", Some(GuildId(1))))
+            .unwrap();
+        posts.add(post("ciao", "12:30", Some(GuildId(1)))).unwrap();
+        posts.add(post("pino", "14:00", Some(GuildId(2)))).unwrap();
+
+        assert!(posts.take_due(now, &pauses).is_empty());
+
+        // the posts of a paused guild wait
+        pauses.pause(Some(GuildId(1)), now, None);
+        assert!(posts.take_due(now + Duration::hours(1), &pauses).is_empty());
+        pauses.resume(Some(GuildId(1)));
+
+        let due: Vec<_> = posts
+            .take_due(now + Duration::hours(1), &pauses)
+            .into_iter()
+            .map(|post| post.word)
+            .collect();
+        assert_eq!(vec!["ciao", "gnocchi"], due);
+        assert!(posts.take_due(now + Duration::hours(1), &pauses).is_empty());
+
+        let due = posts.take_due(now + Duration::days(1), &pauses);
+        assert_eq!(1, due.len());
+    }
+
+    #[test]
+    fn limit_per_guild() {
+        let now = Utc::now();
+        let at = parse_time("12:00").unwrap();
+        let mut posts = ScheduledPosts::default();
+
+        for _ in 0..MAX_PENDING_PER_GUILD {
+            posts
+                .add(ScheduledPost::new(
+                    "ciao".to_owned(),
+                    at,
+                    now,
+                    ChannelId(1),
+                    Some(GuildId(1)),
+                ))
+                .unwrap();
+        }
+
+        assert!(posts
+            .add(ScheduledPost::new(
+                "ciao".to_owned(),
+                at,
+                now,
+                ChannelId(1),
+                Some(GuildId(1))
+            ))
+            .is_err());
+        // other guilds have their own limit
+        assert!(posts
+            .add(ScheduledPost::new(
+                "ciao".to_owned(),
+                at,
+                now,
+                ChannelId(1),
+                Some(GuildId(2))
+            ))
+            .is_ok());
+    }
+}
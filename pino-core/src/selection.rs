@@ -0,0 +1,179 @@
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+use crate::{blocklist::Blocklist, trending, WordMap};
+
+/// What makes a word the one to say.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Scoring {
+    /// How many times it was said.
+    #[default]
+    Count,
+    /// How fast it's growing, see [`trending`].
+    Trending,
+}
+
+impl FromStr for Scoring {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "count" => Ok(Scoring::Count),
+            "trending" => Ok(Scoring::Trending),
+            _ => Err(format!(
+                "`{}` is not a way to score words, use count or trending",
+                s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Scoring {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Scoring::Count => write!(f, "count"),
+            Scoring::Trending => write!(f, "trending"),
+        }
+    }
+}
+
+/// How the word to say is picked at `now`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Selection {
+    pub scoring: Scoring,
+    /// The random boost of [`Scoring::Count`].
+    pub max_boost: usize,
+    /// The length of the windows of [`Scoring::Trending`], in seconds.
+    pub window: u64,
+    pub now: DateTime<Utc>,
+}
+
+impl Selection {
+    /// Pick by count with a random boost of at most `max_boost`, like pino
+    /// always did.
+    pub fn by_count(max_boost: usize) -> Self {
+        Self {
+            scoring: Scoring::Count,
+            max_boost,
+            window: 0,
+            now: Utc::now(),
+        }
+    }
+
+    pub fn pick(
+        &self,
+        words: &WordMap,
+        blocklist: &Blocklist,
+        rng: &mut impl Rng,
+    ) -> Option<String> {
+        match self.scoring {
+            Scoring::Count => pick_word(words, blocklist, rng, self.max_boost),
+            Scoring::Trending => trending::pick_trending(words, blocklist, self.now, self.window),
+        }
+    }
+}
+
+/// Pick the word to say: the most said one, after adding a random boost of at
+/// most `max_boost` to every count. Blocked words are never picked, even if they
+/// were learned before being blocked.
+pub fn pick_word(
+    words: &WordMap,
+    blocklist: &Blocklist,
+    rng: &mut impl Rng,
+    max_boost: usize,
+) -> Option<String> {
+    words
+        .iter()
+        .filter(|(word, _instances)| !blocklist.is_blocked(word))
+        .max_by_key(|(_word, instances)| instances.len() + rng.gen_range(0..=max_boost))
+        .map(|(word, _)| word.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocklist::Pattern;
+    use chrono::Utc;
+    use rand::{rngs::StdRng, SeedableRng};
+    use utils::SortedVec;
+
+    #[test]
+    fn picks_most_said() {
+        let now = Utc::now();
+        let mut rng = StdRng::seed_from_u64(69);
+        let mut words = WordMap::new();
+        words.insert("ciao".to_owned(), SortedVec::from_vec(vec![now; 10]));
+        words.insert("gnocchi".to_owned(), SortedVec::from_vec(vec![now; 2]));
+
+        let mut blocklist = Blocklist::default();
+        assert_eq!(None, pick_word(&WordMap::new(), &blocklist, &mut rng, 0));
+        assert_eq!(
+            Some("ciao".to_owned()),
+            pick_word(&words, &blocklist, &mut rng, 0)
+        );
+
+        blocklist.add(Pattern::parse("/^c/").unwrap());
+        assert_eq!(
+            Some("gnocchi".to_owned()),
+            pick_word(&words, &blocklist, &mut rng, 100)
+        );
+    }
+
+    #[test]
+    fn scorings() {
+        for scoring in &[Scoring::Count, Scoring::Trending] {
+            assert_eq!(Ok(*scoring), scoring.to_string().parse());
+        }
+        assert!("growth".parse::<Scoring>().is_err());
+
+        let now = Utc::now();
+        let mut rng = StdRng::seed_from_u64(69);
+        let blocklist = Blocklist::default();
+        let mut words = WordMap::new();
+        words.insert(
+            "ciao".to_owned(),
+            SortedVec::from_vec(vec![now - chrono::Duration::minutes(8); 10]),
+        );
+        words.insert("gnocchi".to_owned(), SortedVec::from_vec(vec![now; 2]));
+
+        let by_count = Selection::by_count(0);
+        let trending = Selection {
+            scoring: Scoring::Trending,
+            window: 300,
+            now,
+            ..by_count
+        };
+        assert_eq!(
+            Some("ciao".to_owned()),
+            by_count.pick(&words, &blocklist, &mut rng)
+        );
+        assert_eq!(
+            Some("gnocchi".to_owned()),
+            trending.pick(&words, &blocklist, &mut rng)
+        );
+    }
+    #[test]
+    fn same_seed_same_picks() {
+        let now = Utc::now();
+        let blocklist = Blocklist::default();
+        let mut words = WordMap::new();
+        for (word, count) in [("ciao", 5), ("pizza", 4), ("gnocchi", 3), ("pino", 1)] {
+            words.insert(word.to_owned(), SortedVec::from_vec(vec![now; count]));
+        }
+
+        let picks = |seed| -> Vec<_> {
+            let mut rng = StdRng::seed_from_u64(seed);
+            (0..50)
+                .map(|_| pick_word(&words, &blocklist, &mut rng, 10).unwrap())
+                .collect()
+        };
+
+        let first = picks(7);
+        assert_eq!(first, picks(7));
+        // with boosts this big, the most said word doesn't always win
+        assert!(first.iter().any(|word| word != "ciao"));
+    }
+}
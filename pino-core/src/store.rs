@@ -0,0 +1,219 @@
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+use crate::{budget, ids::GuildId, tracking, WordMap};
+
+/// The words learned in each guild, kept apart so that what a guild does with
+/// its words (`forget`, `reset`, `export`...) doesn't touch the others. Words
+/// said outside of any guild, e.g. in direct messages, are under `None`.
+#[derive(Clone, Default)]
+pub struct WordStore {
+    guilds: HashMap<Option<GuildId>, WordMap>,
+    /// Lent out for the guilds without words.
+    empty: WordMap,
+}
+
+impl WordStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The words of `guild`, empty if it has none.
+    pub fn guild(&self, guild: Option<GuildId>) -> &WordMap {
+        self.guilds.get(&guild).unwrap_or(&self.empty)
+    }
+
+    pub fn guild_mut(&mut self, guild: Option<GuildId>) -> &mut WordMap {
+        self.guilds.entry(guild).or_default()
+    }
+
+    /// Every guild with words, and its words.
+    pub fn iter(&self) -> impl Iterator<Item = (Option<GuildId>, &WordMap)> {
+        self.guilds.iter().map(|(&guild, words)| (guild, words))
+    }
+
+    /// Add an instance of a word said in `guild` at `time`.
+    pub fn learn(&mut self, guild: Option<GuildId>, word: String, time: DateTime<Utc>) {
+        tracking::learn(self.guild_mut(guild), word, time);
+    }
+
+    /// Forget every instance of `word` in `guild`. Returns how many there were.
+    pub fn forget(&mut self, guild: Option<GuildId>, word: &str) -> usize {
+        let forgotten = self
+            .guilds
+            .get_mut(&guild)
+            .and_then(|words| words.remove(word))
+            .map_or(0, |instances| instances.len());
+
+        self.drop_empty();
+
+        forgotten
+    }
+
+    /// Forget the words matching `matches` in every guild, e.g. once they
+    /// are blocked. Returns the guild of each forgotten word, with how many
+    /// instances it had.
+    pub fn forget_matching(
+        &mut self,
+        matches: impl Fn(&str) -> bool,
+    ) -> Vec<(Option<GuildId>, String, usize)> {
+        let mut forgotten = Vec::new();
+
+        for (&guild, words) in self.guilds.iter_mut() {
+            words.retain(|word, instances| {
+                let matching = matches(word);
+                if matching {
+                    forgotten.push((guild, word.clone(), instances.len()));
+                }
+
+                !matching
+            });
+        }
+
+        self.drop_empty();
+
+        forgotten
+    }
+
+    /// Forget every word of `guild`. Returns how many instances there were.
+    pub fn reset(&mut self, guild: Option<GuildId>) -> usize {
+        self.guilds.remove(&guild).map_or(0, |words| {
+            words.values().map(|instances| instances.len()).sum()
+        })
+    }
+
+    /// Forget the instances said at or before `older_than` in every guild, and then
+    /// the least recent words above `max_words` in each. Returns how many instances
+    /// expired and how many words were evicted.
+    pub fn forget_old(
+        &mut self,
+        older_than: DateTime<Utc>,
+        max_words: Option<usize>,
+    ) -> (usize, usize) {
+        let mut expired = 0;
+        let mut evicted = 0;
+
+        for words in self.guilds.values_mut() {
+            expired += tracking::expire(words, older_than);
+            evicted +=
+                max_words.map_or(0, |max_words| budget::evict_least_recent(words, max_words));
+        }
+
+        self.drop_empty();
+
+        (expired, evicted)
+    }
+
+    /// How many distinct words each guild knows, added up.
+    pub fn word_count(&self) -> usize {
+        self.guilds.values().map(|words| words.len()).sum()
+    }
+
+    /// How many instances of words every guild knows, added up.
+    pub fn instance_count(&self) -> usize {
+        self.guilds
+            .values()
+            .flat_map(|words| words.values())
+            .map(|instances| instances.len())
+            .sum()
+    }
+
+    fn drop_empty(&mut self) {
+        self.guilds.retain(|_guild, words| !words.is_empty());
+    }
+}
+
+impl From<HashMap<Option<GuildId>, WordMap>> for WordStore {
+    fn from(guilds: HashMap<Option<GuildId>, WordMap>) -> Self {
+        let mut store = Self {
+            guilds,
+            empty: WordMap::new(),
+        };
+        store.drop_empty();
+
+        store
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    const A: Option<GuildId> = Some(GuildId(1));
+    const B: Option<GuildId> = Some(GuildId(2));
+
+    #[test]
+    fn guilds_are_apart() {
+        let now = Utc::now();
+        let mut store = WordStore::new();
+
+        store.learn(A, "ciao".to_owned(), now);
+        store.learn(A, "pizza".to_owned(), now);
+        store.learn(B, "ciao".to_owned(), now);
+        store.learn(None, "dm".to_owned(), now);
+
+        assert_eq!(2, store.guild(A).len());
+        assert_eq!(1, store.guild(B).len());
+        assert_eq!(4, store.word_count());
+        assert_eq!(3, store.iter().count());
+
+        assert_eq!(1, store.forget(A, "ciao"));
+        assert_eq!(0, store.forget(A, "ciao"));
+        assert_eq!(1, store.guild(B)["ciao"].len());
+
+        assert_eq!(1, store.reset(A));
+        assert!(store.guild(A).is_empty());
+        // guild B survives a reset in guild A
+        assert_eq!(1, store.guild(B).len());
+        assert_eq!(1, store.guild(None).len());
+        assert_eq!(0, store.reset(Some(GuildId(3))));
+    }
+
+    #[test]
+    fn forgetting_matching_words() {
+        let now = Utc::now();
+        let mut store = WordStore::new();
+
+        store.learn(A, "gnocchi".to_owned(), now);
+        store.learn(A, "gnocchi".to_owned(), now);
+        store.learn(A, "ciao".to_owned(), now);
+        store.learn(B, "gnu".to_owned(), now);
+        store.learn(None, "gnomo".to_owned(), now);
+
+        let mut forgotten = store.forget_matching(|word| word.starts_with("gn"));
+        forgotten.sort();
+        assert_eq!(
+            vec![
+                (None, "gnomo".to_owned(), 1),
+                (A, "gnocchi".to_owned(), 2),
+                (B, "gnu".to_owned(), 1),
+            ],
+            forgotten
+        );
+        assert_eq!(1, store.word_count());
+        assert_eq!(1, store.iter().count());
+        assert!(store.forget_matching(|word| word == "pizza").is_empty());
+    }
+
+    #[test]
+    fn forgetting_old_words() {
+        let now = Utc::now();
+        let mut store = WordStore::new();
+
+        store.learn(A, "old".to_owned(), now - Duration::hours(2));
+        store.learn(A, "ciao".to_owned(), now);
+        store.learn(A, "pizza".to_owned(), now - Duration::minutes(1));
+        store.learn(B, "old".to_owned(), now - Duration::hours(2));
+        store.learn(B, "ciao".to_owned(), now);
+
+        assert_eq!((2, 1), store.forget_old(now - Duration::hours(1), Some(1)));
+        assert_eq!(1, store.guild(A).len());
+        assert!(store.guild(A).contains_key("ciao"));
+        assert_eq!(1, store.guild(B).len());
+        assert_eq!(2, store.instance_count());
+
+        assert_eq!((2, 0), store.forget_old(now, None));
+        assert_eq!(0, store.iter().count());
+    }
+}
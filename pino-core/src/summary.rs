@@ -0,0 +1,316 @@
+use chrono::{DateTime, Datelike, Duration, TimeZone, Timelike, Utc};
+use std::collections::HashMap;
+
+use crate::ids::{GuildId, UserId};
+
+/// Words listed by a summary.
+pub const TOP_WORDS: usize = 10;
+
+/// The Sunday midnight, UTC, starting the week of `time`.
+pub fn week_start(time: DateTime<Utc>) -> DateTime<Utc> {
+    let days = time.weekday().num_days_from_sunday() as i64;
+    let date = time.date_naive() - Duration::days(days);
+
+    Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).unwrap())
+}
+
+/// The first Sunday midnight after `now`, when the weekly summary is posted.
+pub fn next_week(now: DateTime<Utc>) -> DateTime<Utc> {
+    week_start(now) + Duration::weeks(1)
+}
+
+/// What was said in a guild in a week.
+#[derive(Debug, Clone)]
+struct Week {
+    start: DateTime<Utc>,
+    messages: u64,
+    words: HashMap<String, u64>,
+    /// Messages by author.
+    authors: HashMap<UserId, u64>,
+    /// Messages by hour of the day, in UTC.
+    hours: [u64; 24],
+}
+
+impl Week {
+    fn new(start: DateTime<Utc>) -> Self {
+        Self {
+            start,
+            messages: 0,
+            words: HashMap::new(),
+            authors: HashMap::new(),
+            hours: [0; 24],
+        }
+    }
+
+    fn record(&mut self, author: UserId, words: &[String], time: DateTime<Utc>) {
+        self.messages += 1;
+        *self.authors.entry(author).or_default() += 1;
+        self.hours[time.hour() as usize] += 1;
+
+        for word in words {
+            *self.words.entry(word.clone()).or_default() += 1;
+        }
+    }
+}
+
+/// The week being counted in a guild, and the one before it, which is
+/// summarized right after it ends.
+#[derive(Debug)]
+struct GuildWeeks {
+    current: Week,
+    previous: Option<Week>,
+}
+
+/// The weekly statistics of every guild. They aren't saved, so a restart
+/// starts the week over.
+#[derive(Debug, Default)]
+pub struct WeeklyStats {
+    guilds: HashMap<GuildId, GuildWeeks>,
+}
+
+impl WeeklyStats {
+    /// Count a message of `author` in `guild` sent at `time`, with the words
+    /// learned from it. A message of a new week closes the current one.
+    pub fn record(
+        &mut self,
+        guild: GuildId,
+        author: UserId,
+        words: &[String],
+        time: DateTime<Utc>,
+    ) {
+        let start = week_start(time);
+        let weeks = self.guilds.entry(guild).or_insert_with(|| GuildWeeks {
+            current: Week::new(start),
+            previous: None,
+        });
+
+        if start > weeks.current.start {
+            let ended = std::mem::replace(&mut weeks.current, Week::new(start));
+            // Only the week right before is kept
+            weeks.previous = Some(ended).filter(|ended| ended.start + Duration::weeks(1) == start);
+        }
+
+        let week = if start == weeks.current.start {
+            Some(&mut weeks.current)
+        } else {
+            weeks.previous.as_mut().filter(|week| week.start == start)
+        };
+
+        // Late messages of older weeks are dropped
+        if let Some(week) = week {
+            week.record(author, words, time);
+        }
+    }
+
+    /// Stop counting the words matching `matches` in every week kept, e.g.
+    /// once they are blocked.
+    pub fn forget_matching(&mut self, matches: impl Fn(&str) -> bool) {
+        for weeks in self.guilds.values_mut() {
+            for week in std::iter::once(&mut weeks.current).chain(&mut weeks.previous) {
+                week.words.retain(|word, _count| !matches(word));
+            }
+        }
+    }
+
+    /// The summary of the week of `guild` starting at `start`, empty if
+    /// nothing was counted then.
+    pub fn summary(&self, guild: GuildId, start: DateTime<Utc>) -> Summary {
+        self.summary_with(guild, start, TOP_WORDS)
+    }
+
+    /// Like [`summary`](Self::summary), listing up to `words` words instead
+    /// of [`TOP_WORDS`].
+    pub fn summary_with(&self, guild: GuildId, start: DateTime<Utc>, words: usize) -> Summary {
+        let week = self.guilds.get(&guild).and_then(|weeks| {
+            std::iter::once(&weeks.current)
+                .chain(&weeks.previous)
+                .find(|week| week.start == start)
+        });
+
+        match week {
+            Some(week) => Summary::of(week, words),
+            None => Summary::empty(start),
+        }
+    }
+}
+
+/// The highlights of a week.
+#[derive(Debug, PartialEq)]
+pub struct Summary {
+    pub start: DateTime<Utc>,
+    /// The most said words with their count, at most [`TOP_WORDS`] unless
+    /// asked for more.
+    pub top_words: Vec<(String, u64)>,
+    /// The author of the most messages, and how many they sent.
+    pub top_user: Option<(UserId, u64)>,
+    /// The UTC hour of the day with the most messages, and how many.
+    pub busiest_hour: Option<(usize, u64)>,
+    pub messages: u64,
+}
+
+impl Summary {
+    fn empty(start: DateTime<Utc>) -> Self {
+        Self {
+            start,
+            top_words: Vec::new(),
+            top_user: None,
+            busiest_hour: None,
+            messages: 0,
+        }
+    }
+
+    /// Ties are broken by word, user id or hour, so that the summary doesn't
+    /// depend on the order of the maps.
+    fn of(week: &Week, words: usize) -> Self {
+        let mut top_words: Vec<_> = week
+            .words
+            .iter()
+            .map(|(word, &count)| (word.clone(), count))
+            .collect();
+        top_words.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+        top_words.truncate(words);
+
+        let top_user = week
+            .authors
+            .iter()
+            .map(|(&user, &count)| (user, count))
+            .max_by(|(a, a_count), (b, b_count)| a_count.cmp(b_count).then(b.cmp(a)));
+
+        let busiest_hour = week
+            .hours
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|&(_hour, count)| count > 0)
+            .max_by(|(a, a_count), (b, b_count)| a_count.cmp(b_count).then(b.cmp(a)));
+
+        Self {
+            start: week.start,
+            top_words,
+            top_user,
+            busiest_hour,
+            messages: week.messages,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+
+    fn at(year: i32, month: u32, day: u32, hour: u32) -> DateTime<Utc> {
+        let date = NaiveDate::from_ymd_opt(year, month, day).unwrap();
+        Utc.from_utc_datetime(&date.and_hms_opt(hour, 30, 0).unwrap())
+    }
+
+    fn words(words: &[&str]) -> Vec<String> {
+        words.iter().map(|word| word.to_string()).collect()
+    }
+
+    #[test]
+    fn weeks() {
+        // a Wednesday
+        let sunday = at(2021, 3, 14, 0) - Duration::minutes(30);
+        assert_eq!(sunday, week_start(at(2021, 3, 17, 21)));
+        assert_eq!(sunday, week_start(sunday));
+        assert_eq!(sunday, week_start(at(2021, 3, 20, 23)));
+        assert_eq!(sunday + Duration::weeks(1), next_week(at(2021, 3, 17, 21)));
+        assert_eq!(sunday + Duration::weeks(1), next_week(sunday));
+    }
+
+    #[test]
+    fn summaries() {
+        let mut stats = WeeklyStats::default();
+        let wednesday = at(2021, 3, 17, 21);
+        let start = week_start(wednesday);
+
+        assert_eq!(Summary::empty(start), stats.summary(GuildId(1), start));
+
+        stats.record(
+            GuildId(1),
+            UserId(42),
+            &words(&["pizza", "pasta"]),
+            wednesday,
+        );
+        stats.record(GuildId(1), UserId(42), &words(&["pizza"]), wednesday);
+        stats.record(
+            GuildId(1),
+            UserId(7),
+            &words(&["pasta", "pizza"]),
+            at(2021, 3, 18, 9),
+        );
+        stats.record(GuildId(1), UserId(7), &[], at(2021, 3, 18, 21));
+        // another guild
+        stats.record(GuildId(2), UserId(7), &words(&["gnocchi"]), wednesday);
+
+        let summary = stats.summary(GuildId(1), start);
+        assert_eq!(
+            vec![("pizza".to_owned(), 3), ("pasta".to_owned(), 2)],
+            summary.top_words
+        );
+        // tied, the lowest id wins
+        assert_eq!(Some((UserId(7), 2)), summary.top_user);
+        assert_eq!(Some((21, 3)), summary.busiest_hour);
+        assert_eq!(4, summary.messages);
+
+        // the next week starts from scratch, and the last one is kept
+        let next = at(2021, 3, 22, 10);
+        stats.record(GuildId(1), UserId(42), &words(&["gnocchi"]), next);
+        assert_eq!(1, stats.summary(GuildId(1), week_start(next)).messages);
+        assert_eq!(summary, stats.summary(GuildId(1), start));
+
+        // late messages still count in the last week
+        stats.record(GuildId(1), UserId(42), &[], wednesday);
+        assert_eq!(5, stats.summary(GuildId(1), start).messages);
+
+        // two weeks later nothing is left of the first one
+        stats.record(GuildId(1), UserId(42), &[], next + Duration::weeks(2));
+        assert_eq!(Summary::empty(start), stats.summary(GuildId(1), start));
+        assert_eq!(
+            Summary::empty(week_start(next)),
+            stats.summary(GuildId(1), week_start(next))
+        );
+    }
+
+    #[test]
+    fn forgetting_words() {
+        let mut stats = WeeklyStats::default();
+        let now = at(2021, 3, 17, 21);
+
+        stats.record(GuildId(1), UserId(42), &words(&["gnocchi", "ciao"]), now);
+        stats.record(GuildId(2), UserId(42), &words(&["gnu"]), now);
+        stats.forget_matching(|word| word.starts_with("gn"));
+
+        let summary = stats.summary(GuildId(1), week_start(now));
+        assert_eq!(vec![("ciao".to_owned(), 1)], summary.top_words);
+        // the messages still count
+        assert_eq!(1, summary.messages);
+        assert!(stats
+            .summary(GuildId(2), week_start(now))
+            .top_words
+            .is_empty());
+    }
+
+    #[test]
+    fn top_words() {
+        let mut stats = WeeklyStats::default();
+        let now = at(2021, 3, 17, 21);
+
+        for i in 0..TOP_WORDS + 5 {
+            stats.record(
+                GuildId(1),
+                UserId(42),
+                &words(&[&format!("word{:02}", i)]),
+                now,
+            );
+        }
+
+        let summary = stats.summary(GuildId(1), week_start(now));
+        assert_eq!(TOP_WORDS, summary.top_words.len());
+        assert_eq!(("word00".to_owned(), 1), summary.top_words[0]);
+
+        let summary = stats.summary_with(GuildId(1), week_start(now), 25);
+        assert_eq!(TOP_WORDS + 5, summary.top_words.len());
+    }
+}
@@ -0,0 +1,292 @@
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, collections::HashSet, fmt, str::FromStr};
+use unicode_normalization::UnicodeNormalization;
+use utils::SortedVec;
+
+use crate::{blocklist::Blocklist, WordMap};
+
+/// Unicode normalization form applied to the words before anything else, so
+/// that the same text typed in different ways is the same word.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Normalization {
+    #[default]
+    None,
+    Nfc,
+    Nfd,
+    Nfkc,
+    Nfkd,
+}
+
+const NORMALIZATIONS: &[(&str, Normalization)] = &[
+    ("none", Normalization::None),
+    ("nfc", Normalization::Nfc),
+    ("nfd", Normalization::Nfd),
+    ("nfkc", Normalization::Nfkc),
+    ("nfkd", Normalization::Nfkd),
+];
+
+impl Normalization {
+    pub fn apply(self, word: &str) -> Cow<'_, str> {
+        match self {
+            Normalization::None => Cow::Borrowed(word),
+            Normalization::Nfc => Cow::Owned(word.nfc().collect()),
+            Normalization::Nfd => Cow::Owned(word.nfd().collect()),
+            Normalization::Nfkc => Cow::Owned(word.nfkc().collect()),
+            Normalization::Nfkd => Cow::Owned(word.nfkd().collect()),
+        }
+    }
+}
+
+impl fmt::Display for Normalization {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (name, _) = NORMALIZATIONS
+            .iter()
+            .find(|(_, normalization)| normalization == self)
+            .expect("every normalization to have a name");
+
+        f.write_str(name)
+    }
+}
+
+impl FromStr for Normalization {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        NORMALIZATIONS
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(s))
+            .map(|&(_, normalization)| normalization)
+            .ok_or_else(|| {
+                format!(
+                    "`{}` is not a normalization form, use none, nfc, nfd, nfkc or nfkd",
+                    s
+                )
+            })
+    }
+}
+
+/// A word as it's stored in the word map: normalized and lowercased.
+pub fn normalize_word(word: &str, normalization: Normalization) -> String {
+    normalization.apply(word).to_lowercase()
+}
+
+/// The words of a message that are learned: the whitespace separated ones
+/// matching `regex` after normalizing them, lowercased, except stop words and
+/// blocked words.
+pub fn extract_words<'a>(
+    content: &'a str,
+    regex: &'a Regex,
+    normalization: Normalization,
+    stop_words: &'a HashSet<String>,
+    blocklist: &'a Blocklist,
+) -> impl Iterator<Item = String> + 'a {
+    content
+        .split_whitespace()
+        .map(move |word| normalization.apply(word))
+        .filter(move |word| regex.is_match(word))
+        .map(|word| word.to_lowercase())
+        .filter(move |word| !stop_words.contains(word) && !blocklist.is_blocked(word))
+}
+
+/// Whether a message has more than `max_length` characters, like pasted code
+/// or the output of other bots: its words would flood the word map.
+pub fn is_too_long(content: &str, max_length: usize) -> bool {
+    content.chars().nth(max_length).is_some()
+}
+
+/// Add an instance of a word said at `time`.
+pub fn learn(words: &mut WordMap, word: String, time: DateTime<Utc>) {
+    if let Some(instances) = words.get_mut(&word) {
+        instances.insert(time);
+    } else {
+        words.insert(word, SortedVec::from_vec(vec![time]));
+    }
+}
+
+/// Forget the instances said at or before `older_than`, and the words left
+/// without any. Returns the amount of forgotten instances.
+pub fn expire(words: &mut WordMap, older_than: DateTime<Utc>) -> usize {
+    let mut expired = 0;
+
+    for instances in words.values_mut() {
+        let count = instances.count_le(&older_than);
+
+        if count > 0 {
+            instances.remove_le(&older_than);
+            expired += count;
+
+            // Give back the memory of bursts, but don't reallocate
+            // every time a few instances expire
+            if instances.capacity() > 2 * instances.len() {
+                instances.shrink_to_fit();
+            }
+        }
+    }
+    // Remove entries with empty vectors to save space
+    words.retain(|_word, instances| !instances.is_empty());
+
+    expired
+}
+
+/// The `n` most said words starting with `prefix`, with how many times each
+/// was said, e.g. to suggest them while a word is typed. Ties go in
+/// alphabetical order. An empty `prefix` matches every word.
+pub fn starting_with<'a>(words: &'a WordMap, prefix: &str, n: usize) -> Vec<(&'a str, usize)> {
+    let mut matching: Vec<_> = words
+        .iter()
+        .filter(|(word, _instances)| word.starts_with(prefix))
+        .map(|(word, instances)| (word.as_str(), instances.len()))
+        .collect();
+
+    matching.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    matching.truncate(n);
+
+    matching
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocklist::Pattern;
+
+    #[test]
+    fn extraction() {
+        let regex = Regex::new("^[a-zA-Zàèéìòù']+$").unwrap();
+        let stop_words: HashSet<_> = vec!["il".to_owned()].into_iter().collect();
+        let mut blocklist = Blocklist::default();
+        blocklist.add(Pattern::parse("/^gn/").unwrap());
+
+        let words: Vec<_> = extract_words(
+            "Il Pino mangia 3 GNOCCHI e perché no",
+            &regex,
+            Normalization::None,
+            &stop_words,
+            &blocklist,
+        )
+        .collect();
+
+        assert_eq!(vec!["pino", "mangia", "e", "perché", "no"], words);
+    }
+
+    #[test]
+    fn normalization() {
+        let regex = Regex::new("^[a-zA-Zàèéìòù']+$").unwrap();
+        let words = |content, normalization| -> Vec<_> {
+            extract_words(
+                content,
+                &regex,
+                normalization,
+                &HashSet::new(),
+                &Blocklist::default(),
+            )
+            .collect()
+        };
+        // "perché" typed with a combining accent, and the ﬁ ligature
+        let decomposed = "perche\u{301} ﬁne";
+
+        assert!(words(decomposed, Normalization::None).is_empty());
+        assert_eq!(vec!["perché"], words(decomposed, Normalization::Nfc));
+        assert_eq!(
+            vec!["perché", "fine"],
+            words(decomposed, Normalization::Nfkc)
+        );
+        // the accent is a separate character, which the regex doesn't accept
+        assert!(words("perché", Normalization::Nfd).is_empty());
+
+        assert_eq!(
+            "perché",
+            normalize_word("PERCHE\u{301}", Normalization::Nfc)
+        );
+        assert_eq!(Ok(Normalization::Nfkd), "NFKD".parse());
+        assert!("nfx".parse::<Normalization>().is_err());
+        assert_eq!("nfkc", Normalization::Nfkc.to_string());
+    }
+
+    #[test]
+    fn long_messages() {
+        assert!(!is_too_long("ciao", 4));
+        assert!(is_too_long("ciao!", 4));
+        // characters, not bytes
+        assert!(!is_too_long("àèìòù", 5));
+        assert!(!is_too_long("", 0));
+        assert!(is_too_long("a", 0));
+    }
+
+    #[test]
+    fn learning() {
+        let now = Utc::now();
+        let mut words = WordMap::new();
+
+        learn(&mut words, "ciao".to_owned(), now);
+        learn(
+            &mut words,
+            "ciao".to_owned(),
+            now - chrono::Duration::minutes(1),
+        );
+        learn(&mut words, "pino".to_owned(), now);
+
+        assert_eq!(2, words["ciao"].len());
+        assert_eq!(now, *words["ciao"].as_ref().last().unwrap());
+        assert_eq!(1, words["pino"].len());
+    }
+
+    #[test]
+    fn expiry() {
+        let now = Utc::now();
+        let mut words = WordMap::new();
+
+        for minutes in 0..10 {
+            learn(
+                &mut words,
+                "ciao".to_owned(),
+                now - chrono::Duration::minutes(minutes),
+            );
+        }
+        learn(
+            &mut words,
+            "pino".to_owned(),
+            now - chrono::Duration::hours(1),
+        );
+
+        // six instances of ciao, and the only one of pino
+        assert_eq!(7, expire(&mut words, now - chrono::Duration::minutes(4)));
+        assert_eq!(4, words["ciao"].len());
+        assert!(!words.contains_key("pino"));
+
+        assert_eq!(0, expire(&mut words, now - chrono::Duration::hours(2)));
+        assert_eq!(4, expire(&mut words, now));
+        assert!(words.is_empty());
+    }
+
+    #[test]
+    fn prefixes() {
+        let now = Utc::now();
+        let mut words = WordMap::new();
+
+        for (word, count) in [
+            ("pizza", 3),
+            ("pino", 5),
+            ("pasta", 2),
+            ("piano", 3),
+            ("ciao", 9),
+        ] {
+            for _ in 0..count {
+                learn(&mut words, word.to_owned(), now);
+            }
+        }
+
+        assert_eq!(
+            vec![("pino", 5), ("piano", 3), ("pizza", 3)],
+            starting_with(&words, "pi", 25)
+        );
+        assert_eq!(vec![("pino", 5)], starting_with(&words, "pi", 1));
+        assert_eq!(vec![("pizza", 3)], starting_with(&words, "pizza", 25));
+        assert!(starting_with(&words, "pizzeria", 25).is_empty());
+        // nothing typed yet, the most said words
+        assert_eq!(vec![("ciao", 9), ("pino", 5)], starting_with(&words, "", 2));
+        assert!(starting_with(&WordMap::new(), "", 25).is_empty());
+    }
+}
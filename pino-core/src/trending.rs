@@ -0,0 +1,218 @@
+use chrono::{DateTime, Duration, Utc};
+use std::cmp::Ordering;
+use utils::SortedVec;
+
+use crate::{blocklist::Blocklist, WordMap};
+
+/// Added to the count of the previous window before dividing by it, so that
+/// brand new words don't divide by zero and a single new mention isn't
+/// infinitely trending.
+pub const SMOOTHING: f64 = 1.0;
+
+/// The two windows of `window` seconds that end at `now`, as the instants
+/// they start at: `(previous, current)`. Each window is half-open, from its
+/// start excluded to its end included, so an instant on the boundary counts
+/// for the previous window only.
+pub fn windows(now: DateTime<Utc>, window: u64) -> (DateTime<Utc>, DateTime<Utc>) {
+    let current = now - Duration::seconds(window as i64);
+    let previous = current - Duration::seconds(window as i64);
+
+    (previous, current)
+}
+
+/// How many `instances` fall in the current and in the previous window of
+/// `window` seconds before `now`. Instances after `now` aren't counted.
+pub fn window_counts(
+    instances: &SortedVec<DateTime<Utc>>,
+    now: DateTime<Utc>,
+    window: u64,
+) -> (usize, usize) {
+    let (previous_start, current_start) = windows(now, window);
+    let after_now = instances.get_ge_exclusive(&now).len();
+    let after_current = instances.get_ge_exclusive(&current_start).len();
+    let after_previous = instances.get_ge_exclusive(&previous_start).len();
+
+    (after_current - after_now, after_previous - after_current)
+}
+
+/// How fast a word is growing: its count in the current window over the one
+/// in the previous window, plus [`SMOOTHING`]. A word said as often as
+/// before stays just below 1, a brand new one scores its count.
+pub fn ratio(current: usize, previous: usize) -> f64 {
+    current as f64 / (previous as f64 + SMOOTHING)
+}
+
+/// A word said in the current window, with how fast it's growing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trend {
+    pub word: String,
+    pub current: usize,
+    pub previous: usize,
+    pub ratio: f64,
+}
+
+/// The words said in the last `window` seconds before `now`, growing the
+/// fastest first. Ties go to the word said the most in the current window,
+/// then alphabetically. Words not said lately aren't trending at all.
+pub fn trends(words: &WordMap, now: DateTime<Utc>, window: u64) -> Vec<Trend> {
+    let mut trends: Vec<_> = words
+        .iter()
+        .filter_map(|(word, instances)| {
+            let (current, previous) = window_counts(instances, now, window);
+
+            if current == 0 {
+                return None;
+            }
+
+            Some(Trend {
+                word: word.clone(),
+                current,
+                previous,
+                ratio: ratio(current, previous),
+            })
+        })
+        .collect();
+
+    trends.sort_by(|a, b| {
+        b.ratio
+            .partial_cmp(&a.ratio)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| b.current.cmp(&a.current))
+            .then_with(|| a.word.cmp(&b.word))
+    });
+
+    trends
+}
+
+/// The word growing the fastest, leaving out the blocked ones.
+pub fn pick_trending(
+    words: &WordMap,
+    blocklist: &Blocklist,
+    now: DateTime<Utc>,
+    window: u64,
+) -> Option<String> {
+    trends(words, now, window)
+        .into_iter()
+        .find(|trend| !blocklist.is_blocked(&trend.word))
+        .map(|trend| trend.word)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocklist::Pattern;
+
+    const MINUTE: u64 = 60;
+
+    /// Instants every `every` seconds, from `from` seconds to `to` seconds
+    /// before `now`.
+    fn series(now: DateTime<Utc>, from: i64, to: i64, every: i64) -> Vec<DateTime<Utc>> {
+        (0..)
+            .map(|i| from - i * every)
+            .take_while(|ago| *ago >= to)
+            .map(|ago| now - Duration::seconds(ago))
+            .collect()
+    }
+
+    #[test]
+    fn ratios() {
+        assert_eq!(0.0, ratio(0, 5));
+        // brand new words score their count
+        assert_eq!(8.0, ratio(8, 0));
+        // steady words stay below 1
+        assert!(ratio(10, 10) < 1.0);
+        assert_eq!(2.0, ratio(10, 4));
+    }
+
+    #[test]
+    fn alignment() {
+        let now = Utc::now();
+        let (previous, current) = windows(now, MINUTE);
+        assert_eq!(now - Duration::minutes(1), current);
+        assert_eq!(now - Duration::minutes(2), previous);
+
+        // the boundaries count for the window before them
+        let instances = SortedVec::from_vec(vec![
+            previous,
+            previous + Duration::seconds(1),
+            current,
+            current + Duration::seconds(1),
+            now,
+            now + Duration::seconds(1),
+        ]);
+        assert_eq!((2, 2), window_counts(&instances, now, MINUTE));
+        assert_eq!((0, 0), window_counts(&SortedVec::new(), now, MINUTE));
+    }
+
+    #[test]
+    fn synthetic_series() {
+        let now = Utc::now();
+        let mut words = WordMap::new();
+        // 10 times an hour, all day
+        words.insert(
+            "ciao".to_owned(),
+            SortedVec::from_vec(series(now, 86_400, 0, 360)),
+        );
+        // 8 times in the last five minutes, never before
+        words.insert(
+            "pizza".to_owned(),
+            SortedVec::from_vec(series(now, 290, 10, 40)),
+        );
+        // popular before, fading now
+        let mut fading = series(now, 590, 310, 20);
+        fading.extend(series(now, 200, 100, 100));
+        words.insert("pasta".to_owned(), SortedVec::from_vec(fading));
+        // said long ago only
+        words.insert(
+            "gnocchi".to_owned(),
+            SortedVec::from_vec(series(now, 5000, 4000, 100)),
+        );
+
+        let trends = trends(&words, now, 5 * MINUTE);
+        let ranked: Vec<_> = trends.iter().map(|trend| trend.word.as_str()).collect();
+        assert_eq!(vec!["pizza", "ciao", "pasta"], ranked);
+
+        assert_eq!(8, trends[0].current);
+        assert_eq!(0, trends[0].previous);
+        assert_eq!(8.0, trends[0].ratio);
+        assert_eq!(1, trends[1].current);
+        assert_eq!(2, trends[2].current);
+        assert_eq!(15, trends[2].previous);
+
+        // by count, the steady word would win
+        let blocklist = Blocklist::default();
+        assert_eq!(
+            Some("pizza".to_owned()),
+            pick_trending(&words, &blocklist, now, 5 * MINUTE)
+        );
+    }
+
+    #[test]
+    fn ties_and_blocks() {
+        let now = Utc::now();
+        let mut words = WordMap::new();
+        words.insert("b".to_owned(), SortedVec::from_vec(vec![now; 2]));
+        words.insert("a".to_owned(), SortedVec::from_vec(vec![now; 2]));
+        let mut growing = vec![now; 4];
+        growing.push(now - Duration::seconds(90));
+        words.insert("c".to_owned(), SortedVec::from_vec(growing));
+
+        // 4 / (1 + 1) = 2 / (0 + 1), the one said more wins
+        let ranked: Vec<_> = trends(&words, now, MINUTE)
+            .into_iter()
+            .map(|trend| trend.word)
+            .collect();
+        assert_eq!(vec!["c", "a", "b"], ranked);
+
+        let mut blocklist = Blocklist::default();
+        blocklist.add(Pattern::parse("c").unwrap());
+        assert_eq!(
+            Some("a".to_owned()),
+            pick_trending(&words, &blocklist, now, MINUTE)
+        );
+        assert_eq!(
+            None,
+            pick_trending(&WordMap::new(), &blocklist, now, MINUTE)
+        );
+    }
+}
@@ -0,0 +1,63 @@
+use chrono::{DateTime, Utc};
+use std::{borrow::Borrow, collections::HashMap, hash::Hash};
+
+/// Keys that are only there until a given time.
+#[derive(Debug)]
+pub struct TtlMap<K> {
+    entries: HashMap<K, DateTime<Utc>>,
+}
+
+impl<K: Eq + Hash> TtlMap<K> {
+    /// Add a key until `until`. A key that's already there gets the later of the two times.
+    pub fn insert(&mut self, key: K, until: DateTime<Utc>) {
+        let entry = self.entries.entry(key).or_insert(until);
+        *entry = (*entry).max(until);
+    }
+
+    /// Whether the key is there and hasn't expired at `now`.
+    pub fn contains<Q>(&self, key: &Q, now: DateTime<Utc>) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        matches!(self.entries.get(key), Some(&until) if now < until)
+    }
+
+    /// Drop the keys that expired at `now`.
+    pub fn purge(&mut self, now: DateTime<Utc>) {
+        self.entries.retain(|_key, until| now < *until);
+    }
+}
+
+impl<K> Default for TtlMap<K> {
+    fn default() -> Self {
+        Self {
+            entries: HashMap::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn expiry() {
+        let now = Utc::now();
+        let mut map = TtlMap::default();
+
+        map.insert("ciao".to_owned(), now + Duration::hours(1));
+        assert!(map.contains("ciao", now));
+        assert!(map.contains("ciao", now + Duration::minutes(59)));
+        assert!(!map.contains("ciao", now + Duration::hours(1)));
+        assert!(!map.contains("pino", now));
+
+        // an earlier time doesn't shorten the entry
+        map.insert("ciao".to_owned(), now + Duration::minutes(1));
+        assert!(map.contains("ciao", now + Duration::minutes(30)));
+
+        map.purge(now + Duration::hours(2));
+        assert!(map.entries.is_empty());
+    }
+}
@@ -0,0 +1,195 @@
+use chrono::{DateTime, Utc};
+use std::collections::{HashMap, HashSet};
+
+use crate::ids::{GuildId, UserId};
+
+/// The words nominated in a guild for the word of the week, with who voted
+/// for each of them.
+pub type VotingPool = HashMap<String, HashSet<UserId>>;
+
+/// Why a vote wasn't counted.
+#[derive(Debug, PartialEq)]
+pub enum VoteError {
+    /// Words have to be nominated before they can be voted.
+    NotNominated,
+}
+
+/// The pool of a guild for the week starting at `week`.
+#[derive(Debug)]
+struct Ballot {
+    week: DateTime<Utc>,
+    pool: VotingPool,
+}
+
+/// The word of the week of every guild. Each week starts from an empty pool,
+/// the pool of a past week is thrown away the first time the new week is
+/// touched.
+#[derive(Debug, Default)]
+pub struct Elections {
+    guilds: HashMap<GuildId, Ballot>,
+}
+
+impl Elections {
+    /// The pool of `guild` for `week`, emptied if it was of an older week.
+    fn pool(&mut self, guild: GuildId, week: DateTime<Utc>) -> &mut VotingPool {
+        let ballot = self.guilds.entry(guild).or_insert_with(|| Ballot {
+            week,
+            pool: VotingPool::new(),
+        });
+
+        if ballot.week < week {
+            ballot.week = week;
+            ballot.pool.clear();
+        }
+
+        &mut ballot.pool
+    }
+
+    /// Add `word` to the pool of `guild`. Returns false if it was already
+    /// there.
+    pub fn nominate(&mut self, guild: GuildId, word: &str, week: DateTime<Utc>) -> bool {
+        let pool = self.pool(guild, week);
+
+        if pool.contains_key(word) {
+            return false;
+        }
+
+        pool.insert(word.to_owned(), HashSet::new());
+        true
+    }
+
+    /// Vote `word` for `user`. Everyone has a single vote, a new one moves it:
+    /// returns the word it was taken from, if any.
+    pub fn vote(
+        &mut self,
+        guild: GuildId,
+        user: UserId,
+        word: &str,
+        week: DateTime<Utc>,
+    ) -> Result<Option<String>, VoteError> {
+        let pool = self.pool(guild, week);
+
+        if !pool.contains_key(word) {
+            return Err(VoteError::NotNominated);
+        }
+
+        let previous = pool
+            .iter_mut()
+            .find(|(_word, voters)| voters.contains(&user))
+            .map(|(previous, voters)| {
+                voters.remove(&user);
+                previous.clone()
+            });
+        pool.get_mut(word).unwrap().insert(user);
+
+        Ok(previous.filter(|previous| previous != word))
+    }
+
+    /// Every word nominated in `guild` for `week` with its votes, the most
+    /// voted first. Ties are sorted alphabetically.
+    pub fn standings(&self, guild: GuildId, week: DateTime<Utc>) -> Vec<(String, usize)> {
+        let pool = match self.guilds.get(&guild) {
+            Some(ballot) if ballot.week == week => &ballot.pool,
+            _ => return Vec::new(),
+        };
+
+        let mut standings: Vec<_> = pool
+            .iter()
+            .map(|(word, voters)| (word.clone(), voters.len()))
+            .collect();
+        standings.sort_by(|(a, a_votes), (b, b_votes)| b_votes.cmp(a_votes).then(a.cmp(b)));
+
+        standings
+    }
+
+    /// Close the vote of `week` in `guild`: the most voted word with its
+    /// votes, if anyone voted. The pool is emptied either way.
+    pub fn close(&mut self, guild: GuildId, week: DateTime<Utc>) -> Option<(String, usize)> {
+        let winner = self
+            .standings(guild, week)
+            .into_iter()
+            .next()
+            .filter(|&(_, votes)| votes > 0);
+
+        if self
+            .guilds
+            .get(&guild)
+            .is_some_and(|ballot| ballot.week == week)
+        {
+            self.guilds.remove(&guild);
+        }
+
+        winner
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn voting() {
+        let mut elections = Elections::default();
+        let guild = GuildId(1);
+        let week = Utc::now();
+
+        assert_eq!(
+            Err(VoteError::NotNominated),
+            elections.vote(guild, UserId(7), "pizza", week)
+        );
+
+        assert!(elections.nominate(guild, "pizza", week));
+        assert!(!elections.nominate(guild, "pizza", week));
+        assert!(elections.nominate(guild, "pasta", week));
+        assert_eq!(
+            vec![("pasta".to_owned(), 0), ("pizza".to_owned(), 0)],
+            elections.standings(guild, week)
+        );
+
+        assert_eq!(Ok(None), elections.vote(guild, UserId(7), "pizza", week));
+        assert_eq!(Ok(None), elections.vote(guild, UserId(8), "pizza", week));
+        // voting again for the same word changes nothing
+        assert_eq!(Ok(None), elections.vote(guild, UserId(8), "pizza", week));
+        assert_eq!(
+            Ok(Some("pizza".to_owned())),
+            elections.vote(guild, UserId(7), "pasta", week)
+        );
+        assert_eq!(Ok(None), elections.vote(guild, UserId(9), "pasta", week));
+        assert_eq!(
+            vec![("pasta".to_owned(), 2), ("pizza".to_owned(), 1)],
+            elections.standings(guild, week)
+        );
+
+        // other guilds have their own pool
+        assert!(elections.standings(GuildId(2), week).is_empty());
+        assert!(elections.nominate(GuildId(2), "gnocchi", week));
+
+        assert_eq!(Some(("pasta".to_owned(), 2)), elections.close(guild, week));
+        assert!(elections.standings(guild, week).is_empty());
+        assert_eq!(None, elections.close(guild, week));
+    }
+
+    #[test]
+    fn weeks() {
+        let mut elections = Elections::default();
+        let guild = GuildId(1);
+        let week = Utc::now();
+        let next = week + Duration::weeks(1);
+
+        elections.nominate(guild, "pizza", week);
+        elections.vote(guild, UserId(7), "pizza", week).unwrap();
+
+        // the new week starts from scratch
+        assert_eq!(
+            Err(VoteError::NotNominated),
+            elections.vote(guild, UserId(7), "pizza", next)
+        );
+        assert!(elections.standings(guild, week).is_empty());
+        assert_eq!(None, elections.close(guild, week));
+
+        // nominated words without votes don't win
+        elections.nominate(guild, "pasta", next);
+        assert_eq!(None, elections.close(guild, next));
+    }
+}
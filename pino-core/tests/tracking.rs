@@ -0,0 +1,69 @@
+//! Feeds messages through the same tracking and selection used by the bot,
+//! without connecting to Discord.
+
+use chrono::{Duration, Utc};
+use pino_core::{
+    blocklist::{Blocklist, Pattern},
+    selection, tracking, WordMap,
+};
+use rand::{rngs::StdRng, SeedableRng};
+use regex::Regex;
+use std::collections::HashSet;
+
+/// The default of `--word-regex`.
+const WORD_REGEX: &str = "^[a-zA-ZàáèéìíòóùúÀÁÈÉÌÍÒÓÙÚ']+$";
+
+#[test]
+fn most_said_word_is_picked() {
+    let regex = Regex::new(WORD_REGEX).unwrap();
+    let stop_words: HashSet<_> = vec!["il".to_owned(), "la".to_owned()].into_iter().collect();
+    let mut blocklist = Blocklist::default();
+    blocklist.add(Pattern::parse("/^gn/").unwrap());
+
+    let contents = [
+        "il gnocco è buono",
+        "la pizza è buona",
+        "Pizza pizza PIZZA!",
+        "gnocchi gnocchi gnocchi gnocchi",
+        "ciao pino",
+    ];
+
+    let start = Utc::now() - Duration::minutes(100);
+    let mut words = WordMap::new();
+
+    for i in 0..100 {
+        let content = contents[i % contents.len()];
+        let time = start + Duration::minutes(i as i64);
+
+        for word in tracking::extract_words(
+            content,
+            &regex,
+            tracking::Normalization::None,
+            &stop_words,
+            &blocklist,
+        ) {
+            tracking::learn(&mut words, word, time);
+        }
+    }
+
+    // "PIZZA!" doesn't match the regex, stop words and blocked words aren't learned
+    assert_eq!(60, words["pizza"].len());
+    assert_eq!(40, words["è"].len());
+    assert_eq!(20, words["ciao"].len());
+    assert!(!words.contains_key("il"));
+    assert!(!words.contains_key("gnocchi"));
+    assert!(!words.contains_key("gnocco"));
+
+    let mut rng = StdRng::seed_from_u64(69);
+    assert_eq!(
+        Some("pizza".to_owned()),
+        selection::pick_word(&words, &blocklist, &mut rng, 0)
+    );
+
+    // once blocked, the runner-up is picked even though pizza was learned
+    blocklist.add(Pattern::parse("pizza").unwrap());
+    assert_eq!(
+        Some("è".to_owned()),
+        selection::pick_word(&words, &blocklist, &mut rng, 0)
+    );
+}
@@ -1,5 +1,14 @@
-use std::cmp::Ord;
+use std::{
+    cmp::Ord,
+    collections::HashMap,
+    convert::TryFrom,
+    hash::Hash,
+    ops::{Bound, RangeBounds},
+};
 
+// Cloning copies the inner vec element by element in the same order, so the
+// clone is sorted as well.
+#[derive(Clone)]
 pub struct SortedVec<T: Ord> {
     vec: Vec<T>,
 }
@@ -45,6 +54,52 @@ impl<T: Ord> SortedVec<T> {
         mid + offset_last_le
     }
 
+    /// Index of the first element greater or equal to `key`, in `O(log n)`.
+    /// Unlike [`rank`](Self::rank), duplicates of `key` are not counted.
+    pub fn rank_first(&self, key: &T) -> usize {
+        self.vec.partition_point(|elem| elem < key)
+    }
+
+    /// Index of the first element greater or equal to `key`, like C++'s
+    /// `std::lower_bound`. Same as [`rank_first`](Self::rank_first).
+    pub fn lower_bound(&self, key: &T) -> usize {
+        self.rank_first(key)
+    }
+
+    /// Index of the first element greater than `key`, like C++'s
+    /// `std::upper_bound`. Same as [`rank`](Self::rank).
+    pub fn upper_bound(&self, key: &T) -> usize {
+        self.rank(key)
+    }
+
+    /// Amount of elements less or equal to `key`, in `O(log n + c)`.
+    /// Same as `get_le(key).len()`.
+    pub fn count_le(&self, key: &T) -> usize {
+        self.rank(key)
+    }
+
+    /// Amount of elements greater or equal to `key`, in `O(log n)`.
+    pub fn count_ge(&self, key: &T) -> usize {
+        self.len() - self.rank_first(key)
+    }
+
+    /// The `k`-th smallest element, counting from 0, or none if there are
+    /// only `k` elements or less. `O(1)`, since the vec is already sorted:
+    /// the cost was paid by [`insert`](Self::insert), which shifts `O(n)`
+    /// elements. Prefer a [`BinaryHeap`](std::collections::BinaryHeap) when
+    /// the elements change often and only the smallest or largest one is
+    /// needed; this when any rank is read often.
+    pub fn kth_smallest(&self, k: usize) -> Option<&T> {
+        self.vec.get(k)
+    }
+
+    /// The `k`-th largest element, counting from 0, in `O(1)` like
+    /// [`kth_smallest`](Self::kth_smallest).
+    pub fn kth_largest(&self, k: usize) -> Option<&T> {
+        let index = self.len().checked_sub(k)?.checked_sub(1)?;
+        self.vec.get(index)
+    }
+
     /// Insert element in `O(log n)`.
     /// Element is inserted *after* every other less or equal element.
     pub fn insert(&mut self, key: T) {
@@ -53,6 +108,88 @@ impl<T: Ord> SortedVec<T> {
         self.vec.insert(index, key);
     }
 
+    /// Insert every element of an already sorted iterator in `O(n + m)`, by
+    /// merging instead of sorting again. Like [`insert`](Self::insert), new
+    /// elements go *after* the equal ones already in the vec.
+    /// Panics in debug builds if the iterator isn't sorted.
+    pub fn extend_sorted(&mut self, sorted_iter: impl Iterator<Item = T>) {
+        let new: Vec<T> = sorted_iter.collect();
+        debug_assert!(
+            new.windows(2).all(|pair| pair[0] <= pair[1]),
+            "extend_sorted needs a sorted iterator"
+        );
+
+        // Nothing to merge if the new elements all come after the old ones
+        let appending = match (self.vec.last(), new.first()) {
+            (Some(last), Some(first)) => last <= first,
+            _ => true,
+        };
+
+        if appending {
+            self.vec.extend(new);
+            return;
+        }
+
+        let capacity = self.len() + new.len();
+        let old = std::mem::replace(&mut self.vec, Vec::with_capacity(capacity));
+        let mut old = old.into_iter().peekable();
+        let mut new = new.into_iter().peekable();
+
+        loop {
+            let next = match (old.peek(), new.peek()) {
+                (Some(a), Some(b)) if b < a => new.next(),
+                (Some(_), _) => old.next(),
+                (None, Some(_)) => new.next(),
+                (None, None) => break,
+            };
+
+            self.vec.extend(next);
+        }
+    }
+
+    /// Remove every element equal to `old_key` and insert `new_key`, returning
+    /// the amount of removed elements. The slot of a removed element is reused,
+    /// so the vec is shifted only once.
+    pub fn replace(&mut self, old_key: &T, new_key: T) -> usize {
+        let start = self.rank_first(old_key);
+        let end = self.rank(old_key);
+        let removed = end - start;
+
+        if removed == 0 {
+            self.insert(new_key);
+            return 0;
+        }
+
+        self.vec.drain(start + 1..end);
+        self.vec[start] = new_key;
+
+        // Move the new key to its place, after every other less or equal element
+        let (head, tail) = self.vec.split_at(start);
+        let key = &tail[0];
+        let before = head.partition_point(|elem| elem <= key);
+        let after = tail[1..].partition_point(|elem| elem <= key);
+
+        if before < start {
+            self.vec[before..=start].rotate_right(1);
+        } else if after > 0 {
+            self.vec[start..=start + after].rotate_left(1);
+        }
+
+        debug_assert!(self.is_sorted());
+
+        removed
+    }
+
+    /// Update every element in place with `f`, which must keep them in order,
+    /// e.g. clamping them or truncating timestamps to the minute. Elements
+    /// that become equal are fine.
+    /// Panics in debug builds if the order is broken.
+    pub fn apply_sorted<F: FnMut(&mut T)>(&mut self, f: F) {
+        self.vec.iter_mut().for_each(f);
+
+        debug_assert!(self.is_sorted(), "apply_sorted broke the order");
+    }
+
     /// Position of the last element equal to key, or none
     pub fn position(&self, key: &T) -> Option<usize> {
         let pos = self.rank(key);
@@ -70,12 +207,224 @@ impl<T: Ord> SortedVec<T> {
         &self.vec[0..index]
     }
 
+    /// The elements strictly less than `key`, in `O(log n)`. With
+    /// [`get_ge_exclusive`](Self::get_ge_exclusive), for half-open ranges
+    /// like the messages before a given time.
+    pub fn get_le_exclusive(&self, key: &T) -> &[T] {
+        let index = self.rank_first(key);
+
+        &self.vec[0..index]
+    }
+
+    /// The elements strictly greater than `key`, in `O(log n + c)`.
+    pub fn get_ge_exclusive(&self, key: &T) -> &[T] {
+        let index = self.rank(key);
+
+        &self.vec[index..]
+    }
+
+    /// Up to `n` elements on each side of `key`, together with every element
+    /// equal to it. If `key` is missing, the window is centered on where it
+    /// would be inserted.
+    pub fn get_around(&self, key: &T, n: usize) -> &[T] {
+        let start = self.rank_first(key).saturating_sub(n);
+        let end = self.rank(key).saturating_add(n).min(self.len());
+
+        &self.vec[start..end]
+    }
+
+    /// Overlapping windows of `size` consecutive elements, in order. Yields
+    /// nothing if there are less than `size` elements.
+    ///
+    /// Panics if `size` is 0.
+    pub fn iter_window(&self, size: usize) -> std::slice::Windows<'_, T> {
+        self.vec.windows(size)
+    }
+
+    /// Consume the vec, yielding the elements within `range` in order. The
+    /// bounds are found in `O(log n + c)`, elements outside of them are dropped
+    /// without being compared.
+    pub fn into_iter_range<R: RangeBounds<T>>(mut self, range: R) -> impl Iterator<Item = T> {
+        let start = match range.start_bound() {
+            Bound::Included(key) => self.rank_first(key),
+            Bound::Excluded(key) => self.rank(key),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(key) => self.rank(key),
+            Bound::Excluded(key) => self.rank_first(key),
+            Bound::Unbounded => self.len(),
+        };
+
+        // a range that ends before it starts is empty
+        self.vec.truncate(end.max(start));
+        self.vec.drain(..start);
+
+        self.vec.into_iter()
+    }
+
+    /// How many times each distinct element occurs. `O(n)`, since equal
+    /// elements are next to each other.
+    pub fn frequency_map(&self) -> HashMap<&T, usize>
+    where
+        T: Hash,
+    {
+        let mut frequencies = HashMap::new();
+        let mut elements = self.vec.iter();
+
+        let mut current = match elements.next() {
+            Some(first) => first,
+            None => return frequencies,
+        };
+        let mut count = 1;
+
+        for element in elements {
+            if *element == *current {
+                count += 1;
+            } else {
+                frequencies.insert(current, count);
+                current = element;
+                count = 1;
+            }
+        }
+        frequencies.insert(current, count);
+
+        frequencies
+    }
+
+    /// Consume the vec, yielding each distinct element once with how many
+    /// times it occurs, in order. `O(n)`, like [`frequency_map`], without a map.
+    ///
+    /// [`frequency_map`]: SortedVec::frequency_map
+    pub fn into_deduped_vec(self) -> Vec<(T, usize)> {
+        let mut deduped: Vec<(T, usize)> = Vec::new();
+
+        for element in self.vec {
+            match deduped.last_mut() {
+                Some((last, count)) if *last == element => *count += 1,
+                _ => deduped.push((element, 1)),
+            }
+        }
+
+        deduped
+    }
+
+    /// Consume both vecs, pairing their elements by position like
+    /// [`Iterator::zip`]: the smallest with the smallest, and so on. Stops at
+    /// the end of the shorter one. The pairs are returned as a plain `Vec`,
+    /// to be read by position rather than searched.
+    pub fn zip_sorted<U: Ord>(self, other: SortedVec<U>) -> Vec<(T, U)> {
+        self.vec.into_iter().zip(other.vec).collect()
+    }
+
+    /// How many distinct elements occur more than once, e.g. 2 for
+    /// `[1, 1, 2, 3, 3, 3]`. `O(n)`, since equal elements are next to each other.
+    pub fn count_duplicates(&self) -> usize {
+        self.vec
+            .windows(2)
+            .enumerate()
+            .filter(|(i, pair)| pair[0] == pair[1] && (*i == 0 || self.vec[i - 1] != pair[0]))
+            .count()
+    }
+
+    /// How many distinct elements there are, e.g. 3 for `[1, 1, 2, 3, 3, 3]`.
+    /// `O(n)`, like [`count_duplicates`](Self::count_duplicates).
+    pub fn count_unique(&self) -> usize {
+        self.len() - self.count_extra_copies()
+    }
+
+    /// How many elements are equal to the one before them, i.e. could be
+    /// removed without losing a distinct element.
+    fn count_extra_copies(&self) -> usize {
+        self.vec
+            .windows(2)
+            .filter(|pair| pair[0] == pair[1])
+            .count()
+    }
+
+    /// A uniformly random element, or none if empty.
+    #[cfg(feature = "rand")]
+    pub fn get_random<R: rand::Rng>(&self, rng: &mut R) -> Option<&T> {
+        if self.is_empty() {
+            return None;
+        }
+
+        self.vec.get(rng.gen_range(0..self.len()))
+    }
+
+    /// `k` elements at distinct positions, uniformly random, in order. Every
+    /// element if there are less than `k`.
+    #[cfg(feature = "rand")]
+    pub fn sample<R: rand::Rng>(&self, k: usize, rng: &mut R) -> Vec<&T> {
+        let mut indices = rand::seq::index::sample(rng, self.len(), k.min(self.len())).into_vec();
+        indices.sort_unstable();
+
+        indices.into_iter().map(|i| &self.vec[i]).collect()
+    }
+
     pub fn len(&self) -> usize {
         self.vec.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.vec.is_empty()
+    }
+
+    /// Whether the elements are in order, which they always are unless they
+    /// were rotated with the `_unchecked` methods. Meant for `debug_assert!`
+    /// after operations that could break the order.
+    pub fn is_sorted(&self) -> bool {
+        self.vec.windows(2).all(|w| w[0] <= w[1])
+    }
+
+    /// Amount of elements the vector can hold without reallocating.
+    pub fn capacity(&self) -> usize {
+        self.vec.capacity()
+    }
+
+    /// Free the memory left over by removed elements.
+    pub fn shrink_to_fit(&mut self) {
+        self.vec.shrink_to_fit();
+    }
+
     pub fn remove_le(&mut self, key: &T) {
-        self.vec.retain(|elem| elem > key); // only keep elements strictly greater than key
+        // elements less or equal than key are all at the start
+        let count = self.count_le(key);
+        self.vec.drain(..count);
+    }
+
+    /// Remove the elements at the positions in `range`, yielding them in
+    /// order. What's left stays sorted, since the range is contiguous.
+    ///
+    /// Panics if the range is out of bounds, like [`Vec::drain`].
+    pub fn drain<R: RangeBounds<usize>>(&mut self, range: R) -> std::vec::Drain<'_, T> {
+        self.vec.drain(range)
+    }
+
+    /// Rotate the elements so that the one at `mid` comes first, like
+    /// [`slice::rotate_left`].
+    ///
+    /// Unless `mid` is 0 or the length, or the elements are all equal, the
+    /// vec is **not sorted** afterwards: searches and insertions give
+    /// meaningless results until it is rotated back with
+    /// [`rotate_right_unchecked`](Self::rotate_right_unchecked) and the same
+    /// `mid`. Panics if `mid` is greater than the length.
+    pub fn rotate_left_unchecked(&mut self, mid: usize) {
+        self.vec.rotate_left(mid);
+    }
+
+    /// Rotate the elements so that the last `k` come first, like
+    /// [`slice::rotate_right`]. It breaks the order just like
+    /// [`rotate_left_unchecked`](Self::rotate_left_unchecked), which undoes it.
+    /// Panics if `k` is greater than the length.
+    pub fn rotate_right_unchecked(&mut self, k: usize) {
+        self.vec.rotate_right(k);
+    }
+}
+
+impl<T: Ord> Default for SortedVec<T> {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -85,6 +434,22 @@ impl<T: Ord> AsRef<[T]> for SortedVec<T> {
     }
 }
 
+/// Takes a vec that is already sorted as is, without sorting it again like
+/// [`SortedVec::from_vec`]. An unsorted vec is given back as the error.
+impl<T: Ord> TryFrom<Vec<T>> for SortedVec<T> {
+    type Error = Vec<T>;
+
+    fn try_from(vec: Vec<T>) -> Result<Self, Self::Error> {
+        let sorted = Self { vec };
+
+        if sorted.is_sorted() {
+            Ok(sorted)
+        } else {
+            Err(sorted.vec)
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -135,6 +500,51 @@ mod tests {
         assert_eq!(6, multiple.rank(&2));
     }
 
+    #[test]
+    fn rank_first() {
+        let empty = SortedVec::from_vec(vec![]);
+        assert_eq!(0, empty.rank_first(&10));
+
+        let multiple = SortedVec::from_vec(vec![1, 1, 1, 2, 2, 2, 3]);
+        assert_eq!(0, multiple.rank_first(&0));
+        assert_eq!(0, multiple.rank_first(&1));
+        assert_eq!(3, multiple.rank_first(&2));
+        assert_eq!(6, multiple.rank_first(&3));
+        assert_eq!(7, multiple.rank_first(&4));
+    }
+
+    #[test]
+    fn count() {
+        let vec = SortedVec::from_vec(vec![1, 2, 2, 2, 5, 7]);
+
+        for key in 0..9 {
+            assert_eq!(vec.get_le(&key).len(), vec.count_le(&key));
+            assert_eq!(
+                vec.as_ref().iter().filter(|&&elem| elem >= key).count(),
+                vec.count_ge(&key)
+            );
+        }
+    }
+
+    #[test]
+    fn kth() {
+        let empty = SortedVec::<i32>::new();
+        assert_eq!(None, empty.kth_smallest(0));
+        assert_eq!(None, empty.kth_largest(0));
+
+        let vec = SortedVec::from_vec(vec![3, 1, 3, 2]);
+        assert_eq!(Some(&1), vec.kth_smallest(0));
+        assert_eq!(Some(&3), vec.kth_smallest(2));
+        assert_eq!(Some(&3), vec.kth_smallest(3));
+        assert_eq!(None, vec.kth_smallest(4));
+
+        assert_eq!(Some(&3), vec.kth_largest(0));
+        assert_eq!(Some(&3), vec.kth_largest(1));
+        assert_eq!(Some(&1), vec.kth_largest(3));
+        assert_eq!(None, vec.kth_largest(4));
+        assert_eq!(None, vec.kth_largest(usize::MAX));
+    }
+
     #[test]
     fn insert() {
         let mut vec = SortedVec::new();
@@ -143,6 +553,85 @@ mod tests {
         assert_eq!(Some(0), vec.position(&1));
     }
 
+    #[test]
+    fn extend_sorted() {
+        let mut vec = SortedVec::from_vec(vec![1, 3, 3, 8]);
+        vec.extend_sorted(vec![0, 3, 4, 9, 10].into_iter());
+        assert_eq!(&[0, 1, 3, 3, 3, 4, 8, 9, 10], vec.as_ref());
+
+        // appending
+        vec.extend_sorted(11..13);
+        assert_eq!(&[8, 9, 10, 11, 12], &vec.as_ref()[6..]);
+        vec.extend_sorted(std::iter::empty());
+        assert_eq!(11, vec.len());
+
+        let mut empty = SortedVec::new();
+        empty.extend_sorted(vec![1, 2].into_iter());
+        assert_eq!(&[1, 2], empty.as_ref());
+
+        // equal elements are kept in order, new ones after the old ones
+        #[derive(Debug)]
+        struct Pair(i32, char);
+
+        impl PartialEq for Pair {
+            fn eq(&self, other: &Self) -> bool {
+                self.0 == other.0
+            }
+        }
+
+        impl Eq for Pair {}
+
+        impl PartialOrd for Pair {
+            fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl Ord for Pair {
+            fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+                self.0.cmp(&other.0)
+            }
+        }
+
+        let mut pairs = SortedVec::from_vec(vec![Pair(1, 'a'), Pair(2, 'a')]);
+        pairs.extend_sorted(vec![Pair(1, 'b'), Pair(2, 'b')].into_iter());
+        let tags: Vec<_> = pairs.as_ref().iter().map(|pair| pair.1).collect();
+        assert_eq!(vec!['a', 'b', 'a', 'b'], tags);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "sorted iterator")]
+    fn extend_unsorted() {
+        SortedVec::from_vec(vec![1, 2]).extend_sorted(vec![3, 0].into_iter());
+    }
+
+    #[test]
+    fn apply_sorted() {
+        let mut vec = SortedVec::from_vec(vec![125, 3, 61, 59, 240]);
+        // to the minute
+        vec.apply_sorted(|seconds| *seconds -= *seconds % 60);
+        assert_eq!(&[0, 0, 60, 120, 240], vec.as_ref());
+
+        vec.apply_sorted(|seconds| *seconds = (*seconds).clamp(30, 180));
+        assert_eq!(&[30, 30, 60, 120, 180], vec.as_ref());
+        // still searchable
+        assert_eq!(Some(1), vec.position(&30));
+        vec.insert(90);
+        assert_eq!(&[30, 30, 60, 90, 120, 180], vec.as_ref());
+
+        let mut empty = SortedVec::<i32>::new();
+        empty.apply_sorted(|_| unreachable!());
+        assert!(empty.is_empty());
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "broke the order")]
+    fn apply_unsorted() {
+        SortedVec::from_vec(vec![1, 2, 3]).apply_sorted(|n| *n = -*n);
+    }
+
     #[test]
     fn position() {
         let vec = SortedVec::from_vec(vec![1, 5, 4]);
@@ -152,10 +641,375 @@ mod tests {
         assert_eq!(None, vec.position(&3));
     }
 
+    #[test]
+    fn replace() {
+        let mut vec = SortedVec::from_vec(vec![1, 3, 3, 3, 5, 7]);
+        assert_eq!(3, vec.replace(&3, 4));
+        assert_eq!(&[1, 4, 5, 7], vec.as_ref());
+
+        // moves right
+        assert_eq!(1, vec.replace(&1, 6));
+        assert_eq!(&[4, 5, 6, 7], vec.as_ref());
+
+        // moves left
+        let mut vec = SortedVec::from_vec(vec![1, 5, 5, 8]);
+        assert_eq!(1, vec.replace(&8, 3));
+        assert_eq!(&[1, 3, 5, 5], vec.as_ref());
+
+        // stays after the equal elements
+        let mut vec = SortedVec::from_vec(vec![1, 2, 2, 8, 8, 9]);
+        assert_eq!(2, vec.replace(&8, 2));
+        assert_eq!(&[1, 2, 2, 2, 9], vec.as_ref());
+
+        // moves right, after the equal elements
+        assert_eq!(1, vec.replace(&1, 9));
+        assert_eq!(&[2, 2, 2, 9, 9], vec.as_ref());
+
+        // missing key only inserts
+        assert_eq!(0, vec.replace(&5, 5));
+        assert_eq!(&[2, 2, 2, 5, 9, 9], vec.as_ref());
+
+        let mut empty = SortedVec::new();
+        assert_eq!(0, empty.replace(&1, 2));
+        assert_eq!(&[2], empty.as_ref());
+    }
+
     #[test]
     fn remove() {
         let mut vec = SortedVec::from_vec(vec![9, 8, 7, 6, 5, 4, 3, 2, 1]);
         vec.remove_le(&6);
         assert_eq!(&[7, 8, 9], vec.as_ref());
+
+        vec.remove_le(&0);
+        assert_eq!(&[7, 8, 9], vec.as_ref());
+
+        vec.remove_le(&10);
+        assert!(vec.is_empty());
+    }
+
+    #[test]
+    fn shrink() {
+        let mut vec = SortedVec::from_vec((0..1000).collect());
+        vec.remove_le(&989);
+        assert!(vec.capacity() >= 1000);
+
+        vec.shrink_to_fit();
+        assert!(vec.capacity() < 1000);
+        assert!(vec.capacity() >= 10);
+        assert_eq!((990..1000).collect::<Vec<_>>(), vec.as_ref());
+    }
+
+    #[test]
+    fn clone() {
+        let original = SortedVec::from_vec(vec![3, 1, 2]);
+        let mut clone = original.clone();
+
+        assert_eq!(original.as_ref(), clone.as_ref());
+
+        clone.insert(0);
+        clone.remove_le(&1);
+
+        assert_eq!(&[1, 2, 3], original.as_ref());
+        assert_eq!(&[2, 3], clone.as_ref());
+    }
+
+    #[test]
+    fn get_around() {
+        let vec = SortedVec::from_vec(vec![1, 2, 3, 3, 3, 5, 8, 13]);
+
+        assert_eq!(&[2, 3, 3, 3, 5], vec.get_around(&3, 1));
+        assert_eq!(&[3, 3, 3], vec.get_around(&3, 0));
+        assert_eq!(&[1, 2, 3, 3, 3, 5, 8], vec.get_around(&3, 2));
+        assert_eq!(&[1, 2], vec.get_around(&1, 1));
+        assert_eq!(&[8, 13], vec.get_around(&13, 1));
+        assert_eq!(vec.as_ref(), vec.get_around(&5, usize::MAX));
+
+        // missing keys
+        assert_eq!(&[3, 5, 8, 13], vec.get_around(&6, 2));
+        assert_eq!(&[1], vec.get_around(&0, 1));
+        assert_eq!(&[13], vec.get_around(&100, 1));
+        assert!(vec.get_around(&4, 0).is_empty());
+
+        let empty: SortedVec<i32> = SortedVec::new();
+        assert!(empty.get_around(&1, 3).is_empty());
+    }
+
+    #[test]
+    fn get_exclusive() {
+        let vec = SortedVec::from_vec(vec![1, 2, 3, 3, 3, 5, 8]);
+
+        assert_eq!(&[1, 2], vec.get_le_exclusive(&3));
+        assert_eq!(&[5, 8], vec.get_ge_exclusive(&3));
+        assert_eq!(&[1, 2, 3, 3, 3], vec.get_le_exclusive(&4));
+        assert_eq!(&[5, 8], vec.get_ge_exclusive(&4));
+        assert!(vec.get_le_exclusive(&1).is_empty());
+        assert!(vec.get_ge_exclusive(&8).is_empty());
+        assert_eq!(vec.as_ref(), vec.get_le_exclusive(&100));
+        assert_eq!(vec.as_ref(), vec.get_ge_exclusive(&0));
+
+        // together with the key, they are everything
+        for key in 0..10 {
+            let equal = vec.count_le(&key) - vec.get_le_exclusive(&key).len();
+            assert_eq!(
+                vec.len(),
+                vec.get_le_exclusive(&key).len() + equal + vec.get_ge_exclusive(&key).len()
+            );
+        }
+
+        let empty: SortedVec<i32> = SortedVec::new();
+        assert!(empty.get_le_exclusive(&1).is_empty());
+        assert!(empty.get_ge_exclusive(&1).is_empty());
+    }
+
+    #[test]
+    fn iter_window() {
+        let vec = SortedVec::from_vec(vec![5, 1, 3, 2]);
+
+        let pairs: Vec<_> = vec.iter_window(2).collect();
+        assert_eq!(vec![&[1, 2], &[2, 3], &[3, 5]], pairs);
+
+        assert_eq!(1, vec.iter_window(4).count());
+        assert_eq!(0, vec.iter_window(5).count());
+        assert_eq!(0, SortedVec::<i32>::new().iter_window(1).count());
+    }
+
+    #[test]
+    fn into_iter_range() {
+        let vec = SortedVec::from_vec(vec![8, 1, 3, 5, 3, 2]);
+        let range = |range: (Bound<i32>, Bound<i32>)| -> Vec<i32> {
+            vec.clone().into_iter_range(range).collect()
+        };
+
+        assert_eq!(
+            vec![3, 3, 5],
+            vec.clone().into_iter_range(3..8).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![3, 3, 5, 8],
+            vec.clone().into_iter_range(3..=8).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![1, 2],
+            vec.clone().into_iter_range(..3).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![5, 8],
+            vec.clone().into_iter_range(4..).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec.as_ref(),
+            vec.clone().into_iter_range(..).collect::<Vec<_>>()
+        );
+        assert_eq!(vec![5], range((Bound::Excluded(3), Bound::Excluded(8))));
+
+        // empty ranges
+        assert!(range((Bound::Excluded(3), Bound::Excluded(4))).is_empty());
+        assert!(range((Bound::Included(6), Bound::Included(2))).is_empty());
+        assert!(vec.clone().into_iter_range(9..).next().is_none());
+        assert!(SortedVec::<i32>::new().into_iter_range(..).next().is_none());
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn get_random() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(42);
+
+        let empty: SortedVec<i32> = SortedVec::new();
+        assert_eq!(None, empty.get_random(&mut rng));
+
+        let vec = SortedVec::from_vec(vec![3, 1, 2]);
+        let mut seen = [false; 3];
+        for _ in 0..100 {
+            let element = *vec.get_random(&mut rng).unwrap();
+            seen[element as usize - 1] = true;
+        }
+        assert_eq!([true; 3], seen);
+    }
+
+    #[test]
+    fn drain() {
+        let mut vec = SortedVec::from_vec(vec![5, 1, 4, 2, 3]);
+
+        assert_eq!(vec![2, 3], vec.drain(1..3).collect::<Vec<_>>());
+        assert_eq!(&[1, 4, 5], vec.as_ref());
+        assert_eq!(0, vec.drain(3..).count());
+
+        // dropping the iterator still removes the range
+        vec.drain(..2);
+        assert_eq!(&[5], vec.as_ref());
+
+        vec.insert(2);
+        assert_eq!(vec![2, 5], vec.drain(..).collect::<Vec<_>>());
+        assert!(vec.is_empty());
+    }
+
+    #[test]
+    fn sorted() {
+        assert!(SortedVec::<i32>::new().is_sorted());
+
+        let mut vec = SortedVec::from_vec(vec![5, 1, 3, 3]);
+        assert!(vec.is_sorted());
+        vec.insert(2);
+        vec.replace(&3, 6);
+        vec.extend_sorted(vec![0, 9].into_iter());
+        assert!(vec.is_sorted());
+    }
+
+    #[test]
+    fn rotate() {
+        let mut vec = SortedVec::from_vec(vec![4, 1, 3, 2, 5]);
+
+        vec.rotate_left_unchecked(2);
+        assert_eq!(&[3, 4, 5, 1, 2], vec.as_ref());
+        assert!(!vec.is_sorted());
+        vec.rotate_right_unchecked(2);
+        assert_eq!(&[1, 2, 3, 4, 5], vec.as_ref());
+        assert!(vec.is_sorted());
+
+        vec.rotate_right_unchecked(1);
+        assert_eq!(&[5, 1, 2, 3, 4], vec.as_ref());
+        assert!(!vec.is_sorted());
+        vec.rotate_left_unchecked(1);
+        assert_eq!(&[1, 2, 3, 4, 5], vec.as_ref());
+
+        // a whole turn keeps the order
+        vec.rotate_left_unchecked(5);
+        assert_eq!(&[1, 2, 3, 4, 5], vec.as_ref());
+        assert!(vec.is_sorted());
+        assert_eq!(3, vec.rank(&3));
+    }
+
+    #[test]
+    fn frequency_map() {
+        assert!(SortedVec::<i32>::new().frequency_map().is_empty());
+
+        let vec = SortedVec::from_vec(vec![3, 1, 3, 2, 3, 1]);
+        let frequencies = vec.frequency_map();
+
+        assert_eq!(3, frequencies.len());
+        assert_eq!(Some(&2), frequencies.get(&1));
+        assert_eq!(Some(&1), frequencies.get(&2));
+        assert_eq!(Some(&3), frequencies.get(&3));
+        assert_eq!(None, frequencies.get(&4));
+        assert_eq!(vec.len(), frequencies.values().sum::<usize>());
+
+        let single = SortedVec::from_vec(vec!["pino"]);
+        assert_eq!(Some(&1), single.frequency_map().get(&"pino"));
+    }
+
+    #[test]
+    fn into_deduped_vec() {
+        assert!(SortedVec::<i32>::new().into_deduped_vec().is_empty());
+
+        let vec = SortedVec::from_vec(vec![3, 1, 3, 2, 3, 1]);
+        assert_eq!(vec![(1, 2), (2, 1), (3, 3)], vec.into_deduped_vec());
+
+        let single = SortedVec::from_vec(vec!["pino".to_owned()]);
+        assert_eq!(vec![("pino".to_owned(), 1)], single.into_deduped_vec());
+    }
+
+    #[test]
+    fn zip_sorted() {
+        // the timestamps of some messages, with the scores of their words
+        let timestamps = SortedVec::from_vec(vec![1_600_000_120, 1_600_000_000, 1_600_000_060]);
+        let scores = SortedVec::from_vec(vec![7, 3, 5, 9]);
+
+        assert_eq!(
+            vec![(1_600_000_000, 3), (1_600_000_060, 5), (1_600_000_120, 7)],
+            timestamps.clone().zip_sorted(scores.clone())
+        );
+        // the longer one is cut either way
+        assert_eq!(3, scores.zip_sorted(timestamps).len());
+
+        let empty = SortedVec::<u64>::new();
+        assert!(empty
+            .zip_sorted(SortedVec::from_vec(vec!["pino"]))
+            .is_empty());
+    }
+
+    #[test]
+    fn count_duplicates() {
+        let empty = SortedVec::<i32>::new();
+        assert_eq!(0, empty.count_duplicates());
+        assert_eq!(0, empty.count_unique());
+
+        let vec = SortedVec::from_vec(vec![3, 1, 3, 2, 3, 1]);
+        assert_eq!(2, vec.count_duplicates());
+        assert_eq!(3, vec.count_unique());
+
+        let distinct = SortedVec::from_vec(vec![4, 2, 7]);
+        assert_eq!(0, distinct.count_duplicates());
+        assert_eq!(3, distinct.count_unique());
+
+        let same = SortedVec::from_vec(vec!["pino"; 4]);
+        assert_eq!(1, same.count_duplicates());
+        assert_eq!(1, same.count_unique());
+    }
+
+    #[test]
+    fn bounds() {
+        let vec = SortedVec::from_vec(vec![1, 2, 2, 2, 5]);
+
+        assert_eq!(1, vec.lower_bound(&2));
+        assert_eq!(4, vec.upper_bound(&2));
+        assert_eq!((4, 4), (vec.lower_bound(&3), vec.upper_bound(&3)));
+        assert_eq!((0, 0), (vec.lower_bound(&0), vec.upper_bound(&0)));
+        assert_eq!((5, 5), (vec.lower_bound(&9), vec.upper_bound(&9)));
+
+        for key in 0..7 {
+            assert_eq!(vec.rank_first(&key), vec.lower_bound(&key));
+            assert_eq!(vec.rank(&key), vec.upper_bound(&key));
+        }
+
+        let empty = SortedVec::<i32>::new();
+        assert_eq!((0, 0), (empty.lower_bound(&1), empty.upper_bound(&1)));
+    }
+
+    #[test]
+    fn try_from() {
+        let sorted = SortedVec::try_from(vec![1, 2, 2, 5]).unwrap();
+        assert_eq!(&[1, 2, 2, 5], sorted.as_ref());
+        assert!(sorted.is_sorted());
+
+        assert!(SortedVec::<i32>::try_from(vec![]).unwrap().is_empty());
+        assert_eq!(&[3], SortedVec::try_from(vec![3]).unwrap().as_ref());
+
+        // the unsorted input comes back untouched
+        assert_eq!(
+            vec![2, 1, 3],
+            SortedVec::try_from(vec![2, 1, 3]).map(|_| ()).unwrap_err()
+        );
+    }
+
+    #[cfg(feature = "rand")]
+    #[test]
+    fn sample() {
+        use rand::{rngs::StdRng, SeedableRng};
+
+        let mut rng = StdRng::seed_from_u64(42);
+
+        assert!(SortedVec::<i32>::new().sample(3, &mut rng).is_empty());
+
+        let vec = SortedVec::from_vec(vec![5, 1, 4, 2, 3]);
+        assert!(vec.sample(0, &mut rng).is_empty());
+        assert_eq!(vec![&1, &2, &3, &4, &5], vec.sample(10, &mut rng));
+
+        let mut seen = [false; 5];
+        for _ in 0..100 {
+            let sample = vec.sample(2, &mut rng);
+
+            assert_eq!(2, sample.len());
+            assert!(sample[0] < sample[1]);
+            for &element in sample {
+                seen[element as usize - 1] = true;
+            }
+        }
+        assert_eq!([true; 5], seen);
+
+        // equal elements are still drawn once per position
+        let twins = SortedVec::from_vec(vec![7, 7]);
+        assert_eq!(vec![&7, &7], twins.sample(2, &mut rng));
     }
 }